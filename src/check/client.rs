@@ -0,0 +1,288 @@
+//! 检测用 HTTP 客户端
+//! 通过 `Dialer` 建立到目标站点的连接，在其上用 hyper 发送 HTTP/1.1 请求，
+//! 存活、测速、流媒体检测以及 Clash 健康检查共用这一个客户端
+
+use crate::outbound::dialer::Dialer;
+use crate::outbound::tls::{self, TlsOptions};
+use crate::outbound::{Address, BoxedStream};
+use anyhow::{Result, anyhow};
+use bytes::Bytes;
+use http::{Method, Request, StatusCode, header};
+use http_body_util::{BodyExt, Empty};
+use hyper::body::Incoming;
+use hyper_util::rt::TokioIo;
+use std::sync::Arc;
+use std::time::Duration;
+use url::{Position, Url};
+
+/// 最多跟随的重定向次数
+const MAX_REDIRECTS: usize = 10;
+/// 默认 User-Agent
+const DEFAULT_USER_AGENT: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36";
+
+/// 已读完响应体的 HTTP 响应
+#[derive(Debug)]
+pub struct Response {
+    pub status: StatusCode,
+    pub body: Bytes,
+}
+
+impl Response {
+    /// 以 UTF-8 解码响应体，非法字节替换为占位符
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+}
+
+/// 基于 `Dialer` 的 HTTP 客户端，每个请求单独建立连接
+#[derive(Clone)]
+pub struct HttpClient {
+    dialer: Arc<dyn Dialer>,
+    timeout: Duration,
+    user_agent: String,
+}
+
+impl HttpClient {
+    /// 创建客户端，`timeout` 覆盖从拨号到读完响应体的整个请求
+    pub fn new(dialer: Arc<dyn Dialer>, timeout: Duration) -> Self {
+        Self {
+            dialer,
+            timeout,
+            user_agent: DEFAULT_USER_AGENT.to_string(),
+        }
+    }
+
+    /// 设置 User-Agent
+    pub fn with_user_agent(mut self, user_agent: &str) -> Self {
+        self.user_agent = user_agent.to_string();
+        self
+    }
+
+    /// 发送 GET 请求并读完响应体
+    pub async fn get(&self, url: &str) -> Result<Response> {
+        self.fetch(Method::GET, url).await
+    }
+
+    /// 发送 HEAD 请求
+    pub async fn head(&self, url: &str) -> Result<Response> {
+        self.fetch(Method::HEAD, url).await
+    }
+
+    /// 下载并丢弃响应体，返回收到的字节数
+    pub async fn download(&self, url: &str) -> Result<u64> {
+        self.with_timeout(url, async {
+            let mut body = self.send(Method::GET, url).await?.into_body();
+            let mut total = 0u64;
+            while let Some(frame) = body.frame().await {
+                let frame = frame.map_err(|e| anyhow!("读取响应体失败: {}", e))?;
+                if let Some(data) = frame.data_ref() {
+                    total += data.len() as u64;
+                }
+            }
+            Ok(total)
+        })
+        .await
+    }
+
+    async fn fetch(&self, method: Method, url: &str) -> Result<Response> {
+        self.with_timeout(url, async {
+            let response = self.send(method, url).await?;
+            let status = response.status();
+            let body = response
+                .into_body()
+                .collect()
+                .await
+                .map_err(|e| anyhow!("读取响应体失败: {}", e))?
+                .to_bytes();
+            Ok(Response { status, body })
+        })
+        .await
+    }
+
+    async fn with_timeout<T>(
+        &self,
+        url: &str,
+        future: impl Future<Output = Result<T>>,
+    ) -> Result<T> {
+        tokio::time::timeout(self.timeout, future)
+            .await
+            .map_err(|_| anyhow!("请求超时: {}", url))?
+    }
+
+    /// 发送请求并跟随重定向，返回响应头已到达的响应
+    async fn send(&self, mut method: Method, url: &str) -> Result<hyper::Response<Incoming>> {
+        let mut url = Url::parse(url).map_err(|e| anyhow!("无效的 URL {}: {}", url, e))?;
+        for _ in 0..=MAX_REDIRECTS {
+            let response = self.send_once(&method, &url).await?;
+            if !response.status().is_redirection() {
+                return Ok(response);
+            }
+            let Some(location) = response
+                .headers()
+                .get(header::LOCATION)
+                .and_then(|value| value.to_str().ok())
+            else {
+                return Ok(response);
+            };
+            url = url
+                .join(location)
+                .map_err(|e| anyhow!("无效的重定向地址 {}: {}", location, e))?;
+            if response.status() == StatusCode::SEE_OTHER && method != Method::HEAD {
+                method = Method::GET;
+            }
+        }
+        Err(anyhow!("重定向次数过多: {}", url))
+    }
+
+    async fn send_once(&self, method: &Method, url: &Url) -> Result<hyper::Response<Incoming>> {
+        let host = url
+            .host_str()
+            .ok_or_else(|| anyhow!("URL 缺少主机名: {}", url))?;
+        let port = url
+            .port_or_known_default()
+            .ok_or_else(|| anyhow!("URL 缺少端口: {}", url))?;
+        let target = Address::from_host(host, port);
+
+        let stream = self.dialer.connect_tcp(&target).await?;
+        let stream: BoxedStream = match url.scheme() {
+            "http" => stream,
+            "https" => {
+                let options = TlsOptions {
+                    sni: host
+                        .trim_start_matches('[')
+                        .trim_end_matches(']')
+                        .to_string(),
+                    alpn: vec!["http/1.1".to_string()],
+                    insecure: false,
+                };
+                Box::new(
+                    tls::connect(stream, &options)
+                        .await
+                        .map_err(|e| anyhow!("TLS 握手失败: {}", e))?,
+                )
+            }
+            other => return Err(anyhow!("不支持的 URL 协议: {}", other)),
+        };
+
+        let (mut sender, connection) = hyper::client::conn::http1::handshake(TokioIo::new(stream))
+            .await
+            .map_err(|e| anyhow!("HTTP 握手失败: {}", e))?;
+        tokio::spawn(async move {
+            if let Err(e) = connection.await {
+                log::debug!("HTTP 连接结束: {}", e);
+            }
+        });
+
+        let request = Request::builder()
+            .method(method.clone())
+            .uri(&url[Position::BeforePath..Position::AfterQuery])
+            .header(
+                header::HOST,
+                &url[Position::BeforeHost..Position::AfterPort],
+            )
+            .header(header::USER_AGENT, &self.user_agent)
+            .header(header::ACCEPT, "*/*")
+            .body(Empty::<Bytes>::new())?;
+        sender
+            .send_request(request)
+            .await
+            .map_err(|e| anyhow!("HTTP 请求失败: {}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::outbound::dialer::DirectDialer;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// 启动本地 HTTP 服务端替身，按连接顺序返回预设的响应，并把请求头发回调用方
+    async fn spawn_server(
+        responses: Vec<&'static str>,
+    ) -> (u16, tokio::sync::mpsc::UnboundedReceiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            for response in responses {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut request = Vec::new();
+                while !request.ends_with(b"\r\n\r\n") {
+                    request.push(socket.read_u8().await.unwrap());
+                }
+                tx.send(String::from_utf8(request).unwrap()).unwrap();
+                socket.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        (port, rx)
+    }
+
+    fn client(timeout: Duration) -> HttpClient {
+        HttpClient::new(Arc::new(DirectDialer), timeout)
+    }
+
+    #[tokio::test]
+    async fn test_get_follows_redirect() {
+        let (port, mut requests) = spawn_server(vec![
+            "HTTP/1.1 302 Found\r\nLocation: /final?a=1\r\nContent-Length: 0\r\n\r\n",
+            "HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello",
+        ])
+        .await;
+
+        let response = client(Duration::from_secs(5))
+            .with_user_agent("test-agent")
+            .get(&format!("http://127.0.0.1:{}/start", port))
+            .await
+            .unwrap();
+        assert_eq!(response.status, StatusCode::OK);
+        assert_eq!(response.text(), "hello");
+
+        let first = requests.recv().await.unwrap();
+        assert!(first.starts_with("GET /start HTTP/1.1\r\n"));
+        assert!(first.contains(&format!("host: 127.0.0.1:{}\r\n", port)));
+        assert!(first.contains("user-agent: test-agent\r\n"));
+        let second = requests.recv().await.unwrap();
+        assert!(second.starts_with("GET /final?a=1 HTTP/1.1\r\n"));
+    }
+
+    #[tokio::test]
+    async fn test_head_and_download() {
+        let (port, _requests) = spawn_server(vec![
+            "HTTP/1.1 204 No Content\r\n\r\n",
+            "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nabc\r\n4\r\ndefg\r\n0\r\n\r\n",
+        ])
+        .await;
+        let client = client(Duration::from_secs(5));
+        let url = format!("http://127.0.0.1:{}/", port);
+
+        let response = client.head(&url).await.unwrap();
+        assert_eq!(response.status, StatusCode::NO_CONTENT);
+        assert_eq!(client.download(&url).await.unwrap(), 7);
+    }
+
+    #[tokio::test]
+    async fn test_timeout() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            // 接受连接但不响应
+            let (_socket, _) = listener.accept().await.unwrap();
+            tokio::time::sleep(Duration::from_secs(5)).await;
+        });
+
+        let err = client(Duration::from_millis(200))
+            .get(&format!("http://127.0.0.1:{}/", port))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("请求超时"));
+    }
+
+    #[tokio::test]
+    async fn test_unsupported_scheme() {
+        let result = client(Duration::from_secs(1))
+            .get("ftp://example.com/")
+            .await;
+        assert!(result.is_err());
+    }
+}
@@ -1,6 +1,11 @@
+pub mod client;
+
 use crate::config::Config;
+use crate::outbound::Address;
+use crate::outbound::dialer::{Dialer, NodeDialer};
 use crate::proxy::ProxyNode;
-use reqwest::Client;
+use anyhow::anyhow;
+use client::HttpClient;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use std::sync::atomic::{AtomicU64, Ordering};
@@ -41,6 +46,9 @@ pub struct CheckResult {
     pub is_alive: bool,
     pub latency: Option<Duration>,
     pub speed: Option<f64>, // KB/s
+    /// 能否经由节点收发 UDP
+    #[serde(default)]
+    pub udp: bool,
     pub media_unlock: MediaUnlockResult,
     pub country: Option<String>,
     pub country_code: Option<String>,
@@ -201,11 +209,10 @@ async fn check_single_proxy(
     config: Config,
     stats: Arc<Stats>,
 ) -> anyhow::Result<CheckResult> {
-    // 构建代理 URL
-    let proxy_url = proxy.to_proxy_url();
-
-    // 创建 HTTP 客户端
-    let client = create_http_client(&proxy_url, config.timeout)?;
+    // 所有检测阶段都经由节点拨号，HTTP 客户端建立在拨号之上
+    let timeout = Duration::from_millis(config.timeout);
+    let dialer = Arc::new(NodeDialer::new(proxy.clone()));
+    let client = HttpClient::new(dialer.clone(), timeout);
 
     // 检查存活
     let start = Instant::now();
@@ -220,6 +227,7 @@ async fn check_single_proxy(
             is_alive: false,
             latency,
             speed: None,
+            udp: false,
             media_unlock: MediaUnlockResult::default(),
             country: None,
             country_code: None,
@@ -247,6 +255,7 @@ async fn check_single_proxy(
             is_alive: false,
             latency,
             speed: None,
+            udp: false,
             media_unlock: MediaUnlockResult::default(),
             country: None,
             country_code: None,
@@ -278,6 +287,15 @@ async fn check_single_proxy(
         None
     };
 
+    // UDP 检测，默认关闭
+    let udp = config.udp_check
+        && match Address::parse(&config.udp_check_server) {
+            Ok(server) => check_udp(dialer.as_ref(), &server, timeout)
+                .await
+                .unwrap_or(false),
+            Err(_) => false,
+        };
+
     // 媒体解锁检测
     let media_unlock = if config.is_media_check_enabled() {
         MediaUnlockResult {
@@ -301,6 +319,7 @@ async fn check_single_proxy(
         is_alive: true,
         latency,
         speed,
+        udp,
         media_unlock,
         country: cf_location.clone(),
         country_code: None,
@@ -312,46 +331,25 @@ async fn check_single_proxy(
     })
 }
 
-fn create_http_client(proxy_url: &str, timeout_ms: u64) -> anyhow::Result<Client> {
-    let proxy = reqwest::Proxy::all(proxy_url)?;
+async fn check_alive(client: &HttpClient) -> anyhow::Result<bool> {
+    let response = client.head("https://gstatic.com/generate_204").await?;
 
-    let client = Client::builder()
-        .proxy(proxy)
-        .timeout(Duration::from_millis(timeout_ms))
-        .user_agent("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36")
-        .build()?;
-
-    Ok(client)
+    Ok(response.status.as_u16() == 204)
 }
 
-async fn check_alive(client: &Client) -> anyhow::Result<bool> {
-    let response = client
-        .head("https://gstatic.com/generate_204")
-        .send()
-        .await?;
+async fn check_google(client: &HttpClient) -> anyhow::Result<bool> {
+    let response = client.head("https://www.google.com/generate_204").await?;
 
-    Ok(response.status().as_u16() == 204)
-}
-
-async fn check_google(client: &Client) -> anyhow::Result<bool> {
-    let response = client
-        .head("https://www.google.com/generate_204")
-        .send()
-        .await?;
-
-    Ok(response.status().as_u16() == 204)
+    Ok(response.status.as_u16() == 204)
 }
 
 async fn check_cloudflare(
-    client: &Client,
+    client: &HttpClient,
 ) -> anyhow::Result<(bool, Option<String>, Option<String>)> {
-    let response = client
-        .get("https://cloudflare.com/cdn-cgi/trace")
-        .send()
-        .await?;
+    let response = client.get("https://cloudflare.com/cdn-cgi/trace").await?;
 
-    let is_accessible = response.status().is_success();
-    let body = response.text().await?;
+    let is_accessible = response.status.is_success();
+    let body = response.text();
 
     // 解析 trace 信息获取位置和 IP
     let mut loc = None;
@@ -368,60 +366,94 @@ async fn check_cloudflare(
     Ok((is_accessible, loc, ip))
 }
 
-async fn check_youtube(client: &Client) -> anyhow::Result<bool> {
-    let response = client.get("https://www.youtube.com/premium").send().await?;
+async fn check_youtube(client: &HttpClient) -> anyhow::Result<bool> {
+    let response = client.get("https://www.youtube.com/premium").await?;
 
-    let text = response.text().await?;
+    let text = response.text();
     // 简单检查是否包含特定关键词
     Ok(!text.contains("YouTube Premium is not available in your country"))
 }
 
-async fn check_netflix(client: &Client) -> anyhow::Result<bool> {
+async fn check_netflix(client: &HttpClient) -> anyhow::Result<bool> {
     let response = client
         .get("https://www.netflix.com/title/81280792") // 一个特定的剧集ID
-        .send()
         .await?;
 
-    Ok(response.status().is_success())
+    Ok(response.status.is_success())
 }
 
-async fn check_disney(client: &Client) -> anyhow::Result<bool> {
-    let response = client.get("https://www.disneyplus.com").send().await?;
+async fn check_disney(client: &HttpClient) -> anyhow::Result<bool> {
+    let response = client.get("https://www.disneyplus.com").await?;
 
-    Ok(response.status().is_success())
+    Ok(response.status.is_success())
 }
 
-async fn check_openai(client: &Client) -> anyhow::Result<bool> {
-    let response = client.get("https://chat.openai.com").send().await?;
+async fn check_openai(client: &HttpClient) -> anyhow::Result<bool> {
+    let response = client.get("https://chat.openai.com").await?;
 
-    Ok(response.status().is_success())
+    Ok(response.status.is_success())
 }
 
-async fn check_tiktok(client: &Client) -> anyhow::Result<bool> {
-    let response = client.get("https://www.tiktok.com").send().await?;
+async fn check_tiktok(client: &HttpClient) -> anyhow::Result<bool> {
+    let response = client.get("https://www.tiktok.com").await?;
 
-    Ok(response.status().is_success())
+    Ok(response.status.is_success())
 }
 
-async fn check_gemini(client: &Client) -> anyhow::Result<bool> {
-    let response = client.get("https://gemini.google.com").send().await?;
+async fn check_gemini(client: &HttpClient) -> anyhow::Result<bool> {
+    let response = client.get("https://gemini.google.com").await?;
 
-    Ok(response.status().is_success())
+    Ok(response.status.is_success())
 }
 
-async fn check_speed(client: &Client, test_url: &str, stats: &Stats) -> anyhow::Result<f64> {
-    let start = Instant::now();
+/// 构造查询 `domain` A 记录的 DNS 请求
+fn dns_query(id: u16, domain: &str) -> Vec<u8> {
+    let mut query = Vec::with_capacity(18 + domain.len());
+    query.extend_from_slice(&id.to_be_bytes());
+    // 标准查询、期望递归，1 个问题
+    query.extend_from_slice(&[0x01, 0x00, 0x00, 0x01, 0, 0, 0, 0, 0, 0]);
+    for label in domain.split('.').filter(|label| !label.is_empty()) {
+        query.push(label.len().min(63) as u8);
+        query.extend_from_slice(&label.as_bytes()[..label.len().min(63)]);
+    }
+    // 根标签、QTYPE=A、QCLASS=IN
+    query.extend_from_slice(&[0x00, 0x00, 0x01, 0x00, 0x01]);
+    query
+}
 
-    let response = client.get(test_url).send().await?;
+/// 经由 UDP 关联向 DNS 服务器发送查询，收到 ID 匹配的应答即认为支持 UDP；
+/// 出站未实现 UDP 时直接返回不支持，不发起连接
+pub async fn check_udp(
+    dialer: &dyn Dialer,
+    server: &Address,
+    timeout: Duration,
+) -> anyhow::Result<bool> {
+    if !dialer.supports_udp() {
+        return Ok(false);
+    }
+    let query = dns_query(rand::random(), "www.gstatic.com");
+    tokio::time::timeout(timeout, async {
+        let mut datagram = dialer.connect_udp(server).await?;
+        datagram.send(&query).await?;
+        let mut buf = [0u8; 512];
+        let n = datagram.recv(&mut buf).await?;
+        Ok(n >= 12 && buf[..2] == query[..2])
+    })
+    .await
+    .map_err(|_| anyhow!("UDP 检测超时"))?
+}
 
-    let content = response.bytes().await?;
+async fn check_speed(client: &HttpClient, test_url: &str, stats: &Stats) -> anyhow::Result<f64> {
+    let start = Instant::now();
+
+    let bytes = client.download(test_url).await?;
     let elapsed = start.elapsed();
 
     // 记录流量
-    stats.add_bytes(content.len() as u64);
+    stats.add_bytes(bytes);
 
     // 计算速度 (KB/s)
-    let speed = content.len() as f64 / 1024.0 / elapsed.as_secs_f64();
+    let speed = bytes as f64 / 1024.0 / elapsed.as_secs_f64();
 
     Ok(speed)
 }
@@ -429,7 +461,7 @@ async fn check_speed(client: &Client, test_url: &str, stats: &Stats) -> anyhow::
 pub mod platform {
     use super::*;
 
-    pub async fn check_all_platforms(client: &Client) -> MediaUnlockResult {
+    pub async fn check_all_platforms(client: &HttpClient) -> MediaUnlockResult {
         MediaUnlockResult {
             youtube: check_youtube(client).await.unwrap_or(false),
             netflix: check_netflix(client).await.unwrap_or(false),
@@ -445,3 +477,61 @@ pub mod platform {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::outbound::dialer::DirectDialer;
+    use crate::proxy::Protocol;
+    use tokio::net::UdpSocket;
+
+    #[test]
+    fn test_dns_query() {
+        let query = dns_query(0x1234, "www.gstatic.com");
+        assert_eq!(&query[..4], &[0x12, 0x34, 0x01, 0x00]);
+        assert_eq!(&query[12..16], &[3, b'w', b'w', b'w']);
+        assert_eq!(&query[query.len() - 5..], &[0, 0, 1, 0, 1]);
+        assert_eq!(query.len(), 12 + "www.gstatic.com".len() + 2 + 4);
+    }
+
+    #[tokio::test]
+    async fn test_check_udp() {
+        // 回显服务端的应答 ID 与请求一致
+        let server = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr = Address::Socket(server.local_addr().unwrap());
+        tokio::spawn(async move {
+            let mut buf = [0u8; 512];
+            let (n, peer) = server.recv_from(&mut buf).await.unwrap();
+            server.send_to(&buf[..n], peer).await.unwrap();
+        });
+        let supported = check_udp(&DirectDialer, &addr, Duration::from_secs(5))
+            .await
+            .unwrap();
+        assert!(supported);
+
+        // 无应答时超时
+        let silent = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr = Address::Socket(silent.local_addr().unwrap());
+        assert!(
+            check_udp(&DirectDialer, &addr, Duration::from_millis(200))
+                .await
+                .is_err()
+        );
+
+        // 出站未实现 UDP 时不等待超时
+        let http = NodeDialer::new(ProxyNode::new(
+            "http".to_string(),
+            "127.0.0.1".to_string(),
+            8080,
+            Protocol::Http(Default::default()),
+        ));
+        let supported = tokio::time::timeout(
+            Duration::from_millis(100),
+            check_udp(&http, &addr, Duration::from_secs(60)),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert!(!supported);
+    }
+}
@@ -7,14 +7,16 @@
 //! - 批量测试和并发控制
 //! - 自定义测试URL和超时设置
 
-use anyhow::{Result, anyhow};
-use reqwest::{Client, Proxy as ReqwestProxy};
+use anyhow::Result;
 use std::collections::{HashMap, VecDeque};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
 
-use super::types::ProxyNodeInfo;
+use super::health_check::HEALTH_CHECK_USER_AGENT;
+use crate::check::client::HttpClient;
+use crate::outbound::dialer::NodeDialer;
+use crate::proxy::ProxyNode;
 
 /// 延迟历史记录项
 #[derive(Debug, Clone)]
@@ -118,7 +120,7 @@ impl AdvancedHealthChecker {
     /// 批量检查代理健康状态
     pub async fn check_proxies_health_batch(
         &self,
-        proxies: &[ProxyNodeInfo],
+        proxies: &[ProxyNode],
     ) -> Vec<HealthCheckResult> {
         let mut results = Vec::with_capacity(proxies.len());
         let mut tasks = Vec::new();
@@ -151,7 +153,7 @@ impl AdvancedHealthChecker {
     /// 检查单个代理的健康状态（详细版）
     pub async fn check_proxy_health_detailed(
         &self,
-        proxy_info: &ProxyNodeInfo,
+        proxy_info: &ProxyNode,
     ) -> Result<HealthCheckResult> {
         let start_time = Instant::now();
         let timestamp = std::time::SystemTime::now()
@@ -159,71 +161,17 @@ impl AdvancedHealthChecker {
             .unwrap_or_default()
             .as_secs();
 
-        // 构建代理URL
-        let proxy_url = match self.build_proxy_url(proxy_info) {
-            Ok(url) => url,
-            Err(e) => {
-                return Ok(HealthCheckResult {
-                    name: proxy_info.name.clone(),
-                    alive: false,
-                    connect_delay: None,
-                    tls_handshake_delay: None,
-                    actual_delay: None,
-                    total_delay: None,
-                    error: Some(format!("构建代理URL失败: {}", e)),
-                    timestamp,
-                });
-            }
-        };
-
-        // 创建带代理的客户端
-        let proxy = match ReqwestProxy::all(&proxy_url) {
-            Ok(proxy) => proxy,
-            Err(e) => {
-                return Ok(HealthCheckResult {
-                    name: proxy_info.name.clone(),
-                    alive: false,
-                    connect_delay: None,
-                    tls_handshake_delay: None,
-                    actual_delay: None,
-                    total_delay: None,
-                    error: Some(format!("无效的代理URL: {}", e)),
-                    timestamp,
-                });
-            }
-        };
-
-        let client = match Client::builder()
-            .timeout(Duration::from_millis(self.timeout_ms))
-            .proxy(proxy)
-            .build()
-        {
-            Ok(client) => client,
-            Err(e) => {
-                return Ok(HealthCheckResult {
-                    name: proxy_info.name.clone(),
-                    alive: false,
-                    connect_delay: None,
-                    tls_handshake_delay: None,
-                    actual_delay: None,
-                    total_delay: None,
-                    error: Some(format!("创建HTTP客户端失败: {}", e)),
-                    timestamp,
-                });
-            }
-        };
+        // 经由节点拨号的 HTTP 客户端
+        let client = HttpClient::new(
+            Arc::new(NodeDialer::new(proxy_info.clone())),
+            Duration::from_millis(self.timeout_ms),
+        )
+        .with_user_agent(HEALTH_CHECK_USER_AGENT);
 
         // 发送测试请求并测量延迟
         let connect_start = Instant::now();
 
-        let response = client
-            .get(&self.test_url)
-            .header(
-                "User-Agent",
-                "Mozilla/5.0 (compatible; Proxy-Health-Check/1.0)",
-            )
-            .send()
-            .await;
+        let response = client.get(&self.test_url).await;
 
         let connect_delay = connect_start.elapsed();
         let actual_delay = connect_start.elapsed();
@@ -231,7 +179,7 @@ impl AdvancedHealthChecker {
 
         match response {
             Ok(resp) => {
-                if resp.status().is_success() {
+                if resp.status.is_success() {
                     // 成功
                     let result = HealthCheckResult {
                         name: proxy_info.name.clone(),
@@ -257,7 +205,7 @@ impl AdvancedHealthChecker {
                         tls_handshake_delay: None,
                         actual_delay: Some(actual_delay.as_millis() as u64),
                         total_delay: Some(total_delay.as_millis() as u64),
-                        error: Some(format!("HTTP错误: {}", resp.status())),
+                        error: Some(format!("HTTP错误: {}", resp.status)),
                         timestamp,
                     };
 
@@ -288,259 +236,6 @@ impl AdvancedHealthChecker {
         }
     }
 
-    /// 构建代理URL
-    fn build_proxy_url(&self, proxy_info: &ProxyNodeInfo) -> Result<String> {
-        match proxy_info.proto.to_lowercase().as_str() {
-            "http" | "https" => self.build_http_proxy_url(proxy_info),
-            "socks4" | "socks5" | "socks" => self.build_socks_proxy_url(proxy_info),
-            "ss" | "shadowsocks" => self.build_shadowsocks_url(proxy_info),
-            "vmess" => self.build_vmess_url(proxy_info),
-            "vless" => self.build_vless_url(proxy_info),
-            "trojan" => self.build_trojan_url(proxy_info),
-            "ssr" => self.build_ssr_url(proxy_info),
-            "hysteria" | "hysteria2" => self.build_hysteria_url(proxy_info),
-            "tuic" => self.build_tuic_url(proxy_info),
-            "wireguard" | "wg" => self.build_wireguard_url(proxy_info),
-            _ => Err(anyhow!("不支持的代理协议: {}", proxy_info.proto)),
-        }
-    }
-
-    fn build_http_proxy_url(&self, proxy_info: &ProxyNodeInfo) -> Result<String> {
-        let mut auth_part = String::new();
-
-        if let Some(extra_info) = &proxy_info.extra_info {
-            if let Some(username) = extra_info.get("username").and_then(|v| v.as_str()) {
-                if let Some(password) = extra_info.get("password").and_then(|v| v.as_str()) {
-                    auth_part = format!("{}:{}@", username, password);
-                }
-            }
-        }
-
-        Ok(format!(
-            "{}://{}{}:{}",
-            proxy_info.proto.to_lowercase(),
-            auth_part,
-            proxy_info.server,
-            proxy_info.port
-        ))
-    }
-
-    fn build_socks_proxy_url(&self, proxy_info: &ProxyNodeInfo) -> Result<String> {
-        let mut auth_part = String::new();
-
-        if let Some(extra_info) = &proxy_info.extra_info {
-            if let Some(username) = extra_info.get("username").and_then(|v| v.as_str()) {
-                if let Some(password) = extra_info.get("password").and_then(|v| v.as_str()) {
-                    auth_part = format!("{}:{}@", username, password);
-                }
-            }
-        }
-
-        Ok(format!(
-            "socks5://{}{}:{}",
-            auth_part, proxy_info.server, proxy_info.port
-        ))
-    }
-
-    fn build_shadowsocks_url(&self, proxy_info: &ProxyNodeInfo) -> Result<String> {
-        let cipher = proxy_info
-            .extra_info
-            .as_ref()
-            .and_then(|info| info.get("cipher"))
-            .and_then(|v| v.as_str())
-            .unwrap_or("aes-256-gcm");
-
-        let password = proxy_info
-            .extra_info
-            .as_ref()
-            .and_then(|info| info.get("password"))
-            .and_then(|v| v.as_str())
-            .ok_or_else(|| anyhow!("Shadowsocks需要密码"))?;
-
-        // 构建SS URL格式: ss://method:password@server:port
-        let encoded_password = urlencoding::encode(password);
-        Ok(format!(
-            "ss://{}:{}@{}:{}",
-            cipher, encoded_password, proxy_info.server, proxy_info.port
-        ))
-    }
-
-    fn build_vmess_url(&self, proxy_info: &ProxyNodeInfo) -> Result<String> {
-        let uuid = proxy_info
-            .extra_info
-            .as_ref()
-            .and_then(|info| info.get("uuid"))
-            .and_then(|v| v.as_str())
-            .ok_or_else(|| anyhow!("Vmess需要UUID"))?;
-
-        // 构建VMess配置对象
-        let mut config = HashMap::new();
-        config.insert("v".to_string(), "2".to_string());
-        config.insert("ps".to_string(), proxy_info.name.clone());
-        config.insert("add".to_string(), proxy_info.server.clone());
-        config.insert("port".to_string(), proxy_info.port.to_string());
-        config.insert("id".to_string(), uuid.to_string());
-        config.insert("aid".to_string(), "0".to_string());
-
-        // 添加加密方式
-        if let Some(extra_info) = &proxy_info.extra_info {
-            if let Some(cipher) = extra_info.get("cipher").and_then(|v| v.as_str()) {
-                config.insert("type".to_string(), cipher.to_string());
-            }
-        }
-
-        // 转换为JSON并Base64编码
-        let json_config = serde_json::to_string(&config)?;
-        let base64_config =
-            base64::Engine::encode(&base64::engine::general_purpose::URL_SAFE, json_config);
-
-        Ok(format!("vmess://{}", base64_config))
-    }
-
-    fn build_vless_url(&self, proxy_info: &ProxyNodeInfo) -> Result<String> {
-        let uuid = proxy_info
-            .extra_info
-            .as_ref()
-            .and_then(|info| info.get("uuid"))
-            .and_then(|v| v.as_str())
-            .ok_or_else(|| anyhow!("VLESS需要UUID"))?;
-
-        let mut url = format!("vless://{}@{}:{}", uuid, proxy_info.server, proxy_info.port);
-
-        // 添加参数
-        let mut params = Vec::new();
-
-        if let Some(extra_info) = &proxy_info.extra_info {
-            if let Some(security) = extra_info.get("cipher").and_then(|v| v.as_str()) {
-                params.push(format!("security={}", security));
-            }
-
-            if let Some(network) = extra_info.get("network").and_then(|v| v.as_str()) {
-                params.push(format!("type={}", network));
-            }
-
-            if let Some(sni) = extra_info.get("sni").and_then(|v| v.as_str()) {
-                params.push(format!("sni={}", sni));
-            }
-
-            if let Some(tls) = extra_info.get("tls").and_then(|v| v.as_bool()) {
-                if tls {
-                    params.push("security=tls".to_string());
-                }
-            }
-        }
-
-        if !params.is_empty() {
-            url.push_str("#");
-            url.push_str(&params.join("&"));
-        }
-
-        Ok(url)
-    }
-
-    fn build_trojan_url(&self, proxy_info: &ProxyNodeInfo) -> Result<String> {
-        let password = proxy_info
-            .extra_info
-            .as_ref()
-            .and_then(|info| info.get("password"))
-            .and_then(|v| v.as_str())
-            .ok_or_else(|| anyhow!("Trojan需要密码"))?;
-
-        let mut url = format!(
-            "trojan://{}@{}:{}",
-            password, proxy_info.server, proxy_info.port
-        );
-
-        // 添加参数
-        let mut params = Vec::new();
-
-        if let Some(extra_info) = &proxy_info.extra_info {
-            if let Some(sni) = extra_info.get("sni").and_then(|v| v.as_str()) {
-                params.push(format!("sni={}", sni));
-            }
-
-            if let Some(tls) = extra_info.get("tls").and_then(|v| v.as_bool()) {
-                if tls {
-                    params.push("security=tls".to_string());
-                }
-            }
-        }
-
-        if !params.is_empty() {
-            url.push_str("#");
-            url.push_str(&params.join("&"));
-        }
-
-        Ok(url)
-    }
-
-    fn build_ssr_url(&self, proxy_info: &ProxyNodeInfo) -> Result<String> {
-        let cipher = proxy_info
-            .extra_info
-            .as_ref()
-            .and_then(|info| info.get("cipher"))
-            .and_then(|v| v.as_str())
-            .unwrap_or("aes-256-cfb");
-
-        let password = proxy_info
-            .extra_info
-            .as_ref()
-            .and_then(|info| info.get("password"))
-            .and_then(|v| v.as_str())
-            .ok_or_else(|| anyhow!("SSR需要密码"))?;
-
-        // SSR URL格式更复杂，这里简化处理
-        let encoded_password = urlencoding::encode(password);
-        Ok(format!(
-            "ssr://{}:{}@{}:{}",
-            cipher, encoded_password, proxy_info.server, proxy_info.port
-        ))
-    }
-
-    fn build_hysteria_url(&self, proxy_info: &ProxyNodeInfo) -> Result<String> {
-        // Hysteria协议URL构建
-        let password = proxy_info
-            .extra_info
-            .as_ref()
-            .and_then(|info| info.get("password"))
-            .and_then(|v| v.as_str())
-            .unwrap_or("");
-
-        Ok(format!(
-            "hysteria://{}@{}:{}",
-            password, proxy_info.server, proxy_info.port
-        ))
-    }
-
-    fn build_tuic_url(&self, proxy_info: &ProxyNodeInfo) -> Result<String> {
-        let password = proxy_info
-            .extra_info
-            .as_ref()
-            .and_then(|info| info.get("password"))
-            .and_then(|v| v.as_str())
-            .unwrap_or("");
-
-        let uuid = proxy_info
-            .extra_info
-            .as_ref()
-            .and_then(|info| info.get("uuid"))
-            .and_then(|v| v.as_str())
-            .unwrap_or("");
-
-        Ok(format!(
-            "tuic://{}:{}@{}:{}",
-            uuid, password, proxy_info.server, proxy_info.port
-        ))
-    }
-
-    fn build_wireguard_url(&self, proxy_info: &ProxyNodeInfo) -> Result<String> {
-        // WireGuard协议URL构建
-        Ok(format!(
-            "wireguard://{}:{}",
-            proxy_info.server, proxy_info.port
-        ))
-    }
-
     /// 更新代理状态
     async fn update_proxy_state(&self, proxy_name: &str, result: &HealthCheckResult) {
         let mut states = self.proxy_states.write().await;
@@ -645,7 +340,7 @@ impl AdvancedHealthChecker {
 
 /// 批量检查代理健康状态（简化接口）
 pub async fn check_proxies_health_batch(
-    proxies: &[ProxyNodeInfo],
+    proxies: &[ProxyNode],
     timeout_ms: u64,
     test_url: Option<String>,
     max_concurrent: usize,
@@ -656,7 +351,7 @@ pub async fn check_proxies_health_batch(
 
 /// 批量检查代理健康状态（带配置）
 pub async fn check_proxies_health_with_config(
-    proxies: &[ProxyNodeInfo],
+    proxies: &[ProxyNode],
     timeout_ms: u64,
     test_url: Option<String>,
 ) -> Vec<HealthCheckResult> {
@@ -15,13 +15,13 @@ use std::fs;
 use std::path::Path;
 use url::Url;
 
-use super::types::ProxyNodeInfo;
+use crate::proxy::ProxyNode;
 
 /// Clash配置结构
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ClashConfig {
     /// 代理列表
-    pub proxies: Vec<ProxyNodeInfo>,
+    pub proxies: Vec<ProxyNode>,
     /// 代理组
     pub proxy_groups: Option<Vec<ProxyGroup>>,
     /// DNS配置
@@ -299,269 +299,9 @@ impl ConfigParser {
     }
 
     /// 解析单个代理配置
-    fn parse_proxy_config(config: &serde_yaml::Mapping) -> Result<ProxyNodeInfo> {
-        // 获取代理类型
-        let proxy_type = config
-            .get(&Value::String("type".to_string()))
-            .and_then(|v| v.as_str())
-            .ok_or_else(|| anyhow!("代理配置缺少类型字段"))?
-            .to_string();
-
-        // 获取代理名称
-        let name = config
-            .get(&Value::String("name".to_string()))
-            .and_then(|v| v.as_str())
-            .ok_or_else(|| anyhow!("代理配置缺少名称字段"))?
-            .to_string();
-
-        // 获取服务器地址和端口
-        let server = config
-            .get(&Value::String("server".to_string()))
-            .and_then(|v| v.as_str())
-            .ok_or_else(|| anyhow!("代理配置缺少服务器字段"))?
-            .to_string();
-
-        let port = config
-            .get(&Value::String("port".to_string()))
-            .and_then(|v| v.as_u64())
-            .ok_or_else(|| anyhow!("代理配置缺少端口字段"))? as u16;
-
-        // 创建基础代理信息
-        let mut proxy_info = ProxyNodeInfo::new(name, proxy_type.clone(), server, port);
-
-        // 检查是否支持UDP
-        if let Some(udp) = config.get(&Value::String("udp".to_string())) {
-            if let Some(udp_bool) = udp.as_bool() {
-                proxy_info = proxy_info.with_udp_support(udp_bool);
-            }
-        }
-
-        // 根据代理类型解析特定字段到extra_info
-        let mut extra_info = serde_json::Map::new();
-
-        match proxy_type.as_str() {
-            "ss" | "shadowsocks" => {
-                if let Some(cipher) = config
-                    .get(&Value::String("cipher".to_string()))
-                    .and_then(|v| v.as_str())
-                {
-                    extra_info.insert(
-                        "cipher".to_string(),
-                        serde_json::Value::String(cipher.to_string()),
-                    );
-                }
-                if let Some(password) = config
-                    .get(&Value::String("password".to_string()))
-                    .and_then(|v| v.as_str())
-                {
-                    extra_info.insert(
-                        "password".to_string(),
-                        serde_json::Value::String(password.to_string()),
-                    );
-                }
-                if let Some(plugin) = config
-                    .get(&Value::String("plugin".to_string()))
-                    .and_then(|v| v.as_str())
-                {
-                    extra_info.insert(
-                        "plugin".to_string(),
-                        serde_json::Value::String(plugin.to_string()),
-                    );
-                }
-            }
-
-            "vmess" => {
-                if let Some(uuid) = config
-                    .get(&Value::String("uuid".to_string()))
-                    .and_then(|v| v.as_str())
-                {
-                    extra_info.insert(
-                        "uuid".to_string(),
-                        serde_json::Value::String(uuid.to_string()),
-                    );
-                }
-                if let Some(cipher) = config
-                    .get(&Value::String("cipher".to_string()))
-                    .and_then(|v| v.as_str())
-                {
-                    extra_info.insert(
-                        "cipher".to_string(),
-                        serde_json::Value::String(cipher.to_string()),
-                    );
-                }
-                if let Some(tls) = config
-                    .get(&Value::String("tls".to_string()))
-                    .and_then(|v| v.as_bool())
-                {
-                    extra_info.insert("tls".to_string(), serde_json::Value::Bool(tls));
-                }
-                if let Some(sni) = config
-                    .get(&Value::String("sni".to_string()))
-                    .or_else(|| config.get(&Value::String("servername".to_string())))
-                    .and_then(|v| v.as_str())
-                {
-                    extra_info.insert(
-                        "sni".to_string(),
-                        serde_json::Value::String(sni.to_string()),
-                    );
-                }
-                if let Some(network) = config
-                    .get(&Value::String("network".to_string()))
-                    .and_then(|v| v.as_str())
-                {
-                    extra_info.insert(
-                        "network".to_string(),
-                        serde_json::Value::String(network.to_string()),
-                    );
-                }
-            }
-
-            "vless" => {
-                if let Some(uuid) = config
-                    .get(&Value::String("uuid".to_string()))
-                    .and_then(|v| v.as_str())
-                {
-                    extra_info.insert(
-                        "uuid".to_string(),
-                        serde_json::Value::String(uuid.to_string()),
-                    );
-                }
-                if let Some(tls) = config
-                    .get(&Value::String("tls".to_string()))
-                    .and_then(|v| v.as_bool())
-                {
-                    extra_info.insert("tls".to_string(), serde_json::Value::Bool(tls));
-                }
-                if let Some(sni) = config
-                    .get(&Value::String("sni".to_string()))
-                    .or_else(|| config.get(&Value::String("servername".to_string())))
-                    .and_then(|v| v.as_str())
-                {
-                    extra_info.insert(
-                        "sni".to_string(),
-                        serde_json::Value::String(sni.to_string()),
-                    );
-                }
-                if let Some(network) = config
-                    .get(&Value::String("network".to_string()))
-                    .and_then(|v| v.as_str())
-                {
-                    extra_info.insert(
-                        "network".to_string(),
-                        serde_json::Value::String(network.to_string()),
-                    );
-                }
-            }
-
-            "trojan" => {
-                if let Some(password) = config
-                    .get(&Value::String("password".to_string()))
-                    .and_then(|v| v.as_str())
-                {
-                    extra_info.insert(
-                        "password".to_string(),
-                        serde_json::Value::String(password.to_string()),
-                    );
-                }
-                if let Some(tls) = config
-                    .get(&Value::String("tls".to_string()))
-                    .and_then(|v| v.as_bool())
-                {
-                    extra_info.insert("tls".to_string(), serde_json::Value::Bool(tls));
-                }
-                if let Some(sni) = config
-                    .get(&Value::String("sni".to_string()))
-                    .or_else(|| config.get(&Value::String("servername".to_string())))
-                    .and_then(|v| v.as_str())
-                {
-                    extra_info.insert(
-                        "sni".to_string(),
-                        serde_json::Value::String(sni.to_string()),
-                    );
-                }
-            }
-
-            "http" | "https" => {
-                if let Some(username) = config
-                    .get(&Value::String("username".to_string()))
-                    .and_then(|v| v.as_str())
-                {
-                    extra_info.insert(
-                        "username".to_string(),
-                        serde_json::Value::String(username.to_string()),
-                    );
-                }
-                if let Some(password) = config
-                    .get(&Value::String("password".to_string()))
-                    .and_then(|v| v.as_str())
-                {
-                    extra_info.insert(
-                        "password".to_string(),
-                        serde_json::Value::String(password.to_string()),
-                    );
-                }
-                extra_info.insert(
-                    "tls".to_string(),
-                    serde_json::Value::Bool(proxy_type == "https"),
-                );
-            }
-
-            "socks5" => {
-                if let Some(username) = config
-                    .get(&Value::String("username".to_string()))
-                    .and_then(|v| v.as_str())
-                {
-                    extra_info.insert(
-                        "username".to_string(),
-                        serde_json::Value::String(username.to_string()),
-                    );
-                }
-                if let Some(password) = config
-                    .get(&Value::String("password".to_string()))
-                    .and_then(|v| v.as_str())
-                {
-                    extra_info.insert(
-                        "password".to_string(),
-                        serde_json::Value::String(password.to_string()),
-                    );
-                }
-            }
-
-            _ => {
-                // 对于其他协议，尝试解析通用字段
-                if let Some(password) = config
-                    .get(&Value::String("password".to_string()))
-                    .and_then(|v| v.as_str())
-                {
-                    extra_info.insert(
-                        "password".to_string(),
-                        serde_json::Value::String(password.to_string()),
-                    );
-                }
-                if let Some(username) = config
-                    .get(&Value::String("username".to_string()))
-                    .and_then(|v| v.as_str())
-                {
-                    extra_info.insert(
-                        "username".to_string(),
-                        serde_json::Value::String(username.to_string()),
-                    );
-                }
-                if let Some(tls) = config
-                    .get(&Value::String("tls".to_string()))
-                    .and_then(|v| v.as_bool())
-                {
-                    extra_info.insert("tls".to_string(), serde_json::Value::Bool(tls));
-                }
-            }
-        }
-
-        // 如果有额外信息，设置到proxy_info中
-        if !extra_info.is_empty() {
-            proxy_info = proxy_info.with_extra_info(serde_json::Value::Object(extra_info));
-        }
-
-        Ok(proxy_info)
+    fn parse_proxy_config(config: &serde_yaml::Mapping) -> Result<ProxyNode> {
+        serde_yaml::from_value(Value::Mapping(config.clone()))
+            .map_err(|e| anyhow!("代理配置无效: {}", e))
     }
 
     /// 解析代理组配置
@@ -769,7 +509,7 @@ impl ConfigParser {
     }
 
     /// 获取代理列表
-    pub fn get_proxies(&self) -> Vec<ProxyNodeInfo> {
+    pub fn get_proxies(&self) -> Vec<ProxyNode> {
         self.config
             .as_ref()
             .map(|config| config.proxies.clone())
@@ -865,56 +605,7 @@ impl ConfigParser {
         if !config.proxies.is_empty() {
             let mut proxies_seq = serde_yaml::Sequence::new();
             for proxy in &config.proxies {
-                let mut proxy_map = serde_yaml::Mapping::new();
-                proxy_map.insert(
-                    serde_yaml::Value::String("name".to_string()),
-                    serde_yaml::Value::String(proxy.name.clone()),
-                );
-                proxy_map.insert(
-                    serde_yaml::Value::String("type".to_string()),
-                    serde_yaml::Value::String(proxy.proto.clone()),
-                );
-                proxy_map.insert(
-                    serde_yaml::Value::String("server".to_string()),
-                    serde_yaml::Value::String(proxy.server.clone()),
-                );
-                proxy_map.insert(
-                    serde_yaml::Value::String("port".to_string()),
-                    serde_yaml::Value::Number(serde_yaml::Number::from(proxy.port)),
-                );
-
-                if proxy.support_udp {
-                    proxy_map.insert(
-                        serde_yaml::Value::String("udp".to_string()),
-                        serde_yaml::Value::Bool(true),
-                    );
-                }
-
-                if let Some(extra_info) = &proxy.extra_info {
-                    if let serde_json::Value::Object(obj) = extra_info {
-                        for (key, value) in obj {
-                            let yaml_value = match value {
-                                serde_json::Value::String(s) => {
-                                    serde_yaml::Value::String(s.clone())
-                                }
-                                serde_json::Value::Number(n) => {
-                                    if let Some(i) = n.as_i64() {
-                                        serde_yaml::Value::Number(serde_yaml::Number::from(i))
-                                    } else if let Some(f) = n.as_f64() {
-                                        serde_yaml::Value::Number(serde_yaml::Number::from(f))
-                                    } else {
-                                        continue;
-                                    }
-                                }
-                                serde_json::Value::Bool(b) => serde_yaml::Value::Bool(*b),
-                                _ => continue,
-                            };
-                            proxy_map.insert(serde_yaml::Value::String(key.clone()), yaml_value);
-                        }
-                    }
-                }
-
-                proxies_seq.push(serde_yaml::Value::Mapping(proxy_map));
+                proxies_seq.push(serde_yaml::to_value(proxy)?);
             }
             yaml_config.insert(
                 serde_yaml::Value::String("proxies".to_string()),
@@ -2,7 +2,8 @@
 //! 实现代理健康检查和管理功能
 
 use super::health_check::{check_proxies_health, check_proxies_health_with_config};
-use super::types::ProxyNodeInfo;
+use super::types::CheckedNode;
+use crate::proxy::ProxyNode;
 use anyhow::{Result, anyhow};
 use serde_yaml;
 use std::collections::HashMap;
@@ -13,7 +14,7 @@ use std::path::Path;
 #[derive(Debug, Clone)]
 pub struct ClashProxyManager {
     config_path: String,
-    proxies: Vec<ProxyNodeInfo>,
+    proxies: Vec<CheckedNode>,
 }
 
 impl ClashProxyManager {
@@ -51,73 +52,37 @@ impl ClashProxyManager {
     }
 
     /// 获取所有代理节点信息
-    pub async fn get_all_proxy_nodes(&self) -> Result<Vec<ProxyNodeInfo>> {
+    pub async fn get_all_proxy_nodes(&self) -> Result<Vec<CheckedNode>> {
         Ok(self.proxies.clone())
     }
 
     /// 根据名称查找代理节点
-    pub fn find_proxy_by_name(&self, name: &str) -> Option<&ProxyNodeInfo> {
-        self.proxies.iter().find(|p| p.name == name)
+    pub fn find_proxy_by_name(&self, name: &str) -> Option<&CheckedNode> {
+        self.proxies.iter().find(|p| p.node.name == name)
     }
 
     /// 获取可用代理节点（延迟小于 5000ms）
-    pub fn get_available_proxies(&self) -> Vec<&ProxyNodeInfo> {
+    pub fn get_available_proxies(&self) -> Vec<&CheckedNode> {
         self.proxies.iter().filter(|p| p.is_available()).collect()
     }
 
     /// 按延迟排序代理节点
-    pub fn get_sorted_by_delay(&self) -> Vec<&ProxyNodeInfo> {
-        let mut proxies: Vec<&ProxyNodeInfo> = self.proxies.iter().collect();
+    pub fn get_sorted_by_delay(&self) -> Vec<&CheckedNode> {
+        let mut proxies: Vec<&CheckedNode> = self.proxies.iter().collect();
         proxies.sort_by(|a, b| a.delay_ms.cmp(&b.delay_ms));
         proxies
     }
 
-    /// 从 YAML 配置中提取代理节点信息
-    fn extract_proxies_from_yaml(yaml: &serde_yaml::Value) -> Result<Vec<ProxyNodeInfo>> {
+    /// 从 YAML 配置中提取代理节点信息，无效节点跳过
+    fn extract_proxies_from_yaml(yaml: &serde_yaml::Value) -> Result<Vec<CheckedNode>> {
         let mut proxies = Vec::new();
 
         // 尝试从 "proxies" 字段获取代理列表
         if let Some(proxies_value) = yaml.get("proxies") {
-            if let serde_yaml::Value::Sequence(proxy_list) = proxies_value {
-                for proxy in proxy_list {
-                    if let serde_yaml::Value::Mapping(proxy_map) = proxy {
-                        let name = proxy_map
-                            .get(&serde_yaml::Value::String("name".to_string()))
-                            .and_then(|v| v.as_str())
-                            .unwrap_or("未知节点")
-                            .to_string();
-
-                        let server = proxy_map
-                            .get(&serde_yaml::Value::String("server".to_string()))
-                            .and_then(|v| v.as_str())
-                            .unwrap_or("")
-                            .to_string();
-
-                        let port = proxy_map
-                            .get(&serde_yaml::Value::String("port".to_string()))
-                            .and_then(|v| v.as_u64())
-                            .unwrap_or(0) as u16;
-
-                        let proto = proxy_map
-                            .get(&serde_yaml::Value::String("type".to_string()))
-                            .and_then(|v| v.as_str())
-                            .unwrap_or("unknown")
-                            .to_string();
-
-                        // 检查是否支持 UDP
-                        let support_udp = proxy_map
-                            .get(&serde_yaml::Value::String("udp".to_string()))
-                            .and_then(|v| v.as_bool())
-                            .unwrap_or(false);
-
-                        // 创建代理节点信息
-                        let proto_clone = proto.clone();
-                        let proxy_info = ProxyNodeInfo::new(name, proto, server, port)
-                            .with_udp_support(support_udp)
-                            .with_node_type(proto_clone);
-
-                        proxies.push(proxy_info);
-                    }
+            for proxy in ProxyNode::from_clash_proxies(proxies_value) {
+                match proxy {
+                    Ok(node) => proxies.push(CheckedNode::new(node)),
+                    Err(e) => println!("⚠️  {}", e),
                 }
             }
         }
@@ -130,16 +95,16 @@ impl ClashProxyManager {
     }
 
     /// 执行健康检查
-    async fn check_proxies_health(proxies: &[ProxyNodeInfo]) -> Vec<ProxyNodeInfo> {
+    async fn check_proxies_health(proxies: &[CheckedNode]) -> Vec<CheckedNode> {
         // 使用默认配置进行健康检查
         check_proxies_health(proxies).await
     }
 
     /// 执行带自定义配置的健康检查
     async fn check_proxies_health_with_timeout(
-        proxies: &[ProxyNodeInfo],
+        proxies: &[CheckedNode],
         timeout_ms: u64,
-    ) -> Vec<ProxyNodeInfo> {
+    ) -> Vec<CheckedNode> {
         check_proxies_health_with_config(proxies, timeout_ms, None).await
     }
 
@@ -151,61 +116,22 @@ impl ClashProxyManager {
         let proxies: Vec<serde_yaml::Value> = self
             .proxies
             .iter()
-            .map(|proxy| {
-                let mut proxy_map = serde_yaml::Mapping::new();
-                proxy_map.insert(
-                    serde_yaml::Value::String("name".to_string()),
-                    serde_yaml::Value::String(proxy.name.clone()),
-                );
-                proxy_map.insert(
-                    serde_yaml::Value::String("type".to_string()),
-                    serde_yaml::Value::String(proxy.proto.clone()),
-                );
-                proxy_map.insert(
-                    serde_yaml::Value::String("server".to_string()),
-                    serde_yaml::Value::String(proxy.server.clone()),
-                );
-                proxy_map.insert(
-                    serde_yaml::Value::String("port".to_string()),
-                    serde_yaml::Value::Number(serde_yaml::Number::from(proxy.port)),
-                );
+            .map(|proxy| -> Result<serde_yaml::Value> {
+                let mut value = serde_yaml::to_value(&proxy.node)?;
 
                 // 添加延迟信息作为注释
                 if proxy.delay_ms > 0 {
-                    proxy_map.insert(
-                        serde_yaml::Value::String("delay".to_string()),
-                        serde_yaml::Value::String(format!("{}ms", proxy.delay_ms)),
-                    );
-                }
-
-                // 添加额外信息
-                if let Some(extra_info) = &proxy.extra_info {
-                    if let Some(obj) = extra_info.as_object() {
-                        for (key, value) in obj {
-                            let yaml_value = match value {
-                                serde_json::Value::String(s) => {
-                                    serde_yaml::Value::String(s.clone())
-                                }
-                                serde_json::Value::Number(n) => {
-                                    if let Some(i) = n.as_i64() {
-                                        serde_yaml::Value::Number(serde_yaml::Number::from(i))
-                                    } else if let Some(f) = n.as_f64() {
-                                        serde_yaml::Value::Number(serde_yaml::Number::from(f))
-                                    } else {
-                                        continue;
-                                    }
-                                }
-                                serde_json::Value::Bool(b) => serde_yaml::Value::Bool(*b),
-                                _ => continue,
-                            };
-                            proxy_map.insert(serde_yaml::Value::String(key.clone()), yaml_value);
-                        }
+                    if let serde_yaml::Value::Mapping(proxy_map) = &mut value {
+                        proxy_map.insert(
+                            serde_yaml::Value::String("delay".to_string()),
+                            serde_yaml::Value::String(format!("{}ms", proxy.delay_ms)),
+                        );
                     }
                 }
 
-                serde_yaml::Value::Mapping(proxy_map)
+                Ok(value)
             })
-            .collect();
+            .collect::<Result<_>>()?;
 
         config.insert("proxies".to_string(), serde_yaml::Value::Sequence(proxies));
 
@@ -227,7 +153,7 @@ impl ClashProxyManager {
             .proxies
             .iter()
             .filter(|p| p.is_available())
-            .map(|p| serde_yaml::Value::String(p.name.clone()))
+            .map(|p| serde_yaml::Value::String(p.node.name.clone()))
             .collect();
 
         auto_group.insert(
@@ -260,7 +186,7 @@ impl ClashProxyManager {
             .proxies
             .iter()
             .filter(|p| p.is_available())
-            .map(|p| serde_yaml::Value::String(p.name.clone()))
+            .map(|p| serde_yaml::Value::String(p.node.name.clone()))
             .collect();
 
         manual_group.insert(
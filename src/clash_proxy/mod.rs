@@ -20,7 +20,6 @@ pub use config_parser::{
 };
 pub use health_check::ProxyHealthChecker;
 pub use manager::ClashProxyManager;
-pub use types::ProxyNodeInfo;
 
 // 可选：导出内部错误类型（若需要对外暴露）
 pub type ClashProxyResult<T = ()> = anyhow::Result<T>;
@@ -1,47 +1,21 @@
-//! Clash 代理节点信息结构体定义
+//! Clash 代理节点检测结果结构体定义
 
+use crate::proxy::ProxyNode;
 use serde::{Deserialize, Serialize};
 
-/// 代理节点信息
+/// 经过健康检查的代理节点
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ProxyNodeInfo {
-    /// 节点名称
-    pub name: String,
-    /// 协议类型
-    pub proto: String,
-    /// 服务器地址
-    pub server: String,
-    /// 端口号
-    pub port: u16,
-    /// 是否支持 UDP
-    pub support_udp: bool,
-    /// 延迟（毫秒）
+pub struct CheckedNode {
+    /// 节点配置，检测前后保持不变
+    pub node: ProxyNode,
+    /// 延迟（毫秒），0 表示未检测或检测失败
     pub delay_ms: u64,
-    /// 节点类型（如：vmess, vless, trojan, ss, ssr, http, socks5 等）
-    pub node_type: String,
-    /// 额外信息（JSON 格式）
-    pub extra_info: Option<serde_json::Value>,
 }
 
-impl ProxyNodeInfo {
-    /// 创建新的代理节点信息
-    pub fn new(name: String, proto: String, server: String, port: u16) -> Self {
-        Self {
-            name,
-            proto,
-            server,
-            port,
-            support_udp: false,
-            delay_ms: 0,
-            node_type: "unknown".to_string(),
-            extra_info: None,
-        }
-    }
-
-    /// 设置是否支持 UDP
-    pub fn with_udp_support(mut self, support_udp: bool) -> Self {
-        self.support_udp = support_udp;
-        self
+impl CheckedNode {
+    /// 创建尚未检测的节点
+    pub fn new(node: ProxyNode) -> Self {
+        Self { node, delay_ms: 0 }
     }
 
     /// 设置延迟
@@ -50,21 +24,9 @@ impl ProxyNodeInfo {
         self
     }
 
-    /// 设置节点类型
-    pub fn with_node_type(mut self, node_type: String) -> Self {
-        self.node_type = node_type;
-        self
-    }
-
-    /// 设置额外信息
-    pub fn with_extra_info(mut self, extra_info: serde_json::Value) -> Self {
-        self.extra_info = Some(extra_info);
-        self
-    }
-
     /// 获取代理地址（server:port）
     pub fn get_address(&self) -> String {
-        format!("{}:{}", self.server, self.port)
+        format!("{}:{}", self.node.server, self.node.port)
     }
 
     /// 检查节点是否可用（延迟小于 5000ms）
@@ -87,3 +49,27 @@ impl ProxyNodeInfo {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::Protocol;
+    use crate::proxy::node::AuthParams;
+
+    #[test]
+    fn test_checked_node_keeps_node() {
+        let node = ProxyNode::new(
+            "socks".to_string(),
+            "example.com".to_string(),
+            1080,
+            Protocol::Socks5(AuthParams::default()),
+        );
+        let checked = CheckedNode::new(node.clone()).with_delay(120);
+        assert_eq!(checked.node, node);
+        assert_eq!(checked.get_address(), "example.com:1080");
+        assert!(checked.is_available());
+        assert_eq!(checked.get_delay_description(), "120ms (快速)");
+
+        assert!(!CheckedNode::new(node).is_available());
+    }
+}
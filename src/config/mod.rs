@@ -1,12 +1,30 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::Path;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Subscription {
     pub name: String,
+    /// 远程链接、`file://` 或本地路径（支持 `*`、`?` 通配）、`-` 表示标准输入
+    #[serde(default)]
     pub url: String,
     pub enabled: bool,
+    /// 内联的节点列表（分享链接或 Clash 配置），设置后忽略 `url`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    /// 额外的请求头
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+    /// 覆盖 `subscription_user_agent`，部分机场按 User-Agent 返回不同格式
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_agent: Option<String>,
+    /// 请求超时（秒），覆盖 `subscription_timeout`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
+    /// 获取订阅使用的上游 HTTP 代理，如 `http://127.0.0.1:7890`，覆盖 `system_proxy`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -39,21 +57,65 @@ pub struct Config {
     // 媒体解锁检测
     pub media_check: bool,
 
+    /// 经由节点发送 DNS 查询，检测是否转发 UDP
+    #[serde(default)]
+    pub udp_check: bool,
+    /// UDP 检测使用的 DNS 服务器，`host:port`
+    #[serde(default = "default_udp_check_server")]
+    pub udp_check_server: String,
+
     // 订阅配置
     pub subscriptions: Vec<Subscription>,
+    /// 同时获取的订阅数
+    #[serde(default = "default_subscription_concurrent")]
+    pub subscription_concurrent: usize,
+    /// 获取订阅失败后的重试次数，间隔按指数增长
+    #[serde(default = "default_subscription_retries")]
+    pub subscription_retries: u32,
+    /// 获取订阅的超时时间（秒）
+    #[serde(default = "default_subscription_timeout")]
+    pub subscription_timeout: u64,
+    /// 获取订阅时默认的 User-Agent
+    #[serde(default = "default_subscription_user_agent")]
+    pub subscription_user_agent: String,
+    /// 订阅在多少天内到期时提醒
+    #[serde(default = "default_subscription_expire_warn_days")]
+    pub subscription_expire_warn_days: u32,
+    /// 自有解析器解析不出节点时交给 libsubconverter 兜底
+    #[serde(default = "default_subconverter_fallback")]
+    pub subconverter_fallback: bool,
+    /// 合并订阅后去除重复节点
+    #[serde(default = "default_dedup")]
+    pub dedup: bool,
+    /// 重复节点保留的名称：first, last, shortest, longest
+    #[serde(default = "default_dedup_keep_name")]
+    pub dedup_keep_name: String,
 
     // 输出配置
     pub output_dir: String,
     pub output_format: String,
     pub generate_clash_config: bool,
     pub generate_singbox_config: bool,
+    #[serde(default)]
+    pub generate_surge_config: bool,
+    #[serde(default)]
+    pub generate_quanx_config: bool,
+    #[serde(default)]
+    pub generate_loon_config: bool,
+    #[serde(default)]
+    pub generate_stash_config: bool,
+    /// 把存活节点导出为 base64 分享链接订阅，文件中含节点凭据
+    #[serde(default)]
+    pub generate_base64_subscription: bool,
 
     // 日志配置
     pub log_level: String,
     pub log_file: Option<String>,
 
     // 代理配置
+    /// 检测以外的 HTTP 请求（如获取订阅）使用的上游代理
     pub system_proxy: Option<String>,
+    /// GitHub 加速前缀，GitHub 链接会改写为 `前缀/原链接`
     pub github_proxy: Option<String>,
 
     // 高级配置
@@ -62,6 +124,45 @@ pub struct Config {
     pub gc_threshold: usize,
 }
 
+/// 默认的 UDP 检测服务器
+pub const DEFAULT_UDP_CHECK_SERVER: &str = "8.8.8.8:53";
+
+fn default_udp_check_server() -> String {
+    DEFAULT_UDP_CHECK_SERVER.to_string()
+}
+
+fn default_subscription_concurrent() -> usize {
+    4
+}
+
+fn default_subscription_retries() -> u32 {
+    3
+}
+
+fn default_subscription_timeout() -> u64 {
+    30
+}
+
+fn default_subscription_user_agent() -> String {
+    "clash.meta".to_string()
+}
+
+fn default_subscription_expire_warn_days() -> u32 {
+    7
+}
+
+fn default_subconverter_fallback() -> bool {
+    true
+}
+
+fn default_dedup() -> bool {
+    true
+}
+
+fn default_dedup_keep_name() -> String {
+    "first".to_string()
+}
+
 impl Default for Config {
     fn default() -> Self {
         Self {
@@ -85,11 +186,26 @@ impl Default for Config {
             ),
             threshold: 0.75,
             media_check: true,
+            udp_check: false,
+            udp_check_server: default_udp_check_server(),
             subscriptions: vec![],
+            subscription_concurrent: default_subscription_concurrent(),
+            subscription_retries: default_subscription_retries(),
+            subscription_timeout: default_subscription_timeout(),
+            subscription_user_agent: default_subscription_user_agent(),
+            subscription_expire_warn_days: default_subscription_expire_warn_days(),
+            subconverter_fallback: default_subconverter_fallback(),
+            dedup: default_dedup(),
+            dedup_keep_name: default_dedup_keep_name(),
             output_dir: "./output".to_string(),
             output_format: "both".to_string(),
             generate_clash_config: true,
             generate_singbox_config: true,
+            generate_surge_config: false,
+            generate_quanx_config: false,
+            generate_loon_config: false,
+            generate_stash_config: false,
+            generate_base64_subscription: false,
             log_level: "info".to_string(),
             log_file: None,
             system_proxy: None,
@@ -0,0 +1,240 @@
+//! 离线订阅转换
+//! 读取本地文件或标准输入中的订阅，按名称筛选后导出为指定格式，全程不联网、不检测节点
+
+use crate::export::target::{ExportTarget, Rendered};
+use crate::export::write_atomic;
+use crate::proxy::ProxyNode;
+use crate::proxy::subscription::{
+    SubscriptionFormat, parse_subscription, parse_subscription_as, parse_subscription_with_fallback,
+};
+use anyhow::{Result, anyhow};
+use regex::Regex;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+/// `convert` 子命令参数
+#[derive(clap::Args, Debug)]
+pub struct ConvertArgs {
+    /// 输入文件，省略或为 `-` 时读取标准输入
+    inputs: Vec<String>,
+
+    /// 输入格式：auto, clash, singbox, sip008, links, base64, surge, quanx, subconverter；
+    /// links（别名 uri）为每行一个分享链接，base64（别名 v2ray）为 base64 编码的分享链接订阅
+    #[arg(long, default_value = "auto")]
+    from: String,
+
+    /// 自动识别失败时不使用 libsubconverter 兜底
+    #[arg(long)]
+    no_subconverter: bool,
+
+    /// 输出格式：clash, singbox, links, base64, surge, quanx, loon, stash；
+    /// links 与 base64 的含义与 `--from` 相同
+    #[arg(long, default_value = "clash")]
+    to: String,
+
+    /// 输出文件，省略时写到标准输出
+    #[arg(short = 'o', long)]
+    output: Option<String>,
+
+    /// 只保留名称匹配该正则的节点
+    #[arg(long)]
+    include: Option<String>,
+
+    /// 排除名称匹配该正则的节点
+    #[arg(long)]
+    exclude: Option<String>,
+}
+
+/// 按节点名称筛选
+#[derive(Debug, Default)]
+pub struct NodeFilter {
+    include: Option<Regex>,
+    exclude: Option<Regex>,
+}
+
+impl NodeFilter {
+    pub fn new(include: Option<&str>, exclude: Option<&str>) -> Result<Self> {
+        let compile = |pattern: Option<&str>| {
+            pattern
+                .map(|p| Regex::new(p).map_err(|e| anyhow!("正则表达式无效 {}: {}", p, e)))
+                .transpose()
+        };
+        Ok(Self {
+            include: compile(include)?,
+            exclude: compile(exclude)?,
+        })
+    }
+
+    pub fn matches(&self, node: &ProxyNode) -> bool {
+        self.include
+            .as_ref()
+            .is_none_or(|re| re.is_match(&node.name))
+            && !self
+                .exclude
+                .as_ref()
+                .is_some_and(|re| re.is_match(&node.name))
+    }
+}
+
+/// 解析 `--from`，`auto` 表示自动识别
+fn input_format(value: &str) -> Result<Option<SubscriptionFormat>> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "auto" | "" => Ok(None),
+        "clash" | "mihomo" => Ok(Some(SubscriptionFormat::Clash)),
+        "singbox" | "sing-box" => Ok(Some(SubscriptionFormat::Singbox)),
+        "sip008" => Ok(Some(SubscriptionFormat::Sip008)),
+        "links" | "uri" => Ok(Some(SubscriptionFormat::ShareLinks)),
+        "base64" | "v2ray" => Ok(Some(SubscriptionFormat::Base64Links)),
+        "surge" => Ok(Some(SubscriptionFormat::Surge)),
+        "quanx" | "quantumultx" => Ok(Some(SubscriptionFormat::QuanX)),
+        "subconverter" => Ok(Some(SubscriptionFormat::Subconverter)),
+        other => Err(anyhow!("不支持的输入格式: {}", other)),
+    }
+}
+
+/// 读取全部输入，返回 (来源, 内容)
+fn read_inputs(inputs: &[String]) -> Result<Vec<(String, String)>> {
+    if inputs.is_empty() {
+        return Ok(vec![("标准输入".to_string(), read_stdin()?)]);
+    }
+    inputs
+        .iter()
+        .map(|input| {
+            let content = if input == "-" {
+                read_stdin()?
+            } else {
+                fs::read_to_string(input).map_err(|e| anyhow!("读取 {} 失败: {}", input, e))?
+            };
+            Ok((input.clone(), content))
+        })
+        .collect()
+}
+
+fn read_stdin() -> Result<String> {
+    let mut content = String::new();
+    io::stdin()
+        .read_to_string(&mut content)
+        .map_err(|e| anyhow!("读取标准输入失败: {}", e))?;
+    Ok(content)
+}
+
+/// 解析、筛选并导出节点
+pub fn convert(
+    sources: &[(String, String)],
+    from: Option<SubscriptionFormat>,
+    subconverter_fallback: bool,
+    filter: &NodeFilter,
+    to: ExportTarget,
+) -> Result<Rendered> {
+    let mut nodes = Vec::new();
+    for (source, content) in sources {
+        let parsed = match from {
+            Some(format) => parse_subscription_as(content, format),
+            None if subconverter_fallback => parse_subscription_with_fallback(content),
+            None => parse_subscription(content),
+        };
+        // 输出可能写到标准输出，提示信息一律走标准错误
+        for e in &parsed.errors {
+            eprintln!("⚠️  跳过 {} 中的节点: {}", source, e);
+        }
+        eprintln!(
+            "📄 {}: {}，{} 个节点",
+            source,
+            parsed.format.name(),
+            parsed.nodes.len()
+        );
+        nodes.extend(parsed.nodes);
+    }
+    nodes.retain(|node| filter.matches(node));
+    to.render(&nodes)
+}
+
+/// 执行 `convert` 子命令
+pub fn run(args: ConvertArgs) -> Result<()> {
+    let from = input_format(&args.from)?;
+    let to = ExportTarget::parse(&args.to)?;
+    let filter = NodeFilter::new(args.include.as_deref(), args.exclude.as_deref())?;
+    let sources = read_inputs(&args.inputs)?;
+
+    let rendered = convert(&sources, from, !args.no_subconverter, &filter, to)?;
+    match &args.output {
+        Some(path) => write_atomic(Path::new(path), rendered.content.as_bytes())?,
+        None => io::stdout()
+            .write_all(rendered.content.as_bytes())
+            .map_err(|e| anyhow!("写入标准输出失败: {}", e))?,
+    }
+    for (name, e) in &rendered.skipped {
+        eprintln!("⚠️  跳过无法导出的节点 {}: {}", name, e);
+    }
+    eprintln!("✅ 转换完成，共 {} 个节点", rendered.count);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CLASH: &str = "proxies:\n\
+                         - {name: 香港 01, type: socks5, server: hk.example.com, port: 1080}\n\
+                         - {name: 香港 02 过期, type: socks5, server: hk.example.com, port: 1081}\n\
+                         - {name: 日本 01, type: socks5, server: jp.example.com, port: 1080}\n";
+
+    #[test]
+    fn test_convert_with_filters() {
+        let sources = [("clash.yaml".to_string(), CLASH.to_string())];
+        let filter = NodeFilter::new(Some("香港"), Some("过期")).unwrap();
+        let rendered = convert(&sources, None, true, &filter, ExportTarget::Links).unwrap();
+        assert_eq!(rendered.count, 1);
+        assert_eq!(
+            rendered.content,
+            "socks5://hk.example.com:1080#%E9%A6%99%E6%B8%AF%2001\n"
+        );
+    }
+
+    #[test]
+    fn test_convert_links_to_clash() {
+        let sources = [(
+            "links.txt".to_string(),
+            "trojan://secret@example.com:443#trojan\n".to_string(),
+        )];
+        let rendered = convert(
+            &sources,
+            input_format("links").unwrap(),
+            true,
+            &NodeFilter::default(),
+            ExportTarget::Clash,
+        )
+        .unwrap();
+        assert_eq!(rendered.count, 1);
+        let config: serde_yaml::Value = serde_yaml::from_str(&rendered.content).unwrap();
+        assert_eq!(config["proxies"][0]["type"], "trojan");
+    }
+
+    /// 同一个格式名在输入与输出两侧含义一致，转换结果可以原样读回
+    #[test]
+    fn test_format_names_round_trip() {
+        for name in ["links", "uri", "base64", "v2ray"] {
+            let to = ExportTarget::parse(name).unwrap();
+            let from = input_format(name).unwrap();
+            let expected = match to {
+                ExportTarget::Links => SubscriptionFormat::ShareLinks,
+                _ => SubscriptionFormat::Base64Links,
+            };
+            assert_eq!(from, Some(expected), "{}", name);
+
+            let sources = [("clash.yaml".to_string(), CLASH.to_string())];
+            let rendered = convert(&sources, None, true, &NodeFilter::default(), to).unwrap();
+            let sources = [(name.to_string(), rendered.content)];
+            let again = convert(&sources, from, true, &NodeFilter::default(), to).unwrap();
+            assert_eq!(again.count, 3, "{}", name);
+            assert_eq!(again.content, sources[0].1, "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_invalid_options() {
+        assert!(input_format("xml").is_err());
+        assert!(NodeFilter::new(Some("("), None).is_err());
+    }
+}
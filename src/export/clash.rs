@@ -0,0 +1,150 @@
+//! Clash（mihomo）配置生成
+//! 节点按 mihomo 字段原样写入 `proxies`，再加上与 sing-box 配置一致的选择分组和兜底规则
+
+use super::{SELECT_GROUP, TEST_URL, URL_TEST_GROUP, unique_name, write_atomic};
+use crate::proxy::ProxyNode;
+use anyhow::Result;
+use serde_yaml::{Mapping, Value};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// 输出文件名
+pub const FILE_NAME: &str = "clash.yaml";
+
+/// 生成完整配置；节点名称重复时追加序号，保证分组引用无歧义
+pub fn generate_config(nodes: &[ProxyNode]) -> Result<Value> {
+    let mut used: HashSet<String> = [SELECT_GROUP, URL_TEST_GROUP, "DIRECT", "REJECT"]
+        .into_iter()
+        .map(str::to_string)
+        .collect();
+    let mut names = Vec::new();
+    let mut proxies = Vec::new();
+    for node in nodes {
+        let mut node = node.clone();
+        node.name = unique_name(&mut used, &node.name);
+        names.push(Value::from(node.name.as_str()));
+        proxies.push(serde_yaml::to_value(&node)?);
+    }
+
+    let mut select_members = vec![Value::from(URL_TEST_GROUP)];
+    select_members.extend(names.iter().cloned());
+    select_members.push(Value::from("DIRECT"));
+    // url-test 分组不能为空，没有节点时退回直连
+    if names.is_empty() {
+        names.push(Value::from("DIRECT"));
+    }
+
+    let select = mapping([
+        ("name", Value::from(SELECT_GROUP)),
+        ("type", Value::from("select")),
+        ("proxies", Value::Sequence(select_members)),
+    ]);
+    let url_test = mapping([
+        ("name", Value::from(URL_TEST_GROUP)),
+        ("type", Value::from("url-test")),
+        ("proxies", Value::Sequence(names)),
+        ("url", Value::from(TEST_URL)),
+        ("interval", Value::from(300)),
+    ]);
+
+    Ok(mapping([
+        ("mixed-port", Value::from(7890)),
+        ("allow-lan", Value::from(false)),
+        ("mode", Value::from("rule")),
+        ("log-level", Value::from("info")),
+        ("proxies", Value::Sequence(proxies)),
+        ("proxy-groups", Value::Sequence(vec![select, url_test])),
+        (
+            "rules",
+            Value::Sequence(vec![Value::from(format!("MATCH,{}", SELECT_GROUP))]),
+        ),
+    ]))
+}
+
+/// 生成配置并原子写入 `output_dir/clash.yaml`
+pub fn save_config(nodes: &[ProxyNode], output_dir: &Path) -> Result<PathBuf> {
+    let path = output_dir.join(FILE_NAME);
+    let content = serde_yaml::to_string(&generate_config(nodes)?)?;
+    write_atomic(&path, content.as_bytes())?;
+    Ok(path)
+}
+
+/// 按给定顺序构造 YAML 映射
+fn mapping<const N: usize>(entries: [(&str, Value); N]) -> Value {
+    Value::Mapping(
+        entries
+            .into_iter()
+            .map(|(key, value)| (Value::from(key), value))
+            .collect::<Mapping>(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::Protocol;
+    use crate::proxy::node::AuthParams;
+
+    fn socks_node(name: &str) -> ProxyNode {
+        ProxyNode::new(
+            name.to_string(),
+            "example.com".to_string(),
+            1080,
+            Protocol::Socks5(AuthParams::default()),
+        )
+    }
+
+    #[test]
+    fn test_generate_config() {
+        let config = generate_config(&[socks_node("a"), socks_node("a")]).unwrap();
+        let yaml = serde_yaml::to_string(&config).unwrap();
+        assert_eq!(
+            yaml,
+            "mixed-port: 7890
+allow-lan: false
+mode: rule
+log-level: info
+proxies:
+- name: a
+  server: example.com
+  port: 1080
+  type: socks5
+- name: a 2
+  server: example.com
+  port: 1080
+  type: socks5
+proxy-groups:
+- name: 节点选择
+  type: select
+  proxies:
+  - 自动选择
+  - a
+  - a 2
+  - DIRECT
+- name: 自动选择
+  type: url-test
+  proxies:
+  - a
+  - a 2
+  url: http://www.gstatic.com/generate_204
+  interval: 300
+rules:
+- MATCH,节点选择
+"
+        );
+
+        // 写出的节点可以被重新读取
+        let nodes = ProxyNode::from_clash_proxies(&config["proxies"]);
+        assert_eq!(nodes.len(), 2);
+        assert_eq!(nodes[1].as_ref().unwrap().name, "a 2");
+    }
+
+    #[test]
+    fn test_empty_config_falls_back_to_direct() {
+        let config = generate_config(&[]).unwrap();
+        assert_eq!(
+            config["proxy-groups"][1]["proxies"],
+            Value::Sequence(vec![Value::from("DIRECT")])
+        );
+    }
+}
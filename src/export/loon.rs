@@ -0,0 +1,300 @@
+//! Loon 节点列表生成
+//! 输出 `[Proxy]` 段格式的节点行，可作为远程节点订阅导入；
+//! Loon 不支持的协议（TUIC、AnyTLS、SOCKS4）与传输（gRPC、HTTP/2）写成注释
+
+use super::{Fields, bandwidth_mbps, proxy_list, write_atomic};
+use crate::outbound::plugin::Plugin;
+use crate::outbound::wireguard::parse_reserved;
+use crate::proxy::node::{
+    AuthParams, Hysteria2Params, Network, ShadowsocksParams, SsrParams, TrojanParams, VlessParams,
+    VmessParams, WireGuardParams,
+};
+use crate::proxy::{Protocol, ProxyNode};
+use anyhow::{Result, anyhow};
+use std::path::{Path, PathBuf};
+
+/// 输出文件名
+pub const FILE_NAME: &str = "loon.list";
+
+/// 把单个节点转换为 `[Proxy]` 行
+pub fn proxy_line(node: &ProxyNode) -> Result<String> {
+    let mut fields = Fields::default();
+    match &node.protocol {
+        Protocol::Shadowsocks(params) => shadowsocks(&mut fields, node, params)?,
+        Protocol::Ssr(params) => ssr(&mut fields, node, params),
+        Protocol::Vmess(params) => vmess(&mut fields, node, params)?,
+        Protocol::Vless(params) => vless(&mut fields, node, params)?,
+        Protocol::Trojan(params) => trojan(&mut fields, node, params)?,
+        Protocol::Hysteria2(params) => hysteria2(&mut fields, node, params)?,
+        Protocol::WireGuard(params) => wireguard(&mut fields, node, params)?,
+        Protocol::Http(auth) => http(&mut fields, node, auth),
+        Protocol::Socks5(auth) => socks5(&mut fields, node, auth),
+        _ => return Err(anyhow!("Loon 不支持的协议: {}", node.kind())),
+    }
+    fields.set_flag("fast-open", node.dial.tfo);
+    Ok(format!("{} = {}", node.name, fields.join(",")))
+}
+
+/// 生成节点列表
+pub fn generate_config(nodes: &[ProxyNode]) -> String {
+    proxy_list(nodes, proxy_line)
+}
+
+/// 生成节点列表并原子写入 `output_dir/loon.list`
+pub fn save_config(nodes: &[ProxyNode], output_dir: &Path) -> Result<PathBuf> {
+    let path = output_dir.join(FILE_NAME);
+    write_atomic(&path, generate_config(nodes).as_bytes())?;
+    Ok(path)
+}
+
+/// 类型、服务器与端口三个位置参数
+fn head(fields: &mut Fields, node: &ProxyNode, kind: &str) {
+    fields.push(kind);
+    fields.push(&node.server);
+    fields.push(node.port);
+}
+
+/// 密码类位置参数需要加引号
+fn quoted(value: &str) -> String {
+    format!("\"{}\"", value)
+}
+
+fn udp(fields: &mut Fields, node: &ProxyNode) {
+    fields.set_flag("udp", node.udp);
+}
+
+fn tls(fields: &mut Fields, node: &ProxyNode) {
+    fields.set_opt("sni", node.tls.server_name());
+    fields.set_flag("skip-cert-verify", node.tls.skip_cert_verify);
+}
+
+/// Loon 支持 TCP、WebSocket 与 HTTP 伪装
+fn transport(fields: &mut Fields, node: &ProxyNode) -> Result<()> {
+    let network = node.transport.network.unwrap_or(Network::Tcp);
+    match network {
+        Network::Tcp | Network::Ws | Network::Http => fields.set("transport", network.as_str()),
+        _ => return Err(anyhow!("Loon 不支持 {} 传输", network.as_str())),
+    }
+    fields.set_opt("path", node.transport.path());
+    fields.set_opt("host", node.transport.host());
+    Ok(())
+}
+
+fn shadowsocks(fields: &mut Fields, node: &ProxyNode, params: &ShadowsocksParams) -> Result<()> {
+    head(fields, node, "Shadowsocks");
+    fields.push(&params.cipher);
+    fields.push(quoted(&params.password));
+    match Plugin::from_params(params)? {
+        Plugin::None => {}
+        Plugin::Obfs { mode, host } => {
+            fields.set("obfs-name", mode.as_str());
+            fields.set("obfs-host", host);
+        }
+        Plugin::V2ray(_) => return Err(anyhow!("Loon 不支持 v2ray-plugin")),
+    }
+    udp(fields, node);
+    Ok(())
+}
+
+fn ssr(fields: &mut Fields, node: &ProxyNode, params: &SsrParams) {
+    head(fields, node, "ShadowsocksR");
+    fields.push(&params.cipher);
+    fields.push(quoted(&params.password));
+    fields.set("protocol", &params.protocol);
+    fields.set_opt("protocol-param", params.protocol_param.as_ref());
+    fields.set("obfs", &params.obfs);
+    fields.set_opt("obfs-param", params.obfs_param.as_ref());
+    udp(fields, node);
+}
+
+fn vmess(fields: &mut Fields, node: &ProxyNode, params: &VmessParams) -> Result<()> {
+    head(fields, node, "vmess");
+    fields.push(params.cipher.as_deref().unwrap_or("auto"));
+    fields.push(quoted(&params.uuid));
+    transport(fields, node)?;
+    fields.set("alterId", params.alter_id.unwrap_or(0));
+    if node.tls.tls == Some(true) {
+        fields.set("over-tls", true);
+        tls(fields, node);
+    }
+    udp(fields, node);
+    Ok(())
+}
+
+fn vless(fields: &mut Fields, node: &ProxyNode, params: &VlessParams) -> Result<()> {
+    head(fields, node, "VLESS");
+    fields.push(quoted(&params.uuid));
+    transport(fields, node)?;
+    fields.set_opt("flow", params.flow.as_ref());
+    if let Some(reality) = &node.tls.reality_opts {
+        fields.set("public-key", quoted(&reality.public_key));
+        fields.set_opt("short-id", reality.short_id.as_ref());
+    }
+    if node.tls.tls == Some(true) {
+        fields.set("over-tls", true);
+        tls(fields, node);
+    }
+    udp(fields, node);
+    Ok(())
+}
+
+fn trojan(fields: &mut Fields, node: &ProxyNode, params: &TrojanParams) -> Result<()> {
+    if params.ss_opts.as_ref().and_then(|opts| opts.enabled) == Some(true) {
+        return Err(anyhow!("Loon 不支持 Trojan-Go 的 ss-opts"));
+    }
+    head(fields, node, "trojan");
+    fields.push(quoted(&params.password));
+    transport(fields, node)?;
+    tls(fields, node);
+    udp(fields, node);
+    Ok(())
+}
+
+fn hysteria2(fields: &mut Fields, node: &ProxyNode, params: &Hysteria2Params) -> Result<()> {
+    head(fields, node, "Hysteria2");
+    fields.push(quoted(&params.password));
+    match params.obfs.as_deref() {
+        None => {}
+        Some("salamander") => fields.set_opt("salamander-password", params.obfs_password.as_ref()),
+        Some(obfs) => return Err(anyhow!("Loon 不支持 Hysteria2 混淆: {}", obfs)),
+    }
+    tls(fields, node);
+    fields.set_opt("download-bandwidth", bandwidth_mbps(&params.down));
+    udp(fields, node);
+    Ok(())
+}
+
+fn wireguard(fields: &mut Fields, node: &ProxyNode, params: &WireGuardParams) -> Result<()> {
+    fields.push("WireGuard");
+    fields.set_opt("interface-ip", params.ip.as_ref());
+    fields.set_opt("interface-ipV6", params.ipv6.as_ref());
+    fields.set("private-key", &params.private_key);
+    fields.set_opt("mtu", params.mtu);
+    fields.set_opt("dns", params.dns.as_ref().and_then(|dns| dns.first()));
+    fields.set_opt("keepalive", params.persistent_keepalive);
+
+    let mut peer = Fields::default();
+    peer.set("public-key", &params.public_key);
+    let allowed_ips = params
+        .allowed_ips
+        .as_ref()
+        .map(|ips| ips.join(","))
+        .unwrap_or_else(|| "0.0.0.0/0,::/0".to_string());
+    peer.set("allowed-ips", quoted(&allowed_ips));
+    peer.set("endpoint", format!("{}:{}", node.server, node.port));
+    peer.set_opt("preshared-key", params.pre_shared_key.as_ref());
+    if let Some(reserved) = &params.reserved {
+        let [a, b, c] = parse_reserved(reserved)?;
+        peer.set("reserved", format!("[{},{},{}]", a, b, c));
+    }
+    fields.set("peers", format!("[{{{}}}]", peer.join(",")));
+    Ok(())
+}
+
+fn http(fields: &mut Fields, node: &ProxyNode, auth: &AuthParams) {
+    let secure = node.tls.tls == Some(true);
+    head(fields, node, if secure { "https" } else { "http" });
+    credentials(fields, auth);
+    if secure {
+        tls(fields, node);
+    }
+}
+
+fn socks5(fields: &mut Fields, node: &ProxyNode, auth: &AuthParams) {
+    head(fields, node, "socks5");
+    credentials(fields, auth);
+    if node.tls.tls == Some(true) {
+        fields.set("over-tls", true);
+        tls(fields, node);
+    }
+    udp(fields, node);
+}
+
+fn credentials(fields: &mut Fields, auth: &AuthParams) {
+    if let Some(username) = &auth.username {
+        fields.push(username);
+        fields.push(quoted(auth.password.as_deref().unwrap_or_default()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::node::{RealityOpts, TuicParams};
+    use serde_json::json;
+
+    fn node(name: &str, protocol: Protocol) -> ProxyNode {
+        ProxyNode::new(name.to_string(), "example.com".to_string(), 443, protocol)
+    }
+
+    #[test]
+    fn test_vless_reality() {
+        let mut proxy = node(
+            "vless",
+            Protocol::Vless(VlessParams {
+                uuid: "b831381d-6324-4d53-ad4f-8cda48b30811".to_string(),
+                flow: Some("xtls-rprx-vision".to_string()),
+                ..Default::default()
+            }),
+        );
+        proxy.tls.tls = Some(true);
+        proxy.tls.servername = Some("www.microsoft.com".to_string());
+        proxy.tls.reality_opts = Some(RealityOpts {
+            public_key: "pubkey".to_string(),
+            short_id: Some("abcd".to_string()),
+        });
+        proxy.udp = Some(true);
+        assert_eq!(
+            proxy_line(&proxy).unwrap(),
+            "vless = VLESS,example.com,443,\"b831381d-6324-4d53-ad4f-8cda48b30811\",\
+             transport=tcp,flow=xtls-rprx-vision,public-key=\"pubkey\",short-id=abcd,\
+             over-tls=true,sni=www.microsoft.com,udp=true"
+        );
+    }
+
+    #[test]
+    fn test_ssr() {
+        let proxy = node(
+            "ssr",
+            Protocol::Ssr(SsrParams {
+                cipher: "aes-256-cfb".to_string(),
+                password: "secret".to_string(),
+                obfs: "tls1.2_ticket_auth".to_string(),
+                protocol: "auth_aes128_md5".to_string(),
+                obfs_param: Some("bing.com".to_string()),
+                ..Default::default()
+            }),
+        );
+        assert_eq!(
+            proxy_line(&proxy).unwrap(),
+            "ssr = ShadowsocksR,example.com,443,aes-256-cfb,\"secret\",\
+             protocol=auth_aes128_md5,obfs=tls1.2_ticket_auth,obfs-param=bing.com"
+        );
+    }
+
+    #[test]
+    fn test_wireguard() {
+        let proxy = node(
+            "wg",
+            Protocol::WireGuard(WireGuardParams {
+                private_key: "private".to_string(),
+                public_key: "public".to_string(),
+                ip: Some("172.16.0.2".to_string()),
+                reserved: Some(json!([1, 2, 3])),
+                ..Default::default()
+            }),
+        );
+        assert_eq!(
+            proxy_line(&proxy).unwrap(),
+            "wg = WireGuard,interface-ip=172.16.0.2,private-key=private,\
+             peers=[{public-key=public,allowed-ips=\"0.0.0.0/0,::/0\",\
+             endpoint=example.com:443,reserved=[1,2,3]}]"
+        );
+    }
+
+    #[test]
+    fn test_unsupported_protocol() {
+        let tuic = node("tuic", Protocol::Tuic(TuicParams::default()));
+        assert!(proxy_line(&tuic).is_err());
+    }
+}
@@ -0,0 +1,164 @@
+//! 检测结果导出
+//! 把节点写成各客户端可直接使用的配置文件
+
+pub mod clash;
+pub mod loon;
+pub mod quanx;
+pub mod results;
+pub mod singbox;
+pub mod stash;
+pub mod surge;
+pub mod target;
+
+pub use results::ResultsWriter;
+
+use crate::outbound::hysteria2::parse_bandwidth;
+use crate::proxy::ProxyNode;
+use crate::proxy::node::Bandwidth;
+use anyhow::{Result, anyhow};
+use std::collections::HashSet;
+use std::fmt::Display;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+/// 手动选择分组
+pub const SELECT_GROUP: &str = "节点选择";
+/// 按延迟自动选择分组
+pub const URL_TEST_GROUP: &str = "自动选择";
+/// 自动选择分组的测速地址
+pub const TEST_URL: &str = "http://www.gstatic.com/generate_204";
+
+/// 原子写入：先写同目录下的临时文件再重命名，读取方不会看到写了一半的内容
+pub fn write_atomic(path: &Path, content: &[u8]) -> Result<()> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    fs::create_dir_all(dir).map_err(|e| anyhow!("创建输出目录失败 {}: {}", dir.display(), e))?;
+
+    let mut file = tempfile::NamedTempFile::new_in(dir)
+        .map_err(|e| anyhow!("创建临时文件失败 {}: {}", dir.display(), e))?;
+    file.write_all(content)
+        .and_then(|_| file.as_file().sync_all())
+        .map_err(|e| anyhow!("写入临时文件失败: {}", e))?;
+    file.persist(path)
+        .map_err(|e| anyhow!("写入 {} 失败: {}", path.display(), e.error))?;
+    Ok(())
+}
+
+/// 生成不重复的名称，重名时追加序号
+pub fn unique_name(used: &mut HashSet<String>, name: &str) -> String {
+    let base = if name.is_empty() { "proxy" } else { name };
+    let mut unique = base.to_string();
+    let mut index = 2;
+    while !used.insert(unique.clone()) {
+        unique = format!("{} {}", base, index);
+        index += 1;
+    }
+    unique
+}
+
+/// 带宽换算为 Mbps
+pub fn bandwidth_mbps(bandwidth: &Option<Bandwidth>) -> Option<u64> {
+    bandwidth
+        .as_ref()
+        .and_then(|b| parse_bandwidth(&b.to_string()))
+        .map(|bytes| bytes * 8 / 1_000_000)
+}
+
+/// 按行书写的节点列表（Surge / Loon 的 policy-path、Quantumult X 的 server_remote）
+///
+/// 客户端无法表示的节点写成 `#` 注释行并注明原因，不影响其余节点
+pub fn proxy_list(nodes: &[ProxyNode], line: impl Fn(&ProxyNode) -> Result<String>) -> String {
+    let mut used = HashSet::new();
+    let mut content = String::new();
+    for node in nodes {
+        let mut node = node.clone();
+        // 逗号与等号是这些格式的字段分隔符，换成全角字符
+        let name = node.name.replace(',', "，").replace('=', "＝");
+        node.name = unique_name(&mut used, &name);
+        match line(&node) {
+            Ok(line) => content.push_str(&line),
+            Err(e) => content.push_str(&format!("# 已跳过 {}: {}", node.name, e)),
+        }
+        content.push('\n');
+    }
+    content
+}
+
+/// 节点列表中按顺序拼接的字段，位置参数与 `key=value` 参数混用
+#[derive(Debug, Default)]
+pub struct Fields(Vec<String>);
+
+impl Fields {
+    /// 位置参数
+    pub fn push(&mut self, value: impl Display) {
+        self.0.push(value.to_string());
+    }
+
+    pub fn set(&mut self, key: &str, value: impl Display) {
+        self.0.push(format!("{}={}", key, value));
+    }
+
+    /// 空值不输出
+    pub fn set_opt(&mut self, key: &str, value: Option<impl Display>) {
+        if let Some(value) = value.map(|v| v.to_string()).filter(|v| !v.is_empty()) {
+            self.set(key, value);
+        }
+    }
+
+    /// 仅在开启时输出 `key=true`
+    pub fn set_flag(&mut self, key: &str, value: Option<bool>) {
+        if value == Some(true) {
+            self.set(key, true);
+        }
+    }
+
+    pub fn join(&self, separator: &str) -> String {
+        self.0.join(separator)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_atomic_replaces_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested").join("out.txt");
+        write_atomic(&path, b"first").unwrap();
+        write_atomic(&path, b"second").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "second");
+        // 临时文件已被重命名，目录中只剩目标文件
+        assert_eq!(fs::read_dir(path.parent().unwrap()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_unique_name() {
+        let mut used = HashSet::new();
+        assert_eq!(unique_name(&mut used, "a"), "a");
+        assert_eq!(unique_name(&mut used, "a"), "a 2");
+        assert_eq!(unique_name(&mut used, "a"), "a 3");
+        assert_eq!(unique_name(&mut used, ""), "proxy");
+    }
+
+    #[test]
+    fn test_proxy_list_annotates_failures() {
+        use crate::proxy::Protocol;
+
+        let node = |name: &str, protocol| {
+            ProxyNode::new(name.to_string(), "example.com".to_string(), 1080, protocol)
+        };
+        let nodes = [
+            node("a,b=c", Protocol::Socks5(Default::default())),
+            node("direct", Protocol::Direct),
+        ];
+        let content = proxy_list(&nodes, |node| match node.protocol {
+            Protocol::Direct => Err(anyhow!("不支持")),
+            _ => Ok(format!("{} = socks5", node.name)),
+        });
+        assert_eq!(content, "a，b＝c = socks5\n# 已跳过 direct: 不支持\n");
+    }
+}
@@ -0,0 +1,265 @@
+//! Quantumult X 节点列表生成
+//! 输出 `[server_local]` 格式的节点行，可作为 `server_remote` 资源引用；
+//! Quantumult X 不支持的协议（Hysteria2、TUIC、AnyTLS、WireGuard 等）写成注释
+
+use super::{Fields, proxy_list, write_atomic};
+use crate::outbound::plugin::Plugin;
+use crate::proxy::node::{
+    AuthParams, Network, ShadowsocksParams, SsrParams, TrojanParams, VlessParams, VmessParams,
+};
+use crate::proxy::{Protocol, ProxyNode};
+use anyhow::{Result, anyhow};
+use std::path::{Path, PathBuf};
+
+/// 输出文件名
+pub const FILE_NAME: &str = "quanx.list";
+
+/// 把单个节点转换为 `[server_local]` 行
+pub fn proxy_line(node: &ProxyNode) -> Result<String> {
+    let mut fields = Fields::default();
+    match &node.protocol {
+        Protocol::Shadowsocks(params) => shadowsocks(&mut fields, node, params)?,
+        Protocol::Ssr(params) => ssr(&mut fields, node, params),
+        Protocol::Vmess(params) => vmess(&mut fields, node, params)?,
+        Protocol::Vless(params) => vless(&mut fields, node, params)?,
+        Protocol::Trojan(params) => trojan(&mut fields, node, params)?,
+        Protocol::Http(auth) => auth_proxy(&mut fields, node, auth, "http"),
+        Protocol::Socks5(auth) => auth_proxy(&mut fields, node, auth, "socks5"),
+        _ => return Err(anyhow!("Quantumult X 不支持的协议: {}", node.kind())),
+    }
+    fields.set_flag("fast-open", node.dial.tfo);
+    fields.set_flag("udp-relay", node.udp);
+    fields.set("tag", &node.name);
+    Ok(fields.join(", "))
+}
+
+/// 生成节点列表
+pub fn generate_config(nodes: &[ProxyNode]) -> String {
+    proxy_list(nodes, proxy_line)
+}
+
+/// 生成节点列表并原子写入 `output_dir/quanx.list`
+pub fn save_config(nodes: &[ProxyNode], output_dir: &Path) -> Result<PathBuf> {
+    let path = output_dir.join(FILE_NAME);
+    write_atomic(&path, generate_config(nodes).as_bytes())?;
+    Ok(path)
+}
+
+/// 首个字段为 `类型=服务器:端口`
+fn head(fields: &mut Fields, node: &ProxyNode, kind: &str) {
+    fields.set(kind, format!("{}:{}", node.server, node.port));
+}
+
+fn tls(fields: &mut Fields, node: &ProxyNode) {
+    fields.set_opt("tls-host", node.tls.server_name());
+    if node.tls.skip_cert_verify == Some(true) {
+        fields.set("tls-verification", false);
+    }
+}
+
+/// VMess / VLESS 的传输层统一写成 `obfs` 参数
+fn obfs(fields: &mut Fields, node: &ProxyNode) -> Result<()> {
+    let secure = node.tls.tls == Some(true);
+    let network = node.transport.network.unwrap_or(Network::Tcp);
+    let obfs = match (network, secure) {
+        (Network::Tcp, false) => None,
+        (Network::Tcp, true) => Some("over-tls"),
+        (Network::Ws, false) => Some("ws"),
+        (Network::Ws, true) => Some("wss"),
+        (Network::Http, false) => Some("http"),
+        _ => {
+            return Err(anyhow!("Quantumult X 不支持 {} 传输", network.as_str()));
+        }
+    };
+    fields.set_opt("obfs", obfs);
+    if network != Network::Tcp {
+        fields.set_opt(
+            "obfs-host",
+            node.transport.host().or(node.tls.server_name()),
+        );
+        fields.set_opt("obfs-uri", node.transport.path());
+    }
+    if secure {
+        tls(fields, node);
+    }
+    Ok(())
+}
+
+fn shadowsocks(fields: &mut Fields, node: &ProxyNode, params: &ShadowsocksParams) -> Result<()> {
+    head(fields, node, "shadowsocks");
+    fields.set("method", &params.cipher);
+    fields.set("password", &params.password);
+    match Plugin::from_params(params)? {
+        Plugin::None => {}
+        Plugin::Obfs { mode, host } => {
+            fields.set("obfs", mode.as_str());
+            fields.set("obfs-host", host);
+        }
+        Plugin::V2ray(options) => {
+            fields.set("obfs", if options.tls { "wss" } else { "ws" });
+            fields.set("obfs-host", &options.host);
+            fields.set("obfs-uri", &options.path);
+        }
+    }
+    Ok(())
+}
+
+fn ssr(fields: &mut Fields, node: &ProxyNode, params: &SsrParams) {
+    head(fields, node, "shadowsocks");
+    fields.set("method", &params.cipher);
+    fields.set("password", &params.password);
+    fields.set("ssr-protocol", &params.protocol);
+    fields.set_opt("ssr-protocol-param", params.protocol_param.as_ref());
+    fields.set("obfs", &params.obfs);
+    fields.set_opt("obfs-host", params.obfs_param.as_ref());
+}
+
+/// VMess 加密方式只支持 chacha20-ietf-poly1305、aes-128-gcm 与 none
+fn vmess_method(cipher: Option<&str>) -> &'static str {
+    match cipher {
+        Some("aes-128-gcm") => "aes-128-gcm",
+        Some("none" | "zero") => "none",
+        _ => "chacha20-ietf-poly1305",
+    }
+}
+
+fn vmess(fields: &mut Fields, node: &ProxyNode, params: &VmessParams) -> Result<()> {
+    head(fields, node, "vmess");
+    fields.set("method", vmess_method(params.cipher.as_deref()));
+    fields.set("password", &params.uuid);
+    obfs(fields, node)?;
+    // alterId 不为 0 时使用旧版 MD5 认证
+    if params.alter_id.unwrap_or(0) != 0 {
+        fields.set("aead", false);
+    }
+    Ok(())
+}
+
+fn vless(fields: &mut Fields, node: &ProxyNode, params: &VlessParams) -> Result<()> {
+    head(fields, node, "vless");
+    fields.set("method", "none");
+    fields.set("password", &params.uuid);
+    obfs(fields, node)?;
+    fields.set_opt("vless-flow", params.flow.as_ref());
+    if let Some(reality) = &node.tls.reality_opts {
+        fields.set("reality-base64-pubkey", &reality.public_key);
+        fields.set_opt("reality-hex-shortid", reality.short_id.as_ref());
+    }
+    Ok(())
+}
+
+fn trojan(fields: &mut Fields, node: &ProxyNode, params: &TrojanParams) -> Result<()> {
+    if params.ss_opts.as_ref().and_then(|opts| opts.enabled) == Some(true) {
+        return Err(anyhow!("Quantumult X 不支持 Trojan-Go 的 ss-opts"));
+    }
+    head(fields, node, "trojan");
+    fields.set("password", &params.password);
+    match node.transport.network.unwrap_or(Network::Tcp) {
+        Network::Tcp => fields.set("over-tls", true),
+        Network::Ws => {
+            fields.set("obfs", "wss");
+            fields.set_opt(
+                "obfs-host",
+                node.transport.host().or(node.tls.server_name()),
+            );
+            fields.set_opt("obfs-uri", node.transport.path());
+        }
+        network => {
+            return Err(anyhow!("Quantumult X 不支持 {} 传输", network.as_str()));
+        }
+    }
+    tls(fields, node);
+    Ok(())
+}
+
+fn auth_proxy(fields: &mut Fields, node: &ProxyNode, auth: &AuthParams, kind: &str) {
+    head(fields, node, kind);
+    fields.set_opt("username", auth.username.as_ref());
+    fields.set_opt("password", auth.password.as_ref());
+    if node.tls.tls == Some(true) {
+        fields.set("over-tls", true);
+        tls(fields, node);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::node::{Hysteria2Params, WsOpts};
+    use std::collections::BTreeMap;
+
+    fn node(name: &str, protocol: Protocol) -> ProxyNode {
+        ProxyNode::new(name.to_string(), "example.com".to_string(), 443, protocol)
+    }
+
+    #[test]
+    fn test_vmess_wss() {
+        let mut proxy = node(
+            "vmess",
+            Protocol::Vmess(VmessParams {
+                uuid: "b831381d-6324-4d53-ad4f-8cda48b30811".to_string(),
+                alter_id: Some(64),
+                cipher: Some("auto".to_string()),
+                ..Default::default()
+            }),
+        );
+        proxy.tls.tls = Some(true);
+        proxy.tls.skip_cert_verify = Some(true);
+        proxy.transport.network = Some(Network::Ws);
+        proxy.transport.ws_opts = Some(WsOpts {
+            path: Some("/ws".to_string()),
+            headers: Some(BTreeMap::from([(
+                "Host".to_string(),
+                "cdn.example.com".to_string(),
+            )])),
+            ..Default::default()
+        });
+        assert_eq!(
+            proxy_line(&proxy).unwrap(),
+            "vmess=example.com:443, method=chacha20-ietf-poly1305, \
+             password=b831381d-6324-4d53-ad4f-8cda48b30811, obfs=wss, \
+             obfs-host=cdn.example.com, obfs-uri=/ws, tls-verification=false, aead=false, \
+             tag=vmess"
+        );
+    }
+
+    #[test]
+    fn test_trojan_and_http() {
+        let mut trojan = node(
+            "trojan",
+            Protocol::Trojan(TrojanParams {
+                password: "secret".to_string(),
+                ..Default::default()
+            }),
+        );
+        trojan.tls.sni = Some("example.org".to_string());
+        trojan.udp = Some(true);
+        assert_eq!(
+            proxy_line(&trojan).unwrap(),
+            "trojan=example.com:443, password=secret, over-tls=true, tls-host=example.org, \
+             udp-relay=true, tag=trojan"
+        );
+
+        let http = node(
+            "http",
+            Protocol::Http(AuthParams {
+                username: Some("user".to_string()),
+                password: Some("pass".to_string()),
+                ..Default::default()
+            }),
+        );
+        assert_eq!(
+            proxy_line(&http).unwrap(),
+            "http=example.com:443, username=user, password=pass, tag=http"
+        );
+    }
+
+    #[test]
+    fn test_unsupported_protocol() {
+        let proxy = node("hy2", Protocol::Hysteria2(Hysteria2Params::default()));
+        assert_eq!(
+            generate_config(&[proxy]),
+            "# 已跳过 hy2: Quantumult X 不支持的协议: hysteria2\n"
+        );
+    }
+}
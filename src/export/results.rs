@@ -0,0 +1,284 @@
+//! 检测结果写入
+//! 按 `output_format` 把全部检测结果及订阅的流量与到期信息写成 JSON / YAML，
+//! 并按开关把存活节点导出为 base64 订阅及各客户端配置；所有文件均原子写入
+
+use super::{clash, loon, quanx, singbox, stash, surge, write_atomic};
+use crate::check::CheckResult;
+use crate::config::Config;
+use crate::proxy::ProxyNode;
+use crate::proxy::share_link::ShareLinks;
+use crate::proxy::userinfo::SubscriptionUsage;
+use anyhow::{Result, anyhow};
+use std::path::PathBuf;
+
+/// JSON 结果文件名
+pub const JSON_FILE: &str = "results.json";
+/// YAML 结果文件名
+pub const YAML_FILE: &str = "results.yaml";
+/// base64 订阅文件名，v2rayN / Shadowrocket 可直接导入
+pub const SUBSCRIPTION_FILE: &str = "base64.txt";
+/// 订阅流量与到期信息的 JSON 文件名
+pub const USAGE_JSON_FILE: &str = "subscriptions.json";
+/// 订阅流量与到期信息的 YAML 文件名
+pub const USAGE_YAML_FILE: &str = "subscriptions.yaml";
+
+/// 检测结果的输出格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Json,
+    Yaml,
+    Both,
+}
+
+impl OutputFormat {
+    /// 解析配置中的 `output_format`
+    pub fn parse(value: &str) -> Result<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "json" => Ok(OutputFormat::Json),
+            "yaml" | "yml" => Ok(OutputFormat::Yaml),
+            "both" | "" => Ok(OutputFormat::Both),
+            other => Err(anyhow!("不支持的输出格式: {}", other)),
+        }
+    }
+
+    fn json(self) -> bool {
+        matches!(self, OutputFormat::Json | OutputFormat::Both)
+    }
+
+    fn yaml(self) -> bool {
+        matches!(self, OutputFormat::Yaml | OutputFormat::Both)
+    }
+}
+
+/// 检测结果写入器
+#[derive(Debug, Clone)]
+pub struct ResultsWriter {
+    output_dir: PathBuf,
+    format: OutputFormat,
+    clash: bool,
+    singbox: bool,
+    surge: bool,
+    quanx: bool,
+    loon: bool,
+    stash: bool,
+    base64: bool,
+}
+
+impl ResultsWriter {
+    /// 按配置的输出目录、格式与开关创建写入器
+    pub fn from_config(config: &Config) -> Result<Self> {
+        Ok(Self {
+            output_dir: PathBuf::from(&config.output_dir),
+            format: OutputFormat::parse(&config.output_format)?,
+            clash: config.generate_clash_config,
+            singbox: config.generate_singbox_config,
+            surge: config.generate_surge_config,
+            quanx: config.generate_quanx_config,
+            loon: config.generate_loon_config,
+            stash: config.generate_stash_config,
+            base64: config.generate_base64_subscription,
+        })
+    }
+
+    /// 写入全部输出，返回生成的文件路径；没有订阅返回流量信息时不写订阅信息文件
+    pub fn write(
+        &self,
+        results: &[CheckResult],
+        usages: &[SubscriptionUsage],
+    ) -> Result<Vec<PathBuf>> {
+        let mut written = Vec::new();
+
+        if self.format.json() {
+            let path = self.output_dir.join(JSON_FILE);
+            write_atomic(&path, &serde_json::to_vec_pretty(results)?)?;
+            written.push(path);
+        }
+        if self.format.yaml() {
+            let path = self.output_dir.join(YAML_FILE);
+            write_atomic(&path, serde_yaml::to_string(results)?.as_bytes())?;
+            written.push(path);
+        }
+        if !usages.is_empty() {
+            if self.format.json() {
+                let path = self.output_dir.join(USAGE_JSON_FILE);
+                write_atomic(&path, &serde_json::to_vec_pretty(usages)?)?;
+                written.push(path);
+            }
+            if self.format.yaml() {
+                let path = self.output_dir.join(USAGE_YAML_FILE);
+                write_atomic(&path, serde_yaml::to_string(usages)?.as_bytes())?;
+                written.push(path);
+            }
+        }
+
+        let alive: Vec<ProxyNode> = results
+            .iter()
+            .filter(|r| r.is_alive)
+            .map(|r| r.proxy.clone())
+            .collect();
+
+        if self.base64 {
+            let path = self.output_dir.join(SUBSCRIPTION_FILE);
+            let links = ShareLinks::new(&alive);
+            for (name, e) in &links.skipped {
+                println!("⚠️  base64 订阅跳过 {}: {}", name, e);
+            }
+            write_atomic(&path, links.subscription().as_bytes())?;
+            written.push(path);
+        }
+
+        if self.clash {
+            written.push(clash::save_config(&alive, &self.output_dir)?);
+        }
+        if self.singbox {
+            written.push(singbox::save_config(&alive, &self.output_dir)?);
+        }
+        if self.surge {
+            written.push(surge::save_config(&alive, &self.output_dir)?);
+        }
+        if self.quanx {
+            written.push(quanx::save_config(&alive, &self.output_dir)?);
+        }
+        if self.loon {
+            written.push(loon::save_config(&alive, &self.output_dir)?);
+        }
+        if self.stash {
+            written.push(stash::save_config(&alive, &self.output_dir)?);
+        }
+
+        Ok(written)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::check::MediaUnlockResult;
+    use crate::proxy::Protocol;
+    use crate::proxy::node::AuthParams;
+    use crate::proxy::userinfo::SubscriptionUserInfo;
+    use chrono::Utc;
+    use std::fs;
+    use std::time::Duration;
+
+    fn result(name: &str, is_alive: bool) -> CheckResult {
+        CheckResult {
+            proxy: ProxyNode::new(
+                name.to_string(),
+                "example.com".to_string(),
+                1080,
+                Protocol::Socks5(AuthParams::default()),
+            ),
+            is_alive,
+            latency: Some(Duration::from_millis(120)),
+            speed: None,
+            udp: false,
+            media_unlock: MediaUnlockResult::default(),
+            country: None,
+            country_code: None,
+            ip: None,
+            ip_risk: None,
+            is_cf_accessible: false,
+            cf_location: None,
+            cf_ip: None,
+        }
+    }
+
+    fn writer(dir: &std::path::Path, format: &str, clients: bool) -> ResultsWriter {
+        let config = Config {
+            output_dir: dir.to_string_lossy().to_string(),
+            output_format: format.to_string(),
+            generate_clash_config: clients,
+            generate_singbox_config: clients,
+            generate_surge_config: clients,
+            generate_quanx_config: clients,
+            generate_loon_config: clients,
+            generate_stash_config: clients,
+            generate_base64_subscription: clients,
+            ..Config::default()
+        };
+        ResultsWriter::from_config(&config).unwrap()
+    }
+
+    #[test]
+    fn test_output_format_parse() {
+        assert_eq!(OutputFormat::parse("JSON").unwrap(), OutputFormat::Json);
+        assert_eq!(OutputFormat::parse("yml").unwrap(), OutputFormat::Yaml);
+        assert_eq!(OutputFormat::parse("both").unwrap(), OutputFormat::Both);
+        assert!(OutputFormat::parse("xml").is_err());
+    }
+
+    #[test]
+    fn test_write_both_formats_and_client_configs() {
+        let dir = tempfile::tempdir().unwrap();
+        let results = [result("alive", true), result("dead", false)];
+        let written = writer(dir.path(), "both", true)
+            .write(&results, &[])
+            .unwrap();
+
+        let names: Vec<_> = written
+            .iter()
+            .map(|p| p.file_name().unwrap().to_str().unwrap())
+            .collect();
+        assert_eq!(
+            names,
+            [
+                JSON_FILE,
+                YAML_FILE,
+                SUBSCRIPTION_FILE,
+                clash::FILE_NAME,
+                singbox::FILE_NAME,
+                surge::FILE_NAME,
+                quanx::FILE_NAME,
+                loon::FILE_NAME,
+                stash::FILE_NAME
+            ]
+        );
+
+        let json: Vec<CheckResult> =
+            serde_json::from_str(&fs::read_to_string(dir.path().join(JSON_FILE)).unwrap()).unwrap();
+        assert_eq!(json.len(), 2);
+        let yaml: Vec<CheckResult> =
+            serde_yaml::from_str(&fs::read_to_string(dir.path().join(YAML_FILE)).unwrap()).unwrap();
+        assert_eq!(yaml[1].proxy.name, "dead");
+
+        // 客户端配置只包含存活节点
+        let clash: serde_yaml::Value =
+            serde_yaml::from_str(&fs::read_to_string(dir.path().join(clash::FILE_NAME)).unwrap())
+                .unwrap();
+        let proxies = clash["proxies"].as_sequence().unwrap();
+        assert_eq!(proxies.len(), 1);
+        assert_eq!(proxies[0]["name"], "alive");
+    }
+
+    #[test]
+    fn test_write_json_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let written = writer(dir.path(), "json", false)
+            .write(&[result("alive", true)], &[])
+            .unwrap();
+        assert_eq!(written, [dir.path().join(JSON_FILE)]);
+        assert!(!dir.path().join(YAML_FILE).exists());
+        assert!(!dir.path().join(SUBSCRIPTION_FILE).exists());
+        assert!(!dir.path().join(clash::FILE_NAME).exists());
+    }
+
+    #[test]
+    fn test_write_subscription_usage() {
+        let dir = tempfile::tempdir().unwrap();
+        let info = SubscriptionUserInfo::parse("upload=1; download=2; total=10; expire=0").unwrap();
+        let usage = SubscriptionUsage::new("机场".to_string(), info, Utc::now(), 7);
+        let written = writer(dir.path(), "json", false)
+            .write(&[result("alive", true)], &[usage])
+            .unwrap();
+        assert_eq!(written[1], dir.path().join(USAGE_JSON_FILE));
+        assert!(!dir.path().join(USAGE_YAML_FILE).exists());
+
+        let json: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&written[1]).unwrap()).unwrap();
+        assert_eq!(json[0]["name"], "机场");
+        assert_eq!(json[0]["used"], 3);
+        assert_eq!(json[0]["remaining"], 7);
+        assert!(json[0]["expire"].is_null());
+    }
+}
@@ -0,0 +1,722 @@
+//! sing-box 配置生成
+//! 把节点转换为 sing-box 的 outbound（WireGuard 为 endpoint），
+//! 再加上 selector / urltest 分组、入站、DNS 与路由，组成可直接运行的配置（sing-box 1.12+）
+
+use super::{SELECT_GROUP, TEST_URL, URL_TEST_GROUP, bandwidth_mbps, unique_name, write_atomic};
+use crate::outbound::plugin::Plugin;
+use crate::outbound::wireguard::parse_reserved;
+use crate::proxy::node::{
+    AnyTlsParams, AuthParams, Hysteria2Params, HysteriaParams, Network, ShadowsocksParams,
+    TrojanParams, TuicParams, VlessParams, VmessParams, WireGuardParams,
+};
+use crate::proxy::{Protocol, ProxyNode};
+use anyhow::{Result, anyhow};
+use serde_json::{Map, Value, json};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// 直连出站
+const DIRECT_TAG: &str = "direct";
+/// 输出文件名
+pub const FILE_NAME: &str = "singbox.json";
+
+/// 节点转换结果：WireGuard 在 sing-box 1.11 起改为 endpoint
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outbound {
+    Outbound(Value),
+    Endpoint(Value),
+}
+
+/// 把单个节点转换为 sing-box 出站
+pub fn outbound(node: &ProxyNode, tag: &str) -> Result<Outbound> {
+    let mut value = match &node.protocol {
+        Protocol::Shadowsocks(params) => shadowsocks(node, params)?,
+        Protocol::Vmess(params) => vmess(node, params),
+        Protocol::Vless(params) => vless(node, params),
+        Protocol::Trojan(params) => trojan(node, params),
+        Protocol::Hysteria(params) => hysteria(node, params)?,
+        Protocol::Hysteria2(params) => hysteria2(node, params),
+        Protocol::Tuic(params) => tuic(node, params),
+        Protocol::AnyTls(params) => anytls(node, params),
+        Protocol::WireGuard(params) => {
+            let mut endpoint = wireguard(node, params)?;
+            endpoint["tag"] = tag.into();
+            return Ok(Outbound::Endpoint(strip_nulls(endpoint)));
+        }
+        Protocol::Http(auth) => http(node, auth),
+        Protocol::Socks5(auth) => socks(auth, "5"),
+        Protocol::Socks4(auth) => socks(auth, "4"),
+        Protocol::Ssr(_) | Protocol::Direct | Protocol::Other(_) => {
+            return Err(anyhow!("sing-box 不支持的协议: {}", node.kind()));
+        }
+    };
+
+    value["tag"] = tag.into();
+    value["server"] = node.server.clone().into();
+    value["server_port"] = node.port.into();
+    if node.udp == Some(false) {
+        value["network"] = "tcp".into();
+    }
+    apply_dial(&mut value, node);
+    Ok(Outbound::Outbound(strip_nulls(value)))
+}
+
+/// 生成完整配置，不支持的节点被跳过；节点名称重复时追加序号作为 tag
+pub fn generate_config(nodes: &[ProxyNode]) -> Value {
+    let mut used: HashSet<String> = [SELECT_GROUP, URL_TEST_GROUP, DIRECT_TAG]
+        .into_iter()
+        .map(str::to_string)
+        .collect();
+    let mut tags = Vec::new();
+    let mut outbounds = Vec::new();
+    let mut endpoints = Vec::new();
+
+    for node in nodes {
+        let tag = unique_name(&mut used, &node.name);
+        match outbound(node, &tag) {
+            Ok(Outbound::Outbound(value)) => outbounds.push(value),
+            Ok(Outbound::Endpoint(value)) => endpoints.push(value),
+            Err(_) => {
+                used.remove(&tag);
+                continue;
+            }
+        }
+        tags.push(tag);
+    }
+
+    // urltest 不能为空，没有节点时退回直连
+    let members = if tags.is_empty() {
+        vec![DIRECT_TAG.to_string()]
+    } else {
+        tags.clone()
+    };
+    let mut selector_members = vec![URL_TEST_GROUP.to_string()];
+    selector_members.extend(tags);
+    selector_members.push(DIRECT_TAG.to_string());
+
+    let mut all_outbounds = vec![
+        json!({
+            "type": "selector",
+            "tag": SELECT_GROUP,
+            "outbounds": selector_members,
+            "default": URL_TEST_GROUP,
+        }),
+        json!({
+            "type": "urltest",
+            "tag": URL_TEST_GROUP,
+            "outbounds": members,
+            "url": TEST_URL,
+            "interval": "5m",
+        }),
+    ];
+    all_outbounds.extend(outbounds);
+    all_outbounds.push(json!({"type": "direct", "tag": DIRECT_TAG}));
+
+    let mut config = json!({
+        "log": {"level": "info", "timestamp": true},
+        "dns": {
+            "servers": [
+                {"type": "https", "tag": "remote", "server": "1.1.1.1", "detour": SELECT_GROUP},
+                {"type": "udp", "tag": "local", "server": "223.5.5.5"},
+            ],
+            "final": "remote",
+        },
+        "inbounds": [
+            {"type": "mixed", "tag": "mixed-in", "listen": "127.0.0.1", "listen_port": 7890},
+        ],
+        "outbounds": all_outbounds,
+        "route": {
+            "rules": [
+                {"action": "sniff"},
+                {"protocol": "dns", "action": "hijack-dns"},
+                {"ip_is_private": true, "outbound": DIRECT_TAG},
+            ],
+            "final": SELECT_GROUP,
+            "auto_detect_interface": true,
+            "default_domain_resolver": "local",
+        },
+    });
+    if !endpoints.is_empty() {
+        config["endpoints"] = Value::Array(endpoints);
+    }
+    config
+}
+
+/// 生成配置并写入 `output_dir/singbox.json`
+pub fn save_config(nodes: &[ProxyNode], output_dir: &Path) -> Result<PathBuf> {
+    let path = output_dir.join(FILE_NAME);
+    let content = serde_json::to_string_pretty(&generate_config(nodes))?;
+    write_atomic(&path, content.as_bytes())?;
+    Ok(path)
+}
+
+/// 去掉对象中值为 null 的字段，未配置的选项不输出
+fn strip_nulls(value: Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .filter(|(_, v)| !v.is_null())
+                .map(|(k, v)| (k, strip_nulls(v)))
+                .collect::<Map<_, _>>(),
+        ),
+        Value::Array(items) => Value::Array(items.into_iter().map(strip_nulls).collect()),
+        other => other,
+    }
+}
+
+/// 公共拨号选项
+fn apply_dial(value: &mut Value, node: &ProxyNode) {
+    let dial = &node.dial;
+    value["tcp_fast_open"] = json!(dial.tfo);
+    value["tcp_multi_path"] = json!(dial.mptcp);
+    value["bind_interface"] = json!(dial.interface_name);
+    value["routing_mark"] = json!(dial.routing_mark);
+    value["domain_strategy"] = json!(dial.ip_version.as_deref().and_then(|v| match v {
+        "ipv4" => Some("ipv4_only"),
+        "ipv6" => Some("ipv6_only"),
+        "ipv4-prefer" => Some("prefer_ipv4"),
+        "ipv6-prefer" => Some("prefer_ipv6"),
+        _ => None,
+    }));
+}
+
+/// sing-mux 多路复用
+fn multiplex(node: &ProxyNode) -> Value {
+    let Some(smux) = node.dial.smux.as_ref().filter(|s| s.enabled == Some(true)) else {
+        return Value::Null;
+    };
+    json!({
+        "enabled": true,
+        "protocol": smux.protocol,
+        "max_connections": smux.max_connections,
+        "min_streams": smux.min_streams,
+        "max_streams": smux.max_streams,
+        "padding": smux.padding,
+    })
+}
+
+/// TLS 配置；`always` 表示协议本身基于 TLS
+fn tls(node: &ProxyNode, always: bool) -> Value {
+    let settings = &node.tls;
+    let reality = settings.reality_opts.as_ref();
+    if !always && settings.tls != Some(true) && reality.is_none() {
+        return Value::Null;
+    }
+
+    // REALITY 依赖 uTLS，未指定指纹时使用 chrome
+    let fingerprint = settings
+        .client_fingerprint
+        .as_deref()
+        .or(reality.map(|_| "chrome"));
+    let ech = settings
+        .ech_opts
+        .as_ref()
+        .filter(|ech| ech.enable == Some(true))
+        .map(|ech| {
+            json!({
+                "enabled": true,
+                "config": ech.config.as_ref().map(|config| vec![
+                    "-----BEGIN ECH CONFIGS-----",
+                    config.as_str(),
+                    "-----END ECH CONFIGS-----",
+                ]),
+            })
+        });
+    json!({
+        "enabled": true,
+        "server_name": settings.server_name(),
+        "insecure": settings.skip_cert_verify,
+        "alpn": settings.alpn,
+        "certificate_path": settings.ca,
+        "certificate": settings.ca_str,
+        "utls": fingerprint.map(|fp| json!({"enabled": true, "fingerprint": fp})),
+        "reality": reality.map(|r| json!({
+            "enabled": true,
+            "public_key": r.public_key,
+            "short_id": r.short_id,
+        })),
+        "ech": ech,
+    })
+}
+
+/// V2Ray 传输层
+fn transport(node: &ProxyNode) -> Value {
+    let transport = &node.transport;
+    match transport.network {
+        Some(Network::Ws) => {
+            let ws = transport.ws_opts.clone().unwrap_or_default();
+            if ws.v2ray_http_upgrade == Some(true) {
+                let mut headers = ws.headers.unwrap_or_default();
+                let host = headers
+                    .keys()
+                    .find(|name| name.eq_ignore_ascii_case("host"))
+                    .cloned()
+                    .and_then(|name| headers.remove(&name));
+                json!({
+                    "type": "httpupgrade",
+                    "host": host,
+                    "path": ws.path,
+                    "headers": Some(headers).filter(|h| !h.is_empty()),
+                })
+            } else {
+                json!({
+                    "type": "ws",
+                    "path": ws.path,
+                    "headers": ws.headers,
+                    "max_early_data": ws.max_early_data,
+                    "early_data_header_name": ws.early_data_header_name,
+                })
+            }
+        }
+        Some(Network::H2) => {
+            let h2 = transport.h2_opts.clone().unwrap_or_default();
+            json!({"type": "http", "host": h2.host, "path": h2.path})
+        }
+        // 未配置 TLS 时 sing-box 的 http 传输即 HTTP/1.1
+        Some(Network::Http) => {
+            let http = transport.http_opts.clone().unwrap_or_default();
+            json!({
+                "type": "http",
+                "method": http.method,
+                "path": transport.path(),
+                "headers": http.headers,
+            })
+        }
+        Some(Network::Grpc) => json!({"type": "grpc", "service_name": transport.path()}),
+        Some(Network::Tcp) | None => Value::Null,
+    }
+}
+
+fn shadowsocks(node: &ProxyNode, params: &ShadowsocksParams) -> Result<Value> {
+    // sing-box 只接受 SIP003 字符串形式的插件参数
+    let plugin = match params.plugin.as_deref().filter(|p| !p.is_empty()) {
+        Some(name) => Plugin::new(name, params.plugin_opts.as_ref())?.sip002(),
+        None => None,
+    };
+    let (plugin, plugin_opts) = match plugin.as_deref().map(|p| p.split_once(';')) {
+        Some(Some((name, opts))) => (Some(name.to_string()), Some(opts.to_string())),
+        Some(None) => (plugin.clone(), None),
+        None => (None, None),
+    };
+    Ok(json!({
+        "type": "shadowsocks",
+        "method": params.cipher,
+        "password": params.password,
+        "plugin": plugin,
+        "plugin_opts": plugin_opts,
+        "udp_over_tcp": params.udp_over_tcp,
+        "multiplex": multiplex(node),
+    }))
+}
+
+/// xudp 开关换算成 packet_encoding
+fn packet_encoding(encoding: &Option<String>, xudp: Option<bool>) -> Option<String> {
+    encoding
+        .clone()
+        .or_else(|| (xudp == Some(true)).then(|| "xudp".to_string()))
+}
+
+fn vmess(node: &ProxyNode, params: &VmessParams) -> Value {
+    json!({
+        "type": "vmess",
+        "uuid": params.uuid,
+        "security": params.cipher.as_deref().unwrap_or("auto"),
+        "alter_id": params.alter_id.unwrap_or(0),
+        "global_padding": params.global_padding,
+        "authenticated_length": params.authenticated_length,
+        "packet_encoding": packet_encoding(&params.packet_encoding, params.xudp),
+        "tls": tls(node, false),
+        "transport": transport(node),
+        "multiplex": multiplex(node),
+    })
+}
+
+fn vless(node: &ProxyNode, params: &VlessParams) -> Value {
+    json!({
+        "type": "vless",
+        "uuid": params.uuid,
+        "flow": params.flow,
+        "packet_encoding": packet_encoding(&params.packet_encoding, params.xudp),
+        "tls": tls(node, false),
+        "transport": transport(node),
+        "multiplex": multiplex(node),
+    })
+}
+
+fn trojan(node: &ProxyNode, params: &TrojanParams) -> Value {
+    json!({
+        "type": "trojan",
+        "password": params.password,
+        "tls": tls(node, true),
+        "transport": transport(node),
+        "multiplex": multiplex(node),
+    })
+}
+
+/// Clash 的端口跳跃写作 `20000-30000,40000`，sing-box 为 `20000:30000` 列表
+fn server_ports(ports: &Option<String>) -> Option<Vec<String>> {
+    ports.as_ref().map(|ports| {
+        ports
+            .split(',')
+            .map(|range| range.trim().replace('-', ":"))
+            .collect()
+    })
+}
+
+fn hysteria(node: &ProxyNode, params: &HysteriaParams) -> Result<Value> {
+    let protocol = params.protocol.as_ref().or(params.obfs_protocol.as_ref());
+    if let Some(protocol) = protocol.filter(|protocol| *protocol != "udp") {
+        return Err(anyhow!("sing-box 不支持 Hysteria 伪装协议: {}", protocol));
+    }
+    Ok(json!({
+        "type": "hysteria",
+        "auth_str": params.auth_str,
+        "auth": params.auth,
+        "server_ports": server_ports(&params.ports),
+        "hop_interval": params.hop_interval.map(|s| format!("{}s", s)),
+        "up_mbps": bandwidth_mbps(&params.up).or(params.up_speed),
+        "down_mbps": bandwidth_mbps(&params.down).or(params.down_speed),
+        "obfs": params.obfs,
+        "recv_window_conn": params.recv_window_conn,
+        "recv_window": params.recv_window,
+        "disable_mtu_discovery": params.disable_mtu_discovery,
+        "tls": tls(node, true),
+    }))
+}
+
+fn hysteria2(node: &ProxyNode, params: &Hysteria2Params) -> Value {
+    json!({
+        "type": "hysteria2",
+        "password": params.password,
+        "server_ports": server_ports(&params.ports),
+        "hop_interval": params.hop_interval.map(|s| format!("{}s", s)),
+        "up_mbps": bandwidth_mbps(&params.up),
+        "down_mbps": bandwidth_mbps(&params.down),
+        "obfs": params.obfs.as_ref().map(|obfs| json!({
+            "type": obfs,
+            "password": params.obfs_password,
+        })),
+        "tls": tls(node, true),
+    })
+}
+
+fn tuic(node: &ProxyNode, params: &TuicParams) -> Value {
+    let mut tls = tls(node, true);
+    if params.disable_sni == Some(true) {
+        tls["disable_sni"] = true.into();
+    }
+    json!({
+        "type": "tuic",
+        "uuid": params.uuid,
+        "password": params.password,
+        "congestion_control": params.congestion_controller,
+        "udp_relay_mode": params.udp_relay_mode,
+        "zero_rtt_handshake": params.reduce_rtt,
+        "heartbeat": params.heartbeat_interval.map(|ms| format!("{}ms", ms)),
+        "tls": tls,
+    })
+}
+
+fn anytls(node: &ProxyNode, params: &AnyTlsParams) -> Value {
+    let seconds = |value: Option<u32>| value.map(|s| format!("{}s", s));
+    json!({
+        "type": "anytls",
+        "password": params.password,
+        "idle_session_check_interval": seconds(params.idle_session_check_interval),
+        "idle_session_timeout": seconds(params.idle_session_timeout),
+        "min_idle_session": params.min_idle_session,
+        "tls": tls(node, true),
+    })
+}
+
+fn wireguard(node: &ProxyNode, params: &WireGuardParams) -> Result<Value> {
+    let address: Vec<String> = [(&params.ip, "/32"), (&params.ipv6, "/128")]
+        .into_iter()
+        .filter_map(|(ip, prefix)| {
+            let ip = ip.as_deref().filter(|ip| !ip.is_empty())?;
+            Some(if ip.contains('/') {
+                ip.to_string()
+            } else {
+                format!("{}{}", ip, prefix)
+            })
+        })
+        .collect();
+    let reserved = params.reserved.as_ref().map(parse_reserved).transpose()?;
+    let allowed_ips = params
+        .allowed_ips
+        .clone()
+        .unwrap_or_else(|| vec!["0.0.0.0/0".to_string(), "::/0".to_string()]);
+    Ok(json!({
+        "type": "wireguard",
+        "address": address,
+        "private_key": params.private_key,
+        "mtu": params.mtu,
+        "peers": [{
+            "address": node.server,
+            "port": node.port,
+            "public_key": params.public_key,
+            "pre_shared_key": params.pre_shared_key,
+            "allowed_ips": allowed_ips,
+            "persistent_keepalive_interval": params.persistent_keepalive,
+            "reserved": reserved,
+        }],
+    }))
+}
+
+fn http(node: &ProxyNode, auth: &AuthParams) -> Value {
+    json!({
+        "type": "http",
+        "username": auth.username,
+        "password": auth.password,
+        "headers": auth.headers,
+        "tls": tls(node, false),
+    })
+}
+
+fn socks(auth: &AuthParams, version: &str) -> Value {
+    json!({
+        "type": "socks",
+        "version": version,
+        "username": auth.username,
+        "password": auth.password,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::node::{Bandwidth, RealityOpts, SsrParams, WsOpts};
+    use std::collections::BTreeMap;
+
+    fn node(name: &str, protocol: Protocol) -> ProxyNode {
+        ProxyNode::new(name.to_string(), "example.com".to_string(), 443, protocol)
+    }
+
+    fn trojan_node(name: &str) -> ProxyNode {
+        node(
+            name,
+            Protocol::Trojan(TrojanParams {
+                password: "secret".to_string(),
+                ..Default::default()
+            }),
+        )
+    }
+
+    #[test]
+    fn test_vless_reality_grpc_outbound() {
+        let mut proxy = node(
+            "vless",
+            Protocol::Vless(VlessParams {
+                uuid: "uuid".to_string(),
+                flow: Some("xtls-rprx-vision".to_string()),
+                xudp: Some(true),
+                ..Default::default()
+            }),
+        );
+        proxy.tls.tls = Some(true);
+        proxy.tls.servername = Some("www.microsoft.com".to_string());
+        proxy.tls.reality_opts = Some(RealityOpts {
+            public_key: "pbk".to_string(),
+            short_id: Some("sid".to_string()),
+        });
+        proxy.transport.network = Some(Network::Grpc);
+        proxy.transport.grpc_opts = Some(crate::proxy::node::GrpcOpts {
+            grpc_service_name: Some("grpc".to_string()),
+        });
+        proxy.dial.ip_version = Some("ipv4-prefer".to_string());
+
+        let Outbound::Outbound(value) = outbound(&proxy, "vless").unwrap() else {
+            panic!("vless 应生成 outbound");
+        };
+        assert_eq!(
+            value,
+            json!({
+                "type": "vless",
+                "tag": "vless",
+                "server": "example.com",
+                "server_port": 443,
+                "uuid": "uuid",
+                "flow": "xtls-rprx-vision",
+                "packet_encoding": "xudp",
+                "domain_strategy": "prefer_ipv4",
+                "tls": {
+                    "enabled": true,
+                    "server_name": "www.microsoft.com",
+                    "utls": {"enabled": true, "fingerprint": "chrome"},
+                    "reality": {"enabled": true, "public_key": "pbk", "short_id": "sid"},
+                },
+                "transport": {"type": "grpc", "service_name": "grpc"},
+            })
+        );
+    }
+
+    #[test]
+    fn test_shadowsocks_and_hysteria2_outbound() {
+        let proxy = node(
+            "ss",
+            Protocol::Shadowsocks(ShadowsocksParams {
+                cipher: "aes-128-gcm".to_string(),
+                password: "secret".to_string(),
+                plugin: Some("obfs".to_string()),
+                plugin_opts: Some(json!({"mode": "tls", "host": "bing.com"})),
+                ..Default::default()
+            }),
+        );
+        let Outbound::Outbound(value) = outbound(&proxy, "ss").unwrap() else {
+            panic!("ss 应生成 outbound");
+        };
+        assert_eq!(value["plugin"], "obfs-local");
+        assert_eq!(value["plugin_opts"], "obfs=tls;obfs-host=bing.com");
+
+        let proxy = node(
+            "hy2",
+            Protocol::Hysteria2(Hysteria2Params {
+                password: "secret".to_string(),
+                ports: Some("20000-30000,40000".to_string()),
+                hop_interval: Some(30),
+                up: Some(Bandwidth::Mbps(50)),
+                down: Some(Bandwidth::Text("1 Gbps".to_string())),
+                obfs: Some("salamander".to_string()),
+                obfs_password: Some("gawr".to_string()),
+                ..Default::default()
+            }),
+        );
+        let Outbound::Outbound(value) = outbound(&proxy, "hy2").unwrap() else {
+            panic!("hysteria2 应生成 outbound");
+        };
+        assert_eq!(value["server_ports"], json!(["20000:30000", "40000"]));
+        assert_eq!(value["hop_interval"], "30s");
+        assert_eq!(value["up_mbps"], 50);
+        assert_eq!(value["down_mbps"], 1000);
+        assert_eq!(
+            value["obfs"],
+            json!({"type": "salamander", "password": "gawr"})
+        );
+        assert_eq!(value["tls"], json!({"enabled": true}));
+    }
+
+    #[test]
+    fn test_hysteria_outbound() {
+        let mut proxy = node(
+            "hy",
+            Protocol::Hysteria(HysteriaParams {
+                auth_str: Some("secret".to_string()),
+                up: Some(Bandwidth::Text("11 Mbps".to_string())),
+                down_speed: Some(55),
+                protocol: Some("udp".to_string()),
+                ..Default::default()
+            }),
+        );
+        proxy.tls.sni = Some("www.bing.com".to_string());
+        let Outbound::Outbound(value) = outbound(&proxy, "hy").unwrap() else {
+            panic!("hysteria 应生成 outbound");
+        };
+        assert_eq!(value["type"], "hysteria");
+        assert_eq!(value["auth_str"], "secret");
+        assert_eq!(value["up_mbps"], 11);
+        assert_eq!(value["down_mbps"], 55);
+        assert_eq!(value["tls"]["server_name"], "www.bing.com");
+
+        if let Protocol::Hysteria(params) = &mut proxy.protocol {
+            params.protocol = Some("faketcp".to_string());
+        }
+        assert!(outbound(&proxy, "hy").is_err());
+    }
+
+    #[test]
+    fn test_ws_http_upgrade_transport() {
+        let mut proxy = node(
+            "vmess",
+            Protocol::Vmess(VmessParams {
+                uuid: "uuid".to_string(),
+                ..Default::default()
+            }),
+        );
+        proxy.transport.network = Some(Network::Ws);
+        proxy.transport.ws_opts = Some(WsOpts {
+            path: Some("/up".to_string()),
+            headers: Some(BTreeMap::from([(
+                "Host".to_string(),
+                "cdn.example.com".to_string(),
+            )])),
+            v2ray_http_upgrade: Some(true),
+            ..Default::default()
+        });
+        let Outbound::Outbound(value) = outbound(&proxy, "vmess").unwrap() else {
+            panic!("vmess 应生成 outbound");
+        };
+        assert_eq!(
+            value["transport"],
+            json!({"type": "httpupgrade", "host": "cdn.example.com", "path": "/up"})
+        );
+        assert!(value.get("tls").is_none());
+    }
+
+    #[test]
+    fn test_generate_config() {
+        let wireguard = node(
+            "wg",
+            Protocol::WireGuard(WireGuardParams {
+                private_key: "private".to_string(),
+                public_key: "public".to_string(),
+                ip: Some("172.16.0.2".to_string()),
+                reserved: Some(json!([1, 2, 3])),
+                ..Default::default()
+            }),
+        );
+        let ssr = node(
+            "ssr",
+            Protocol::Ssr(SsrParams {
+                cipher: "aes-256-cfb".to_string(),
+                password: "secret".to_string(),
+                obfs: "plain".to_string(),
+                protocol: "origin".to_string(),
+                ..Default::default()
+            }),
+        );
+        let nodes = [trojan_node("a"), trojan_node("a"), ssr, wireguard];
+
+        let config = generate_config(&nodes);
+        let outbounds = config["outbounds"].as_array().unwrap();
+        let tags: Vec<&str> = outbounds
+            .iter()
+            .map(|o| o["tag"].as_str().unwrap())
+            .collect();
+        assert_eq!(tags, [SELECT_GROUP, URL_TEST_GROUP, "a", "a 2", DIRECT_TAG]);
+        assert_eq!(
+            outbounds[0]["outbounds"],
+            json!([URL_TEST_GROUP, "a", "a 2", "wg", DIRECT_TAG])
+        );
+        assert_eq!(outbounds[1]["outbounds"], json!(["a", "a 2", "wg"]));
+        assert_eq!(
+            config["endpoints"],
+            json!([{
+                "type": "wireguard",
+                "tag": "wg",
+                "address": ["172.16.0.2/32"],
+                "private_key": "private",
+                "peers": [{
+                    "address": "example.com",
+                    "port": 443,
+                    "public_key": "public",
+                    "allowed_ips": ["0.0.0.0/0", "::/0"],
+                    "reserved": [1, 2, 3],
+                }],
+            }])
+        );
+
+        // 没有节点时 urltest 退回直连
+        let config = generate_config(&[]);
+        assert_eq!(config["outbounds"][1]["outbounds"], json!([DIRECT_TAG]));
+        assert!(config.get("endpoints").is_none());
+    }
+
+    #[test]
+    fn test_save_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = save_config(&[trojan_node("a")], dir.path()).unwrap();
+        assert_eq!(path, dir.path().join(FILE_NAME));
+        let saved: Value = serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+        assert_eq!(saved, generate_config(&[trojan_node("a")]));
+    }
+}
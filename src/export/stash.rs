@@ -0,0 +1,68 @@
+//! Stash 配置生成
+//! Stash 兼容 Clash 配置格式，沿用 Clash 的分组与规则；
+//! Stash 不支持的协议（AnyTLS、SOCKS4）不写入节点，在文件开头以注释列出
+
+use super::{clash, write_atomic};
+use crate::proxy::{Protocol, ProxyNode};
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+
+/// 输出文件名
+pub const FILE_NAME: &str = "stash.yaml";
+
+/// Stash 能否表示该节点
+pub fn is_supported(node: &ProxyNode) -> bool {
+    !matches!(
+        node.protocol,
+        Protocol::AnyTls(_) | Protocol::Socks4(_) | Protocol::Direct
+    )
+}
+
+/// 生成完整配置文本
+pub fn generate_config(nodes: &[ProxyNode]) -> Result<String> {
+    let (supported, skipped): (Vec<ProxyNode>, Vec<ProxyNode>) =
+        nodes.iter().cloned().partition(is_supported);
+
+    let mut content = String::new();
+    for node in &skipped {
+        content.push_str(&format!(
+            "# 已跳过 {}: Stash 不支持的协议: {}\n",
+            node.name,
+            node.kind()
+        ));
+    }
+    let config = clash::generate_config(&supported)?;
+    content.push_str(&serde_yaml::to_string(&config)?);
+    Ok(content)
+}
+
+/// 生成配置并原子写入 `output_dir/stash.yaml`
+pub fn save_config(nodes: &[ProxyNode], output_dir: &Path) -> Result<PathBuf> {
+    let path = output_dir.join(FILE_NAME);
+    write_atomic(&path, generate_config(nodes)?.as_bytes())?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::node::{AnyTlsParams, AuthParams};
+
+    #[test]
+    fn test_skips_unsupported_protocols() {
+        let node = |name: &str, protocol| {
+            ProxyNode::new(name.to_string(), "example.com".to_string(), 443, protocol)
+        };
+        let nodes = [
+            node("anytls", Protocol::AnyTls(AnyTlsParams::default())),
+            node("socks", Protocol::Socks5(AuthParams::default())),
+        ];
+        let content = generate_config(&nodes).unwrap();
+        assert!(content.starts_with("# 已跳过 anytls: Stash 不支持的协议: anytls\n"));
+
+        let config: serde_yaml::Value = serde_yaml::from_str(&content).unwrap();
+        let proxies = config["proxies"].as_sequence().unwrap();
+        assert_eq!(proxies.len(), 1);
+        assert_eq!(proxies[0]["name"], "socks");
+    }
+}
@@ -0,0 +1,257 @@
+//! Surge 节点列表生成
+//! 输出 `[Proxy]` 段格式的节点行，可直接作为 `policy-path` 引用；
+//! Surge 不支持的协议（SSR、VLESS、AnyTLS、WireGuard 等）写成注释
+
+use super::{Fields, bandwidth_mbps, proxy_list, write_atomic};
+use crate::outbound::plugin::Plugin;
+use crate::proxy::node::{
+    AuthParams, Hysteria2Params, Network, ShadowsocksParams, TrojanParams, TuicParams, VmessParams,
+};
+use crate::proxy::{Protocol, ProxyNode};
+use anyhow::{Result, anyhow};
+use std::path::{Path, PathBuf};
+
+/// 输出文件名
+pub const FILE_NAME: &str = "surge.list";
+
+/// 把单个节点转换为 `[Proxy]` 行
+pub fn proxy_line(node: &ProxyNode) -> Result<String> {
+    let mut fields = Fields::default();
+    match &node.protocol {
+        Protocol::Shadowsocks(params) => shadowsocks(&mut fields, node, params)?,
+        Protocol::Vmess(params) => vmess(&mut fields, node, params)?,
+        Protocol::Trojan(params) => trojan(&mut fields, node, params)?,
+        Protocol::Hysteria2(params) => hysteria2(&mut fields, node, params)?,
+        Protocol::Tuic(params) => tuic(&mut fields, node, params),
+        Protocol::Http(auth) => auth_proxy(&mut fields, node, auth, "http", "https"),
+        Protocol::Socks5(auth) => auth_proxy(&mut fields, node, auth, "socks5", "socks5-tls"),
+        _ => return Err(anyhow!("Surge 不支持的协议: {}", node.kind())),
+    }
+    fields.set_flag("tfo", node.dial.tfo);
+    Ok(format!("{} = {}", node.name, fields.join(", ")))
+}
+
+/// 生成节点列表
+pub fn generate_config(nodes: &[ProxyNode]) -> String {
+    proxy_list(nodes, proxy_line)
+}
+
+/// 生成节点列表并原子写入 `output_dir/surge.list`
+pub fn save_config(nodes: &[ProxyNode], output_dir: &Path) -> Result<PathBuf> {
+    let path = output_dir.join(FILE_NAME);
+    write_atomic(&path, generate_config(nodes).as_bytes())?;
+    Ok(path)
+}
+
+/// 类型、服务器与端口三个位置参数
+fn head(fields: &mut Fields, node: &ProxyNode, kind: &str) {
+    fields.push(kind);
+    fields.push(&node.server);
+    fields.push(node.port);
+}
+
+fn udp_relay(fields: &mut Fields, node: &ProxyNode) {
+    fields.set_flag("udp-relay", node.udp);
+}
+
+fn tls(fields: &mut Fields, node: &ProxyNode) -> Result<()> {
+    if node.tls.reality_opts.is_some() {
+        return Err(anyhow!("Surge 不支持 REALITY"));
+    }
+    fields.set_opt("sni", node.tls.server_name());
+    fields.set_flag("skip-cert-verify", node.tls.skip_cert_verify);
+    Ok(())
+}
+
+/// Surge 只支持 TCP 与 WebSocket 传输
+fn transport(fields: &mut Fields, node: &ProxyNode) -> Result<()> {
+    match node.transport.network {
+        None | Some(Network::Tcp) => {}
+        Some(Network::Ws) => {
+            fields.set("ws", true);
+            fields.set_opt("ws-path", node.transport.path());
+            fields.set_opt(
+                "ws-headers",
+                node.transport.host().map(|host| format!("Host:{}", host)),
+            );
+        }
+        Some(network) => return Err(anyhow!("Surge 不支持 {} 传输", network.as_str())),
+    }
+    Ok(())
+}
+
+fn shadowsocks(fields: &mut Fields, node: &ProxyNode, params: &ShadowsocksParams) -> Result<()> {
+    head(fields, node, "ss");
+    fields.set("encrypt-method", &params.cipher);
+    fields.set("password", &params.password);
+    match Plugin::from_params(params)? {
+        Plugin::None => {}
+        Plugin::Obfs { mode, host } => {
+            fields.set("obfs", mode.as_str());
+            fields.set("obfs-host", host);
+        }
+        Plugin::V2ray(_) => return Err(anyhow!("Surge 不支持 v2ray-plugin")),
+    }
+    udp_relay(fields, node);
+    Ok(())
+}
+
+fn vmess(fields: &mut Fields, node: &ProxyNode, params: &VmessParams) -> Result<()> {
+    head(fields, node, "vmess");
+    fields.set("username", &params.uuid);
+    transport(fields, node)?;
+    if node.tls.tls == Some(true) {
+        fields.set("tls", true);
+        tls(fields, node)?;
+    }
+    // alterId 为 0 时使用 AEAD 认证
+    if params.alter_id.unwrap_or(0) == 0 {
+        fields.set("vmess-aead", true);
+    }
+    udp_relay(fields, node);
+    Ok(())
+}
+
+fn trojan(fields: &mut Fields, node: &ProxyNode, params: &TrojanParams) -> Result<()> {
+    if params.ss_opts.as_ref().and_then(|opts| opts.enabled) == Some(true) {
+        return Err(anyhow!("Surge 不支持 Trojan-Go 的 ss-opts"));
+    }
+    head(fields, node, "trojan");
+    fields.set("password", &params.password);
+    transport(fields, node)?;
+    tls(fields, node)?;
+    udp_relay(fields, node);
+    Ok(())
+}
+
+fn hysteria2(fields: &mut Fields, node: &ProxyNode, params: &Hysteria2Params) -> Result<()> {
+    if params.obfs.is_some() {
+        return Err(anyhow!("Surge 不支持 Hysteria2 混淆"));
+    }
+    head(fields, node, "hysteria2");
+    fields.set("password", &params.password);
+    tls(fields, node)?;
+    fields.set_opt("download-bandwidth", bandwidth_mbps(&params.down));
+    Ok(())
+}
+
+fn tuic(fields: &mut Fields, node: &ProxyNode, params: &TuicParams) {
+    head(fields, node, "tuic-v5");
+    fields.set("uuid", &params.uuid);
+    fields.set("password", &params.password);
+    fields.set_opt("sni", node.tls.server_name());
+    fields.set_flag("skip-cert-verify", node.tls.skip_cert_verify);
+    // Surge 只接受单个 ALPN
+    fields.set_opt("alpn", node.tls.alpn.as_ref().and_then(|alpn| alpn.first()));
+    udp_relay(fields, node);
+}
+
+fn auth_proxy(fields: &mut Fields, node: &ProxyNode, auth: &AuthParams, plain: &str, tls: &str) {
+    let secure = node.tls.tls == Some(true);
+    head(fields, node, if secure { tls } else { plain });
+    if let Some(username) = &auth.username {
+        fields.push(username);
+        fields.push(auth.password.as_deref().unwrap_or_default());
+    }
+    if secure {
+        fields.set_opt("sni", node.tls.server_name());
+        fields.set_flag("skip-cert-verify", node.tls.skip_cert_verify);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::node::{VlessParams, WsOpts};
+    use serde_json::json;
+    use std::collections::BTreeMap;
+
+    fn node(name: &str, protocol: Protocol) -> ProxyNode {
+        ProxyNode::new(name.to_string(), "example.com".to_string(), 443, protocol)
+    }
+
+    #[test]
+    fn test_shadowsocks_with_obfs() {
+        let mut proxy = node(
+            "ss",
+            Protocol::Shadowsocks(ShadowsocksParams {
+                cipher: "aes-128-gcm".to_string(),
+                password: "secret".to_string(),
+                plugin: Some("obfs".to_string()),
+                plugin_opts: Some(json!({"mode": "http", "host": "bing.com"})),
+                ..Default::default()
+            }),
+        );
+        proxy.udp = Some(true);
+        assert_eq!(
+            proxy_line(&proxy).unwrap(),
+            "ss = ss, example.com, 443, encrypt-method=aes-128-gcm, password=secret, \
+             obfs=http, obfs-host=bing.com, udp-relay=true"
+        );
+    }
+
+    #[test]
+    fn test_vmess_ws_tls() {
+        let mut proxy = node(
+            "vmess",
+            Protocol::Vmess(VmessParams {
+                uuid: "b831381d-6324-4d53-ad4f-8cda48b30811".to_string(),
+                alter_id: Some(0),
+                ..Default::default()
+            }),
+        );
+        proxy.tls.tls = Some(true);
+        proxy.tls.servername = Some("cdn.example.com".to_string());
+        proxy.transport.network = Some(Network::Ws);
+        proxy.transport.ws_opts = Some(WsOpts {
+            path: Some("/ws".to_string()),
+            headers: Some(BTreeMap::from([(
+                "Host".to_string(),
+                "cdn.example.com".to_string(),
+            )])),
+            ..Default::default()
+        });
+        assert_eq!(
+            proxy_line(&proxy).unwrap(),
+            "vmess = vmess, example.com, 443, username=b831381d-6324-4d53-ad4f-8cda48b30811, \
+             ws=true, ws-path=/ws, ws-headers=Host:cdn.example.com, tls=true, \
+             sni=cdn.example.com, vmess-aead=true"
+        );
+
+        proxy.transport.network = Some(Network::Grpc);
+        assert!(proxy_line(&proxy).is_err());
+    }
+
+    #[test]
+    fn test_auth_proxies() {
+        let mut proxy = node(
+            "https",
+            Protocol::Http(AuthParams {
+                username: Some("user".to_string()),
+                password: Some("pass".to_string()),
+                ..Default::default()
+            }),
+        );
+        proxy.tls.tls = Some(true);
+        assert_eq!(
+            proxy_line(&proxy).unwrap(),
+            "https = https, example.com, 443, user, pass"
+        );
+
+        let socks = node("socks", Protocol::Socks5(AuthParams::default()));
+        assert_eq!(
+            proxy_line(&socks).unwrap(),
+            "socks = socks5, example.com, 443"
+        );
+    }
+
+    #[test]
+    fn test_unsupported_protocol_is_annotated() {
+        let vless = node("vless", Protocol::Vless(VlessParams::default()));
+        let socks = node("socks", Protocol::Socks5(AuthParams::default()));
+        assert_eq!(
+            generate_config(&[vless, socks]),
+            "# 已跳过 vless: Surge 不支持的协议: vless\nsocks = socks5, example.com, 443\n"
+        );
+    }
+}
@@ -0,0 +1,126 @@
+//! 节点导出格式
+//! 把一组节点渲染为指定客户端的配置文本，供离线转换使用
+
+use super::{clash, loon, quanx, singbox, stash, surge};
+use crate::proxy::ProxyNode;
+use crate::proxy::share_link::{ShareLinks, to_share_link};
+use anyhow::{Result, anyhow};
+
+/// 导出格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportTarget {
+    Clash,
+    Singbox,
+    /// 每行一个分享链接
+    Links,
+    /// base64 编码的分享链接订阅
+    Base64,
+    Surge,
+    QuanX,
+    Loon,
+    Stash,
+}
+
+impl ExportTarget {
+    /// 解析命令行中的格式名称
+    pub fn parse(value: &str) -> Result<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "clash" | "mihomo" => Ok(ExportTarget::Clash),
+            "singbox" | "sing-box" => Ok(ExportTarget::Singbox),
+            "links" | "uri" => Ok(ExportTarget::Links),
+            "base64" | "v2ray" => Ok(ExportTarget::Base64),
+            "surge" => Ok(ExportTarget::Surge),
+            "quanx" | "quantumultx" => Ok(ExportTarget::QuanX),
+            "loon" => Ok(ExportTarget::Loon),
+            "stash" => Ok(ExportTarget::Stash),
+            other => Err(anyhow!("不支持的导出格式: {}", other)),
+        }
+    }
+
+    /// 渲染配置文本，无法以该格式表示的节点被跳过并单独列出
+    pub fn render(self, nodes: &[ProxyNode]) -> Result<Rendered> {
+        let content = match self {
+            ExportTarget::Clash => serde_yaml::to_string(&clash::generate_config(nodes)?)?,
+            ExportTarget::Singbox => {
+                serde_json::to_string_pretty(&singbox::generate_config(nodes))? + "\n"
+            }
+            ExportTarget::Links => ShareLinks::new(nodes)
+                .links
+                .into_iter()
+                .map(|link| link + "\n")
+                .collect(),
+            ExportTarget::Base64 => ShareLinks::new(nodes).subscription(),
+            ExportTarget::Surge => surge::generate_config(nodes),
+            ExportTarget::QuanX => quanx::generate_config(nodes),
+            ExportTarget::Loon => loon::generate_config(nodes),
+            ExportTarget::Stash => stash::generate_config(nodes)?,
+        };
+        let skipped: Vec<(String, anyhow::Error)> = nodes
+            .iter()
+            .filter_map(|node| Some((node.name.clone(), self.check(node).err()?)))
+            .collect();
+        Ok(Rendered {
+            content,
+            count: nodes.len() - skipped.len(),
+            skipped,
+        })
+    }
+
+    /// 节点能否以该格式表示
+    fn check(self, node: &ProxyNode) -> Result<()> {
+        match self {
+            ExportTarget::Clash => Ok(()),
+            ExportTarget::Singbox => singbox::outbound(node, &node.name).map(drop),
+            ExportTarget::Links | ExportTarget::Base64 => to_share_link(node).map(drop),
+            ExportTarget::Surge => surge::proxy_line(node).map(drop),
+            ExportTarget::QuanX => quanx::proxy_line(node).map(drop),
+            ExportTarget::Loon => loon::proxy_line(node).map(drop),
+            ExportTarget::Stash if stash::is_supported(node) => Ok(()),
+            ExportTarget::Stash => Err(anyhow!("Stash 不支持的协议: {}", node.kind())),
+        }
+    }
+}
+
+/// 渲染得到的配置文本
+#[derive(Debug)]
+pub struct Rendered {
+    pub content: String,
+    /// 实际写入的节点数
+    pub count: usize,
+    /// 被跳过的节点名称与原因
+    pub skipped: Vec<(String, anyhow::Error)>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::Protocol;
+    use crate::proxy::node::AuthParams;
+
+    #[test]
+    fn test_render_links() {
+        let nodes = [
+            ProxyNode::new(
+                "socks".to_string(),
+                "example.com".to_string(),
+                1080,
+                Protocol::Socks5(AuthParams::default()),
+            ),
+            ProxyNode::new(
+                "direct".to_string(),
+                "example.com".to_string(),
+                0,
+                Protocol::Direct,
+            ),
+        ];
+        for target in ["Links", "base64", "singbox", "surge"] {
+            let rendered = ExportTarget::parse(target).unwrap().render(&nodes).unwrap();
+            assert_eq!(rendered.count, 1, "{}", target);
+            assert_eq!(rendered.skipped.len(), 1, "{}", target);
+            assert_eq!(rendered.skipped[0].0, "direct");
+        }
+        let rendered = ExportTarget::Links.render(&nodes).unwrap();
+        assert_eq!(rendered.content, "socks5://example.com:1080#socks\n");
+        assert!(ExportTarget::parse("xml").is_err());
+    }
+}
@@ -1,18 +1,22 @@
 use anyhow::Result;
 use check::{CheckResult, ProxyChecker};
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use config::{Config, Subscription};
+use export::ResultsWriter;
 use proxy::ProxyNode;
-use regex;
-use serde_yaml;
-use serde_yaml::Value;
-use std::fs;
+use proxy::dedup::{KeepName, dedup_nodes};
+use proxy::fetch::{FetchOptions, fetch_subscriptions};
+use proxy::subscription::{parse_subscription, parse_subscription_with_fallback};
+use proxy::userinfo::SubscriptionUsage;
 use std::path::Path;
 
 use tokio;
 
 mod check;
 mod config;
+mod convert;
+mod export;
+mod outbound;
 mod proxy;
 mod ui;
 
@@ -24,11 +28,14 @@ use clash_proxy::{ClashProxyManager, ProxyHealthChecker};
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// 配置文件路径
     #[arg(short = 'f', long, default_value = "config/config.yaml")]
     config: String,
 
-    /// 订阅链接（多个，用逗号分隔）
+    /// 订阅链接、本地路径或 `-`（多个，用逗号分隔）
     #[arg(short = 's', long)]
     subscriptions: Option<String>,
 
@@ -69,8 +76,8 @@ struct Args {
     speed_url: Option<String>,
 
     /// 输出格式：json, yaml, both
-    #[arg(long, default_value = "both")]
-    format: String,
+    #[arg(long)]
+    format: Option<String>,
 
     /// 生成 Clash 配置文件
     #[arg(long)]
@@ -80,261 +87,113 @@ struct Args {
     #[arg(long)]
     singbox: Option<bool>,
 
-    /// 详细输出
-    #[arg(short, long)]
-    verbose: bool,
-}
-
-fn read_sample_proxies() -> Vec<ProxyNode> {
-    // 读取文件
-    let content = match fs::read_to_string("sample-tiny.yaml") {
-        Ok(v) => v,
-        Err(e) => {
-            eprintln!("Failed to read sample.yaml: {}", e);
-            return vec![];
-        }
-    };
+    /// 生成 Surge 节点列表
+    #[arg(long)]
+    surge: Option<bool>,
 
-    // 先解析成通用 YAML Value
-    let yaml: Value = match serde_yaml::from_str(&content) {
-        Ok(v) => v,
-        Err(e) => {
-            eprintln!("Failed to parse YAML: {}", e);
-            return vec![];
-        }
-    };
+    /// 生成 Quantumult X 节点列表
+    #[arg(long)]
+    quanx: Option<bool>,
 
-    // 获取 "proxies" key
-    let proxies_value = match yaml.get("proxies") {
-        Some(v) => v,
-        None => {
-            eprintln!("No 'proxies' key found in YAML");
-            return vec![];
-        }
-    };
+    /// 生成 Loon 节点列表
+    #[arg(long)]
+    loon: Option<bool>,
 
-    // 反序列化成 Vec<ProxyNode>
-    let proxies: Vec<ProxyNode> = match serde_yaml::from_value(proxies_value.clone()) {
-        Ok(v) => v,
-        Err(e) => {
-            eprintln!("Failed to parse 'proxies': {}", e);
-            return vec![];
-        }
-    };
+    /// 生成 Stash 配置文件
+    #[arg(long)]
+    stash: Option<bool>,
 
-    proxies
+    /// 详细输出
+    #[arg(short, long)]
+    verbose: bool,
 }
 
-async fn fetch_proxies_from_subscriptions(subscriptions: &[Subscription]) -> Vec<ProxyNode> {
-    use base64::Engine;
-    use base64::engine::general_purpose;
-    use reqwest::Client;
-    // use url::Url; // 暂时不需要
-
-    let mut all_proxies = Vec::new();
-    let client = Client::new();
-
-    for subscription in subscriptions {
-        if !subscription.enabled {
-            continue;
-        }
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// 离线转换订阅格式，不检测节点
+    Convert(convert::ConvertArgs),
+}
 
+/// 获取并解析全部订阅，返回各订阅的节点与机场返回的流量信息
+async fn fetch_proxies_from_subscriptions(
+    config: &Config,
+) -> (Vec<(String, Vec<ProxyNode>)>, Vec<SubscriptionUsage>) {
+    let subscriptions: Vec<Subscription> = config
+        .subscriptions
+        .iter()
+        .filter(|subscription| subscription.enabled)
+        .cloned()
+        .collect();
+    for subscription in &subscriptions {
         println!("📡 获取订阅: {} ({})", subscription.name, subscription.url);
+    }
 
-        match client.get(&subscription.url).send().await {
-            Ok(response) => {
-                if response.status().is_success() {
-                    match response.text().await {
-                        Ok(content) => {
-                            // 尝试解码base64内容
-                            let decoded_content = match general_purpose::STANDARD.decode(&content) {
-                                Ok(decoded) => String::from_utf8_lossy(&decoded).to_string(),
-                                Err(_) => content, // 如果不是base64，直接使用原内容
-                            };
-
-                            // 先尝试解析为YAML（Clash配置文件格式）
-                            match serde_yaml::from_str::<Value>(&decoded_content) {
-                                Ok(yaml) => {
-                                    if let Some(proxies_value) = yaml.get("proxies") {
-                                        match serde_yaml::from_value::<Vec<ProxyNode>>(
-                                            proxies_value.clone(),
-                                        ) {
-                                            Ok(proxies) => {
-                                                println!(
-                                                    "✅ 从订阅 {} 获取到 {} 个代理节点 (Clash格式)",
-                                                    subscription.name,
-                                                    proxies.len()
-                                                );
-                                                all_proxies.extend(proxies);
-                                            }
-                                            Err(e) => {
-                                                println!(
-                                                    "⚠️  解析订阅 {} 的代理节点失败: {}",
-                                                    subscription.name, e
-                                                );
-                                            }
-                                        }
-                                    } else {
-                                        // 如果没有找到proxies字段，尝试解析为代理分享链接格式
-                                        println!("🔄 尝试解析为代理分享链接格式...");
-                                        parse_proxy_links(
-                                            &decoded_content,
-                                            &mut all_proxies,
-                                            &subscription.name,
-                                        );
-                                    }
-                                }
-                                Err(_) => {
-                                    // 如果不是YAML格式，尝试解析为代理分享链接格式
-                                    println!("🔄 尝试解析为代理分享链接格式...");
-                                    parse_proxy_links(
-                                        &decoded_content,
-                                        &mut all_proxies,
-                                        &subscription.name,
-                                    );
-                                }
-                            }
-                        }
-                        Err(e) => {
-                            println!("⚠️  读取订阅 {} 内容失败: {}", subscription.name, e);
-                        }
-                    }
-                } else {
-                    println!(
-                        "⚠️  订阅 {} 请求失败: {}",
-                        subscription.name,
-                        response.status()
-                    );
-                }
-            }
+    let results = fetch_subscriptions(&subscriptions, &FetchOptions::from_config(config)).await;
+    let now = chrono::Utc::now();
+    let mut all_proxies = Vec::new();
+    let mut usages = Vec::new();
+    for (subscription, documents) in subscriptions.iter().zip(results) {
+        let documents = match documents {
+            Ok(documents) => documents,
             Err(e) => {
                 println!("⚠️  获取订阅 {} 失败: {}", subscription.name, e);
+                continue;
             }
-        }
-    }
-
-    all_proxies
-}
-
-fn parse_proxy_links(content: &str, all_proxies: &mut Vec<ProxyNode>, subscription_name: &str) {
-    let mut count = 0;
-
-    for line in content.lines() {
-        let line = line.trim();
-        if line.is_empty() || line.starts_with('#') {
-            continue;
-        }
-
-        // 尝试解析为代理链接
-        if let Ok(proxy_node) = parse_proxy_link(line) {
-            all_proxies.push(proxy_node);
-            count += 1;
-        }
-    }
-
-    if count > 0 {
-        println!(
-            "✅ 从订阅 {} 解析到 {} 个代理节点 (分享链接格式)",
-            subscription_name, count
-        );
-    } else {
-        println!("⚠️  订阅 {} 中没有找到有效的代理链接", subscription_name);
-    }
-}
-
-fn parse_proxy_link(link: &str) -> anyhow::Result<ProxyNode> {
-    // 使用新的解析器解析代理链接
-    match proxy::parser::parse_proxy_link(link) {
-        Ok(parsed) => {
-            // 转换为ProxyNode
-            Ok(proxy::parser::to_proxy_node(parsed))
-        }
-        Err(e) => {
-            // 如果解析失败，回退到简单解析
-            println!("⚠️  代理链接解析失败: {} - {}", link, e);
-
-            // 提取名称（如果有的话）
-            let name = if let Some(pos) = link.find('#') {
-                // 提取#后面的部分作为名称
-                let name_part = &link[pos + 1..];
-                // 解码URL编码的名称
-                urlencoding::decode(name_part)
-                    .map(|s| s.to_string())
-                    .unwrap_or_else(|_| name_part.to_string())
+        };
+        let mut nodes = Vec::new();
+        for document in documents {
+            // 流量与到期提醒统一在检测摘要中打印
+            if let Some(info) = document.userinfo {
+                usages.push(SubscriptionUsage::new(
+                    subscription.name.clone(),
+                    info,
+                    now,
+                    config.subscription_expire_warn_days,
+                ));
+            }
+            let parsed = if config.subconverter_fallback {
+                parse_subscription_with_fallback(&document.content)
             } else {
-                // 如果没有名称，使用链接的一部分作为名称
-                let truncated = if link.len() > 30 { &link[..30] } else { link };
-                format!("代理-{}", truncated)
+                parse_subscription(&document.content)
             };
-
-            // 尝试提取服务器和端口
-            let (server, port) = extract_server_port(link);
-
-            // 尝试猜测协议
-            let protocol = guess_protocol(link);
-
-            Ok(ProxyNode {
-                name,
-                server,
-                port,
-                protocol: Some(protocol),
-                ..Default::default()
-            })
-        }
-    }
-}
-
-/// 从链接中提取服务器和端口
-fn extract_server_port(link: &str) -> (String, u16) {
-    // 尝试匹配 IP:PORT 或 域名:PORT 格式
-    if let Ok(re) = regex::Regex::new(r"([a-zA-Z0-9\.\-]+):(\d+)") {
-        if let Some(caps) = re.captures(link) {
-            if let Ok(port) = caps[2].parse::<u16>() {
-                return (caps[1].to_string(), port);
+            for e in &parsed.errors {
+                println!("⚠️  跳过 {} 中的节点: {}", document.label, e);
             }
+            if parsed.nodes.is_empty() {
+                println!("⚠️  订阅 {} 中没有找到有效的代理节点", document.label);
+            } else {
+                println!(
+                    "✅ 从订阅 {} 获取到 {} 个代理节点 ({})",
+                    document.label,
+                    parsed.nodes.len(),
+                    parsed.format.name()
+                );
+            }
+            nodes.extend(parsed.nodes);
         }
+        all_proxies.push((subscription.name.clone(), nodes));
     }
 
-    // 尝试从URL中提取
-    if let Ok(url) = url::Url::parse(link) {
-        if let Some(host) = url.host_str() {
-            let port = url.port().unwrap_or(0);
-            return (host.to_string(), port);
-        }
-    }
-
-    ("unknown".to_string(), 0)
+    (all_proxies, usages)
 }
 
-/// 根据链接猜测协议类型
-fn guess_protocol(link: &str) -> String {
-    let link_lower = link.to_lowercase();
-
-    if link_lower.contains("vmess://") {
-        "vmess".to_string()
-    } else if link_lower.contains("vless://") {
-        "vless".to_string()
-    } else if link_lower.contains("trojan://") {
-        "trojan".to_string()
-    } else if link_lower.contains("ss://") {
-        "ss".to_string()
-    } else if link_lower.contains("ssr://") {
-        "ssr".to_string()
-    } else if link_lower.contains("hysteria://") {
-        "hysteria".to_string()
-    } else if link_lower.contains("hysteria2://") {
-        "hysteria2".to_string()
-    } else if link_lower.contains("http://") {
-        "http".to_string()
-    } else if link_lower.contains("https://") {
-        "https".to_string()
-    } else if link_lower.contains("socks5://") {
-        "socks5".to_string()
-    } else if link_lower.contains("socks4://") {
-        "socks4".to_string()
-    } else {
-        "unknown".to_string()
+/// 合并各订阅的节点，按配置去除重复节点并打印每个订阅贡献的重复数
+fn merge_subscription_nodes(
+    groups: Vec<(String, Vec<ProxyNode>)>,
+    dedup: Option<KeepName>,
+) -> Vec<ProxyNode> {
+    let Some(keep) = dedup else {
+        return groups.into_iter().flat_map(|(_, nodes)| nodes).collect();
+    };
+
+    let (nodes, report) = dedup_nodes(groups, keep);
+    if report.total() > 0 {
+        println!("🔁 去除 {} 个重复节点", report.total());
+        for (name, count) in report.duplicates.iter().filter(|(_, count)| *count > 0) {
+            println!("   {}: {} 个重复", name, count);
+        }
     }
+    nodes
 }
 
 fn print_results(results: &[CheckResult]) {
@@ -379,6 +238,15 @@ fn print_results(results: &[CheckResult]) {
                 }
             );
 
+            println!(
+                "   UDP: {}",
+                if result.udp {
+                    "✅ 支持"
+                } else {
+                    "❌ 不支持"
+                }
+            );
+
             if result.media_unlock.youtube
                 || result.media_unlock.netflix
                 || result.media_unlock.disney
@@ -412,7 +280,7 @@ fn print_results(results: &[CheckResult]) {
     }
 }
 
-fn print_summary(results: &[CheckResult]) {
+fn print_summary(results: &[CheckResult], usages: &[SubscriptionUsage]) {
     let total = results.len();
     let alive: Vec<&CheckResult> = results.iter().filter(|r| r.is_alive).collect();
     let dead: Vec<&CheckResult> = results.iter().filter(|r| !r.is_alive).collect();
@@ -463,19 +331,34 @@ fn print_summary(results: &[CheckResult]) {
         println!("    Disney+: {}/{}", disney_unlock, alive.len());
         println!("    OpenAI: {}/{}", openai_unlock, alive.len());
     }
+
+    if !usages.is_empty() {
+        println!("\n订阅流量:");
+        for usage in usages {
+            println!("  {}: {}", usage.name, usage.info.summary());
+            for warning in &usage.warnings {
+                println!("    ⚠️  {}", warning);
+            }
+        }
+    }
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    // 解析命令行参数
+    let mut args = Args::parse();
+
+    // 离线转换不需要联网，直接执行后退出
+    if let Some(Command::Convert(convert_args)) = args.command.take() {
+        return convert::run(convert_args);
+    }
+
     // 运行clash_proxy测试
     if let Err(e) = clash_proxy_test().await {
         eprintln!("错误: {:#}", e);
         std::process::exit(1);
     }
 
-    // 解析命令行参数
-    let args = Args::parse();
-
     // 设置日志级别
     unsafe {
         if args.verbose {
@@ -530,12 +413,27 @@ async fn main() -> Result<()> {
     if let Some(speed_url) = args.speed_url {
         config.speed_test_url = Some(speed_url);
     }
+    if let Some(format) = args.format {
+        config.output_format = format;
+    }
     if let Some(clash) = args.clash {
         config.generate_clash_config = clash;
     }
     if let Some(singbox) = args.singbox {
         config.generate_singbox_config = singbox;
     }
+    if let Some(surge) = args.surge {
+        config.generate_surge_config = surge;
+    }
+    if let Some(quanx) = args.quanx {
+        config.generate_quanx_config = quanx;
+    }
+    if let Some(loon) = args.loon {
+        config.generate_loon_config = loon;
+    }
+    if let Some(stash) = args.stash {
+        config.generate_stash_config = stash;
+    }
 
     // 处理订阅链接
     if let Some(subscriptions) = args.subscriptions {
@@ -545,6 +443,7 @@ async fn main() -> Result<()> {
                 name: format!("订阅-{}", url),
                 url: url.to_string(),
                 enabled: true,
+                ..Default::default()
             });
         }
     }
@@ -586,7 +485,7 @@ async fn main() -> Result<()> {
             "❌ 禁用"
         }
     );
-    println!("  输出格式: {}", args.format);
+    println!("  输出格式: {}", config.output_format);
     println!(
         "  Clash 配置: {}",
         if config.generate_clash_config {
@@ -603,6 +502,23 @@ async fn main() -> Result<()> {
             "❌ 不生成"
         }
     );
+    let clients: Vec<&str> = [
+        ("Surge", config.generate_surge_config),
+        ("Quantumult X", config.generate_quanx_config),
+        ("Loon", config.generate_loon_config),
+        ("Stash", config.generate_stash_config),
+    ]
+    .into_iter()
+    .filter_map(|(name, enabled)| enabled.then_some(name))
+    .collect();
+    println!(
+        "  其他客户端: {}",
+        if clients.is_empty() {
+            "❌ 不生成".to_string()
+        } else {
+            format!("✅ {}", clients.join(", "))
+        }
+    );
 
     // 创建检测器
     let config_clone = config.clone();
@@ -610,17 +526,20 @@ async fn main() -> Result<()> {
 
     // 获取代理列表
     println!("\n📡 获取代理节点...");
-    let mut proxies = if !config.subscriptions.is_empty() {
-        // 从订阅链接获取代理节点
-        fetch_proxies_from_subscriptions(&config.subscriptions).await
+    if config.subscriptions.is_empty() {
+        println!("⚠️  没有配置订阅，请在配置文件的 subscriptions 中添加或使用 -s 参数");
+        return Ok(());
+    }
+    let dedup = if config.dedup {
+        Some(KeepName::parse(&config.dedup_keep_name)?)
     } else {
-        // 如果没有订阅链接，使用示例数据
-        read_sample_proxies()
+        None
     };
-
+    let (groups, usages) = fetch_proxies_from_subscriptions(&config).await;
+    let mut proxies = merge_subscription_nodes(groups, dedup);
     if proxies.is_empty() {
-        println!("⚠️  没有获取到任何代理节点，使用示例数据");
-        proxies = read_sample_proxies();
+        println!("⚠️  没有获取到任何代理节点");
+        return Ok(());
     }
 
     println!("✅ 获取到 {} 个代理节点", proxies.len());
@@ -653,13 +572,21 @@ async fn main() -> Result<()> {
     print_results(&results);
 
     // 打印摘要
-    print_summary(&results);
+    print_summary(&results, &usages);
 
     // 保存结果（如果配置了输出目录）
     if !config.output_dir.is_empty() {
         println!("\n💾 保存检测结果到: {}", config.output_dir);
-        // 这里可以添加保存结果的逻辑
-        println!("✅ 结果保存完成");
+        match ResultsWriter::from_config(&config).and_then(|writer| writer.write(&results, &usages))
+        {
+            Ok(paths) => {
+                for path in paths {
+                    println!("  ├── {}", path.display());
+                }
+                println!("✅ 结果保存完成");
+            }
+            Err(e) => println!("⚠️  保存检测结果失败: {}", e),
+        }
     }
 
     println!("\n🎉 检测完成!");
@@ -681,23 +608,28 @@ async fn clash_proxy_test() -> Result<()> {
 
     // 4. 打印代理节点信息
     println!("📄 共加载 {} 个代理节点：", proxy_nodes.len());
-    for (idx, node) in proxy_nodes.iter().enumerate() {
+    for (idx, checked) in proxy_nodes.iter().enumerate() {
+        let node = &checked.node;
         println!(
             "\n[{}/{}] 代理节点: {}",
             idx + 1,
             proxy_nodes.len(),
             node.name
         );
-        println!("  ├── 协议类型: {}", node.proto);
+        println!("  ├── 协议类型: {}", node.kind());
         println!("  ├── 服务器地址: {}:{}", node.server, node.port);
         println!(
             "  ├── 支持 UDP: {}",
-            if node.support_udp { "✅" } else { "❌" }
+            if node.udp == Some(true) { "✅" } else { "❌" }
         );
         println!(
             "  └── 延迟: {}",
-            if node.delay_ms > 0 {
-                format!("{}ms ({})", node.delay_ms, node.get_delay_description())
+            if checked.delay_ms > 0 {
+                format!(
+                    "{}ms ({})",
+                    checked.delay_ms,
+                    checked.get_delay_description()
+                )
             } else {
                 "❌ 检查失败".to_string()
             }
@@ -720,7 +652,7 @@ async fn clash_proxy_test() -> Result<()> {
                 println!(
                     "  {}. {} - {}ms ({})",
                     idx + 1,
-                    proxy.name,
+                    proxy.node.name,
                     proxy.delay_ms,
                     proxy.get_delay_description()
                 );
@@ -773,7 +705,7 @@ async fn test_health_checker(clash_manager: &ClashProxyManager) -> Result<()> {
             println!("\n🏆 最快的3个节点:");
             for (i, proxy) in sorted_proxies.iter().take(3).enumerate() {
                 if proxy.delay_ms > 0 {
-                    println!("  {}. {} - {}ms", i + 1, proxy.name, proxy.delay_ms);
+                    println!("  {}. {} - {}ms", i + 1, proxy.node.name, proxy.delay_ms);
                 }
             }
         }
@@ -0,0 +1,524 @@
+//! AnyTLS 出站
+//! TLS 握手后发送 SHA256 密码认证，之后以会话帧承载单个子流：
+//! 帧头为命令（1 字节）+ 流 ID（4 字节）+ 长度（2 字节），会话前若干个包按填充方案补齐长度
+
+use super::util::{FramedWrite, copy_plain, invalid_data, poll_drain, poll_fill};
+use super::{Address, BoxedStream, transport};
+use crate::proxy::ProxyNode;
+use crate::proxy::node::AnyTlsParams;
+use anyhow::{Result, anyhow};
+use md5::Md5;
+use rand::Rng;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll, ready};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+const CMD_WASTE: u8 = 0;
+const CMD_SYN: u8 = 1;
+const CMD_PSH: u8 = 2;
+const CMD_FIN: u8 = 3;
+const CMD_SETTINGS: u8 = 4;
+const CMD_ALERT: u8 = 5;
+const CMD_UPDATE_PADDING_SCHEME: u8 = 6;
+const CMD_SYNACK: u8 = 7;
+const CMD_HEART_REQUEST: u8 = 8;
+const CMD_HEART_RESPONSE: u8 = 9;
+const CMD_SERVER_SETTINGS: u8 = 10;
+
+/// 帧头长度
+const FRAME_HEADER_LEN: usize = 7;
+/// 子流 ID，会话内从 1 开始分配
+const STREAM_ID: u32 = 1;
+/// 单帧最大数据长度
+const MAX_FRAME_DATA: usize = u16::MAX as usize;
+/// 客户端标识
+const CLIENT_NAME: &str = "anytls/0.0.1";
+
+/// 参考实现的默认填充方案
+const DEFAULT_PADDING_SCHEME: &str = "stop=8\n0=30-30\n1=100-400\n2=400-500,c,500-1000,c,500-1000,c,500-1000,c,500-1000\n3=9-9,500-1000\n4=500-1000\n5=500-1000\n6=500-1000\n7=500-1000";
+
+/// 填充方案：前 `stop` 个包按对应的长度区间补齐
+///
+/// 分段标记 `c` 之后的区间只在拆包发送时使用，这里每个包只取第一个区间
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PaddingScheme {
+    raw: String,
+    stop: u32,
+    sizes: HashMap<u32, (usize, usize)>,
+}
+
+impl PaddingScheme {
+    /// 解析 `key=value` 逐行格式的填充方案
+    pub fn parse(raw: &str) -> Option<Self> {
+        let mut stop = None;
+        let mut sizes = HashMap::new();
+        for line in raw.lines() {
+            let (key, value) = line.split_once('=')?;
+            if key == "stop" {
+                stop = Some(value.trim().parse().ok()?);
+                continue;
+            }
+            let pkt: u32 = key.trim().parse().ok()?;
+            let first = value.split(',').next()?;
+            let (min, max) = first.split_once('-')?;
+            let (min, max): (usize, usize) = (min.trim().parse().ok()?, max.trim().parse().ok()?);
+            if min > max {
+                return None;
+            }
+            sizes.insert(pkt, (min, max));
+        }
+        Some(Self {
+            raw: raw.to_string(),
+            stop: stop?,
+            sizes,
+        })
+    }
+
+    /// 方案文本的 MD5，客户端在设置帧中上报，服务端据此决定是否下发新方案
+    pub fn md5_hex(&self) -> String {
+        Md5::digest(self.raw.as_bytes())
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+
+    /// 第 `pkt` 个包的目标长度
+    fn size(&self, pkt: u32) -> Option<usize> {
+        if pkt >= self.stop {
+            return None;
+        }
+        let &(min, max) = self.sizes.get(&pkt)?;
+        Some(rand::thread_rng().gen_range(min..=max))
+    }
+}
+
+impl Default for PaddingScheme {
+    fn default() -> Self {
+        Self::parse(DEFAULT_PADDING_SCHEME).expect("默认填充方案格式错误")
+    }
+}
+
+/// 会话帧
+fn frame(cmd: u8, stream_id: u32, data: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(FRAME_HEADER_LEN + data.len());
+    frame.push(cmd);
+    frame.extend_from_slice(&stream_id.to_be_bytes());
+    frame.extend_from_slice(&(data.len() as u16).to_be_bytes());
+    frame.extend_from_slice(data);
+    frame
+}
+
+/// 从缓冲区开头解析一帧，返回命令、流 ID、数据与帧长；数据不足时返回 `None`
+fn parse_frame(buf: &[u8]) -> Option<(u8, u32, Vec<u8>, usize)> {
+    if buf.len() < FRAME_HEADER_LEN {
+        return None;
+    }
+    let stream_id = u32::from_be_bytes([buf[1], buf[2], buf[3], buf[4]]);
+    let len = FRAME_HEADER_LEN + u16::from_be_bytes([buf[5], buf[6]]) as usize;
+    if buf.len() < len {
+        return None;
+    }
+    Some((buf[0], stream_id, buf[FRAME_HEADER_LEN..len].to_vec(), len))
+}
+
+/// 认证包：SHA256(password) + 填充长度 + 填充
+fn auth_packet(password: &str, scheme: &PaddingScheme) -> Vec<u8> {
+    let mut packet = Sha256::digest(password.as_bytes()).to_vec();
+    let padding = scheme
+        .size(0)
+        .map_or(0, |size| size.saturating_sub(packet.len() + 2));
+    packet.extend_from_slice(&(padding as u16).to_be_bytes());
+    packet.resize(packet.len() + padding, 0);
+    packet
+}
+
+/// AnyTLS 单子流连接
+pub struct AnyTlsStream<S> {
+    inner: S,
+    /// 尚未发出的认证包与建流帧，随第一次写入发出
+    preface: Option<(Vec<u8>, Vec<u8>)>,
+    scheme: PaddingScheme,
+    /// 已发出的包数，认证包为第 0 个
+    packets: u32,
+    closed: bool,
+    rbuf: Vec<u8>,
+    plain: Vec<u8>,
+    plain_pos: usize,
+    wbuf: Vec<u8>,
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> AnyTlsStream<S> {
+    pub fn new(inner: S, password: &str, target: &Address) -> Self {
+        let scheme = PaddingScheme::default();
+        let auth = auth_packet(password, &scheme);
+        let settings = format!(
+            "v=2\nclient={}\npadding-md5={}",
+            CLIENT_NAME,
+            scheme.md5_hex()
+        );
+        let mut header = frame(CMD_SETTINGS, 0, settings.as_bytes());
+        header.extend(frame(CMD_SYN, STREAM_ID, &[]));
+        let mut addr = Vec::new();
+        target.write_socks(&mut addr);
+        header.extend(frame(CMD_PSH, STREAM_ID, &addr));
+
+        Self {
+            inner,
+            preface: Some((auth, header)),
+            scheme,
+            packets: 0,
+            closed: false,
+            rbuf: Vec::new(),
+            plain: Vec::new(),
+            plain_pos: 0,
+            wbuf: Vec::new(),
+        }
+    }
+
+    /// 把一个包写入发送缓冲区，首个包前带上认证包，填充期内补上 Waste 帧
+    fn push_packet(&mut self, mut packet: Vec<u8>) {
+        if let Some((auth, mut header)) = self.preface.take() {
+            // 认证包单独计为第 0 个包，建流帧与首段数据合并为第 1 个包
+            self.wbuf.extend(auth);
+            header.append(&mut packet);
+            packet = header;
+            self.packets = 1;
+        }
+        if let Some(size) = self.scheme.size(self.packets)
+            && packet.len() + FRAME_HEADER_LEN <= size
+        {
+            let waste = vec![0u8; size - packet.len() - FRAME_HEADER_LEN];
+            packet.extend(frame(CMD_WASTE, 0, &waste));
+        }
+        self.packets = self.packets.saturating_add(1);
+        self.wbuf.extend(packet);
+    }
+
+    /// 处理一帧服务端数据
+    fn handle_frame(&mut self, cmd: u8, stream_id: u32, data: Vec<u8>) -> io::Result<()> {
+        match cmd {
+            CMD_PSH if stream_id == STREAM_ID => {
+                self.plain = data;
+                self.plain_pos = 0;
+            }
+            CMD_FIN if stream_id == STREAM_ID => self.closed = true,
+            CMD_SYNACK if !data.is_empty() => {
+                return Err(io::Error::new(
+                    io::ErrorKind::ConnectionRefused,
+                    format!("AnyTLS 服务端拒绝连接: {}", String::from_utf8_lossy(&data)),
+                ));
+            }
+            CMD_ALERT => {
+                return Err(io::Error::new(
+                    io::ErrorKind::ConnectionAborted,
+                    format!("AnyTLS 服务端告警: {}", String::from_utf8_lossy(&data)),
+                ));
+            }
+            CMD_UPDATE_PADDING_SCHEME => {
+                if let Some(scheme) = std::str::from_utf8(&data)
+                    .ok()
+                    .and_then(PaddingScheme::parse)
+                {
+                    self.scheme = scheme;
+                }
+            }
+            CMD_HEART_REQUEST => self.wbuf.extend(frame(CMD_HEART_RESPONSE, stream_id, &[])),
+            CMD_WASTE | CMD_SYNACK | CMD_SERVER_SETTINGS | CMD_HEART_RESPONSE => {}
+            // 未知命令与单子流无关，直接忽略
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> AsyncRead for AnyTlsStream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        loop {
+            if copy_plain(&mut this.plain, &mut this.plain_pos, buf) || this.closed {
+                return Poll::Ready(Ok(()));
+            }
+            if let Some((cmd, stream_id, data, len)) = parse_frame(&this.rbuf) {
+                this.rbuf.drain(..len);
+                this.handle_frame(cmd, stream_id, data)?;
+                if !this.wbuf.is_empty() && this.preface.is_none() {
+                    // 心跳响应尽力发出，写不进去时留到下次写入
+                    if let Poll::Ready(Err(e)) = poll_drain(&mut this.inner, cx, &mut this.wbuf) {
+                        return Poll::Ready(Err(e));
+                    }
+                }
+                continue;
+            }
+            let need = this.rbuf.len() + 1;
+            if !ready!(poll_fill(&mut this.inner, cx, &mut this.rbuf, need))? {
+                if this.rbuf.is_empty() {
+                    return Poll::Ready(Ok(()));
+                }
+                return Poll::Ready(Err(invalid_data("AnyTLS 帧不完整")));
+            }
+        }
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> FramedWrite for AnyTlsStream<S> {
+    type Inner = S;
+
+    fn write_parts(&mut self) -> (&mut S, &mut Vec<u8>) {
+        (&mut self.inner, &mut self.wbuf)
+    }
+
+    fn encode_frame(&mut self, data: &[u8]) -> usize {
+        let n = data.len().min(MAX_FRAME_DATA);
+        self.push_packet(frame(CMD_PSH, STREAM_ID, &data[..n]));
+        n
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> AsyncWrite for AnyTlsStream<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.get_mut().poll_write_framed(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if this.preface.is_some() {
+            this.push_packet(Vec::new());
+        }
+        ready!(poll_drain(&mut this.inner, cx, &mut this.wbuf))?;
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        // 子流已建立时通知服务端结束
+        if this.preface.is_none() && !this.closed {
+            this.closed = true;
+            this.wbuf.extend(frame(CMD_FIN, STREAM_ID, &[]));
+        }
+        ready!(poll_drain(&mut this.inner, cx, &mut this.wbuf))?;
+        Pin::new(&mut this.inner).poll_shutdown(cx)
+    }
+}
+
+/// 通过 AnyTLS 节点连接目标地址
+///
+/// 认证包与建流帧随首段数据一起发出，因此这里不主动 flush
+pub async fn connect(
+    proxy: &ProxyNode,
+    params: &AnyTlsParams,
+    target: &Address,
+) -> Result<BoxedStream> {
+    let stream = transport::connect(proxy, true)
+        .await
+        .map_err(|e| anyhow!("AnyTLS {}", e))?;
+    Ok(Box::new(AnyTlsStream::new(
+        stream,
+        &params.password,
+        target,
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::outbound::dial;
+    use crate::outbound::tls::test_server;
+    use crate::proxy::Protocol;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt, duplex};
+    use tokio::net::TcpListener;
+
+    /// 读取一帧，跳过填充用的 Waste 帧
+    async fn read_frame<R: AsyncRead + Unpin>(reader: &mut R) -> (u8, u32, Vec<u8>) {
+        loop {
+            let cmd = reader.read_u8().await.unwrap();
+            let stream_id = reader.read_u32().await.unwrap();
+            let mut data = vec![0u8; reader.read_u16().await.unwrap() as usize];
+            reader.read_exact(&mut data).await.unwrap();
+            if cmd != CMD_WASTE {
+                return (cmd, stream_id, data);
+            }
+        }
+    }
+
+    /// 启动本地 AnyTLS 服务端替身：校验认证与建流帧后回显数据
+    async fn spawn_server(password: &'static str) -> u16 {
+        let acceptor = test_server::acceptor("anytls.test", &[]);
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (tcp, _) = listener.accept().await.unwrap();
+            let mut stream = acceptor.accept(tcp).await.unwrap();
+
+            let mut hash = [0u8; 32];
+            stream.read_exact(&mut hash).await.unwrap();
+            if hash[..] != Sha256::digest(password.as_bytes())[..] {
+                return;
+            }
+            let mut padding = vec![0u8; stream.read_u16().await.unwrap() as usize];
+            stream.read_exact(&mut padding).await.unwrap();
+
+            let (cmd, _, settings) = read_frame(&mut stream).await;
+            assert_eq!(cmd, CMD_SETTINGS);
+            let settings = String::from_utf8(settings).unwrap();
+            assert!(settings.starts_with("v=2\n"));
+            assert!(settings.contains(&PaddingScheme::default().md5_hex()));
+            assert_eq!(read_frame(&mut stream).await, (CMD_SYN, STREAM_ID, vec![]));
+            let (cmd, stream_id, addr) = read_frame(&mut stream).await;
+            assert_eq!((cmd, stream_id), (CMD_PSH, STREAM_ID));
+            let target = Address::read_socks(&mut addr.as_slice()).await.unwrap();
+            assert_eq!(target, Address::Domain("example.com".to_string(), 80));
+
+            stream
+                .write_all(&frame(CMD_SERVER_SETTINGS, 0, b"v=2"))
+                .await
+                .unwrap();
+            stream
+                .write_all(&frame(CMD_SYNACK, STREAM_ID, &[]))
+                .await
+                .unwrap();
+            loop {
+                let (cmd, stream_id, data) = read_frame(&mut stream).await;
+                match cmd {
+                    CMD_PSH => stream
+                        .write_all(&frame(CMD_PSH, stream_id, &data))
+                        .await
+                        .unwrap(),
+                    CMD_FIN => {
+                        stream
+                            .write_all(&frame(CMD_FIN, stream_id, &[]))
+                            .await
+                            .unwrap();
+                        break;
+                    }
+                    _ => {}
+                }
+            }
+        });
+        port
+    }
+
+    fn node(port: u16, password: &str) -> ProxyNode {
+        let mut node = ProxyNode::new(
+            "anytls".to_string(),
+            "127.0.0.1".to_string(),
+            port,
+            Protocol::AnyTls(AnyTlsParams {
+                password: password.to_string(),
+                ..Default::default()
+            }),
+        );
+        node.tls.sni = Some("anytls.test".to_string());
+        node.tls.skip_cert_verify = Some(true);
+        node
+    }
+
+    #[test]
+    fn test_padding_scheme() {
+        let scheme = PaddingScheme::default();
+        assert_eq!(scheme.stop, 8);
+        assert_eq!(scheme.sizes[&2], (400, 500));
+        assert_eq!(scheme.size(0), Some(30));
+        assert_eq!(scheme.size(8), None);
+        assert_eq!(scheme.md5_hex().len(), 32);
+        assert!(PaddingScheme::parse("stop=2\n0=9-1").is_none());
+        assert!(PaddingScheme::parse("0=1-2").is_none());
+    }
+
+    #[test]
+    fn test_auth_packet() {
+        let packet = auth_packet("password", &PaddingScheme::default());
+        // 默认方案第 0 个包为 30 字节，不足以容纳认证头，因此不填充
+        assert_eq!(packet.len(), 34);
+        assert_eq!(&packet[32..], &[0, 0]);
+
+        let scheme = PaddingScheme::parse("stop=1\n0=100-100").unwrap();
+        let packet = auth_packet("password", &scheme);
+        assert_eq!(packet.len(), 100);
+        assert_eq!(u16::from_be_bytes([packet[32], packet[33]]), 66);
+    }
+
+    #[tokio::test]
+    async fn test_stream_frames() {
+        let (client, mut server) = duplex(65536);
+        let target = Address::Domain("example.com".to_string(), 443);
+        let mut stream = AnyTlsStream::new(client, "password", &target);
+        stream.write_all(b"hello").await.unwrap();
+
+        let mut auth = [0u8; 34];
+        server.read_exact(&mut auth).await.unwrap();
+        assert_eq!(read_frame(&mut server).await.0, CMD_SETTINGS);
+        assert_eq!(read_frame(&mut server).await.0, CMD_SYN);
+        assert_eq!(read_frame(&mut server).await.0, CMD_PSH);
+        assert_eq!(
+            read_frame(&mut server).await,
+            (CMD_PSH, STREAM_ID, b"hello".to_vec())
+        );
+
+        let mut response = frame(CMD_HEART_REQUEST, 0, &[]);
+        response.extend(frame(CMD_UPDATE_PADDING_SCHEME, 0, b"stop=1\n0=1-2"));
+        response.extend(frame(CMD_PSH, STREAM_ID, b"hello"));
+        response.extend(frame(CMD_PSH, STREAM_ID, b" anytls"));
+        response.extend(frame(CMD_FIN, STREAM_ID, &[]));
+        server.write_all(&response).await.unwrap();
+        let mut reply = Vec::new();
+        stream.read_to_end(&mut reply).await.unwrap();
+        assert_eq!(reply, b"hello anytls");
+        assert_eq!(stream.scheme.stop, 1);
+        assert_eq!(
+            read_frame(&mut server).await,
+            (CMD_HEART_RESPONSE, 0, vec![])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_alert_is_error() {
+        let (client, mut server) = duplex(65536);
+        let target = Address::Domain("example.com".to_string(), 443);
+        let mut stream = AnyTlsStream::new(client, "password", &target);
+        server
+            .write_all(&frame(CMD_ALERT, 0, b"bad auth"))
+            .await
+            .unwrap();
+        let mut buf = [0u8; 16];
+        let err = stream.read(&mut buf).await.unwrap_err();
+        assert!(err.to_string().contains("bad auth"));
+    }
+
+    #[tokio::test]
+    async fn test_anytls_roundtrip() {
+        let port = spawn_server("password").await;
+        let target = Address::Domain("example.com".to_string(), 80);
+        let mut stream = dial(&node(port, "password"), &target).await.unwrap();
+
+        stream.write_all(b"hello anytls").await.unwrap();
+        let mut buf = [0u8; 12];
+        stream.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"hello anytls");
+
+        stream.shutdown().await.unwrap();
+        let mut rest = Vec::new();
+        stream.read_to_end(&mut rest).await.unwrap();
+        assert!(rest.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_anytls_wrong_password() {
+        let port = spawn_server("password").await;
+        let target = Address::Domain("example.com".to_string(), 80);
+        let mut stream = dial(&node(port, "wrong"), &target).await.unwrap();
+        stream.write_all(b"hello").await.unwrap();
+        let mut buf = [0u8; 5];
+        assert!(stream.read_exact(&mut buf).await.is_err());
+    }
+}
@@ -0,0 +1,146 @@
+//! 出站协议共用的加密原语封装
+
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::{Aes128Gcm, Aes256Gcm};
+use chacha20poly1305::ChaCha20Poly1305;
+use md5::{Digest, Md5};
+use std::io;
+
+/// AEAD 认证标签长度
+pub const TAG_LEN: usize = 16;
+
+/// AEAD 算法类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AeadKind {
+    Aes128Gcm,
+    Aes256Gcm,
+    ChaCha20Poly1305,
+}
+
+impl AeadKind {
+    /// 密钥长度（字节）
+    pub fn key_len(self) -> usize {
+        match self {
+            AeadKind::Aes128Gcm => 16,
+            AeadKind::Aes256Gcm | AeadKind::ChaCha20Poly1305 => 32,
+        }
+    }
+}
+
+/// 已初始化密钥的 AEAD 加解密器
+pub enum AeadCipher {
+    Aes128Gcm(Box<Aes128Gcm>),
+    Aes256Gcm(Box<Aes256Gcm>),
+    ChaCha20Poly1305(Box<ChaCha20Poly1305>),
+}
+
+impl AeadCipher {
+    /// 创建加解密器，`key` 长度必须与算法匹配
+    pub fn new(kind: AeadKind, key: &[u8]) -> Self {
+        match kind {
+            AeadKind::Aes128Gcm => AeadCipher::Aes128Gcm(Box::new(
+                Aes128Gcm::new_from_slice(key).expect("AES-128-GCM 密钥长度错误"),
+            )),
+            AeadKind::Aes256Gcm => AeadCipher::Aes256Gcm(Box::new(
+                Aes256Gcm::new_from_slice(key).expect("AES-256-GCM 密钥长度错误"),
+            )),
+            AeadKind::ChaCha20Poly1305 => AeadCipher::ChaCha20Poly1305(Box::new(
+                ChaCha20Poly1305::new_from_slice(key).expect("ChaCha20-Poly1305 密钥长度错误"),
+            )),
+        }
+    }
+
+    /// 加密并附加认证标签，`nonce` 为 12 字节
+    pub fn encrypt(&self, nonce: &[u8], plaintext: &[u8]) -> Vec<u8> {
+        self.encrypt_aad(nonce, plaintext, &[])
+    }
+
+    /// 带附加认证数据的加密
+    pub fn encrypt_aad(&self, nonce: &[u8], plaintext: &[u8], aad: &[u8]) -> Vec<u8> {
+        let nonce = aes_gcm::Nonce::from_slice(nonce);
+        let payload = Payload {
+            msg: plaintext,
+            aad,
+        };
+        let result = match self {
+            AeadCipher::Aes128Gcm(c) => c.encrypt(nonce, payload),
+            AeadCipher::Aes256Gcm(c) => c.encrypt(nonce, payload),
+            AeadCipher::ChaCha20Poly1305(c) => c.encrypt(nonce, payload),
+        };
+        result.expect("AEAD 加密失败")
+    }
+
+    /// 校验认证标签并解密
+    pub fn decrypt(&self, nonce: &[u8], ciphertext: &[u8]) -> io::Result<Vec<u8>> {
+        self.decrypt_aad(nonce, ciphertext, &[])
+    }
+
+    /// 带附加认证数据的解密
+    pub fn decrypt_aad(&self, nonce: &[u8], ciphertext: &[u8], aad: &[u8]) -> io::Result<Vec<u8>> {
+        let nonce = aes_gcm::Nonce::from_slice(nonce);
+        let payload = Payload {
+            msg: ciphertext,
+            aad,
+        };
+        let result = match self {
+            AeadCipher::Aes128Gcm(c) => c.decrypt(nonce, payload),
+            AeadCipher::Aes256Gcm(c) => c.decrypt(nonce, payload),
+            AeadCipher::ChaCha20Poly1305(c) => c.decrypt(nonce, payload),
+        };
+        result.map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "AEAD 解密失败"))
+    }
+}
+
+/// 将 nonce 视为小端序计数器加一
+pub fn increment_nonce(nonce: &mut [u8]) {
+    for byte in nonce.iter_mut() {
+        let (value, overflow) = byte.overflowing_add(1);
+        *byte = value;
+        if !overflow {
+            break;
+        }
+    }
+}
+
+/// OpenSSL `EVP_BytesToKey`（MD5，单轮）密钥派生，Shadowsocks 系列协议用它把密码转为主密钥
+pub fn evp_bytes_to_key(password: &[u8], key_len: usize) -> Vec<u8> {
+    let mut key = Vec::with_capacity(key_len + 16);
+    let mut prev: Vec<u8> = Vec::new();
+    while key.len() < key_len {
+        let mut hasher = Md5::new();
+        hasher.update(&prev);
+        hasher.update(password);
+        prev = hasher.finalize().to_vec();
+        key.extend_from_slice(&prev);
+    }
+    key.truncate(key_len);
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evp_bytes_to_key() {
+        // 16 字节时等于 MD5(password)
+        let key = evp_bytes_to_key(b"password", 16);
+        assert_eq!(
+            key,
+            vec![
+                0x5f, 0x4d, 0xcc, 0x3b, 0x5a, 0xa7, 0x65, 0xd6, 0x1d, 0x83, 0x27, 0xde, 0xb8, 0x82,
+                0xcf, 0x99
+            ]
+        );
+        let long_key = evp_bytes_to_key(b"password", 32);
+        assert_eq!(&long_key[..16], key.as_slice());
+        assert_eq!(long_key.len(), 32);
+    }
+
+    #[test]
+    fn test_increment_nonce() {
+        let mut nonce = [0xffu8, 0x00, 0x00];
+        increment_nonce(&mut nonce);
+        assert_eq!(nonce, [0x00, 0x01, 0x00]);
+    }
+}
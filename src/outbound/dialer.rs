@@ -0,0 +1,198 @@
+//! 统一拨号接口
+//! 检测流程的每个阶段都通过 `Dialer` 建立到目标的 TCP 连接或 UDP 关联，
+//! 不再区分直连、原生协议或代理 URL
+
+use super::{Address, BoxedDatagram, BoxedStream, Datagram, dial, socks, trojan};
+use crate::proxy::{Protocol, ProxyNode};
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use std::io;
+use std::net::SocketAddr;
+use tokio::net::{TcpStream, UdpSocket};
+
+/// 把节点（或直连）变成到目标地址的连接
+#[async_trait]
+pub trait Dialer: Send + Sync {
+    /// 建立到目标的 TCP 连接
+    async fn connect_tcp(&self, target: &Address) -> Result<BoxedStream>;
+
+    /// 建立到目标的 UDP 关联
+    async fn connect_udp(&self, target: &Address) -> Result<BoxedDatagram> {
+        Err(anyhow!("该出站不支持 UDP: {}", target))
+    }
+
+    /// 是否实现了 UDP 关联，未实现时不必发起连接
+    fn supports_udp(&self) -> bool {
+        false
+    }
+}
+
+/// 不经过代理直接连接
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DirectDialer;
+
+#[async_trait]
+impl Dialer for DirectDialer {
+    async fn connect_tcp(&self, target: &Address) -> Result<BoxedStream> {
+        let stream = match target {
+            Address::Domain(domain, port) => TcpStream::connect((domain.as_str(), *port)).await,
+            Address::Socket(addr) => TcpStream::connect(addr).await,
+        }
+        .map_err(|e| anyhow!("连接 {} 失败: {}", target, e))?;
+        stream.set_nodelay(true)?;
+        Ok(Box::new(stream))
+    }
+
+    async fn connect_udp(&self, target: &Address) -> Result<BoxedDatagram> {
+        let remote = resolve(target).await?;
+        let socket = bind_udp(remote).await?;
+        socket.connect(remote).await?;
+        Ok(Box::new(DirectDatagram(socket)))
+    }
+
+    fn supports_udp(&self) -> bool {
+        true
+    }
+}
+
+/// 直连的 UDP 关联
+struct DirectDatagram(UdpSocket);
+
+#[async_trait]
+impl Datagram for DirectDatagram {
+    async fn send(&mut self, data: &[u8]) -> io::Result<()> {
+        self.0.send(data).await.map(|_| ())
+    }
+
+    async fn recv(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.recv(buf).await
+    }
+}
+
+/// 通过代理节点拨号
+#[derive(Debug, Clone)]
+pub struct NodeDialer {
+    proxy: ProxyNode,
+}
+
+impl NodeDialer {
+    pub fn new(proxy: ProxyNode) -> Self {
+        Self { proxy }
+    }
+}
+
+#[async_trait]
+impl Dialer for NodeDialer {
+    async fn connect_tcp(&self, target: &Address) -> Result<BoxedStream> {
+        match self.proxy.protocol {
+            Protocol::Direct => DirectDialer.connect_tcp(target).await,
+            _ => dial(&self.proxy, target).await,
+        }
+    }
+
+    async fn connect_udp(&self, target: &Address) -> Result<BoxedDatagram> {
+        match &self.proxy.protocol {
+            Protocol::Direct => DirectDialer.connect_udp(target).await,
+            Protocol::Socks5(auth) => socks::associate(&self.proxy, auth, target).await,
+            Protocol::Trojan(params) => trojan::associate(&self.proxy, params, target).await,
+            other => Err(anyhow!("{} 出站暂不支持 UDP", other.kind())),
+        }
+    }
+
+    fn supports_udp(&self) -> bool {
+        matches!(
+            self.proxy.protocol,
+            Protocol::Direct | Protocol::Socks5(_) | Protocol::Trojan(_)
+        )
+    }
+}
+
+/// 解析目标地址，域名取第一条解析结果
+pub async fn resolve(target: &Address) -> Result<SocketAddr> {
+    match target {
+        Address::Socket(addr) => Ok(*addr),
+        Address::Domain(domain, port) => tokio::net::lookup_host((domain.as_str(), *port))
+            .await
+            .map_err(|e| anyhow!("解析 {} 失败: {}", domain, e))?
+            .next()
+            .ok_or_else(|| anyhow!("{} 没有可用的地址", domain)),
+    }
+}
+
+/// 绑定与远端地址族一致的本地 UDP 端口
+pub async fn bind_udp(remote: SocketAddr) -> io::Result<UdpSocket> {
+    let local = if remote.is_ipv4() {
+        "0.0.0.0:0"
+    } else {
+        "[::]:0"
+    };
+    UdpSocket::bind(local).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::node::VmessParams;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn test_direct_tcp() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 4];
+            socket.read_exact(&mut buf).await.unwrap();
+            socket.write_all(&buf).await.unwrap();
+        });
+
+        let mut stream = DirectDialer
+            .connect_tcp(&Address::Socket(addr))
+            .await
+            .unwrap();
+        stream.write_all(b"ping").await.unwrap();
+        let mut buf = [0u8; 4];
+        stream.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"ping");
+    }
+
+    #[tokio::test]
+    async fn test_direct_udp() {
+        let server = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr = server.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut buf = [0u8; 64];
+            let (n, peer) = server.recv_from(&mut buf).await.unwrap();
+            server.send_to(&buf[..n], peer).await.unwrap();
+        });
+
+        let mut datagram = DirectDialer
+            .connect_udp(&Address::Socket(addr))
+            .await
+            .unwrap();
+        datagram.send(b"hello").await.unwrap();
+        let mut buf = [0u8; 64];
+        let n = datagram.recv(&mut buf).await.unwrap();
+        assert_eq!(&buf[..n], b"hello");
+    }
+
+    #[tokio::test]
+    async fn test_node_dialer_udp_unsupported() {
+        let proxy = ProxyNode::new(
+            "vmess".to_string(),
+            "127.0.0.1".to_string(),
+            1,
+            Protocol::Vmess(VmessParams {
+                uuid: "b831381d-6324-4d53-ad4f-8cda48b30811".to_string(),
+                alter_id: None,
+                cipher: None,
+                ..Default::default()
+            }),
+        );
+        let result = NodeDialer::new(proxy)
+            .connect_udp(&Address::Domain("example.com".to_string(), 53))
+            .await;
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,167 @@
+//! HTTP 代理出站
+//! 通过 CONNECT 方法建立隧道，https 节点先与代理服务器完成 TLS 握手
+
+use super::{Address, BoxedStream, transport};
+use crate::proxy::ProxyNode;
+use crate::proxy::node::AuthParams;
+use anyhow::{Result, anyhow};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+
+/// 响应头的最大长度
+const MAX_HEADER_LEN: usize = 8192;
+
+/// 构造 CONNECT 请求，节点带用户名时附加 Basic 认证
+pub fn connect_request(target: &Address, auth: Option<(&str, &str)>) -> Vec<u8> {
+    let mut request = format!("CONNECT {target} HTTP/1.1\r\nHost: {target}\r\n");
+    if let Some((username, password)) = auth {
+        let credentials = STANDARD.encode(format!("{}:{}", username, password));
+        request.push_str(&format!("Proxy-Authorization: Basic {}\r\n", credentials));
+    }
+    request.push_str("Proxy-Connection: Keep-Alive\r\n\r\n");
+    request.into_bytes()
+}
+
+/// 逐字节读取响应头，避免吞掉隧道建立后的数据，返回状态码
+async fn read_response<R: AsyncRead + Unpin>(reader: &mut R) -> Result<u16> {
+    let mut header = Vec::with_capacity(128);
+    while !header.ends_with(b"\r\n\r\n") {
+        if header.len() >= MAX_HEADER_LEN {
+            return Err(anyhow!("HTTP 代理响应头过长"));
+        }
+        header.push(reader.read_u8().await?);
+    }
+    let status_line = header
+        .split(|&b| b == b'\r')
+        .next()
+        .map(String::from_utf8_lossy)
+        .unwrap_or_default();
+    status_line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse().ok())
+        .ok_or_else(|| anyhow!("无效的 HTTP 代理响应: {}", status_line))
+}
+
+/// 通过 HTTP 代理节点连接目标地址
+pub async fn connect(
+    proxy: &ProxyNode,
+    auth: &AuthParams,
+    target: &Address,
+) -> Result<BoxedStream> {
+    let mut stream = transport::connect(proxy, proxy.tls.tls.unwrap_or(false))
+        .await
+        .map_err(|e| anyhow!("HTTP {}", e))?;
+
+    let credentials = auth
+        .username
+        .as_deref()
+        .map(|username| (username, auth.password.as_deref().unwrap_or("")));
+    stream
+        .write_all(&connect_request(target, credentials))
+        .await?;
+    stream.flush().await?;
+
+    let status = read_response(&mut stream).await?;
+    if !(200..300).contains(&status) {
+        return Err(anyhow!("HTTP 代理拒绝 CONNECT {}: {}", target, status));
+    }
+    Ok(stream)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::Protocol;
+    use tokio::net::TcpListener;
+
+    /// 启动本地 HTTP 代理替身：校验 CONNECT 请求后回显数据
+    async fn spawn_server(expected_auth: Option<&'static str>) -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            while !request.ends_with(b"\r\n\r\n") {
+                request.push(socket.read_u8().await.unwrap());
+            }
+            let request = String::from_utf8(request).unwrap();
+            assert!(request.starts_with("CONNECT example.com:443 HTTP/1.1\r\n"));
+            let authorized = match expected_auth {
+                Some(auth) => request.contains(&format!("Proxy-Authorization: Basic {}", auth)),
+                None => true,
+            };
+            if !authorized {
+                socket
+                    .write_all(b"HTTP/1.1 407 Proxy Authentication Required\r\n\r\n")
+                    .await
+                    .unwrap();
+                return;
+            }
+            socket
+                .write_all(b"HTTP/1.1 200 Connection established\r\n\r\necho:")
+                .await
+                .unwrap();
+            let mut buf = [0u8; 4];
+            socket.read_exact(&mut buf).await.unwrap();
+            socket.write_all(&buf).await.unwrap();
+        });
+        port
+    }
+
+    fn node(port: u16) -> ProxyNode {
+        ProxyNode::new(
+            "http".to_string(),
+            "127.0.0.1".to_string(),
+            port,
+            Protocol::Http(AuthParams::default()),
+        )
+    }
+
+    async fn dial(proxy: &ProxyNode, target: &Address) -> Result<BoxedStream> {
+        let Protocol::Http(auth) = &proxy.protocol else {
+            unreachable!()
+        };
+        connect(proxy, auth, target).await
+    }
+
+    #[test]
+    fn test_connect_request() {
+        let target = Address::Socket("[::1]:443".parse().unwrap());
+        let request = String::from_utf8(connect_request(&target, Some(("user", "pass")))).unwrap();
+        assert!(request.starts_with("CONNECT [::1]:443 HTTP/1.1\r\nHost: [::1]:443\r\n"));
+        assert!(request.contains("Proxy-Authorization: Basic dXNlcjpwYXNz\r\n"));
+        assert!(request.ends_with("\r\n\r\n"));
+    }
+
+    #[tokio::test]
+    async fn test_connect_tunnel() {
+        let port = spawn_server(Some("dXNlcjpwYXNz")).await;
+        let mut proxy = node(port);
+        proxy.protocol = Protocol::Http(AuthParams {
+            username: Some("user".to_string()),
+            password: Some("pass".to_string()),
+            ..Default::default()
+        });
+        let target = Address::Domain("example.com".to_string(), 443);
+        let mut stream = dial(&proxy, &target).await.unwrap();
+
+        // 与 200 响应同包到达的数据不能丢失
+        let mut prefix = [0u8; 5];
+        stream.read_exact(&mut prefix).await.unwrap();
+        assert_eq!(&prefix, b"echo:");
+        stream.write_all(b"ping").await.unwrap();
+        let mut buf = [0u8; 4];
+        stream.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"ping");
+    }
+
+    #[tokio::test]
+    async fn test_connect_rejected() {
+        let port = spawn_server(Some("dXNlcjpwYXNz")).await;
+        let target = Address::Domain("example.com".to_string(), 443);
+        let err = dial(&node(port), &target).await.err().unwrap();
+        assert!(err.to_string().contains("407"));
+    }
+}
@@ -0,0 +1,310 @@
+//! Hysteria2 出站
+//! QUIC 连接上先以 HTTP/3 `POST /auth` 认证（成功状态码为 233），之后每个 TCP 请求占用一条双向流
+//!
+//! 节点的 `up`/`down` 只作为 `Hysteria-CC-RX` 提示发给服务端，本地仍使用 QUIC 默认拥塞控制
+
+use super::quic::{self, QuicStream, Salamander, read_varint, write_varint};
+use super::tls::TlsOptions;
+use super::{Address, BoxedStream};
+use crate::proxy::ProxyNode;
+use crate::proxy::node::Hysteria2Params;
+use anyhow::{Result, anyhow};
+use bytes::Bytes;
+use h3::client::SendRequest;
+use h3_quinn::OpenStreams;
+use quinn::Connection;
+use rand::Rng;
+use rand::distributions::Alphanumeric;
+use std::io;
+use std::ops::Range;
+use tokio::io::AsyncReadExt;
+
+const AUTH_URL: &str = "https://hysteria/auth";
+const AUTH_STATUS_OK: u16 = 233;
+const HEADER_AUTH: &str = "Hysteria-Auth";
+const HEADER_CC_RX: &str = "Hysteria-CC-RX";
+const HEADER_PADDING: &str = "Hysteria-Padding";
+const FRAME_TCP_REQUEST: u64 = 0x401;
+const AUTH_PADDING: Range<usize> = 256..2048;
+const REQUEST_PADDING: Range<usize> = 64..512;
+
+/// 解析带宽字符串（如 `100 Mbps`、`1g`），返回字节每秒；不带单位时按 Mbps 计
+pub fn parse_bandwidth(value: &str) -> Option<u64> {
+    let value = value.trim().to_ascii_lowercase();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(value.len());
+    let number: f64 = value[..split].parse().ok()?;
+    let unit = value[split..].trim();
+    let unit = unit.strip_suffix("ps").unwrap_or(unit);
+    let bits = match unit {
+        "" => 1_000_000.0,
+        "b" => 1.0,
+        "k" | "kb" => 1_000.0,
+        "m" | "mb" => 1_000_000.0,
+        "g" | "gb" => 1_000_000_000.0,
+        "t" | "tb" => 1_000_000_000_000.0,
+        _ => return None,
+    };
+    Some((number * bits / 8.0) as u64)
+}
+
+fn padding(range: Range<usize>) -> String {
+    let mut rng = rand::thread_rng();
+    let len = rng.gen_range(range);
+    (0..len).map(|_| rng.sample(Alphanumeric) as char).collect()
+}
+
+/// 构造 TCP 请求：类型 + 地址长度 + `host:port` + 填充长度 + 填充
+pub fn tcp_request(target: &Address) -> Vec<u8> {
+    let addr = target.to_string();
+    let padding = padding(REQUEST_PADDING);
+    let mut buf = Vec::with_capacity(16 + addr.len() + padding.len());
+    write_varint(&mut buf, FRAME_TCP_REQUEST);
+    write_varint(&mut buf, addr.len() as u64);
+    buf.extend_from_slice(addr.as_bytes());
+    write_varint(&mut buf, padding.len() as u64);
+    buf.extend_from_slice(padding.as_bytes());
+    buf
+}
+
+/// 读取 TCP 响应：状态 + 消息长度 + 消息 + 填充长度 + 填充
+async fn read_tcp_response(recv: &mut quinn::RecvStream) -> io::Result<()> {
+    let status = recv.read_u8().await?;
+    let len = read_varint(recv).await? as usize;
+    let mut message = vec![0u8; len];
+    AsyncReadExt::read_exact(recv, &mut message).await?;
+    let len = read_varint(recv).await? as usize;
+    AsyncReadExt::read_exact(recv, &mut vec![0u8; len]).await?;
+    if status != 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::ConnectionRefused,
+            format!("服务端拒绝连接: {}", String::from_utf8_lossy(&message)),
+        ));
+    }
+    Ok(())
+}
+
+/// HTTP/3 认证，返回的请求句柄需要保留，否则 HTTP/3 层会关闭连接
+async fn authenticate(
+    connection: &Connection,
+    auth: &str,
+    rx: u64,
+) -> Result<SendRequest<OpenStreams, Bytes>> {
+    let (mut driver, mut send_request) =
+        h3::client::new(h3_quinn::Connection::new(connection.clone()))
+            .await
+            .map_err(|e| anyhow!("HTTP/3 初始化失败: {}", e))?;
+    tokio::spawn(async move {
+        let e = driver.wait_idle().await;
+        log::debug!("Hysteria2 HTTP/3 连接结束: {}", e);
+    });
+
+    let request = http::Request::post(AUTH_URL)
+        .header(HEADER_AUTH, auth)
+        .header(HEADER_CC_RX, rx.to_string())
+        .header(HEADER_PADDING, padding(AUTH_PADDING))
+        .body(())
+        .map_err(|e| anyhow!("构造认证请求失败: {}", e))?;
+    let mut stream = send_request
+        .send_request(request)
+        .await
+        .map_err(|e| anyhow!("发送认证请求失败: {}", e))?;
+    stream
+        .finish()
+        .await
+        .map_err(|e| anyhow!("发送认证请求失败: {}", e))?;
+    let response = stream
+        .recv_response()
+        .await
+        .map_err(|e| anyhow!("读取认证响应失败: {}", e))?;
+    if response.status().as_u16() != AUTH_STATUS_OK {
+        return Err(anyhow!("认证失败，状态码: {}", response.status()));
+    }
+    Ok(send_request)
+}
+
+/// 通过 Hysteria2 节点连接目标地址
+pub async fn connect(
+    proxy: &ProxyNode,
+    params: &Hysteria2Params,
+    target: &Address,
+) -> Result<BoxedStream> {
+    let obfs = match params.obfs.as_deref().unwrap_or("") {
+        "" | "none" => None,
+        "salamander" => {
+            let password = params
+                .obfs_password
+                .as_deref()
+                .ok_or_else(|| anyhow!("Hysteria2 节点缺少混淆密码: {}", proxy.name))?;
+            Some(Salamander::new(password))
+        }
+        other => return Err(anyhow!("不支持的 Hysteria2 混淆方式: {}", other)),
+    };
+    let rx = params
+        .down
+        .as_ref()
+        .and_then(|down| parse_bandwidth(&down.to_string()))
+        .unwrap_or(0);
+
+    let mut options = TlsOptions::from_node(proxy);
+    options.alpn = vec!["h3".to_string()];
+    let (endpoint, connection) = quic::connect(&proxy.server, proxy.port, &options, obfs)
+        .await
+        .map_err(|e| anyhow!("Hysteria2 {}", e))?;
+    let send_request = authenticate(&connection, &params.password, rx)
+        .await
+        .map_err(|e| anyhow!("Hysteria2 {}", e))?;
+
+    let (mut send, mut recv) = connection
+        .open_bi()
+        .await
+        .map_err(|e| anyhow!("Hysteria2 打开流失败: {}", e))?;
+    send.write_all(&tcp_request(target)).await?;
+    read_tcp_response(&mut recv)
+        .await
+        .map_err(|e| anyhow!("Hysteria2 {}", e))?;
+    Ok(Box::new(QuicStream::new(
+        send,
+        recv,
+        connection,
+        endpoint,
+        send_request,
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::outbound::dial;
+    use crate::outbound::quic::test_server;
+    use crate::proxy::Protocol;
+    use crate::proxy::node::Bandwidth;
+    use bytes::Buf;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// 启动本地 Hysteria2 服务端替身：HTTP/3 认证后，在双向流上校验请求并回显数据
+    fn spawn_server(password: &'static str, obfs: Option<Salamander>) -> u16 {
+        let (endpoint, addr) = test_server::endpoint("hy2.test", &["h3"], obfs);
+        tokio::spawn(async move {
+            let connection = endpoint.accept().await.unwrap().await.unwrap();
+            let mut h3_conn = h3::server::Connection::<_, Bytes>::new(h3_quinn::Connection::new(
+                connection.clone(),
+            ))
+            .await
+            .unwrap();
+            let resolver = h3_conn.accept().await.unwrap().unwrap();
+            let (request, mut stream) = resolver.resolve_request().await.unwrap();
+            assert_eq!(request.uri().path(), "/auth");
+            assert_eq!(request.uri().host(), Some("hysteria"));
+            assert_eq!(request.headers()[HEADER_CC_RX], "12500000");
+            let status = if request.headers()[HEADER_AUTH] == password {
+                AUTH_STATUS_OK
+            } else {
+                404
+            };
+            while let Some(chunk) = stream.recv_data().await.unwrap() {
+                assert_eq!(chunk.remaining(), 0);
+            }
+            let response = http::Response::builder().status(status).body(()).unwrap();
+            stream.send_response(response).await.unwrap();
+            stream.finish().await.unwrap();
+            if status != AUTH_STATUS_OK {
+                // 与真实服务端一样保持连接，由客户端关闭
+                connection.closed().await;
+                return;
+            }
+
+            // 认证之后不再经 HTTP/3 接收请求，直接取裸双向流
+            let (mut send, mut recv) = connection.accept_bi().await.unwrap();
+            assert_eq!(read_varint(&mut recv).await.unwrap(), FRAME_TCP_REQUEST);
+            let len = read_varint(&mut recv).await.unwrap() as usize;
+            let mut addr = vec![0u8; len];
+            recv.read_exact(&mut addr).await.unwrap();
+            assert_eq!(addr, b"example.com:80");
+            let len = read_varint(&mut recv).await.unwrap() as usize;
+            assert!(REQUEST_PADDING.contains(&len));
+            recv.read_exact(&mut vec![0u8; len]).await.unwrap();
+            send.write_all(&[0x00, 0x00, 0x00]).await.unwrap();
+
+            let mut buf = [0u8; 1024];
+            while let Ok(Some(n)) = recv.read(&mut buf).await {
+                send.write_all(&buf[..n]).await.unwrap();
+            }
+            let _ = send.finish();
+            drop(h3_conn);
+        });
+        addr.port()
+    }
+
+    fn node(port: u16, password: &str) -> ProxyNode {
+        let mut node = ProxyNode::new(
+            "hy2".to_string(),
+            "127.0.0.1".to_string(),
+            port,
+            Protocol::Hysteria2(Hysteria2Params {
+                password: password.to_string(),
+                obfs: None,
+                obfs_password: None,
+                up: None,
+                down: Some(Bandwidth::Text("100 Mbps".to_string())),
+                ..Default::default()
+            }),
+        );
+        node.tls.sni = Some("hy2.test".to_string());
+        node.tls.skip_cert_verify = Some(true);
+        node
+    }
+
+    async fn roundtrip(node: &ProxyNode) {
+        let target = Address::Domain("example.com".to_string(), 80);
+        let mut stream = dial(node, &target).await.unwrap();
+        stream.write_all(b"hello hysteria2").await.unwrap();
+        let mut buf = [0u8; 15];
+        stream.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"hello hysteria2");
+    }
+
+    #[test]
+    fn test_parse_bandwidth() {
+        assert_eq!(parse_bandwidth("100 Mbps"), Some(12_500_000));
+        assert_eq!(parse_bandwidth("100"), Some(12_500_000));
+        assert_eq!(parse_bandwidth("1g"), Some(125_000_000));
+        assert_eq!(parse_bandwidth("800 kbps"), Some(100_000));
+        assert_eq!(parse_bandwidth("fast"), None);
+        assert_eq!(parse_bandwidth("10 furlongs"), None);
+    }
+
+    #[test]
+    fn test_tcp_request() {
+        let request = tcp_request(&Address::Socket("[::1]:443".parse().unwrap()));
+        assert_eq!(&request[..2], &[0x44, 0x01]);
+        assert_eq!(request[2] as usize, "[::1]:443".len());
+        assert_eq!(&request[3..12], b"[::1]:443");
+    }
+
+    #[tokio::test]
+    async fn test_hysteria2_roundtrip() {
+        let port = spawn_server("secret", None);
+        roundtrip(&node(port, "secret")).await;
+    }
+
+    #[tokio::test]
+    async fn test_hysteria2_salamander_roundtrip() {
+        let port = spawn_server("secret", Some(Salamander::new("obfs-pass")));
+        let mut node = node(port, "secret");
+        if let Protocol::Hysteria2(params) = &mut node.protocol {
+            params.obfs = Some("salamander".to_string());
+            params.obfs_password = Some("obfs-pass".to_string());
+        }
+        roundtrip(&node).await;
+    }
+
+    #[tokio::test]
+    async fn test_hysteria2_auth_failure() {
+        let port = spawn_server("secret", None);
+        let target = Address::Domain("example.com".to_string(), 80);
+        let err = dial(&node(port, "wrong"), &target).await.err().unwrap();
+        assert!(err.to_string().contains("认证失败"));
+    }
+}
@@ -0,0 +1,300 @@
+//! 原生出站协议模块
+//! 在进程内实现各代理协议的客户端，检测流程通过这里直接拨号到目标地址
+
+pub mod anytls;
+mod crypto;
+pub mod dialer;
+pub mod http;
+pub mod hysteria2;
+pub mod plugin;
+mod quic;
+pub mod reality;
+pub mod shadowsocks;
+pub mod socks;
+pub mod ssr;
+mod stream_cipher;
+pub mod tls;
+pub mod tls13;
+pub mod transport;
+pub mod trojan;
+pub mod tuic;
+mod util;
+pub mod vision;
+pub mod vless;
+pub mod vmess;
+pub mod wireguard;
+
+use crate::proxy::{Protocol, ProxyNode};
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use std::fmt;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite};
+use tokio::net::TcpStream;
+
+/// 出站连接的统一流类型
+pub trait ProxyStream: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> ProxyStream for T {}
+
+/// 装箱后的出站连接
+pub type BoxedStream = Box<dyn ProxyStream>;
+
+/// 出站的 UDP 关联，收发的都是与固定目标之间的数据报
+#[async_trait]
+pub trait Datagram: Send {
+    /// 向目标发送一个数据报
+    async fn send(&mut self, data: &[u8]) -> io::Result<()>;
+    /// 接收一个数据报，超出缓冲区的部分被丢弃
+    async fn recv(&mut self, buf: &mut [u8]) -> io::Result<usize>;
+}
+
+/// 装箱后的 UDP 关联
+pub type BoxedDatagram = Box<dyn Datagram>;
+
+/// 代理请求的目标地址
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Address {
+    /// 域名 + 端口
+    Domain(String, u16),
+    /// IP + 端口
+    Socket(SocketAddr),
+}
+
+impl Address {
+    /// 由主机名与端口构造，IP 字面量（含带方括号的 IPv6）解析为套接字地址
+    pub fn from_host(host: &str, port: u16) -> Self {
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        match host.parse::<IpAddr>() {
+            Ok(ip) => Address::Socket(SocketAddr::new(ip, port)),
+            Err(_) => Address::Domain(host.to_string(), port),
+        }
+    }
+
+    /// 解析 `host:port`，IPv6 地址需加方括号
+    pub fn parse(value: &str) -> Result<Self> {
+        let (host, port) = value
+            .trim()
+            .rsplit_once(':')
+            .filter(|(host, _)| !host.is_empty())
+            .ok_or_else(|| anyhow!("地址缺少端口: {}", value))?;
+        let port = port.parse().map_err(|_| anyhow!("无效的端口: {}", value))?;
+        Ok(Self::from_host(host, port))
+    }
+
+    /// 获取端口
+    pub fn port(&self) -> u16 {
+        match self {
+            Address::Domain(_, port) => *port,
+            Address::Socket(addr) => addr.port(),
+        }
+    }
+
+    /// 按 SOCKS5 地址格式（ATYP + 地址 + 端口）写入缓冲区
+    ///
+    /// Shadowsocks、Trojan 等协议的目标地址都使用这种编码
+    pub fn write_socks(&self, buf: &mut Vec<u8>) {
+        match self {
+            Address::Socket(SocketAddr::V4(addr)) => {
+                buf.push(0x01);
+                buf.extend_from_slice(&addr.ip().octets());
+            }
+            Address::Socket(SocketAddr::V6(addr)) => {
+                buf.push(0x04);
+                buf.extend_from_slice(&addr.ip().octets());
+            }
+            Address::Domain(domain, _) => {
+                let bytes = domain.as_bytes();
+                let len = bytes.len().min(255);
+                buf.push(0x03);
+                buf.push(len as u8);
+                buf.extend_from_slice(&bytes[..len]);
+            }
+        }
+        buf.extend_from_slice(&self.port().to_be_bytes());
+    }
+
+    /// 按 VMess/VLESS 地址格式（端口 + 类型 + 地址）写入缓冲区
+    pub fn write_vmess(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.port().to_be_bytes());
+        match self {
+            Address::Socket(SocketAddr::V4(addr)) => {
+                buf.push(0x01);
+                buf.extend_from_slice(&addr.ip().octets());
+            }
+            Address::Domain(domain, _) => {
+                let bytes = domain.as_bytes();
+                let len = bytes.len().min(255);
+                buf.push(0x02);
+                buf.push(len as u8);
+                buf.extend_from_slice(&bytes[..len]);
+            }
+            Address::Socket(SocketAddr::V6(addr)) => {
+                buf.push(0x03);
+                buf.extend_from_slice(&addr.ip().octets());
+            }
+        }
+    }
+
+    /// 按 TUIC 地址格式（类型 + 地址 + 端口）写入缓冲区
+    pub fn write_tuic(&self, buf: &mut Vec<u8>) {
+        match self {
+            Address::Domain(domain, _) => {
+                let bytes = domain.as_bytes();
+                let len = bytes.len().min(255);
+                buf.push(0x00);
+                buf.push(len as u8);
+                buf.extend_from_slice(&bytes[..len]);
+            }
+            Address::Socket(SocketAddr::V4(addr)) => {
+                buf.push(0x01);
+                buf.extend_from_slice(&addr.ip().octets());
+            }
+            Address::Socket(SocketAddr::V6(addr)) => {
+                buf.push(0x02);
+                buf.extend_from_slice(&addr.ip().octets());
+            }
+        }
+        buf.extend_from_slice(&self.port().to_be_bytes());
+    }
+
+    /// 从流中读取 SOCKS5 格式的地址
+    pub async fn read_socks<R: AsyncRead + Unpin>(reader: &mut R) -> io::Result<Self> {
+        let atyp = reader.read_u8().await?;
+        let address = match atyp {
+            0x01 => {
+                let mut octets = [0u8; 4];
+                reader.read_exact(&mut octets).await?;
+                let port = reader.read_u16().await?;
+                Address::Socket(SocketAddr::new(IpAddr::V4(Ipv4Addr::from(octets)), port))
+            }
+            0x04 => {
+                let mut octets = [0u8; 16];
+                reader.read_exact(&mut octets).await?;
+                let port = reader.read_u16().await?;
+                Address::Socket(SocketAddr::new(IpAddr::V6(Ipv6Addr::from(octets)), port))
+            }
+            0x03 => {
+                let len = reader.read_u8().await? as usize;
+                let mut domain = vec![0u8; len];
+                reader.read_exact(&mut domain).await?;
+                let port = reader.read_u16().await?;
+                let domain = String::from_utf8(domain).map_err(|_| {
+                    io::Error::new(io::ErrorKind::InvalidData, "域名不是有效的UTF-8")
+                })?;
+                Address::Domain(domain, port)
+            }
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("未知的地址类型: {}", atyp),
+                ));
+            }
+        };
+        Ok(address)
+    }
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Address::Domain(domain, port) => write!(f, "{}:{}", domain, port),
+            Address::Socket(addr) => write!(f, "{}", addr),
+        }
+    }
+}
+
+/// 建立到代理服务器的 TCP 连接
+pub async fn connect_tcp(server: &str, port: u16) -> io::Result<TcpStream> {
+    let host = server.trim_start_matches('[').trim_end_matches(']');
+    let stream = TcpStream::connect((host, port)).await?;
+    stream.set_nodelay(true)?;
+    Ok(stream)
+}
+
+/// 通过代理节点拨号到目标地址
+pub async fn dial(proxy: &ProxyNode, target: &Address) -> Result<BoxedStream> {
+    match &proxy.protocol {
+        Protocol::Shadowsocks(params) => {
+            Ok(Box::new(shadowsocks::connect(proxy, params, target).await?))
+        }
+        Protocol::Trojan(params) => trojan::connect(proxy, params, target).await,
+        Protocol::Vmess(params) => Ok(Box::new(vmess::connect(proxy, params, target).await?)),
+        Protocol::Vless(params) => vless::connect(proxy, params, target).await,
+        Protocol::Hysteria2(params) => hysteria2::connect(proxy, params, target).await,
+        Protocol::Tuic(params) => tuic::connect(proxy, params, target).await,
+        Protocol::Ssr(params) => Ok(Box::new(ssr::connect(proxy, params, target).await?)),
+        Protocol::AnyTls(params) => anytls::connect(proxy, params, target).await,
+        Protocol::WireGuard(params) => wireguard::connect(proxy, params, target).await,
+        Protocol::Http(auth) => http::connect(proxy, auth, target).await,
+        Protocol::Socks5(auth) => socks::connect(proxy, auth, target).await,
+        Protocol::Socks4(auth) => socks::connect_v4(proxy, auth, target).await,
+        Protocol::Direct => Err(anyhow!("直连节点无需拨号: {}", proxy.name)),
+        Protocol::Hysteria(_) | Protocol::Other(_) => {
+            Err(anyhow!("不支持的代理协议: {}", proxy.kind()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_address_write_socks() {
+        let mut buf = Vec::new();
+        Address::Socket("1.2.3.4:443".parse().unwrap()).write_socks(&mut buf);
+        assert_eq!(buf, vec![0x01, 1, 2, 3, 4, 0x01, 0xbb]);
+
+        let mut buf = Vec::new();
+        Address::Domain("example.com".to_string(), 80).write_socks(&mut buf);
+        assert_eq!(buf[0], 0x03);
+        assert_eq!(buf[1] as usize, "example.com".len());
+        assert_eq!(&buf[buf.len() - 2..], &[0x00, 0x50]);
+    }
+
+    #[test]
+    fn test_address_from_host() {
+        assert_eq!(
+            Address::from_host("[::1]", 443),
+            Address::Socket("[::1]:443".parse().unwrap())
+        );
+        assert_eq!(
+            Address::from_host("10.0.0.1", 80),
+            Address::Socket("10.0.0.1:80".parse().unwrap())
+        );
+        assert_eq!(
+            Address::from_host("example.com", 80),
+            Address::Domain("example.com".to_string(), 80)
+        );
+    }
+
+    #[test]
+    fn test_address_parse() {
+        assert_eq!(
+            Address::parse("[2001:4860:4860::8888]:53").unwrap(),
+            Address::Socket("[2001:4860:4860::8888]:53".parse().unwrap())
+        );
+        assert_eq!(
+            Address::parse("dns.google:53").unwrap(),
+            Address::Domain("dns.google".to_string(), 53)
+        );
+        assert!(Address::parse("8.8.8.8").is_err());
+        assert!(Address::parse(":53").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_address_socks_roundtrip() {
+        for addr in [
+            Address::Domain("example.com".to_string(), 8080),
+            Address::Socket("[::1]:443".parse().unwrap()),
+            Address::Socket("10.0.0.1:53".parse().unwrap()),
+        ] {
+            let mut buf = Vec::new();
+            addr.write_socks(&mut buf);
+            let parsed = Address::read_socks(&mut buf.as_slice()).await.unwrap();
+            assert_eq!(parsed, addr);
+        }
+    }
+}
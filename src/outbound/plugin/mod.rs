@@ -0,0 +1,350 @@
+//! Shadowsocks 插件
+//! 内置 simple-obfs（obfs-local）与 v2ray-plugin 的客户端
+//!
+//! 插件参数既可以是 Clash 的 `plugin-opts` 映射（`mode`、`host`、`path`、`tls`、`mux` 等），
+//! 也可以是 SIP002 链接中以 `;` 分隔的字符串（`obfs=http;obfs-host=...`）
+
+mod mux;
+mod simple_obfs;
+
+pub use self::mux::MuxStream;
+pub use self::simple_obfs::{ObfsMode, ObfsStream};
+
+use super::transport;
+use super::{Address, BoxedStream, connect_tcp};
+use crate::proxy::node::{Network, ShadowsocksParams, WsOpts};
+use crate::proxy::{Protocol, ProxyNode};
+use anyhow::{Result, anyhow};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+
+/// 与 Clash 一致的默认伪装域名
+const DEFAULT_HOST: &str = "bing.com";
+
+/// v2ray-plugin 参数
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct V2rayOptions {
+    pub host: String,
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+    pub tls: bool,
+    pub skip_cert_verify: bool,
+    pub fingerprint: Option<String>,
+    pub mux: bool,
+    /// 使用 HTTPUpgrade 代替 WebSocket（mihomo 的 `v2ray-http-upgrade`）
+    pub http_upgrade: bool,
+}
+
+/// Shadowsocks 插件
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Plugin {
+    None,
+    Obfs { mode: ObfsMode, host: String },
+    V2ray(V2rayOptions),
+}
+
+/// 统一两种格式的插件参数
+#[derive(Default)]
+struct PluginOpts {
+    values: HashMap<String, String>,
+    headers: Vec<(String, String)>,
+}
+
+impl PluginOpts {
+    fn parse(opts: Option<&Value>) -> Self {
+        let mut parsed = Self::default();
+        match opts {
+            Some(Value::Object(map)) => {
+                for (key, value) in map {
+                    let value = match value {
+                        Value::String(s) => s.clone(),
+                        Value::Bool(b) => b.to_string(),
+                        Value::Number(n) => n.to_string(),
+                        Value::Object(headers) if key == "headers" => {
+                            parsed.headers = headers
+                                .iter()
+                                .filter_map(|(k, v)| Some((k.clone(), v.as_str()?.to_string())))
+                                .collect();
+                            continue;
+                        }
+                        _ => continue,
+                    };
+                    parsed.values.insert(key.to_ascii_lowercase(), value);
+                }
+            }
+            Some(Value::String(s)) => {
+                for item in s.split(';').map(str::trim).filter(|i| !i.is_empty()) {
+                    // 不带值的项为开关，如 v2ray-plugin 的 `tls`
+                    let (key, value) = item.split_once('=').unwrap_or((item, "true"));
+                    parsed
+                        .values
+                        .insert(key.trim().to_ascii_lowercase(), value.trim().to_string());
+                }
+            }
+            _ => {}
+        }
+        parsed
+    }
+
+    fn get(&self, keys: &[&str]) -> Option<&str> {
+        keys.iter()
+            .find_map(|key| self.values.get(*key))
+            .map(String::as_str)
+            .filter(|v| !v.is_empty())
+    }
+
+    fn flag(&self, keys: &[&str], default: bool) -> bool {
+        match self.get(keys) {
+            Some(v) => !matches!(v.to_ascii_lowercase().as_str(), "false" | "0" | "off"),
+            None => default,
+        }
+    }
+}
+
+impl Plugin {
+    /// 根据插件名称与参数创建插件
+    pub fn new(name: &str, opts: Option<&Value>) -> Result<Self> {
+        let opts = PluginOpts::parse(opts);
+        match name.trim().to_ascii_lowercase().as_str() {
+            "" | "none" => Ok(Plugin::None),
+            "obfs" | "obfs-local" | "simple-obfs" => Ok(Plugin::Obfs {
+                mode: ObfsMode::from_name(opts.get(&["mode", "obfs"]).unwrap_or("http"))?,
+                host: opts
+                    .get(&["host", "obfs-host"])
+                    .unwrap_or(DEFAULT_HOST)
+                    .to_string(),
+            }),
+            "v2ray-plugin" => {
+                let mode = opts.get(&["mode"]).unwrap_or("websocket");
+                if !mode.eq_ignore_ascii_case("websocket") {
+                    return Err(anyhow!("不支持的 v2ray-plugin 模式: {}", mode));
+                }
+                Ok(Plugin::V2ray(V2rayOptions {
+                    host: opts.get(&["host"]).unwrap_or(DEFAULT_HOST).to_string(),
+                    path: opts.get(&["path"]).unwrap_or("/").to_string(),
+                    tls: opts.flag(&["tls"], false),
+                    skip_cert_verify: opts.flag(&["skip-cert-verify"], false),
+                    fingerprint: opts.get(&["fingerprint"]).map(str::to_string),
+                    mux: opts.flag(&["mux"], true),
+                    http_upgrade: opts.flag(&["v2ray-http-upgrade"], false),
+                    headers: opts.headers,
+                }))
+            }
+            other => Err(anyhow!("不支持的 Shadowsocks 插件: {}", other)),
+        }
+    }
+
+    /// 从节点的 `plugin` 与 `plugin-opts` 字段创建插件
+    pub fn from_params(params: &ShadowsocksParams) -> Result<Self> {
+        Self::new(
+            params.plugin.as_deref().unwrap_or(""),
+            params.plugin_opts.as_ref(),
+        )
+    }
+
+    /// SIP002 链接中的 `plugin` 参数（未编码）
+    pub fn sip002(&self) -> Option<String> {
+        match self {
+            Plugin::None => None,
+            Plugin::Obfs { mode, host } => Some(format!(
+                "obfs-local;obfs={};obfs-host={}",
+                mode.as_str(),
+                host
+            )),
+            Plugin::V2ray(options) => {
+                let mut value = String::from("v2ray-plugin");
+                if options.tls {
+                    value.push_str(";tls");
+                }
+                value.push_str(&format!(";host={};path={}", options.host, options.path));
+                if !options.mux {
+                    value.push_str(";mux=0");
+                }
+                Some(value)
+            }
+        }
+    }
+
+    /// 连接 Shadowsocks 服务器并完成插件握手
+    pub async fn connect(&self, proxy: &ProxyNode) -> Result<BoxedStream> {
+        match self {
+            Plugin::None => Ok(Box::new(
+                connect_tcp(&proxy.server, proxy.port)
+                    .await
+                    .map_err(|e| anyhow!("连接服务器失败: {}", e))?,
+            )),
+            Plugin::Obfs { mode, host } => {
+                let tcp = connect_tcp(&proxy.server, proxy.port)
+                    .await
+                    .map_err(|e| anyhow!("连接服务器失败: {}", e))?;
+                Ok(Box::new(ObfsStream::new(tcp, *mode, host, proxy.port)))
+            }
+            Plugin::V2ray(options) => {
+                // 复用传输层：插件参数换算成 WebSocket / HTTPUpgrade 节点字段
+                let mut node = ProxyNode::new(
+                    proxy.name.clone(),
+                    proxy.server.clone(),
+                    proxy.port,
+                    Protocol::Direct,
+                );
+                let mut headers = BTreeMap::new();
+                headers.insert("Host".to_string(), options.host.clone());
+                for (name, value) in &options.headers {
+                    headers.insert(name.clone(), value.clone());
+                }
+                node.transport.network = Some(Network::Ws);
+                node.transport.ws_opts = Some(WsOpts {
+                    path: Some(options.path.clone()),
+                    headers: Some(headers),
+                    v2ray_http_upgrade: Some(options.http_upgrade),
+                    ..Default::default()
+                });
+                node.tls.sni = Some(options.host.clone());
+                node.tls.skip_cert_verify = Some(options.skip_cert_verify);
+                node.tls.fingerprint = options.fingerprint.clone();
+
+                let stream = transport::connect(&node, options.tls).await?;
+                if options.mux {
+                    let target = Address::Domain(proxy.server.clone(), proxy.port);
+                    Ok(Box::new(MuxStream::new(stream, &target)))
+                } else {
+                    Ok(stream)
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn test_parse_clash_opts() {
+        let plugin = Plugin::new("obfs", Some(&json!({"mode": "tls", "host": "a.com"}))).unwrap();
+        assert_eq!(
+            plugin,
+            Plugin::Obfs {
+                mode: ObfsMode::Tls,
+                host: "a.com".to_string()
+            }
+        );
+
+        let opts = json!({
+            "mode": "websocket",
+            "tls": true,
+            "host": "cdn.example.com",
+            "path": "/ws",
+            "mux": false,
+            "headers": {"User-Agent": "test"}
+        });
+        let Plugin::V2ray(options) = Plugin::new("v2ray-plugin", Some(&opts)).unwrap() else {
+            panic!("应解析为 v2ray-plugin");
+        };
+        assert!(options.tls && !options.mux);
+        assert_eq!(options.path, "/ws");
+        assert_eq!(
+            options.headers,
+            vec![("User-Agent".to_string(), "test".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_parse_sip002_opts() {
+        let plugin = Plugin::new(
+            "obfs-local",
+            Some(&json!("obfs=http;obfs-host=www.bing.com")),
+        )
+        .unwrap();
+        assert_eq!(
+            plugin.sip002().unwrap(),
+            "obfs-local;obfs=http;obfs-host=www.bing.com"
+        );
+
+        let Plugin::V2ray(options) =
+            Plugin::new("v2ray-plugin", Some(&json!("tls;host=a.com;path=/x"))).unwrap()
+        else {
+            panic!("应解析为 v2ray-plugin");
+        };
+        assert!(options.tls && options.mux);
+        assert_eq!(options.host, "a.com");
+
+        // 缺省参数与 Clash 一致
+        let Plugin::V2ray(options) = Plugin::new("v2ray-plugin", None).unwrap() else {
+            panic!("应解析为 v2ray-plugin");
+        };
+        assert_eq!(
+            (options.host.as_str(), options.path.as_str()),
+            ("bing.com", "/")
+        );
+        assert!(!options.tls && options.mux);
+    }
+
+    #[test]
+    fn test_unsupported_plugin() {
+        assert!(Plugin::new("kcptun", None).is_err());
+        assert!(Plugin::new("v2ray-plugin", Some(&json!({"mode": "quic"}))).is_err());
+        assert!(Plugin::new("obfs", Some(&json!({"mode": "ws"}))).is_err());
+        assert_eq!(Plugin::new("", None).unwrap(), Plugin::None);
+    }
+
+    #[tokio::test]
+    async fn test_v2ray_plugin_http_upgrade_mux() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(async move {
+            let (mut sock, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            while !request.ends_with(b"\r\n\r\n") {
+                request.push(sock.read_u8().await.unwrap());
+            }
+            sock.write_all(b"HTTP/1.1 101 Switching Protocols\r\n\r\n")
+                .await
+                .unwrap();
+
+            // New 帧之后是携带数据的 Keep 帧，原样回显数据
+            let meta_len = sock.read_u16().await.unwrap() as usize;
+            let mut meta = vec![0u8; meta_len];
+            sock.read_exact(&mut meta).await.unwrap();
+            assert_eq!(&meta[..4], &[0x00, 0x01, 0x01, 0x00]);
+            let mut header = [0u8; 8];
+            sock.read_exact(&mut header).await.unwrap();
+            let mut data = vec![0u8; u16::from_be_bytes([header[6], header[7]]) as usize];
+            sock.read_exact(&mut data).await.unwrap();
+            sock.write_all(&header).await.unwrap();
+            sock.write_all(&data).await.unwrap();
+            String::from_utf8(request).unwrap()
+        });
+
+        let params = ShadowsocksParams {
+            cipher: "aes-128-gcm".to_string(),
+            password: "test-password".to_string(),
+            plugin: Some("v2ray-plugin".to_string()),
+            plugin_opts: Some(
+                json!({"host": "cdn.example.com", "path": "/ray", "v2ray-http-upgrade": true}),
+            ),
+            ..Default::default()
+        };
+        let node = ProxyNode::new(
+            "ss".to_string(),
+            "127.0.0.1".to_string(),
+            port,
+            Protocol::Shadowsocks(params.clone()),
+        );
+        let mut stream = Plugin::from_params(&params)
+            .unwrap()
+            .connect(&node)
+            .await
+            .unwrap();
+        stream.write_all(b"hello plugin").await.unwrap();
+        let mut reply = [0u8; 12];
+        stream.read_exact(&mut reply).await.unwrap();
+        assert_eq!(&reply, b"hello plugin");
+
+        let request = server.await.unwrap();
+        assert!(request.starts_with("GET /ray HTTP/1.1\r\nHost: cdn.example.com\r\n"));
+    }
+}
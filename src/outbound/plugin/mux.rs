@@ -0,0 +1,249 @@
+//! Mux.Cool
+//! v2ray-plugin 默认开启多路复用，这里只实现单个子连接：
+//! 首次写入前发送 New 帧，之后以 Keep 帧携带数据，关闭时发送 End 帧
+
+use crate::outbound::Address;
+use crate::outbound::util::{FramedWrite, copy_plain, invalid_data, poll_drain, poll_fill};
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll, ready};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+const STATUS_NEW: u8 = 0x01;
+const STATUS_KEEP: u8 = 0x02;
+const STATUS_END: u8 = 0x03;
+const STATUS_KEEP_ALIVE: u8 = 0x04;
+const OPTION_DATA: u8 = 0x01;
+const NETWORK_TCP: u8 = 0x01;
+/// 子连接 ID，v2ray 从 1 开始分配
+const SESSION_ID: u16 = 1;
+/// 单帧最大数据长度，与 v2ray 的缓冲区大小一致
+const MAX_FRAME_DATA: usize = 8192;
+/// 帧元数据长度上限
+const MAX_META_LEN: usize = 512;
+
+/// New 帧：元数据长度 + 会话 ID + 状态 + 选项 + 网络类型 + 端口 + 地址
+fn new_frame(target: &Address) -> Vec<u8> {
+    let mut meta = SESSION_ID.to_be_bytes().to_vec();
+    meta.extend_from_slice(&[STATUS_NEW, 0x00, NETWORK_TCP]);
+    target.write_vmess(&mut meta);
+    let mut frame = (meta.len() as u16).to_be_bytes().to_vec();
+    frame.extend_from_slice(&meta);
+    frame
+}
+
+/// 不带数据的帧
+fn control_frame(status: u8) -> Vec<u8> {
+    let mut frame = vec![0x00, 0x04];
+    frame.extend_from_slice(&SESSION_ID.to_be_bytes());
+    frame.extend_from_slice(&[status, 0x00]);
+    frame
+}
+
+/// 携带数据的 Keep 帧
+fn data_frame(data: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(8 + data.len());
+    frame.extend_from_slice(&[0x00, 0x04]);
+    frame.extend_from_slice(&SESSION_ID.to_be_bytes());
+    frame.extend_from_slice(&[STATUS_KEEP, OPTION_DATA]);
+    frame.extend_from_slice(&(data.len() as u16).to_be_bytes());
+    frame.extend_from_slice(data);
+    frame
+}
+
+/// 从缓冲区开头解析一帧，返回状态、数据与帧长；数据不足时返回 `None`
+fn parse_frame(buf: &[u8]) -> io::Result<Option<(u8, Vec<u8>, usize)>> {
+    if buf.len() < 2 {
+        return Ok(None);
+    }
+    let meta_len = u16::from_be_bytes([buf[0], buf[1]]) as usize;
+    if !(4..=MAX_META_LEN).contains(&meta_len) {
+        return Err(invalid_data("Mux.Cool 元数据长度错误"));
+    }
+    if buf.len() < 2 + meta_len {
+        return Ok(None);
+    }
+    let status = buf[4];
+    let option = buf[5];
+    let mut len = 2 + meta_len;
+    if option & OPTION_DATA == 0 {
+        return Ok(Some((status, Vec::new(), len)));
+    }
+    if buf.len() < len + 2 {
+        return Ok(None);
+    }
+    let data_len = u16::from_be_bytes([buf[len], buf[len + 1]]) as usize;
+    len += 2;
+    if buf.len() < len + data_len {
+        return Ok(None);
+    }
+    Ok(Some((
+        status,
+        buf[len..len + data_len].to_vec(),
+        len + data_len,
+    )))
+}
+
+/// Mux.Cool 单子连接
+pub struct MuxStream<S> {
+    inner: S,
+    /// 尚未发出的 New 帧
+    new_frame: Option<Vec<u8>>,
+    closed: bool,
+    rbuf: Vec<u8>,
+    plain: Vec<u8>,
+    plain_pos: usize,
+    wbuf: Vec<u8>,
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> MuxStream<S> {
+    /// `target` 为子连接的目标地址，New 帧随第一次写入发出
+    pub fn new(inner: S, target: &Address) -> Self {
+        Self {
+            inner,
+            new_frame: Some(new_frame(target)),
+            closed: false,
+            rbuf: Vec::new(),
+            plain: Vec::new(),
+            plain_pos: 0,
+            wbuf: Vec::new(),
+        }
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> AsyncRead for MuxStream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        loop {
+            if copy_plain(&mut this.plain, &mut this.plain_pos, buf) || this.closed {
+                return Poll::Ready(Ok(()));
+            }
+            if let Some((status, data, len)) = parse_frame(&this.rbuf)? {
+                this.rbuf.drain(..len);
+                match status {
+                    STATUS_NEW | STATUS_KEEP => {
+                        this.plain = data;
+                        this.plain_pos = 0;
+                    }
+                    STATUS_END => this.closed = true,
+                    STATUS_KEEP_ALIVE => {}
+                    _ => return Poll::Ready(Err(invalid_data("Mux.Cool 未知的帧状态"))),
+                }
+                continue;
+            }
+            let need = this.rbuf.len() + 1;
+            if !ready!(poll_fill(&mut this.inner, cx, &mut this.rbuf, need))? {
+                if this.rbuf.is_empty() {
+                    return Poll::Ready(Ok(()));
+                }
+                return Poll::Ready(Err(io::ErrorKind::UnexpectedEof.into()));
+            }
+        }
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> FramedWrite for MuxStream<S> {
+    type Inner = S;
+
+    fn write_parts(&mut self) -> (&mut S, &mut Vec<u8>) {
+        (&mut self.inner, &mut self.wbuf)
+    }
+
+    fn encode_frame(&mut self, data: &[u8]) -> usize {
+        if let Some(frame) = self.new_frame.take() {
+            self.wbuf.extend_from_slice(&frame);
+        }
+        let n = data.len().min(MAX_FRAME_DATA);
+        self.wbuf.extend_from_slice(&data_frame(&data[..n]));
+        n
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> AsyncWrite for MuxStream<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.get_mut().poll_write_framed(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(poll_drain(&mut this.inner, cx, &mut this.wbuf))?;
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        // 子连接已建立时通知服务端结束
+        if this.new_frame.take().is_none() && !this.closed {
+            this.closed = true;
+            this.wbuf.extend_from_slice(&control_frame(STATUS_END));
+        }
+        ready!(poll_drain(&mut this.inner, cx, &mut this.wbuf))?;
+        Pin::new(&mut this.inner).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt, duplex};
+
+    #[test]
+    fn test_new_frame() {
+        let frame = new_frame(&Address::Socket("127.0.0.1:8388".parse().unwrap()));
+        // 元数据：会话 ID + New + 无选项 + TCP，之后为端口与 IPv4 地址
+        assert_eq!(
+            &frame[..7],
+            &[0x00, 0x0c, 0x00, 0x01, STATUS_NEW, 0x00, NETWORK_TCP]
+        );
+        assert_eq!(&frame[7..], &[0x20, 0xc4, 0x01, 127, 0, 0, 1]);
+    }
+
+    #[test]
+    fn test_parse_frame() {
+        let frame = data_frame(b"hello");
+        assert_eq!(
+            parse_frame(&frame).unwrap(),
+            Some((STATUS_KEEP, b"hello".to_vec(), frame.len()))
+        );
+        assert_eq!(parse_frame(&frame[..frame.len() - 1]).unwrap(), None);
+        assert_eq!(
+            parse_frame(&control_frame(STATUS_END)).unwrap(),
+            Some((STATUS_END, Vec::new(), 6))
+        );
+        assert!(parse_frame(&[0xff, 0xff]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_mux_stream() {
+        let (client, mut server) = duplex(65536);
+        let target = Address::Domain("example.com".to_string(), 443);
+        let mut stream = MuxStream::new(client, &target);
+        stream.write_all(&[1u8; 10000]).await.unwrap();
+
+        let mut expected = new_frame(&target);
+        expected.extend(data_frame(&[1u8; MAX_FRAME_DATA]));
+        expected.extend(data_frame(&[1u8; 10000 - MAX_FRAME_DATA]));
+        let mut sent = vec![0u8; expected.len()];
+        server.read_exact(&mut sent).await.unwrap();
+        assert_eq!(sent, expected);
+
+        let mut response = control_frame(STATUS_KEEP_ALIVE);
+        response.extend(data_frame(b"hello"));
+        response.extend(data_frame(b" mux"));
+        response.extend(control_frame(STATUS_END));
+        server.write_all(&response).await.unwrap();
+        let mut reply = Vec::new();
+        stream.read_to_end(&mut reply).await.unwrap();
+        assert_eq!(reply, b"hello mux");
+
+        stream.shutdown().await.unwrap();
+    }
+}
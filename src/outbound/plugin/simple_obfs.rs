@@ -0,0 +1,383 @@
+//! simple-obfs（obfs-local）
+//! http 模式把首包放进伪装的 WebSocket 升级请求体，tls 模式把首包放进 ClientHello 的 SessionTicket 扩展，
+//! 之后的数据分别以原始流和 TLS 应用数据记录传输
+
+use crate::outbound::util::{FramedWrite, copy_plain, invalid_data, poll_drain, poll_fill};
+use anyhow::{Result, anyhow};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use rand::{Rng, RngCore};
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll, ready};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+/// TLS 记录最大负载
+const TLS_CHUNK: usize = 16 * 1024;
+/// 响应头长度上限
+const MAX_RESPONSE_HEADER: usize = 8192;
+const TLS_HANDSHAKE: u8 = 0x16;
+const TLS_CHANGE_CIPHER_SPEC: u8 = 0x14;
+const TLS_APPLICATION_DATA: u8 = 0x17;
+
+/// 混淆模式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObfsMode {
+    Http,
+    Tls,
+}
+
+impl ObfsMode {
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "http" => Ok(ObfsMode::Http),
+            "tls" => Ok(ObfsMode::Tls),
+            other => Err(anyhow!("不支持的 simple-obfs 模式: {}", other)),
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ObfsMode::Http => "http",
+            ObfsMode::Tls => "tls",
+        }
+    }
+}
+
+/// 伪装成 WebSocket 升级的 HTTP 请求，首包作为请求体
+fn http_request(host: &str, port: u16, data: &[u8]) -> Vec<u8> {
+    let mut rng = rand::thread_rng();
+    let mut key = [0u8; 16];
+    rng.fill_bytes(&mut key);
+    let host = if port == 80 {
+        host.to_string()
+    } else {
+        format!("{}:{}", host, port)
+    };
+    let mut request = format!(
+        "GET / HTTP/1.1\r\nHost: {}\r\nUser-Agent: curl/7.{}.{}\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Key: {}\r\nContent-Length: {}\r\n\r\n",
+        host,
+        rng.gen_range(0..54),
+        rng.gen_range(0..2),
+        STANDARD.encode(key),
+        data.len()
+    )
+    .into_bytes();
+    request.extend_from_slice(data);
+    request
+}
+
+/// 携带首包的 ClientHello 记录
+fn client_hello(host: &str, data: &[u8]) -> Vec<u8> {
+    let mut rng = rand::thread_rng();
+    let host = host.as_bytes();
+    let mut extensions = Vec::with_capacity(79 + data.len() + host.len());
+    // session_ticket
+    extensions.extend_from_slice(&[0x00, 0x23]);
+    extensions.extend_from_slice(&(data.len() as u16).to_be_bytes());
+    extensions.extend_from_slice(data);
+    // server_name
+    extensions.extend_from_slice(&[0x00, 0x00]);
+    extensions.extend_from_slice(&(host.len() as u16 + 5).to_be_bytes());
+    extensions.extend_from_slice(&(host.len() as u16 + 3).to_be_bytes());
+    extensions.push(0x00);
+    extensions.extend_from_slice(&(host.len() as u16).to_be_bytes());
+    extensions.extend_from_slice(host);
+    // ec_point_formats、supported_groups、signature_algorithms、encrypt_then_mac、extended_master_secret
+    extensions.extend_from_slice(&[0x00, 0x0b, 0x00, 0x04, 0x03, 0x01, 0x00, 0x02]);
+    extensions.extend_from_slice(&[
+        0x00, 0x0a, 0x00, 0x0a, 0x00, 0x08, 0x00, 0x1d, 0x00, 0x17, 0x00, 0x19, 0x00, 0x18,
+    ]);
+    extensions.extend_from_slice(&[
+        0x00, 0x0d, 0x00, 0x20, 0x00, 0x1e, 0x06, 0x01, 0x06, 0x02, 0x06, 0x03, 0x05, 0x01, 0x05,
+        0x02, 0x05, 0x03, 0x04, 0x01, 0x04, 0x02, 0x04, 0x03, 0x03, 0x01, 0x03, 0x02, 0x03, 0x03,
+        0x02, 0x01, 0x02, 0x02, 0x02, 0x03,
+    ]);
+    extensions.extend_from_slice(&[0x00, 0x16, 0x00, 0x00, 0x00, 0x17, 0x00, 0x00]);
+
+    let mut hello = vec![0x03, 0x03];
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as u32;
+    hello.extend_from_slice(&timestamp.to_be_bytes());
+    let mut random = [0u8; 60];
+    rng.fill_bytes(&mut random);
+    hello.extend_from_slice(&random[..28]);
+    hello.push(32);
+    hello.extend_from_slice(&random[28..]);
+    hello.extend_from_slice(&[
+        0x00, 0x38, 0xc0, 0x2c, 0xc0, 0x30, 0x00, 0x9f, 0xcc, 0xa9, 0xcc, 0xa8, 0xcc, 0xaa, 0xc0,
+        0x2b, 0xc0, 0x2f, 0x00, 0x9e, 0xc0, 0x24, 0xc0, 0x28, 0x00, 0x6b, 0xc0, 0x23, 0xc0, 0x27,
+        0x00, 0x67, 0xc0, 0x0a, 0xc0, 0x14, 0x00, 0x39, 0xc0, 0x09, 0xc0, 0x13, 0x00, 0x33, 0x00,
+        0x9d, 0x00, 0x9c, 0x00, 0x3d, 0x00, 0x3c, 0x00, 0x35, 0x00, 0x2f, 0x00, 0xff,
+    ]);
+    hello.extend_from_slice(&[0x01, 0x00]);
+    hello.extend_from_slice(&(extensions.len() as u16).to_be_bytes());
+    hello.extend_from_slice(&extensions);
+
+    let mut handshake = vec![0x01, 0x00];
+    handshake.extend_from_slice(&(hello.len() as u16).to_be_bytes());
+    handshake.extend_from_slice(&hello);
+    let mut record = vec![TLS_HANDSHAKE, 0x03, 0x01];
+    record.extend_from_slice(&(handshake.len() as u16).to_be_bytes());
+    record.extend_from_slice(&handshake);
+    record
+}
+
+/// TLS 应用数据记录
+fn application_record(data: &[u8]) -> Vec<u8> {
+    let mut record = Vec::with_capacity(5 + data.len());
+    record.extend_from_slice(&[TLS_APPLICATION_DATA, 0x03, 0x03]);
+    record.extend_from_slice(&(data.len() as u16).to_be_bytes());
+    record.extend_from_slice(data);
+    record
+}
+
+/// simple-obfs 混淆连接
+pub struct ObfsStream<S> {
+    inner: S,
+    mode: ObfsMode,
+    host: String,
+    port: u16,
+    request_sent: bool,
+    /// http 模式：响应头已跳过；tls 模式：已收到 ChangeCipherSpec
+    handshake_done: bool,
+    rbuf: Vec<u8>,
+    plain: Vec<u8>,
+    plain_pos: usize,
+    wbuf: Vec<u8>,
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> ObfsStream<S> {
+    /// `host`/`port` 用于 HTTP Host 头与 TLS SNI，混淆握手随第一次写入发出
+    pub fn new(inner: S, mode: ObfsMode, host: &str, port: u16) -> Self {
+        Self {
+            inner,
+            mode,
+            host: host.to_string(),
+            port,
+            request_sent: false,
+            handshake_done: false,
+            rbuf: Vec::new(),
+            plain: Vec::new(),
+            plain_pos: 0,
+            wbuf: Vec::new(),
+        }
+    }
+
+    fn encode(&mut self, data: &[u8]) {
+        let first = !self.request_sent;
+        self.request_sent = true;
+        match (self.mode, first) {
+            (ObfsMode::Http, true) => self
+                .wbuf
+                .extend_from_slice(&http_request(&self.host, self.port, data)),
+            (ObfsMode::Http, false) => self.wbuf.extend_from_slice(data),
+            (ObfsMode::Tls, true) => self.wbuf.extend_from_slice(&client_hello(&self.host, data)),
+            (ObfsMode::Tls, false) => self.wbuf.extend_from_slice(&application_record(data)),
+        }
+    }
+
+    /// http 模式：跳过响应头，剩余数据作为明文
+    fn poll_http_header(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        loop {
+            if let Some(pos) = self.rbuf.windows(4).position(|w| w == b"\r\n\r\n") {
+                if !self.rbuf.starts_with(b"HTTP/1.1 101") {
+                    return Poll::Ready(Err(invalid_data("simple-obfs 服务端响应不是 101")));
+                }
+                self.plain = self.rbuf.split_off(pos + 4);
+                self.plain_pos = 0;
+                self.rbuf.clear();
+                self.handshake_done = true;
+                return Poll::Ready(Ok(()));
+            }
+            if self.rbuf.len() > MAX_RESPONSE_HEADER {
+                return Poll::Ready(Err(invalid_data("simple-obfs 响应头过长")));
+            }
+            let need = self.rbuf.len() + 1;
+            if !ready!(poll_fill(&mut self.inner, cx, &mut self.rbuf, need))? {
+                return Poll::Ready(Err(io::ErrorKind::UnexpectedEof.into()));
+            }
+        }
+    }
+
+    /// tls 模式：读取一条记录，握手完成后的记录负载作为明文
+    ///
+    /// 返回 `Ok(false)` 表示在记录边界遇到 EOF
+    fn poll_tls_record(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<bool>> {
+        if !ready!(poll_fill(&mut self.inner, cx, &mut self.rbuf, 5))? {
+            if self.rbuf.is_empty() {
+                return Poll::Ready(Ok(false));
+            }
+            return Poll::Ready(Err(io::ErrorKind::UnexpectedEof.into()));
+        }
+        let len = 5 + u16::from_be_bytes([self.rbuf[3], self.rbuf[4]]) as usize;
+        if !ready!(poll_fill(&mut self.inner, cx, &mut self.rbuf, len))? {
+            return Poll::Ready(Err(io::ErrorKind::UnexpectedEof.into()));
+        }
+        let record: Vec<u8> = self.rbuf.drain(..len).collect();
+        if self.handshake_done {
+            self.plain = record[5..].to_vec();
+            self.plain_pos = 0;
+        } else if record[0] == TLS_CHANGE_CIPHER_SPEC {
+            // ServerHello 与 ChangeCipherSpec 之后的记录才是数据
+            self.handshake_done = true;
+        } else if record[0] != TLS_HANDSHAKE {
+            return Poll::Ready(Err(invalid_data("simple-obfs 服务端握手记录类型错误")));
+        }
+        Poll::Ready(Ok(true))
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> AsyncRead for ObfsStream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        loop {
+            if copy_plain(&mut this.plain, &mut this.plain_pos, buf) {
+                return Poll::Ready(Ok(()));
+            }
+            match this.mode {
+                ObfsMode::Http if this.handshake_done => {
+                    return Pin::new(&mut this.inner).poll_read(cx, buf);
+                }
+                ObfsMode::Http => ready!(this.poll_http_header(cx))?,
+                ObfsMode::Tls => {
+                    if !ready!(this.poll_tls_record(cx))? {
+                        return Poll::Ready(Ok(()));
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> FramedWrite for ObfsStream<S> {
+    type Inner = S;
+
+    fn write_parts(&mut self) -> (&mut S, &mut Vec<u8>) {
+        (&mut self.inner, &mut self.wbuf)
+    }
+
+    fn encode_frame(&mut self, data: &[u8]) -> usize {
+        let n = data.len().min(TLS_CHUNK);
+        self.encode(&data[..n]);
+        n
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> AsyncWrite for ObfsStream<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.get_mut().poll_write_framed(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(poll_drain(&mut this.inner, cx, &mut this.wbuf))?;
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(poll_drain(&mut this.inner, cx, &mut this.wbuf))?;
+        Pin::new(&mut this.inner).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt, duplex};
+
+    #[test]
+    fn test_client_hello_layout() {
+        let hello = client_hello("www.bing.com", b"payload");
+        assert_eq!(&hello[..3], &[TLS_HANDSHAKE, 0x03, 0x01]);
+        let record_len = u16::from_be_bytes([hello[3], hello[4]]) as usize;
+        assert_eq!(record_len, hello.len() - 5);
+        assert_eq!(record_len, 212 + b"payload".len() + b"www.bing.com".len());
+        // 会话票据扩展紧跟在扩展总长度之后
+        let ticket = 5 + 4 + 2 + 32 + 1 + 32 + 2 + 56 + 2 + 2;
+        assert_eq!(&hello[ticket..ticket + 4], &[0x00, 0x23, 0x00, 0x07]);
+        assert_eq!(&hello[ticket + 4..ticket + 11], b"payload");
+    }
+
+    #[tokio::test]
+    async fn test_http_mode() {
+        let (client, mut server) = duplex(65536);
+        let mut stream = ObfsStream::new(client, ObfsMode::Http, "cdn.example.com", 8080);
+        stream.write_all(b"first").await.unwrap();
+        stream.write_all(b" second").await.unwrap();
+
+        let mut request = vec![0u8; 4096];
+        let n = server.read(&mut request).await.unwrap();
+        let request = String::from_utf8_lossy(&request[..n]).to_string();
+        assert!(request.starts_with("GET / HTTP/1.1\r\nHost: cdn.example.com:8080\r\n"));
+        assert!(request.contains("Content-Length: 5\r\n"));
+        assert!(request.ends_with("\r\n\r\nfirst second"));
+
+        server
+            .write_all(b"HTTP/1.1 101 Switching Protocols\r\nServer: nginx\r\n\r\nhello")
+            .await
+            .unwrap();
+        server.write_all(b" obfs").await.unwrap();
+        let mut reply = [0u8; 10];
+        stream.read_exact(&mut reply).await.unwrap();
+        assert_eq!(&reply, b"hello obfs");
+    }
+
+    #[tokio::test]
+    async fn test_tls_mode() {
+        let (client, mut server) = duplex(65536);
+        let mut stream = ObfsStream::new(client, ObfsMode::Tls, "www.bing.com", 443);
+        stream.write_all(b"first").await.unwrap();
+        stream.write_all(&[7u8; 20000]).await.unwrap();
+
+        let hello_len = 5 + 212 + 5 + "www.bing.com".len();
+        let mut hello = vec![0u8; hello_len];
+        server.read_exact(&mut hello).await.unwrap();
+        assert_eq!(&hello[138..143], &[0x00, 0x23, 0x00, 0x05, b'f']);
+        for len in [TLS_CHUNK, 20000 - TLS_CHUNK] {
+            let mut record = vec![0u8; 5 + len];
+            server.read_exact(&mut record).await.unwrap();
+            assert_eq!(&record[..3], &[TLS_APPLICATION_DATA, 0x03, 0x03]);
+            assert_eq!(u16::from_be_bytes([record[3], record[4]]) as usize, len);
+        }
+
+        // ServerHello + ChangeCipherSpec + 携带数据的握手记录
+        let mut response = vec![TLS_HANDSHAKE, 0x03, 0x03, 0x00, 91];
+        response.extend_from_slice(&[0u8; 91]);
+        response.extend_from_slice(&[TLS_CHANGE_CIPHER_SPEC, 0x03, 0x03, 0x00, 0x01, 0x01]);
+        response.extend_from_slice(&[TLS_HANDSHAKE, 0x03, 0x03, 0x00, 0x05]);
+        response.extend_from_slice(b"hello");
+        response.extend_from_slice(&application_record(b" tls"));
+        server.write_all(&response).await.unwrap();
+        drop(server);
+
+        let mut reply = Vec::new();
+        stream.read_to_end(&mut reply).await.unwrap();
+        assert_eq!(reply, b"hello tls");
+    }
+
+    #[tokio::test]
+    async fn test_http_mode_rejected() {
+        let (client, mut server) = duplex(65536);
+        let mut stream = ObfsStream::new(client, ObfsMode::Http, "bing.com", 80);
+        stream.write_all(b"data").await.unwrap();
+        server
+            .write_all(b"HTTP/1.1 404 Not Found\r\n\r\n")
+            .await
+            .unwrap();
+        let mut buf = [0u8; 4];
+        assert!(stream.read(&mut buf).await.is_err());
+    }
+}
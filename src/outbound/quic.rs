@@ -0,0 +1,358 @@
+//! 基于 QUIC 的出站（Hysteria2、TUIC）共用部分
+//! 负责建立 QUIC 连接、Salamander 混淆以及把双向流包装成普通连接
+
+use super::tls::{self, TlsOptions};
+use anyhow::{Result, anyhow};
+use blake2::digest::consts::U32;
+use blake2::{Blake2b, Digest};
+use quinn::crypto::rustls::QuicClientConfig;
+use quinn::udp::{RecvMeta, Transmit};
+use quinn::{
+    AsyncUdpSocket, ClientConfig, Connection, Endpoint, EndpointConfig, RecvStream, SendStream,
+    TokioRuntime, TransportConfig, UdpPoller,
+};
+use std::fmt;
+use std::io::{self, IoSliceMut};
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll, ready};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::UdpSocket;
+
+const SALT_LEN: usize = 8;
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(10);
+const IDLE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Salamander 混淆：每个 UDP 包前加 8 字节随机盐，
+/// 负载与 BLAKE2b-256(密码 + 盐) 循环异或
+#[derive(Clone)]
+pub struct Salamander {
+    key: Vec<u8>,
+}
+
+impl Salamander {
+    pub fn new(password: &str) -> Self {
+        Self {
+            key: password.as_bytes().to_vec(),
+        }
+    }
+
+    fn mask(&self, salt: &[u8]) -> [u8; 32] {
+        let mut hasher = Blake2b::<U32>::new();
+        hasher.update(&self.key);
+        hasher.update(salt);
+        hasher.finalize().into()
+    }
+
+    /// 混淆一个 UDP 包
+    pub fn obfuscate(&self, packet: &[u8]) -> Vec<u8> {
+        let salt: [u8; SALT_LEN] = rand::random();
+        let mask = self.mask(&salt);
+        let mut out = Vec::with_capacity(SALT_LEN + packet.len());
+        out.extend_from_slice(&salt);
+        out.extend(packet.iter().enumerate().map(|(i, b)| b ^ mask[i % 32]));
+        out
+    }
+
+    /// 原地还原一个 UDP 包，返回还原后的长度；包过短时返回 `None`
+    pub fn deobfuscate(&self, packet: &mut [u8]) -> Option<usize> {
+        if packet.len() <= SALT_LEN {
+            return None;
+        }
+        let mask = self.mask(&packet[..SALT_LEN]);
+        let len = packet.len() - SALT_LEN;
+        for i in 0..len {
+            packet[i] = packet[SALT_LEN + i] ^ mask[i % 32];
+        }
+        Some(len)
+    }
+}
+
+impl fmt::Debug for Salamander {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Salamander")
+    }
+}
+
+/// 收发时做 Salamander 混淆的 UDP 套接字
+#[derive(Debug)]
+struct SalamanderSocket {
+    io: UdpSocket,
+    obfs: Salamander,
+}
+
+#[derive(Debug)]
+struct SalamanderPoller(Arc<SalamanderSocket>);
+
+impl UdpPoller for SalamanderPoller {
+    fn poll_writable(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+        self.0.io.poll_send_ready(cx)
+    }
+}
+
+impl AsyncUdpSocket for SalamanderSocket {
+    fn create_io_poller(self: Arc<Self>) -> Pin<Box<dyn UdpPoller>> {
+        Box::pin(SalamanderPoller(self))
+    }
+
+    fn try_send(&self, transmit: &Transmit) -> io::Result<()> {
+        let segment = transmit.segment_size.unwrap_or(transmit.contents.len());
+        for datagram in transmit.contents.chunks(segment.max(1)) {
+            self.io
+                .try_send_to(&self.obfs.obfuscate(datagram), transmit.destination)?;
+        }
+        Ok(())
+    }
+
+    fn poll_recv(
+        &self,
+        cx: &mut Context,
+        bufs: &mut [IoSliceMut<'_>],
+        meta: &mut [RecvMeta],
+    ) -> Poll<io::Result<usize>> {
+        loop {
+            let mut read_buf = ReadBuf::new(&mut bufs[0]);
+            let addr = ready!(self.io.poll_recv_from(cx, &mut read_buf))?;
+            let len = read_buf.filled().len();
+            // 无法还原的包直接丢弃
+            if let Some(len) = self.obfs.deobfuscate(&mut bufs[0][..len]) {
+                meta[0] = RecvMeta {
+                    addr,
+                    len,
+                    stride: len,
+                    ecn: None,
+                    dst_ip: None,
+                };
+                return Poll::Ready(Ok(1));
+            }
+        }
+    }
+
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.io.local_addr()
+    }
+}
+
+/// 建立到代理服务器的 QUIC 连接
+///
+/// 返回的 `Endpoint` 需要与连接一同保留
+pub async fn connect(
+    server: &str,
+    port: u16,
+    options: &TlsOptions,
+    obfs: Option<Salamander>,
+) -> Result<(Endpoint, Connection)> {
+    let host = server.trim_start_matches('[').trim_end_matches(']');
+    let addr = tokio::net::lookup_host((host, port))
+        .await
+        .map_err(|e| anyhow!("解析服务器地址失败: {}", e))?
+        .next()
+        .ok_or_else(|| anyhow!("解析服务器地址失败: {}", server))?;
+    let bind: SocketAddr = if addr.is_ipv4() {
+        (Ipv4Addr::UNSPECIFIED, 0).into()
+    } else {
+        (Ipv6Addr::UNSPECIFIED, 0).into()
+    };
+
+    let mut endpoint = match obfs {
+        Some(obfs) => {
+            let io = UdpSocket::bind(bind).await?;
+            Endpoint::new_with_abstract_socket(
+                EndpointConfig::default(),
+                None,
+                Arc::new(SalamanderSocket { io, obfs }),
+                Arc::new(TokioRuntime),
+            )?
+        }
+        None => Endpoint::client(bind)?,
+    };
+
+    let crypto = QuicClientConfig::try_from(tls::client_config(options))
+        .map_err(|e| anyhow!("QUIC TLS 配置错误: {}", e))?;
+    let mut transport = TransportConfig::default();
+    transport.keep_alive_interval(Some(KEEP_ALIVE_INTERVAL));
+    transport.max_idle_timeout(Some(IDLE_TIMEOUT.try_into().expect("空闲超时超出范围")));
+    let mut config = ClientConfig::new(Arc::new(crypto));
+    config.transport_config(Arc::new(transport));
+    endpoint.set_default_client_config(config);
+
+    let connection = endpoint
+        .connect(addr, &options.sni)
+        .map_err(|e| anyhow!("QUIC 连接参数错误: {}", e))?
+        .await
+        .map_err(|e| anyhow!("QUIC 握手失败: {}", e))?;
+    Ok((endpoint, connection))
+}
+
+/// 编码 QUIC 变长整数
+pub fn write_varint(buf: &mut Vec<u8>, value: u64) {
+    match value {
+        0..=0x3f => buf.push(value as u8),
+        0x40..=0x3fff => buf.extend_from_slice(&(value as u16 | 0x4000).to_be_bytes()),
+        0x4000..=0x3fff_ffff => buf.extend_from_slice(&(value as u32 | 0x8000_0000).to_be_bytes()),
+        _ => buf.extend_from_slice(&(value | 0xc000_0000_0000_0000).to_be_bytes()),
+    }
+}
+
+/// 读取 QUIC 变长整数
+pub async fn read_varint<R: AsyncRead + Unpin>(reader: &mut R) -> io::Result<u64> {
+    use tokio::io::AsyncReadExt;
+
+    let first = reader.read_u8().await?;
+    let len = 1usize << (first >> 6);
+    let mut value = (first & 0x3f) as u64;
+    for _ in 1..len {
+        value = (value << 8) | reader.read_u8().await? as u64;
+    }
+    Ok(value)
+}
+
+/// QUIC 双向流，持有连接与端点以保证流存活期间连接不被关闭
+///
+/// 每次拨号独占一条连接，流释放时主动关闭连接，避免保活包使其一直存活
+pub struct QuicStream<K> {
+    send: SendStream,
+    recv: RecvStream,
+    connection: Connection,
+    _endpoint: Endpoint,
+    _keep: K,
+}
+
+impl<K: Unpin> QuicStream<K> {
+    pub fn new(
+        send: SendStream,
+        recv: RecvStream,
+        connection: Connection,
+        endpoint: Endpoint,
+        keep: K,
+    ) -> Self {
+        Self {
+            send,
+            recv,
+            connection,
+            _endpoint: endpoint,
+            _keep: keep,
+        }
+    }
+}
+
+impl<K> Drop for QuicStream<K> {
+    fn drop(&mut self) {
+        self.connection.close(0u32.into(), b"");
+    }
+}
+
+impl<K: Unpin> AsyncRead for QuicStream<K> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().recv).poll_read(cx, buf)
+    }
+}
+
+impl<K: Unpin> AsyncWrite for QuicStream<K> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        AsyncWrite::poll_write(Pin::new(&mut self.get_mut().send), cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        AsyncWrite::poll_flush(Pin::new(&mut self.get_mut().send), cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        AsyncWrite::poll_shutdown(Pin::new(&mut self.get_mut().send), cx)
+    }
+}
+
+#[cfg(test)]
+pub mod test_server {
+    use super::{Salamander, SalamanderSocket};
+    use crate::outbound::tls::test_server::server_config;
+    use quinn::crypto::rustls::QuicServerConfig;
+    use quinn::{Endpoint, EndpointConfig, ServerConfig, TokioRuntime};
+    use std::net::SocketAddr;
+    use std::sync::Arc;
+
+    /// 在本地随机端口启动 QUIC 服务端，可选 Salamander 混淆
+    pub fn endpoint(
+        domain: &str,
+        alpn: &[&str],
+        obfs: Option<Salamander>,
+    ) -> (Endpoint, SocketAddr) {
+        let crypto = QuicServerConfig::try_from(server_config(domain, alpn)).unwrap();
+        let config = ServerConfig::with_crypto(Arc::new(crypto));
+        let socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let endpoint = match obfs {
+            Some(obfs) => {
+                socket.set_nonblocking(true).unwrap();
+                let io = tokio::net::UdpSocket::from_std(socket).unwrap();
+                Endpoint::new_with_abstract_socket(
+                    EndpointConfig::default(),
+                    Some(config),
+                    Arc::new(SalamanderSocket { io, obfs }),
+                    Arc::new(TokioRuntime),
+                )
+                .unwrap()
+            }
+            None => Endpoint::new(
+                EndpointConfig::default(),
+                Some(config),
+                socket,
+                Arc::new(TokioRuntime),
+            )
+            .unwrap(),
+        };
+        let addr = endpoint.local_addr().unwrap();
+        (endpoint, addr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_salamander_roundtrip() {
+        let obfs = Salamander::new("cry_me_a_r1ver");
+        let packet = b"quic initial packet".to_vec();
+        let mut obfuscated = obfs.obfuscate(&packet);
+        assert_eq!(obfuscated.len(), packet.len() + SALT_LEN);
+        assert_ne!(&obfuscated[SALT_LEN..], &packet[..]);
+
+        let len = obfs.deobfuscate(&mut obfuscated).unwrap();
+        assert_eq!(&obfuscated[..len], &packet[..]);
+        assert!(obfs.deobfuscate(&mut [0u8; SALT_LEN]).is_none());
+    }
+
+    #[test]
+    fn test_salamander_mask() {
+        // BLAKE2b-256("password" + 8 字节零盐)，由 Python hashlib 独立计算
+        let mask = Salamander::new("password").mask(&[0u8; 8]);
+        let hex: String = mask.iter().map(|b| format!("{:02x}", b)).collect();
+        assert_eq!(
+            hex,
+            "dd0c77a34b83444146a47028a665977dcc5f10ee38a30aa739fcd5c0c547e54a"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_varint() {
+        for value in [0u64, 37, 0x401, 15293, 494878333, 151288809941952652] {
+            let mut buf = Vec::new();
+            write_varint(&mut buf, value);
+            let mut reader = &buf[..];
+            assert_eq!(read_varint(&mut reader).await.unwrap(), value);
+        }
+        let mut buf = Vec::new();
+        write_varint(&mut buf, 0x401);
+        assert_eq!(buf, [0x44, 0x01]);
+    }
+}
@@ -0,0 +1,331 @@
+//! REALITY 客户端认证
+//! 在 ClientHello 的 session id 中携带加密的认证信息，并用 HMAC 校验服务端下发的临时证书
+
+use super::crypto::{AeadCipher, AeadKind};
+use super::util::invalid_data;
+use crate::proxy::ProxyNode;
+use anyhow::{Result, anyhow};
+use base64::Engine;
+use base64::engine::general_purpose::{STANDARD_NO_PAD, URL_SAFE_NO_PAD};
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
+use rustls::pki_types::CertificateDer;
+use sha2::{Sha256, Sha512};
+use std::io;
+use std::time::{SystemTime, UNIX_EPOCH};
+use x25519_dalek::{PublicKey, StaticSecret};
+
+/// 写入 session id 的客户端版本号
+const CLIENT_VERSION: [u8; 3] = [25, 8, 3];
+/// Ed25519 SubjectPublicKeyInfo 的 DER 前缀，其后紧跟 32 字节公钥
+const ED25519_SPKI_PREFIX: [u8; 12] = [
+    0x30, 0x2a, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x03, 0x21, 0x00,
+];
+/// 临时证书末尾被替换为 HMAC 的签名长度
+const SIGNATURE_LEN: usize = 64;
+
+/// REALITY 连接参数
+#[derive(Debug, Clone)]
+pub struct RealityOptions {
+    /// 服务端 X25519 公钥
+    pub public_key: [u8; 32],
+    /// short id，不足 8 字节时右侧补零
+    pub short_id: [u8; 8],
+}
+
+impl RealityOptions {
+    /// 由 base64 公钥与十六进制 short id 构建
+    pub fn new(public_key: &str, short_id: &str) -> Result<Self> {
+        let encoded = public_key.trim().trim_end_matches('=');
+        let decoded = URL_SAFE_NO_PAD
+            .decode(encoded)
+            .or_else(|_| STANDARD_NO_PAD.decode(encoded))
+            .map_err(|e| anyhow!("REALITY 公钥不是有效的 base64: {}", e))?;
+        let public_key: [u8; 32] = decoded
+            .try_into()
+            .map_err(|_| anyhow!("REALITY 公钥长度应为 32 字节"))?;
+
+        let short_id = short_id.trim();
+        if short_id.len() > 16 || !short_id.len().is_multiple_of(2) {
+            return Err(anyhow!("REALITY short-id 格式错误: {}", short_id));
+        }
+        let mut id = [0u8; 8];
+        for (byte, pair) in id.iter_mut().zip(short_id.as_bytes().chunks(2)) {
+            *byte = std::str::from_utf8(pair)
+                .ok()
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                .ok_or_else(|| anyhow!("REALITY short-id 不是十六进制: {}", short_id))?;
+        }
+
+        Ok(Self {
+            public_key,
+            short_id: id,
+        })
+    }
+
+    /// 从节点的 `reality-opts` 解析，未配置时返回 `None`
+    pub fn from_node(proxy: &ProxyNode) -> Result<Option<Self>> {
+        proxy
+            .tls
+            .reality_opts
+            .as_ref()
+            .map(|opts| Self::new(&opts.public_key, opts.short_id.as_deref().unwrap_or("")))
+            .transpose()
+    }
+
+    /// 由客户端临时私钥与服务端公钥协商认证密钥
+    pub fn auth_key(&self, secret: &StaticSecret, random: &[u8; 32]) -> [u8; 32] {
+        let shared = secret.diffie_hellman(&PublicKey::from(self.public_key));
+        let mut key = [0u8; 32];
+        Hkdf::<Sha256>::new(Some(&random[..20]), shared.as_bytes())
+            .expand(b"REALITY", &mut key)
+            .expect("REALITY 密钥派生长度错误");
+        key
+    }
+
+    /// 加密 session id：版本号 + 时间戳 + short id，附加数据为 session id 清零的 ClientHello
+    pub fn seal_session_id(
+        &self,
+        auth_key: &[u8; 32],
+        random: &[u8; 32],
+        hello: &[u8],
+    ) -> [u8; 32] {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs() as u32;
+        let mut plain = [0u8; 16];
+        plain[..3].copy_from_slice(&CLIENT_VERSION);
+        plain[4..8].copy_from_slice(&timestamp.to_be_bytes());
+        plain[8..].copy_from_slice(&self.short_id);
+
+        let sealed = AeadCipher::new(AeadKind::Aes256Gcm, auth_key).encrypt_aad(
+            &random[20..],
+            &plain,
+            hello,
+        );
+        let mut session_id = [0u8; 32];
+        session_id.copy_from_slice(&sealed);
+        session_id
+    }
+
+    /// 校验服务端临时证书：签名位置应为以认证密钥计算的 Ed25519 公钥 HMAC-SHA512
+    pub fn verify_certificate(auth_key: &[u8; 32], cert: &CertificateDer<'_>) -> io::Result<()> {
+        let der = cert.as_ref();
+        let public_key = der
+            .windows(ED25519_SPKI_PREFIX.len())
+            .position(|window| window == ED25519_SPKI_PREFIX)
+            .and_then(|pos| {
+                let start = pos + ED25519_SPKI_PREFIX.len();
+                der.get(start..start + 32)
+            })
+            .ok_or_else(|| invalid_data("REALITY 证书不是 Ed25519 临时证书"))?;
+        if der.len() < SIGNATURE_LEN {
+            return Err(invalid_data("REALITY 证书长度错误"));
+        }
+
+        let mut mac = Hmac::<Sha512>::new_from_slice(auth_key).expect("HMAC 密钥长度错误");
+        mac.update(public_key);
+        mac.verify_slice(&der[der.len() - SIGNATURE_LEN..])
+            .map_err(|_| invalid_data("REALITY 证书校验失败，公钥或 short-id 可能不匹配"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::outbound::tls::crypto_provider;
+    use crate::outbound::tls13::{self, ServerAuth};
+    use crate::proxy::Protocol;
+    use crate::proxy::node::RealityOpts;
+    use rustls::ServerConfig;
+    use rustls::pki_types::{PrivateKeyDer, PrivatePkcs8KeyDer};
+    use std::pin::Pin;
+    use std::sync::Arc;
+    use std::task::{Context, Poll};
+    use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadBuf};
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio_rustls::TlsAcceptor;
+
+    /// 把已读取的 ClientHello 放回流前端，交给 rustls 继续握手
+    struct Replay {
+        prefix: Vec<u8>,
+        inner: TcpStream,
+    }
+
+    impl AsyncRead for Replay {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut ReadBuf<'_>,
+        ) -> Poll<io::Result<()>> {
+            if self.prefix.is_empty() {
+                return Pin::new(&mut self.inner).poll_read(cx, buf);
+            }
+            let n = self.prefix.len().min(buf.remaining());
+            buf.put_slice(&self.prefix[..n]);
+            self.prefix.drain(..n);
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    impl AsyncWrite for Replay {
+        fn poll_write(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            Pin::new(&mut self.inner).poll_write(cx, buf)
+        }
+
+        fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Pin::new(&mut self.inner).poll_flush(cx)
+        }
+
+        fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Pin::new(&mut self.inner).poll_shutdown(cx)
+        }
+    }
+
+    /// 从 ClientHello 握手消息中取出随机数与 X25519 公钥
+    fn parse_hello(hello: &[u8]) -> ([u8; 32], [u8; 32]) {
+        let random: [u8; 32] = hello[6..38].try_into().unwrap();
+        let mut pos = 39 + hello[38] as usize;
+        pos += 2 + u16::from_be_bytes([hello[pos], hello[pos + 1]]) as usize;
+        pos += 1 + hello[pos] as usize;
+        pos += 2;
+        while pos < hello.len() {
+            let ext_type = u16::from_be_bytes([hello[pos], hello[pos + 1]]);
+            let len = u16::from_be_bytes([hello[pos + 2], hello[pos + 3]]) as usize;
+            if ext_type == 0x0033 {
+                let share = &hello[pos + 4..pos + 4 + len];
+                return (random, share[6..38].try_into().unwrap());
+            }
+            pos += 4 + len;
+        }
+        panic!("ClientHello 缺少 key_share");
+    }
+
+    /// REALITY 服务端替身：认证通过时下发 HMAC 签名的临时证书，否则下发普通证书，之后回显数据
+    async fn spawn_server(private_key: [u8; 32], short_id: [u8; 8]) -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (mut tcp, _) = listener.accept().await.unwrap();
+            let mut header = [0u8; 5];
+            tcp.read_exact(&mut header).await.unwrap();
+            let mut hello = vec![0u8; u16::from_be_bytes([header[3], header[4]]) as usize];
+            tcp.read_exact(&mut hello).await.unwrap();
+
+            let (random, client_share) = parse_hello(&hello);
+            let shared =
+                StaticSecret::from(private_key).diffie_hellman(&PublicKey::from(client_share));
+            let mut auth_key = [0u8; 32];
+            Hkdf::<Sha256>::new(Some(&random[..20]), shared.as_bytes())
+                .expand(b"REALITY", &mut auth_key)
+                .unwrap();
+            let mut aad = hello.clone();
+            aad[39..71].fill(0);
+            let authenticated = AeadCipher::new(AeadKind::Aes256Gcm, &auth_key)
+                .decrypt_aad(&random[20..], &hello[39..71], &aad)
+                .map(|plain| plain[..3] == CLIENT_VERSION && plain[8..] == short_id)
+                .unwrap_or(false);
+
+            let key_pair = rcgen::KeyPair::generate_for(&rcgen::PKCS_ED25519).unwrap();
+            let cert = rcgen::CertificateParams::new(vec!["reality.test".to_string()])
+                .unwrap()
+                .self_signed(&key_pair)
+                .unwrap();
+            let mut der = cert.der().to_vec();
+            if authenticated {
+                let mut mac = Hmac::<Sha512>::new_from_slice(&auth_key).unwrap();
+                mac.update(key_pair.public_key_raw());
+                let len = der.len();
+                der[len - SIGNATURE_LEN..].copy_from_slice(&mac.finalize().into_bytes());
+            }
+
+            let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(key_pair.serialize_der()));
+            let config = ServerConfig::builder_with_provider(crypto_provider())
+                .with_protocol_versions(&[&rustls::version::TLS13])
+                .unwrap()
+                .with_no_client_auth()
+                .with_single_cert(vec![der.into()], key)
+                .unwrap();
+            let mut prefix = header.to_vec();
+            prefix.extend_from_slice(&hello);
+            let replay = Replay { prefix, inner: tcp };
+            let Ok(mut stream) = TlsAcceptor::from(Arc::new(config)).accept(replay).await else {
+                return;
+            };
+
+            let mut buf = [0u8; 1024];
+            loop {
+                let n = stream.read(&mut buf).await.unwrap_or(0);
+                if n == 0 {
+                    break;
+                }
+                stream.write_all(&buf[..n]).await.unwrap();
+            }
+        });
+        port
+    }
+
+    fn public_key_of(private_key: [u8; 32]) -> String {
+        URL_SAFE_NO_PAD.encode(PublicKey::from(&StaticSecret::from(private_key)).as_bytes())
+    }
+
+    #[tokio::test]
+    async fn test_reality_handshake() {
+        let private_key = [0x11u8; 32];
+        let options = RealityOptions::new(&public_key_of(private_key), "0123abcd").unwrap();
+        let port = spawn_server(private_key, options.short_id).await;
+
+        let tcp = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+        let auth = ServerAuth::Reality(options);
+        let mut stream = tls13::connect(tcp, "reality.test", &[], &auth)
+            .await
+            .unwrap();
+        stream.write_all(b"hello reality").await.unwrap();
+        let mut buf = [0u8; 13];
+        stream.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"hello reality");
+    }
+
+    #[tokio::test]
+    async fn test_reality_rejects_wrong_public_key() {
+        let options = RealityOptions::new(&public_key_of([0x22u8; 32]), "").unwrap();
+        let port = spawn_server([0x11u8; 32], options.short_id).await;
+
+        let tcp = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+        let auth = ServerAuth::Reality(options);
+        assert!(
+            tls13::connect(tcp, "reality.test", &[], &auth)
+                .await
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_options_parsing() {
+        let key = public_key_of([0x11u8; 32]);
+        let options = RealityOptions::new(&key, "0123abcd").unwrap();
+        assert_eq!(options.short_id, [0x01, 0x23, 0xab, 0xcd, 0, 0, 0, 0]);
+        assert_eq!(RealityOptions::new(&key, "").unwrap().short_id, [0u8; 8]);
+        assert!(RealityOptions::new(&key, "abc").is_err());
+        assert!(RealityOptions::new("c2hvcnQ", "").is_err());
+
+        let mut node = ProxyNode::new(
+            "r".to_string(),
+            "1.2.3.4".to_string(),
+            443,
+            Protocol::Direct,
+        );
+        assert!(RealityOptions::from_node(&node).unwrap().is_none());
+        node.tls.reality_opts = Some(RealityOpts {
+            public_key: key,
+            short_id: Some("ff".to_string()),
+        });
+        let parsed = RealityOptions::from_node(&node).unwrap().unwrap();
+        assert_eq!(parsed.short_id[0], 0xff);
+    }
+}
@@ -0,0 +1,615 @@
+//! Shadowsocks 出站
+//! 实现 AEAD（SIP004）与 Shadowsocks 2022（SIP022）加密方式的 TCP 客户端，
+//! 节点配置了 simple-obfs / v2ray-plugin 插件时在插件连接之上加密
+
+use super::crypto::{AeadCipher, AeadKind, TAG_LEN, evp_bytes_to_key, increment_nonce};
+use super::plugin::Plugin;
+use super::util::{FramedWrite, copy_plain, invalid_data, poll_drain, poll_fill};
+use super::{Address, BoxedStream};
+use crate::proxy::ProxyNode;
+use crate::proxy::node::ShadowsocksParams;
+use aes::cipher::{BlockEncrypt, KeyInit, generic_array::GenericArray};
+use anyhow::{Result, anyhow};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use hkdf::Hkdf;
+use rand::{Rng, RngCore};
+use sha1::Sha1;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll, ready};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt, ReadBuf};
+
+/// 经典 AEAD 单个数据块的最大负载
+const AEAD_MAX_PAYLOAD: usize = 0x3FFF;
+/// 2022 单个数据块的最大负载
+const AEAD_2022_MAX_PAYLOAD: usize = 0xFFFF;
+/// 2022 时间戳允许的最大偏差（秒）
+const MAX_TIME_DIFF: u64 = 30;
+/// 2022 请求头随机填充的最大长度
+const MAX_PADDING_LEN: usize = 900;
+
+/// Shadowsocks 加密方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Method {
+    Aes128Gcm,
+    Aes256Gcm,
+    Chacha20IetfPoly1305,
+    Blake3Aes128Gcm,
+    Blake3Aes256Gcm,
+    Blake3Chacha20Poly1305,
+}
+
+impl Method {
+    /// 根据 Clash 配置中的 `cipher` 名称解析加密方式
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "aes-128-gcm" => Ok(Method::Aes128Gcm),
+            "aes-256-gcm" => Ok(Method::Aes256Gcm),
+            "chacha20-ietf-poly1305" | "chacha20-poly1305" => Ok(Method::Chacha20IetfPoly1305),
+            "2022-blake3-aes-128-gcm" => Ok(Method::Blake3Aes128Gcm),
+            "2022-blake3-aes-256-gcm" => Ok(Method::Blake3Aes256Gcm),
+            "2022-blake3-chacha20-poly1305" => Ok(Method::Blake3Chacha20Poly1305),
+            other => Err(anyhow!("不支持的 Shadowsocks 加密方式: {}", other)),
+        }
+    }
+
+    fn aead_kind(self) -> AeadKind {
+        match self {
+            Method::Aes128Gcm | Method::Blake3Aes128Gcm => AeadKind::Aes128Gcm,
+            Method::Aes256Gcm | Method::Blake3Aes256Gcm => AeadKind::Aes256Gcm,
+            Method::Chacha20IetfPoly1305 | Method::Blake3Chacha20Poly1305 => {
+                AeadKind::ChaCha20Poly1305
+            }
+        }
+    }
+
+    /// 密钥长度，同时也是 salt 长度
+    fn key_len(self) -> usize {
+        self.aead_kind().key_len()
+    }
+
+    /// 是否为 Shadowsocks 2022 加密方式
+    pub fn is_2022(self) -> bool {
+        matches!(
+            self,
+            Method::Blake3Aes128Gcm | Method::Blake3Aes256Gcm | Method::Blake3Chacha20Poly1305
+        )
+    }
+
+    fn max_payload(self) -> usize {
+        if self.is_2022() {
+            AEAD_2022_MAX_PAYLOAD
+        } else {
+            AEAD_MAX_PAYLOAD
+        }
+    }
+}
+
+/// 由密码派生出的 Shadowsocks 密钥
+#[derive(Clone)]
+pub struct Keys {
+    method: Method,
+    /// 用户密钥（2022 中为最后一个 PSK）
+    key: Vec<u8>,
+    /// 2022 多用户模式下位于用户密钥之前的身份密钥（iPSK）
+    identity_keys: Vec<Vec<u8>>,
+}
+
+impl Keys {
+    /// 从 `cipher` 与 `password` 派生密钥
+    ///
+    /// 2022 加密方式的密码是 base64 编码的 PSK，多个 PSK 用 `:` 分隔
+    pub fn new(method: Method, password: &str) -> Result<Self> {
+        if !method.is_2022() {
+            return Ok(Self {
+                method,
+                key: evp_bytes_to_key(password.as_bytes(), method.key_len()),
+                identity_keys: Vec::new(),
+            });
+        }
+
+        let mut psks = Vec::new();
+        for part in password.split(':') {
+            let psk = STANDARD
+                .decode(part.trim())
+                .map_err(|e| anyhow!("Shadowsocks 2022 密钥不是有效的 base64: {}", e))?;
+            if psk.len() != method.key_len() {
+                return Err(anyhow!(
+                    "Shadowsocks 2022 密钥长度应为 {} 字节，实际为 {} 字节",
+                    method.key_len(),
+                    psk.len()
+                ));
+            }
+            psks.push(psk);
+        }
+
+        let key = psks
+            .pop()
+            .ok_or_else(|| anyhow!("Shadowsocks 2022 密钥为空"))?;
+        if !psks.is_empty() && method.aead_kind() == AeadKind::ChaCha20Poly1305 {
+            return Err(anyhow!(
+                "2022-blake3-chacha20-poly1305 不支持多用户身份密钥"
+            ));
+        }
+
+        Ok(Self {
+            method,
+            key,
+            identity_keys: psks,
+        })
+    }
+
+    /// 派生会话子密钥
+    fn session_subkey(&self, salt: &[u8]) -> Vec<u8> {
+        let key_len = self.method.key_len();
+        if self.method.is_2022() {
+            let mut material = self.key.clone();
+            material.extend_from_slice(salt);
+            blake3::derive_key("shadowsocks 2022 session subkey", &material)[..key_len].to_vec()
+        } else {
+            let hkdf = Hkdf::<Sha1>::new(Some(salt), &self.key);
+            let mut subkey = vec![0u8; key_len];
+            hkdf.expand(b"ss-subkey", &mut subkey)
+                .expect("HKDF 输出长度错误");
+            subkey
+        }
+    }
+
+    /// 生成 2022 多用户模式的身份头（EIH）
+    fn identity_headers(&self, salt: &[u8]) -> Vec<u8> {
+        let mut headers = Vec::with_capacity(self.identity_keys.len() * 16);
+        for (i, ipsk) in self.identity_keys.iter().enumerate() {
+            let next = self.identity_keys.get(i + 1).unwrap_or(&self.key);
+            let mut material = ipsk.clone();
+            material.extend_from_slice(salt);
+            let subkey = blake3::derive_key("shadowsocks 2022 identity subkey", &material);
+            let mut block = GenericArray::clone_from_slice(&blake3::hash(next).as_bytes()[..16]);
+            match self.method.key_len() {
+                16 => aes::Aes128::new(GenericArray::from_slice(&subkey[..16]))
+                    .encrypt_block(&mut block),
+                _ => aes::Aes256::new(GenericArray::from_slice(&subkey)).encrypt_block(&mut block),
+            }
+            headers.extend_from_slice(&block);
+        }
+        headers
+    }
+}
+
+/// 带递增 nonce 的 AEAD 状态
+struct CipherState {
+    cipher: AeadCipher,
+    nonce: [u8; 12],
+}
+
+impl CipherState {
+    fn new(kind: AeadKind, subkey: &[u8]) -> Self {
+        Self {
+            cipher: AeadCipher::new(kind, subkey),
+            nonce: [0u8; 12],
+        }
+    }
+
+    fn seal(&mut self, plaintext: &[u8]) -> Vec<u8> {
+        let sealed = self.cipher.encrypt(&self.nonce, plaintext);
+        increment_nonce(&mut self.nonce);
+        sealed
+    }
+
+    fn open(&mut self, ciphertext: &[u8]) -> io::Result<Vec<u8>> {
+        let opened = self.cipher.decrypt(&self.nonce, ciphertext)?;
+        increment_nonce(&mut self.nonce);
+        Ok(opened)
+    }
+}
+
+fn unix_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// 响应读取状态
+enum ReadState {
+    /// 等待服务端 salt
+    Salt,
+    /// 等待 2022 响应固定头
+    FixedHeader,
+    /// 等待长度块
+    Length,
+    /// 等待指定长度的负载块
+    Payload(usize),
+}
+
+/// Shadowsocks 加密连接
+pub struct ShadowsocksStream<S> {
+    inner: S,
+    keys: Keys,
+    request_salt: Vec<u8>,
+    enc: CipherState,
+    dec: Option<CipherState>,
+    read_state: ReadState,
+    rbuf: Vec<u8>,
+    plain: Vec<u8>,
+    plain_pos: usize,
+    wbuf: Vec<u8>,
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> ShadowsocksStream<S> {
+    /// 在已建立的底层连接上包装 Shadowsocks 加密，请求头在第一次写入或 flush 时发出
+    pub fn new(inner: S, keys: &Keys, target: &Address) -> Self {
+        let method = keys.method;
+        let mut salt = vec![0u8; method.key_len()];
+        rand::thread_rng().fill_bytes(&mut salt);
+
+        let mut enc = CipherState::new(method.aead_kind(), &keys.session_subkey(&salt));
+        let mut wbuf = salt.clone();
+
+        let mut address = Vec::new();
+        target.write_socks(&mut address);
+
+        if method.is_2022() {
+            wbuf.extend_from_slice(&keys.identity_headers(&salt));
+
+            // 变长头：目标地址 + 填充长度 + 填充（无初始负载时填充必须非空）
+            let mut rng = rand::thread_rng();
+            let padding_len = rng.gen_range(1..=MAX_PADDING_LEN);
+            let mut variable = address;
+            variable.extend_from_slice(&(padding_len as u16).to_be_bytes());
+            let mut padding = vec![0u8; padding_len];
+            rng.fill_bytes(&mut padding);
+            variable.extend_from_slice(&padding);
+
+            // 固定头：类型(0=请求) + 时间戳 + 变长头长度
+            let mut fixed = Vec::with_capacity(11);
+            fixed.push(0u8);
+            fixed.extend_from_slice(&unix_timestamp().to_be_bytes());
+            fixed.extend_from_slice(&(variable.len() as u16).to_be_bytes());
+
+            wbuf.extend_from_slice(&enc.seal(&fixed));
+            wbuf.extend_from_slice(&enc.seal(&variable));
+        } else {
+            wbuf.extend_from_slice(&enc.seal(&(address.len() as u16).to_be_bytes()));
+            wbuf.extend_from_slice(&enc.seal(&address));
+        }
+
+        Self {
+            inner,
+            keys: keys.clone(),
+            request_salt: salt,
+            enc,
+            dec: None,
+            read_state: ReadState::Salt,
+            rbuf: Vec::new(),
+            plain: Vec::new(),
+            plain_pos: 0,
+            wbuf,
+        }
+    }
+
+    /// 当前状态下需要的密文字节数
+    fn frame_len(&self) -> usize {
+        let salt_len = self.keys.method.key_len();
+        match self.read_state {
+            ReadState::Salt => salt_len,
+            ReadState::FixedHeader => 1 + 8 + salt_len + 2 + TAG_LEN,
+            ReadState::Length => 2 + TAG_LEN,
+            ReadState::Payload(len) => len + TAG_LEN,
+        }
+    }
+
+    /// 处理一帧完整的密文
+    fn process_frame(&mut self, frame: Vec<u8>) -> io::Result<()> {
+        match self.read_state {
+            ReadState::Salt => {
+                let subkey = self.keys.session_subkey(&frame);
+                self.dec = Some(CipherState::new(self.keys.method.aead_kind(), &subkey));
+                self.read_state = if self.keys.method.is_2022() {
+                    ReadState::FixedHeader
+                } else {
+                    ReadState::Length
+                };
+            }
+            ReadState::FixedHeader => {
+                let header = self.decoder()?.open(&frame)?;
+                let salt_len = self.keys.method.key_len();
+                if header[0] != 1 {
+                    return Err(invalid_data("Shadowsocks 2022 响应类型错误"));
+                }
+                let timestamp = u64::from_be_bytes(header[1..9].try_into().unwrap());
+                if unix_timestamp().abs_diff(timestamp) > MAX_TIME_DIFF {
+                    return Err(invalid_data("Shadowsocks 2022 响应时间戳超出允许范围"));
+                }
+                if header[9..9 + salt_len] != self.request_salt[..] {
+                    return Err(invalid_data("Shadowsocks 2022 响应中的请求 salt 不匹配"));
+                }
+                let len = u16::from_be_bytes([header[9 + salt_len], header[10 + salt_len]]);
+                self.read_state = ReadState::Payload(len as usize);
+            }
+            ReadState::Length => {
+                let len_bytes = self.decoder()?.open(&frame)?;
+                let mut len = u16::from_be_bytes([len_bytes[0], len_bytes[1]]) as usize;
+                if !self.keys.method.is_2022() {
+                    len &= AEAD_MAX_PAYLOAD;
+                }
+                self.read_state = ReadState::Payload(len);
+            }
+            ReadState::Payload(_) => {
+                self.plain = self.decoder()?.open(&frame)?;
+                self.plain_pos = 0;
+                self.read_state = ReadState::Length;
+            }
+        }
+        Ok(())
+    }
+
+    fn decoder(&mut self) -> io::Result<&mut CipherState> {
+        self.dec
+            .as_mut()
+            .ok_or_else(|| invalid_data("Shadowsocks 解密状态未初始化"))
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> AsyncRead for ShadowsocksStream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        loop {
+            if copy_plain(&mut this.plain, &mut this.plain_pos, buf) {
+                return Poll::Ready(Ok(()));
+            }
+
+            let need = this.frame_len();
+            if !ready!(poll_fill(&mut this.inner, cx, &mut this.rbuf, need))? {
+                let at_boundary = matches!(this.read_state, ReadState::Salt | ReadState::Length);
+                if this.rbuf.is_empty() && at_boundary {
+                    return Poll::Ready(Ok(()));
+                }
+                return Poll::Ready(Err(io::ErrorKind::UnexpectedEof.into()));
+            }
+
+            let frame: Vec<u8> = this.rbuf.drain(..need).collect();
+            this.process_frame(frame)?;
+        }
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> FramedWrite for ShadowsocksStream<S> {
+    type Inner = S;
+
+    fn write_parts(&mut self) -> (&mut S, &mut Vec<u8>) {
+        (&mut self.inner, &mut self.wbuf)
+    }
+
+    fn encode_frame(&mut self, data: &[u8]) -> usize {
+        let n = data.len().min(self.keys.method.max_payload());
+        let len_chunk = self.enc.seal(&(n as u16).to_be_bytes());
+        let payload_chunk = self.enc.seal(&data[..n]);
+        self.wbuf.extend_from_slice(&len_chunk);
+        self.wbuf.extend_from_slice(&payload_chunk);
+        n
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> AsyncWrite for ShadowsocksStream<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.get_mut().poll_write_framed(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(poll_drain(&mut this.inner, cx, &mut this.wbuf))?;
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(poll_drain(&mut this.inner, cx, &mut this.wbuf))?;
+        Pin::new(&mut this.inner).poll_shutdown(cx)
+    }
+}
+
+/// 通过 Shadowsocks 节点连接目标地址
+pub async fn connect(
+    proxy: &ProxyNode,
+    params: &ShadowsocksParams,
+    target: &Address,
+) -> Result<ShadowsocksStream<BoxedStream>> {
+    let keys = Keys::new(Method::from_name(&params.cipher)?, &params.password)?;
+    let plugin = Plugin::from_params(params)?;
+
+    let inner = plugin
+        .connect(proxy)
+        .await
+        .map_err(|e| anyhow!("连接 Shadowsocks 服务器失败: {}", e))?;
+
+    let mut stream = ShadowsocksStream::new(inner, &keys, target);
+    stream.flush().await?;
+    Ok(stream)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::outbound::dial;
+    use crate::proxy::Protocol;
+    use serde_json::{Value, json};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+
+    async fn read_chunk(sock: &mut TcpStream, dec: &mut CipherState) -> Vec<u8> {
+        let mut len = vec![0u8; 2 + TAG_LEN];
+        sock.read_exact(&mut len).await.unwrap();
+        let len = dec.open(&len).unwrap();
+        let len = u16::from_be_bytes([len[0], len[1]]) as usize;
+        let mut payload = vec![0u8; len + TAG_LEN];
+        sock.read_exact(&mut payload).await.unwrap();
+        dec.open(&payload).unwrap()
+    }
+
+    /// 本地 Shadowsocks 服务端替身：解析请求后把负载原样回写
+    async fn serve_echo_once(listener: TcpListener, keys: Keys) -> Address {
+        let (sock, _) = listener.accept().await.unwrap();
+        serve_echo(sock, keys).await
+    }
+
+    async fn serve_echo(mut sock: TcpStream, keys: Keys) -> Address {
+        let method = keys.method;
+        let salt_len = method.key_len();
+
+        let mut salt = vec![0u8; salt_len];
+        sock.read_exact(&mut salt).await.unwrap();
+        let mut dec = CipherState::new(method.aead_kind(), &keys.session_subkey(&salt));
+
+        let target = if method.is_2022() {
+            let mut fixed = vec![0u8; 11 + TAG_LEN];
+            sock.read_exact(&mut fixed).await.unwrap();
+            let fixed = dec.open(&fixed).unwrap();
+            assert_eq!(fixed[0], 0);
+            let var_len = u16::from_be_bytes([fixed[9], fixed[10]]) as usize;
+            let mut variable = vec![0u8; var_len + TAG_LEN];
+            sock.read_exact(&mut variable).await.unwrap();
+            let variable = dec.open(&variable).unwrap();
+            Address::read_socks(&mut variable.as_slice()).await.unwrap()
+        } else {
+            let header = read_chunk(&mut sock, &mut dec).await;
+            Address::read_socks(&mut header.as_slice()).await.unwrap()
+        };
+
+        let payload = read_chunk(&mut sock, &mut dec).await;
+
+        let mut resp_salt = vec![0u8; salt_len];
+        rand::thread_rng().fill_bytes(&mut resp_salt);
+        let mut enc = CipherState::new(method.aead_kind(), &keys.session_subkey(&resp_salt));
+        let mut out = resp_salt.clone();
+        if method.is_2022() {
+            let mut fixed = vec![1u8];
+            fixed.extend_from_slice(&unix_timestamp().to_be_bytes());
+            fixed.extend_from_slice(&salt);
+            fixed.extend_from_slice(&(payload.len() as u16).to_be_bytes());
+            out.extend_from_slice(&enc.seal(&fixed));
+        } else {
+            out.extend_from_slice(&enc.seal(&(payload.len() as u16).to_be_bytes()));
+        }
+        out.extend_from_slice(&enc.seal(&payload));
+        sock.write_all(&out).await.unwrap();
+        target
+    }
+
+    /// 本地 Shadowsocks 节点，带插件参数时使用 simple-obfs
+    fn node(port: u16, cipher: &str, password: &str, obfs: Option<Value>) -> ProxyNode {
+        ProxyNode::new(
+            "ss".to_string(),
+            "127.0.0.1".to_string(),
+            port,
+            Protocol::Shadowsocks(ShadowsocksParams {
+                cipher: cipher.to_string(),
+                password: password.to_string(),
+                plugin: obfs.is_some().then(|| "obfs".to_string()),
+                plugin_opts: obfs,
+                ..Default::default()
+            }),
+        )
+    }
+
+    async fn roundtrip(cipher: &str, password: &str) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let keys = Keys::new(Method::from_name(cipher).unwrap(), password).unwrap();
+        let server = tokio::spawn(serve_echo_once(listener, keys));
+
+        let node = node(port, cipher, password, None);
+        let target = Address::Domain("example.com".to_string(), 80);
+        let mut stream = dial(&node, &target).await.unwrap();
+        stream.write_all(b"hello shadowsocks").await.unwrap();
+        stream.flush().await.unwrap();
+
+        let mut reply = vec![0u8; b"hello shadowsocks".len()];
+        stream.read_exact(&mut reply).await.unwrap();
+        assert_eq!(reply, b"hello shadowsocks");
+        assert_eq!(server.await.unwrap(), target);
+    }
+
+    #[tokio::test]
+    async fn test_aead_roundtrip() {
+        roundtrip("aes-128-gcm", "test-password").await;
+        roundtrip("aes-256-gcm", "test-password").await;
+        roundtrip("chacha20-ietf-poly1305", "test-password").await;
+    }
+
+    #[tokio::test]
+    async fn test_simple_obfs_roundtrip() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let keys = Keys::new(Method::Aes128Gcm, "test-password").unwrap();
+        let server = tokio::spawn(async move {
+            // obfs-server 替身：校验伪装请求头后回应 101，之后即为原始数据
+            let (mut sock, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            while !request.ends_with(b"\r\n\r\n") {
+                request.push(sock.read_u8().await.unwrap());
+            }
+            let request = String::from_utf8(request).unwrap();
+            assert!(request.contains("Host: cdn.example.com:"));
+            sock.write_all(b"HTTP/1.1 101 Switching Protocols\r\n\r\n")
+                .await
+                .unwrap();
+            serve_echo(sock, keys).await
+        });
+
+        let node = node(
+            port,
+            "aes-128-gcm",
+            "test-password",
+            Some(json!({"mode": "http", "host": "cdn.example.com"})),
+        );
+        let target = Address::Domain("example.com".to_string(), 80);
+        let mut stream = dial(&node, &target).await.unwrap();
+        stream.write_all(b"hello obfs").await.unwrap();
+        stream.flush().await.unwrap();
+        let mut reply = [0u8; 10];
+        stream.read_exact(&mut reply).await.unwrap();
+        assert_eq!(&reply, b"hello obfs");
+        assert_eq!(server.await.unwrap(), target);
+    }
+
+    #[tokio::test]
+    async fn test_2022_roundtrip() {
+        roundtrip("2022-blake3-aes-128-gcm", &STANDARD.encode([7u8; 16])).await;
+        roundtrip("2022-blake3-aes-256-gcm", &STANDARD.encode([7u8; 32])).await;
+        roundtrip("2022-blake3-chacha20-poly1305", &STANDARD.encode([7u8; 32])).await;
+    }
+
+    #[test]
+    fn test_2022_key_validation() {
+        let method = Method::from_name("2022-blake3-aes-128-gcm").unwrap();
+        assert!(Keys::new(method, &STANDARD.encode([1u8; 32])).is_err());
+        assert!(Keys::new(method, "not base64!").is_err());
+
+        let keys = Keys::new(
+            method,
+            &format!(
+                "{}:{}",
+                STANDARD.encode([1u8; 16]),
+                STANDARD.encode([2u8; 16])
+            ),
+        )
+        .unwrap();
+        assert_eq!(keys.identity_keys.len(), 1);
+        assert_eq!(keys.identity_headers(&[0u8; 16]).len(), 16);
+    }
+
+    #[test]
+    fn test_unsupported_method() {
+        assert!(Method::from_name("rc4-md5").is_err());
+    }
+}
@@ -0,0 +1,370 @@
+//! SOCKS 代理出站
+//! 支持 SOCKS5（无认证 / 用户名密码认证）的 CONNECT 与 UDP ASSOCIATE，以及 SOCKS4a 的 CONNECT
+
+use super::dialer::{bind_udp, resolve};
+use super::{Address, BoxedDatagram, BoxedStream, Datagram, transport};
+use crate::proxy::ProxyNode;
+use crate::proxy::node::AuthParams;
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::UdpSocket;
+
+const VERSION: u8 = 0x05;
+const METHOD_NONE: u8 = 0x00;
+const METHOD_PASSWORD: u8 = 0x02;
+const CMD_CONNECT: u8 = 0x01;
+const CMD_UDP_ASSOCIATE: u8 = 0x03;
+
+/// 完成 SOCKS5 方法协商与认证
+async fn handshake<S>(stream: &mut S, auth: Option<(&str, &str)>) -> Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    match auth {
+        Some(_) => {
+            stream
+                .write_all(&[VERSION, 2, METHOD_NONE, METHOD_PASSWORD])
+                .await?
+        }
+        None => stream.write_all(&[VERSION, 1, METHOD_NONE]).await?,
+    }
+    let mut reply = [0u8; 2];
+    stream.read_exact(&mut reply).await?;
+    if reply[0] != VERSION {
+        return Err(anyhow!("不是 SOCKS5 服务器"));
+    }
+    match (reply[1], auth) {
+        (METHOD_NONE, _) => Ok(()),
+        (METHOD_PASSWORD, Some((username, password))) => {
+            let mut request = vec![0x01, username.len().min(255) as u8];
+            request.extend_from_slice(&username.as_bytes()[..request[1] as usize]);
+            request.push(password.len().min(255) as u8);
+            request.extend_from_slice(&password.as_bytes()[..password.len().min(255)]);
+            stream.write_all(&request).await?;
+            stream.read_exact(&mut reply).await?;
+            if reply[1] != 0x00 {
+                return Err(anyhow!("SOCKS5 认证失败"));
+            }
+            Ok(())
+        }
+        (method, _) => Err(anyhow!("SOCKS5 服务器要求不支持的认证方式: {}", method)),
+    }
+}
+
+/// 发送请求并读取应答中的绑定地址
+async fn request<S>(stream: &mut S, cmd: u8, target: &Address) -> Result<Address>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut request = vec![VERSION, cmd, 0x00];
+    target.write_socks(&mut request);
+    stream.write_all(&request).await?;
+    stream.flush().await?;
+
+    let mut reply = [0u8; 3];
+    stream.read_exact(&mut reply).await?;
+    if reply[1] != 0x00 {
+        return Err(anyhow!("SOCKS5 请求 {} 失败，错误码: {}", target, reply[1]));
+    }
+    Ok(Address::read_socks(stream).await?)
+}
+
+/// 建立到代理服务器的控制连接并完成认证
+async fn open(proxy: &ProxyNode, auth: &AuthParams) -> Result<BoxedStream> {
+    let mut stream = transport::connect(proxy, proxy.tls.tls.unwrap_or(false))
+        .await
+        .map_err(|e| anyhow!("SOCKS5 {}", e))?;
+    let credentials = auth
+        .username
+        .as_deref()
+        .map(|username| (username, auth.password.as_deref().unwrap_or("")));
+    handshake(&mut stream, credentials).await?;
+    Ok(stream)
+}
+
+/// 通过 SOCKS5 节点连接目标地址
+pub async fn connect(
+    proxy: &ProxyNode,
+    auth: &AuthParams,
+    target: &Address,
+) -> Result<BoxedStream> {
+    let mut stream = open(proxy, auth).await?;
+    request(&mut stream, CMD_CONNECT, target).await?;
+    Ok(stream)
+}
+
+/// 通过 SOCKS4a 节点连接目标地址
+pub async fn connect_v4(
+    proxy: &ProxyNode,
+    auth: &AuthParams,
+    target: &Address,
+) -> Result<BoxedStream> {
+    let mut stream = transport::connect(proxy, false)
+        .await
+        .map_err(|e| anyhow!("SOCKS4 {}", e))?;
+
+    let mut request = vec![0x04, CMD_CONNECT];
+    request.extend_from_slice(&target.port().to_be_bytes());
+    let domain = match target {
+        Address::Socket(SocketAddr::V4(addr)) => {
+            request.extend_from_slice(&addr.ip().octets());
+            None
+        }
+        Address::Domain(domain, _) => {
+            // 0.0.0.x 表示由服务器解析后附的域名
+            request.extend_from_slice(&[0, 0, 0, 1]);
+            Some(domain)
+        }
+        Address::Socket(SocketAddr::V6(_)) => {
+            return Err(anyhow!("SOCKS4 不支持 IPv6 目标: {}", target));
+        }
+    };
+    request.extend_from_slice(auth.username.as_deref().unwrap_or("").as_bytes());
+    request.push(0x00);
+    if let Some(domain) = domain {
+        request.extend_from_slice(domain.as_bytes());
+        request.push(0x00);
+    }
+    stream.write_all(&request).await?;
+    stream.flush().await?;
+
+    let mut reply = [0u8; 8];
+    stream.read_exact(&mut reply).await?;
+    if reply[1] != 0x5A {
+        return Err(anyhow!("SOCKS4 请求 {} 失败，错误码: {}", target, reply[1]));
+    }
+    Ok(stream)
+}
+
+/// 通过 SOCKS5 节点建立到目标的 UDP 关联
+pub async fn associate(
+    proxy: &ProxyNode,
+    auth: &AuthParams,
+    target: &Address,
+) -> Result<BoxedDatagram> {
+    let mut control = open(proxy, auth).await?;
+    let unspecified = Address::Socket(SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0));
+    let bound = request(&mut control, CMD_UDP_ASSOCIATE, &unspecified).await?;
+
+    // 服务器返回未指定地址时，中继就在控制连接所连的主机上
+    let mut relay = resolve(&bound).await?;
+    if relay.ip().is_unspecified() {
+        let server = resolve(&Address::from_host(&proxy.server, proxy.port)).await?;
+        relay.set_ip(server.ip());
+    }
+    let socket = bind_udp(relay).await?;
+    socket.connect(relay).await?;
+
+    Ok(Box::new(SocksDatagram {
+        _control: control,
+        socket,
+        target: target.clone(),
+    }))
+}
+
+/// SOCKS5 UDP 关联，控制连接断开时服务器会释放中继
+struct SocksDatagram {
+    _control: BoxedStream,
+    socket: UdpSocket,
+    target: Address,
+}
+
+#[async_trait]
+impl Datagram for SocksDatagram {
+    async fn send(&mut self, data: &[u8]) -> io::Result<()> {
+        // RSV(2) + FRAG(1) + 目标地址 + 数据
+        let mut packet = vec![0x00, 0x00, 0x00];
+        self.target.write_socks(&mut packet);
+        packet.extend_from_slice(data);
+        self.socket.send(&packet).await.map(|_| ())
+    }
+
+    async fn recv(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut packet = vec![0u8; 65536];
+        loop {
+            let n = self.socket.recv(&mut packet).await?;
+            // 丢弃分片包与头部不完整的包
+            if n < 3 || packet[2] != 0x00 {
+                continue;
+            }
+            let mut reader = &packet[3..n];
+            if Address::read_socks(&mut reader).await.is_err() {
+                continue;
+            }
+            let len = reader.len().min(buf.len());
+            buf[..len].copy_from_slice(&reader[..len]);
+            return Ok(len);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::Protocol;
+    use tokio::net::{TcpListener, TcpStream};
+
+    /// 服务端完成方法协商与可选的用户名密码认证
+    async fn accept_handshake(socket: &mut TcpStream, password: Option<&str>) -> bool {
+        assert_eq!(socket.read_u8().await.unwrap(), VERSION);
+        let count = socket.read_u8().await.unwrap() as usize;
+        let mut methods = vec![0u8; count];
+        socket.read_exact(&mut methods).await.unwrap();
+        let Some(expected) = password else {
+            socket.write_all(&[VERSION, METHOD_NONE]).await.unwrap();
+            return true;
+        };
+        assert!(methods.contains(&METHOD_PASSWORD));
+        socket.write_all(&[VERSION, METHOD_PASSWORD]).await.unwrap();
+        assert_eq!(socket.read_u8().await.unwrap(), 0x01);
+        let mut username = vec![0u8; socket.read_u8().await.unwrap() as usize];
+        socket.read_exact(&mut username).await.unwrap();
+        let mut password = vec![0u8; socket.read_u8().await.unwrap() as usize];
+        socket.read_exact(&mut password).await.unwrap();
+        let ok = password == expected.as_bytes();
+        socket
+            .write_all(&[0x01, if ok { 0x00 } else { 0x01 }])
+            .await
+            .unwrap();
+        ok
+    }
+
+    /// 启动本地 SOCKS5 服务端替身：CONNECT 回显数据，UDP ASSOCIATE 回显数据报
+    async fn spawn_server(password: Option<&'static str>) -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            if !accept_handshake(&mut socket, password).await {
+                return;
+            }
+            let mut header = [0u8; 3];
+            socket.read_exact(&mut header).await.unwrap();
+            let target = Address::read_socks(&mut socket).await.unwrap();
+            match header[1] {
+                CMD_CONNECT => {
+                    assert_eq!(target, Address::Domain("example.com".to_string(), 80));
+                    socket
+                        .write_all(&[VERSION, 0x00, 0x00, 0x01, 0, 0, 0, 0, 0, 0])
+                        .await
+                        .unwrap();
+                    let mut buf = [0u8; 4];
+                    socket.read_exact(&mut buf).await.unwrap();
+                    socket.write_all(&buf).await.unwrap();
+                }
+                CMD_UDP_ASSOCIATE => {
+                    let relay = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+                    let mut reply = vec![VERSION, 0x00, 0x00];
+                    // 返回未指定地址，客户端应改用服务器地址
+                    let mut bound = relay.local_addr().unwrap();
+                    bound.set_ip(IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+                    Address::Socket(bound).write_socks(&mut reply);
+                    socket.write_all(&reply).await.unwrap();
+
+                    let mut buf = [0u8; 1024];
+                    let (n, peer) = relay.recv_from(&mut buf).await.unwrap();
+                    let mut reader = &buf[3..n];
+                    let target = Address::read_socks(&mut reader).await.unwrap();
+                    assert_eq!(target, Address::Socket("8.8.8.8:53".parse().unwrap()));
+                    relay.send_to(&buf[..n], peer).await.unwrap();
+                    // 保持控制连接直到客户端断开
+                    let _ = socket.read_u8().await;
+                }
+                _ => unreachable!(),
+            }
+        });
+        port
+    }
+
+    fn node(port: u16) -> ProxyNode {
+        ProxyNode::new(
+            "socks".to_string(),
+            "127.0.0.1".to_string(),
+            port,
+            Protocol::Socks5(AuthParams::default()),
+        )
+    }
+
+    fn auth(username: &str, password: &str) -> AuthParams {
+        AuthParams {
+            username: Some(username.to_string()),
+            password: Some(password.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_connect() {
+        let port = spawn_server(None).await;
+        let target = Address::Domain("example.com".to_string(), 80);
+        let mut stream = connect(&node(port), &AuthParams::default(), &target)
+            .await
+            .unwrap();
+        stream.write_all(b"ping").await.unwrap();
+        let mut buf = [0u8; 4];
+        stream.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"ping");
+    }
+
+    #[tokio::test]
+    async fn test_connect_with_password() {
+        let port = spawn_server(Some("pass")).await;
+        let target = Address::Domain("example.com".to_string(), 80);
+        assert!(
+            connect(&node(port), &auth("user", "pass"), &target)
+                .await
+                .is_ok()
+        );
+
+        let port = spawn_server(Some("pass")).await;
+        assert!(
+            connect(&node(port), &auth("user", "wrong"), &target)
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_udp_associate() {
+        let port = spawn_server(None).await;
+        let target = Address::Socket("8.8.8.8:53".parse().unwrap());
+        let mut datagram = associate(&node(port), &AuthParams::default(), &target)
+            .await
+            .unwrap();
+        datagram.send(b"query").await.unwrap();
+        let mut buf = [0u8; 64];
+        let n = datagram.recv(&mut buf).await.unwrap();
+        assert_eq!(&buf[..n], b"query");
+    }
+
+    #[tokio::test]
+    async fn test_connect_v4() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut header = [0u8; 8];
+            socket.read_exact(&mut header).await.unwrap();
+            assert_eq!(&header, &[0x04, CMD_CONNECT, 0, 80, 0, 0, 0, 1]);
+            let mut rest = Vec::new();
+            while rest.iter().filter(|&&b| b == 0).count() < 2 {
+                rest.push(socket.read_u8().await.unwrap());
+            }
+            assert_eq!(rest, b"\0example.com\0");
+            socket
+                .write_all(&[0x00, 0x5A, 0, 0, 0, 0, 0, 0])
+                .await
+                .unwrap();
+        });
+
+        let target = Address::Domain("example.com".to_string(), 80);
+        assert!(
+            connect_v4(&node(port), &AuthParams::default(), &target)
+                .await
+                .is_ok()
+        );
+    }
+}
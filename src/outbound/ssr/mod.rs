@@ -0,0 +1,407 @@
+//! ShadowsocksR 出站
+//! 写入方向依次经过协议插件、流加密与混淆插件，读取方向按相反顺序处理
+//!
+//! 支持的协议：origin、auth_aes128_md5、auth_aes128_sha1、auth_chain_a；
+//! 支持的混淆：plain、http_simple、tls1.2_ticket_auth
+
+mod obfs;
+mod protocol;
+
+use self::obfs::Obfs;
+use self::protocol::Protocol;
+use super::crypto::evp_bytes_to_key;
+use super::stream_cipher::{StreamCipher, StreamMethod};
+use super::util::{FramedWrite, copy_plain, poll_drain};
+use super::{Address, connect_tcp};
+use crate::proxy::ProxyNode;
+use crate::proxy::node::SsrParams;
+use anyhow::{Result, anyhow};
+use hmac::{Hmac, Mac};
+use md5::Md5;
+use rand::RngCore;
+use sha1::{Digest, Sha1};
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll, ready};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::TcpStream;
+
+/// 单次写入封装的最大明文长度
+const MAX_WRITE_LEN: usize = 16 * 1024;
+
+/// 插件使用的摘要算法
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashKind {
+    Md5,
+    Sha1,
+}
+
+impl HashKind {
+    pub fn digest(self, data: &[u8]) -> Vec<u8> {
+        match self {
+            HashKind::Md5 => Md5::digest(data).to_vec(),
+            HashKind::Sha1 => Sha1::digest(data).to_vec(),
+        }
+    }
+}
+
+/// HMAC-MD5 / HMAC-SHA1
+pub fn hmac(kind: HashKind, key: &[u8], data: &[u8]) -> Vec<u8> {
+    match kind {
+        HashKind::Md5 => {
+            let mut mac = Hmac::<Md5>::new_from_slice(key).expect("HMAC 密钥长度错误");
+            mac.update(data);
+            mac.finalize().into_bytes().to_vec()
+        }
+        HashKind::Sha1 => {
+            let mut mac = Hmac::<Sha1>::new_from_slice(key).expect("HMAC 密钥长度错误");
+            mac.update(data);
+            mac.finalize().into_bytes().to_vec()
+        }
+    }
+}
+
+/// 插件共用的连接参数
+#[derive(Debug, Clone)]
+pub struct ServerInfo {
+    pub host: String,
+    pub port: u16,
+    /// 由密码派生的主密钥
+    pub key: Vec<u8>,
+    /// 客户端 IV
+    pub iv: Vec<u8>,
+    /// 请求头（目标地址）长度
+    pub head_len: usize,
+    pub protocol_param: String,
+    pub obfs_param: String,
+    /// 混淆插件给每个数据包增加的长度
+    pub obfs_overhead: usize,
+}
+
+/// ShadowsocksR 连接
+pub struct SsrStream<S> {
+    inner: S,
+    protocol: Protocol,
+    obfs: Obfs,
+    method: StreamMethod,
+    key: Vec<u8>,
+    enc: StreamCipher,
+    /// 尚未发出的客户端 IV，随第一段密文发送
+    send_iv: Option<Vec<u8>>,
+    /// 尚未发出的请求头，与第一次写入的数据合并
+    header: Option<Vec<u8>>,
+    dec: Option<StreamCipher>,
+    recv_iv: Vec<u8>,
+    plain: Vec<u8>,
+    plain_pos: usize,
+    wbuf: Vec<u8>,
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> SsrStream<S> {
+    /// 在已建立的底层连接上包装 ShadowsocksR，请求头在第一次写入或 flush 时发出
+    pub fn new(
+        inner: S,
+        proxy: &ProxyNode,
+        params: &SsrParams,
+        method: StreamMethod,
+        target: &Address,
+    ) -> Result<Self> {
+        let key = evp_bytes_to_key(params.password.as_bytes(), method.key_len());
+        let mut iv = vec![0u8; method.iv_len()];
+        rand::thread_rng().fill_bytes(&mut iv);
+        let mut header = Vec::new();
+        target.write_socks(&mut header);
+
+        let mut info = ServerInfo {
+            host: proxy.server.clone(),
+            port: proxy.port,
+            key: key.clone(),
+            iv: iv.clone(),
+            head_len: header.len(),
+            protocol_param: params.protocol_param.clone().unwrap_or_default(),
+            obfs_param: params.obfs_param.clone().unwrap_or_default(),
+            obfs_overhead: 0,
+        };
+        let obfs = Obfs::new(&params.obfs, &info)?;
+        info.obfs_overhead = obfs.overhead();
+        let protocol = Protocol::new(&params.protocol, &info)?;
+
+        Ok(Self {
+            inner,
+            protocol,
+            obfs,
+            method,
+            enc: StreamCipher::encryptor(method, &key, &iv),
+            key,
+            send_iv: Some(iv),
+            header: Some(header),
+            dec: None,
+            recv_iv: Vec::new(),
+            plain: Vec::new(),
+            plain_pos: 0,
+            wbuf: Vec::new(),
+        })
+    }
+
+    /// 把明文依次交给协议插件、流加密和混淆插件，结果放入发送缓冲区
+    fn encode(&mut self, data: &[u8]) {
+        let mut payload = self.header.take().unwrap_or_default();
+        payload.extend_from_slice(data);
+        let mut encrypted = self.protocol.encode(&payload);
+        self.enc.apply(&mut encrypted);
+        if let Some(mut iv) = self.send_iv.take() {
+            iv.extend_from_slice(&encrypted);
+            encrypted = iv;
+        }
+        let encoded = self.obfs.encode(&encrypted);
+        self.wbuf.extend_from_slice(&encoded);
+    }
+
+    /// 处理从底层读到的数据，返回需要回发给服务端的混淆数据
+    fn decode(&mut self, data: &[u8]) -> io::Result<Vec<u8>> {
+        let (mut payload, reply) = self.obfs.decode(data)?;
+        if self.dec.is_none() {
+            self.recv_iv.extend_from_slice(&payload);
+            let iv_len = self.method.iv_len();
+            if self.recv_iv.len() < iv_len {
+                return Ok(reply);
+            }
+            payload = self.recv_iv.split_off(iv_len);
+            self.dec = Some(StreamCipher::decryptor(
+                self.method,
+                &self.key,
+                &self.recv_iv,
+            ));
+        }
+        if let Some(dec) = self.dec.as_mut() {
+            dec.apply(&mut payload);
+        }
+        let plain = self.protocol.decode(&payload)?;
+        if !plain.is_empty() {
+            self.plain.extend_from_slice(&plain);
+        }
+        Ok(reply)
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> AsyncRead for SsrStream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        loop {
+            // 混淆握手的回应必须先发出，服务端才会继续发送数据
+            ready!(poll_drain(&mut this.inner, cx, &mut this.wbuf))?;
+            if copy_plain(&mut this.plain, &mut this.plain_pos, buf) {
+                return Poll::Ready(Ok(()));
+            }
+
+            let mut tmp = [0u8; 8192];
+            let mut read_buf = ReadBuf::new(&mut tmp);
+            ready!(Pin::new(&mut this.inner).poll_read(cx, &mut read_buf))?;
+            if read_buf.filled().is_empty() {
+                return Poll::Ready(Ok(()));
+            }
+            let reply = this.decode(read_buf.filled())?;
+            this.wbuf.extend_from_slice(&reply);
+        }
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> FramedWrite for SsrStream<S> {
+    type Inner = S;
+
+    fn write_parts(&mut self) -> (&mut S, &mut Vec<u8>) {
+        (&mut self.inner, &mut self.wbuf)
+    }
+
+    fn encode_frame(&mut self, data: &[u8]) -> usize {
+        let n = data.len().min(MAX_WRITE_LEN);
+        self.encode(&data[..n]);
+        n
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> AsyncWrite for SsrStream<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.get_mut().poll_write_framed(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if this.header.is_some() {
+            this.encode(&[]);
+        }
+        ready!(poll_drain(&mut this.inner, cx, &mut this.wbuf))?;
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(poll_drain(&mut this.inner, cx, &mut this.wbuf))?;
+        Pin::new(&mut this.inner).poll_shutdown(cx)
+    }
+}
+
+/// 通过 ShadowsocksR 节点连接目标地址
+///
+/// 协议插件会把请求头与首段数据合并成一个认证包，因此这里不主动 flush
+pub async fn connect(
+    proxy: &ProxyNode,
+    params: &SsrParams,
+    target: &Address,
+) -> Result<SsrStream<TcpStream>> {
+    let method = StreamMethod::from_name(&params.cipher)?;
+
+    // 先校验插件名称，避免为不支持的节点建立连接
+    let probe = ServerInfo {
+        host: proxy.server.clone(),
+        port: proxy.port,
+        key: Vec::new(),
+        iv: Vec::new(),
+        head_len: 0,
+        protocol_param: String::new(),
+        obfs_param: String::new(),
+        obfs_overhead: 0,
+    };
+    Obfs::new(&params.obfs, &probe)?;
+    Protocol::new(&params.protocol, &probe)?;
+
+    let tcp = connect_tcp(&proxy.server, proxy.port)
+        .await
+        .map_err(|e| anyhow!("连接 ShadowsocksR 服务器失败: {}", e))?;
+    SsrStream::new(tcp, proxy, params, method, target)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::obfs::test_server::ServerObfs;
+    use super::protocol::test_server::ServerProtocol;
+    use super::*;
+    use crate::outbound::dial;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// 本地 ShadowsocksR 服务端替身：逐层解开请求，校验目标地址后回显负载
+    async fn serve_echo_once(listener: TcpListener, params: SsrParams) {
+        let (mut sock, _) = listener.accept().await.unwrap();
+        let method = StreamMethod::from_name(&params.cipher).unwrap();
+        let key = evp_bytes_to_key(params.password.as_bytes(), method.key_len());
+        let mut obfs = ServerObfs::new(&params.obfs, &key);
+        let mut protocol = None;
+        let mut dec = None;
+        let mut ciphertext = Vec::new();
+        let mut request = Vec::new();
+
+        let mut buf = vec![0u8; 4096];
+        // 目标地址 example.com:80 编码后为 15 字节
+        let expected = 15 + b"hello ssr".len();
+        while request.len() < expected {
+            let n = sock.read(&mut buf).await.unwrap();
+            assert!(n > 0, "客户端提前关闭连接");
+            let (payload, reply) = obfs.decode(&buf[..n]);
+            sock.write_all(&reply).await.unwrap();
+            ciphertext.extend_from_slice(&payload);
+            if dec.is_none() {
+                if ciphertext.len() < method.iv_len() {
+                    continue;
+                }
+                let rest = ciphertext.split_off(method.iv_len());
+                protocol = Some(ServerProtocol::new(&params.protocol, &key, &ciphertext));
+                dec = Some(StreamCipher::decryptor(method, &key, &ciphertext));
+                ciphertext = rest;
+            }
+            dec.as_mut().unwrap().apply(&mut ciphertext);
+            request.extend(protocol.as_mut().unwrap().decode(&ciphertext));
+            ciphertext.clear();
+        }
+
+        let target = Address::read_socks(&mut request.as_slice()).await.unwrap();
+        assert_eq!(target, Address::Domain("example.com".to_string(), 80));
+        assert_eq!(&request[15..], b"hello ssr");
+
+        let mut iv = vec![0u8; method.iv_len()];
+        rand::thread_rng().fill_bytes(&mut iv);
+        let mut enc = StreamCipher::encryptor(method, &key, &iv);
+        let mut response = protocol.as_mut().unwrap().encode(&request[15..]);
+        enc.apply(&mut response);
+        iv.extend_from_slice(&response);
+        sock.write_all(&obfs.encode(&iv)).await.unwrap();
+    }
+
+    fn params(cipher: &str, protocol: &str, obfs: &str) -> SsrParams {
+        SsrParams {
+            cipher: cipher.to_string(),
+            password: "password".to_string(),
+            obfs: obfs.to_string(),
+            protocol: protocol.to_string(),
+            obfs_param: None,
+            protocol_param: None,
+        }
+    }
+
+    fn node(port: u16, params: SsrParams) -> ProxyNode {
+        ProxyNode::new(
+            "ssr".to_string(),
+            "127.0.0.1".to_string(),
+            port,
+            crate::proxy::Protocol::Ssr(params),
+        )
+    }
+
+    async fn roundtrip(cipher: &str, protocol: &str, obfs: &str) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let mut params = params(cipher, protocol, obfs);
+        params.password = "ssr-password".to_string();
+        params.obfs_param = Some("cdn.example.com".to_string());
+        let node = node(port, params.clone());
+        let server = tokio::spawn(serve_echo_once(listener, params));
+
+        let target = Address::Domain("example.com".to_string(), 80);
+        let mut stream = dial(&node, &target).await.unwrap();
+        stream.write_all(b"hello ssr").await.unwrap();
+        stream.flush().await.unwrap();
+
+        let mut reply = [0u8; 9];
+        stream.read_exact(&mut reply).await.unwrap();
+        assert_eq!(&reply, b"hello ssr");
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_origin_plain_roundtrip() {
+        roundtrip("aes-256-cfb", "origin", "plain").await;
+        roundtrip("rc4-md5", "origin", "plain").await;
+    }
+
+    #[tokio::test]
+    async fn test_auth_aes128_http_simple_roundtrip() {
+        roundtrip("aes-128-ctr", "auth_aes128_md5", "http_simple").await;
+        roundtrip("chacha20-ietf", "auth_aes128_sha1", "http_simple").await;
+    }
+
+    #[tokio::test]
+    async fn test_auth_chain_a_tls_roundtrip() {
+        roundtrip("none", "auth_chain_a", "tls1.2_ticket_auth").await;
+        roundtrip("aes-256-cfb", "auth_chain_a", "tls1.2_ticket_auth").await;
+    }
+
+    #[tokio::test]
+    async fn test_unsupported_plugins() {
+        let target = Address::Domain("example.com".to_string(), 80);
+        let proxy = node(1, params("aes-256-cfb", "auth_sha1_v4", "plain"));
+        let err = dial(&proxy, &target).await.err().unwrap();
+        assert!(err.to_string().contains("auth_sha1_v4"));
+
+        let proxy = node(1, params("aes-256-cfb", "origin", "random_head"));
+        let err = dial(&proxy, &target).await.err().unwrap();
+        assert!(err.to_string().contains("random_head"));
+    }
+}
@@ -0,0 +1,628 @@
+//! ShadowsocksR 混淆插件
+//! 把加密后的数据伪装成 HTTP 请求或 TLS 1.2 会话
+
+use super::{HashKind, ServerInfo, hmac};
+use crate::outbound::util::invalid_data;
+use anyhow::{Result, anyhow};
+use rand::seq::SliceRandom;
+use rand::{Rng, RngCore};
+use std::io;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const USER_AGENTS: &[&str] = &[
+    "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36",
+    "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.1 Safari/605.1.15",
+    "Mozilla/5.0 (X11; Linux x86_64; rv:121.0) Gecko/20100101 Firefox/121.0",
+];
+const TLS_VERSION: [u8; 2] = [0x03, 0x03];
+const TLS_APPLICATION_DATA: u8 = 0x17;
+const TLS_HANDSHAKE: u8 = 0x16;
+const TLS_CHANGE_CIPHER_SPEC: u8 = 0x14;
+/// 握手完成后单条记录的分片阈值
+const TLS_RECORD_SPLIT: usize = 2048;
+
+/// 混淆插件
+pub enum Obfs {
+    Plain,
+    HttpSimple(Box<HttpSimple>),
+    TlsTicketAuth(Box<TlsTicketAuth>),
+}
+
+impl Obfs {
+    /// 根据节点的 `obfs` 名称创建插件
+    pub fn new(name: &str, info: &ServerInfo) -> Result<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "" | "plain" => Ok(Obfs::Plain),
+            "http_simple" => Ok(Obfs::HttpSimple(Box::new(HttpSimple::new(info)))),
+            "tls1.2_ticket_auth" => Ok(Obfs::TlsTicketAuth(Box::new(TlsTicketAuth::new(info)))),
+            other => Err(anyhow!("不支持的 ShadowsocksR 混淆方式: {}", other)),
+        }
+    }
+
+    /// 每个数据包额外增加的长度
+    pub fn overhead(&self) -> usize {
+        match self {
+            Obfs::TlsTicketAuth(_) => 5,
+            _ => 0,
+        }
+    }
+
+    /// 编码待发送的数据
+    pub fn encode(&mut self, data: &[u8]) -> Vec<u8> {
+        match self {
+            Obfs::Plain => data.to_vec(),
+            Obfs::HttpSimple(o) => o.encode(data),
+            Obfs::TlsTicketAuth(o) => o.encode(data),
+        }
+    }
+
+    /// 解码收到的数据，返回负载与需要立即回发给服务端的数据
+    pub fn decode(&mut self, data: &[u8]) -> io::Result<(Vec<u8>, Vec<u8>)> {
+        match self {
+            Obfs::Plain => Ok((data.to_vec(), Vec::new())),
+            Obfs::HttpSimple(o) => Ok((o.decode(data), Vec::new())),
+            Obfs::TlsTicketAuth(o) => o.decode(data),
+        }
+    }
+}
+
+/// 混淆参数为空时使用服务器地址，多个主机名用 `,` 分隔，随机选择一个
+fn pick_host(param: &str, server: &str) -> String {
+    let hosts = if param.is_empty() { server } else { param };
+    let hosts: Vec<&str> = hosts.split(',').collect();
+    hosts
+        .choose(&mut rand::thread_rng())
+        .copied()
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// http_simple：首包伪装成 GET 请求，请求头数据以百分号编码放在路径中
+pub struct HttpSimple {
+    host: String,
+    port: u16,
+    param: String,
+    /// IV 与目标地址的长度，放入路径的数据至少包含这部分
+    head_size: usize,
+    has_sent_header: bool,
+    has_recv_header: bool,
+    recv_buf: Vec<u8>,
+}
+
+impl HttpSimple {
+    fn new(info: &ServerInfo) -> Self {
+        Self {
+            host: info.host.clone(),
+            port: info.port,
+            param: info.obfs_param.clone(),
+            head_size: info.iv.len() + info.head_len,
+            has_sent_header: false,
+            has_recv_header: false,
+            recv_buf: Vec::new(),
+        }
+    }
+
+    fn encode(&mut self, data: &[u8]) -> Vec<u8> {
+        if self.has_sent_header {
+            return data.to_vec();
+        }
+        self.has_sent_header = true;
+
+        let head_len = if data.len() > self.head_size + 64 {
+            self.head_size + rand::thread_rng().gen_range(0..=64)
+        } else {
+            data.len()
+        };
+        let (head, body) = data.split_at(head_len);
+
+        // 混淆参数形如 `host1,host2#Header: value\nHeader2: value`
+        let (hosts, custom) = match self.param.split_once('#') {
+            Some((hosts, custom)) => (
+                hosts.to_string(),
+                Some(custom.replace('\n', "\r\n").replace("\\n", "\r\n")),
+            ),
+            None => (self.param.clone(), None),
+        };
+        let host = pick_host(&hosts, &self.host);
+        let port = if self.port == 80 {
+            String::new()
+        } else {
+            format!(":{}", self.port)
+        };
+
+        let mut request = String::from("GET /");
+        for byte in head {
+            request.push_str(&format!("%{:02x}", byte));
+        }
+        request.push_str(" HTTP/1.1\r\n");
+        request.push_str(&format!("Host: {}{}\r\n", host, port));
+        match custom {
+            Some(custom) => request.push_str(&format!("{}\r\n\r\n", custom)),
+            None => {
+                let user_agent = USER_AGENTS.choose(&mut rand::thread_rng()).unwrap();
+                request.push_str(&format!("User-Agent: {}\r\n", user_agent));
+                request.push_str(
+                    "Accept: text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8\r\n\
+                     Accept-Language: en-US,en;q=0.8\r\n\
+                     Accept-Encoding: gzip, deflate\r\n\
+                     DNT: 1\r\n\
+                     Connection: keep-alive\r\n\r\n",
+                );
+            }
+        }
+
+        let mut out = request.into_bytes();
+        out.extend_from_slice(body);
+        out
+    }
+
+    /// 跳过服务端的 HTTP 响应头
+    fn decode(&mut self, data: &[u8]) -> Vec<u8> {
+        if self.has_recv_header {
+            return data.to_vec();
+        }
+        self.recv_buf.extend_from_slice(data);
+        match self.recv_buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            Some(pos) => {
+                self.has_recv_header = true;
+                self.recv_buf.split_off(pos + 4)
+            }
+            None => Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TlsState {
+    /// 尚未发送 ClientHello
+    Start,
+    /// 已发送 ClientHello，等待服务端握手
+    HelloSent,
+    /// 握手完成，数据以应用数据记录传输
+    Established,
+}
+
+/// tls1.2_ticket_auth：模拟带会话票据的 TLS 1.2 握手，握手随机数中带 HMAC 认证
+pub struct TlsTicketAuth {
+    key: Vec<u8>,
+    host: String,
+    client_id: [u8; 32],
+    state: TlsState,
+    /// 握手完成前暂存的应用数据记录
+    send_buf: Vec<u8>,
+    recv_buf: Vec<u8>,
+}
+
+impl TlsTicketAuth {
+    fn new(info: &ServerInfo) -> Self {
+        let mut host = if info.obfs_param.is_empty() {
+            info.host.as_str()
+        } else {
+            info.obfs_param.as_str()
+        };
+        // IP 地址不作为 SNI
+        if host.ends_with(|c: char| c.is_ascii_digit()) {
+            host = "";
+        }
+        Self {
+            key: info.key.clone(),
+            host: pick_host(host, ""),
+            client_id: rand::random(),
+            state: TlsState::Start,
+            send_buf: Vec::new(),
+            recv_buf: Vec::new(),
+        }
+    }
+
+    fn hmac(&self, data: &[u8]) -> Vec<u8> {
+        let mut key = self.key.clone();
+        key.extend_from_slice(&self.client_id);
+        hmac(HashKind::Sha1, &key, data)[..10].to_vec()
+    }
+
+    /// 握手随机数：时间戳 + 18 字节随机数 + 10 字节 HMAC
+    fn auth_random(&self) -> Vec<u8> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs() as u32;
+        let mut random = timestamp.to_be_bytes().to_vec();
+        random.extend_from_slice(&rand::random::<[u8; 18]>());
+        let tag = self.hmac(&random);
+        random.extend_from_slice(&tag);
+        random
+    }
+
+    fn client_hello(&self) -> Vec<u8> {
+        let mut rng = rand::thread_rng();
+        let mut hello = TLS_VERSION.to_vec();
+        hello.extend_from_slice(&self.auth_random());
+        hello.push(32);
+        hello.extend_from_slice(&self.client_id);
+        hello.extend_from_slice(&hex(
+            "001cc02bc02fcca9cca8cc14cc13c00ac014c009c013009c0035002f000a0100",
+        ));
+
+        let mut ext = hex("ff01000100");
+        let host = self.host.as_bytes();
+        ext.extend_from_slice(&[0x00, 0x00]);
+        ext.extend_from_slice(&((host.len() + 5) as u16).to_be_bytes());
+        ext.extend_from_slice(&((host.len() + 3) as u16).to_be_bytes());
+        ext.push(0x00);
+        ext.extend_from_slice(&(host.len() as u16).to_be_bytes());
+        ext.extend_from_slice(host);
+        ext.extend_from_slice(&hex("00170000"));
+        let mut ticket = vec![0u8; (rng.gen_range(0..17) + 8) * 16];
+        rng.fill_bytes(&mut ticket);
+        ext.extend_from_slice(&[0x00, 0x23]);
+        ext.extend_from_slice(&(ticket.len() as u16).to_be_bytes());
+        ext.extend_from_slice(&ticket);
+        ext.extend_from_slice(&hex("000d001600140601060305010503040104030301030302010203"));
+        ext.extend_from_slice(&hex("000500050100000000"));
+        ext.extend_from_slice(&hex("00120000"));
+        ext.extend_from_slice(&hex("75500000"));
+        ext.extend_from_slice(&hex("000b00020100"));
+        ext.extend_from_slice(&hex("000a0006000400170018"));
+        hello.extend_from_slice(&(ext.len() as u16).to_be_bytes());
+        hello.extend_from_slice(&ext);
+
+        let mut handshake = vec![0x01, 0x00];
+        handshake.extend_from_slice(&(hello.len() as u16).to_be_bytes());
+        handshake.extend_from_slice(&hello);
+        let mut record = vec![TLS_HANDSHAKE, 0x03, 0x01];
+        record.extend_from_slice(&(handshake.len() as u16).to_be_bytes());
+        record.extend_from_slice(&handshake);
+        record
+    }
+
+    /// ChangeCipherSpec + 伪造的 Finished，之后接握手期间暂存的数据
+    fn client_finished(&mut self) -> Vec<u8> {
+        let mut data = vec![TLS_CHANGE_CIPHER_SPEC];
+        data.extend_from_slice(&TLS_VERSION);
+        data.extend_from_slice(&[0x00, 0x01, 0x01]);
+        data.push(TLS_HANDSHAKE);
+        data.extend_from_slice(&TLS_VERSION);
+        data.extend_from_slice(&[0x00, 0x20]);
+        data.extend_from_slice(&rand::random::<[u8; 22]>());
+        let tag = self.hmac(&data);
+        data.extend_from_slice(&tag);
+        data.append(&mut self.send_buf);
+        data
+    }
+
+    fn encode(&mut self, data: &[u8]) -> Vec<u8> {
+        match self.state {
+            TlsState::Established => application_records(data),
+            TlsState::Start => {
+                self.send_buf.extend_from_slice(&application_records(data));
+                self.state = TlsState::HelloSent;
+                self.client_hello()
+            }
+            TlsState::HelloSent => {
+                self.send_buf.extend_from_slice(&application_records(data));
+                Vec::new()
+            }
+        }
+    }
+
+    fn decode(&mut self, data: &[u8]) -> io::Result<(Vec<u8>, Vec<u8>)> {
+        self.recv_buf.extend_from_slice(data);
+        let mut reply = Vec::new();
+        match self.state {
+            TlsState::Start => return Err(invalid_data("TLS 混淆在握手前收到数据")),
+            TlsState::HelloSent => {
+                let Some(len) = server_handshake_len(&self.recv_buf) else {
+                    return Ok((Vec::new(), reply));
+                };
+                let handshake = &self.recv_buf[..len];
+                if len < 11 + 32 + 1 + 32
+                    || self.hmac(&handshake[11..33]) != handshake[33..43]
+                    || self.hmac(&handshake[..len - 10]) != handshake[len - 10..]
+                {
+                    return Err(invalid_data("TLS 混淆服务端握手校验失败"));
+                }
+                self.recv_buf.drain(..len);
+                self.state = TlsState::Established;
+                reply = self.client_finished();
+            }
+            TlsState::Established => {}
+        }
+
+        let mut out = Vec::new();
+        while self.recv_buf.len() >= 5 {
+            if self.recv_buf[0] != TLS_APPLICATION_DATA {
+                return Err(invalid_data("TLS 混淆记录类型错误"));
+            }
+            let len = u16::from_be_bytes([self.recv_buf[3], self.recv_buf[4]]) as usize;
+            if self.recv_buf.len() < 5 + len {
+                break;
+            }
+            out.extend_from_slice(&self.recv_buf[5..5 + len]);
+            self.recv_buf.drain(..5 + len);
+        }
+        Ok((out, reply))
+    }
+}
+
+/// 把数据封装为应用数据记录，较长的数据按随机长度分片
+fn application_records(mut data: &[u8]) -> Vec<u8> {
+    let mut rng = rand::thread_rng();
+    let mut out = Vec::with_capacity(data.len() + 64);
+    while !data.is_empty() {
+        let len = if data.len() > TLS_RECORD_SPLIT {
+            (rng.gen_range(0..4096) + 100).min(data.len())
+        } else {
+            data.len()
+        };
+        out.push(TLS_APPLICATION_DATA);
+        out.extend_from_slice(&TLS_VERSION);
+        out.extend_from_slice(&(len as u16).to_be_bytes());
+        out.extend_from_slice(&data[..len]);
+        data = &data[len..];
+    }
+    out
+}
+
+/// 服务端握手（ServerHello [+ NewSessionTicket] + ChangeCipherSpec + Finished）的总长度，
+/// 数据不完整时返回 `None`
+fn server_handshake_len(buf: &[u8]) -> Option<usize> {
+    let mut pos = 0;
+    let mut change_cipher_spec = false;
+    while pos + 5 <= buf.len() {
+        let end = pos + 5 + u16::from_be_bytes([buf[pos + 3], buf[pos + 4]]) as usize;
+        if end > buf.len() {
+            return None;
+        }
+        match buf[pos] {
+            TLS_CHANGE_CIPHER_SPEC => change_cipher_spec = true,
+            TLS_HANDSHAKE if change_cipher_spec => return Some(end),
+            _ => {}
+        }
+        pos = end;
+    }
+    None
+}
+
+fn hex(s: &str) -> Vec<u8> {
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+        .collect()
+}
+
+#[cfg(test)]
+pub mod test_server {
+    //! 服务端视角的混淆处理，供端到端测试使用
+
+    use super::*;
+
+    /// 与客户端配对的服务端混淆状态
+    pub enum ServerObfs {
+        Plain,
+        HttpSimple {
+            header_done: bool,
+            response_sent: bool,
+            buf: Vec<u8>,
+        },
+        Tls(TlsTicketAuth),
+    }
+
+    impl ServerObfs {
+        pub fn new(name: &str, key: &[u8]) -> Self {
+            match name {
+                "plain" => ServerObfs::Plain,
+                "http_simple" => ServerObfs::HttpSimple {
+                    header_done: false,
+                    response_sent: false,
+                    buf: Vec::new(),
+                },
+                _ => ServerObfs::Tls(TlsTicketAuth {
+                    key: key.to_vec(),
+                    host: String::new(),
+                    client_id: [0u8; 32],
+                    state: TlsState::Start,
+                    send_buf: Vec::new(),
+                    recv_buf: Vec::new(),
+                }),
+            }
+        }
+
+        /// 返回解码出的负载与需要回发的数据
+        pub fn decode(&mut self, data: &[u8]) -> (Vec<u8>, Vec<u8>) {
+            match self {
+                ServerObfs::Plain => (data.to_vec(), Vec::new()),
+                ServerObfs::HttpSimple {
+                    header_done, buf, ..
+                } => {
+                    if *header_done {
+                        return (data.to_vec(), Vec::new());
+                    }
+                    buf.extend_from_slice(data);
+                    let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") else {
+                        return (Vec::new(), Vec::new());
+                    };
+                    *header_done = true;
+                    let header = String::from_utf8(buf[..pos].to_vec()).unwrap();
+                    let path = header
+                        .strip_prefix("GET /")
+                        .and_then(|h| h.split(' ').next())
+                        .unwrap();
+                    let mut out: Vec<u8> = (0..path.len())
+                        .step_by(3)
+                        .map(|i| u8::from_str_radix(&path[i + 1..i + 3], 16).unwrap())
+                        .collect();
+                    out.extend_from_slice(&buf[pos + 4..]);
+                    (out, Vec::new())
+                }
+                ServerObfs::Tls(tls) => {
+                    tls.recv_buf.extend_from_slice(data);
+                    let mut reply = Vec::new();
+                    match tls.state {
+                        TlsState::Start => {
+                            if tls.recv_buf.len() < 5 {
+                                return (Vec::new(), reply);
+                            }
+                            let len =
+                                5 + u16::from_be_bytes([tls.recv_buf[3], tls.recv_buf[4]]) as usize;
+                            if tls.recv_buf.len() < len {
+                                return (Vec::new(), reply);
+                            }
+                            let hello: Vec<u8> = tls.recv_buf.drain(..len).collect();
+                            tls.client_id.copy_from_slice(&hello[44..76]);
+                            assert_eq!(tls.hmac(&hello[11..33]), hello[33..43]);
+
+                            let mut server_hello = TLS_VERSION.to_vec();
+                            server_hello.extend_from_slice(&tls.auth_random());
+                            server_hello.push(32);
+                            server_hello.extend_from_slice(&tls.client_id);
+                            server_hello.extend_from_slice(&hex("c02f000005ff01000100"));
+                            let mut handshake = vec![0x02, 0x00];
+                            handshake.extend_from_slice(&(server_hello.len() as u16).to_be_bytes());
+                            handshake.extend_from_slice(&server_hello);
+                            reply.push(TLS_HANDSHAKE);
+                            reply.extend_from_slice(&TLS_VERSION);
+                            reply.extend_from_slice(&(handshake.len() as u16).to_be_bytes());
+                            reply.extend_from_slice(&handshake);
+                            reply.extend_from_slice(&hex("140303000101"));
+                            reply.extend_from_slice(&hex("16030300"));
+                            reply.push(40);
+                            reply.extend_from_slice(&[0u8; 30]);
+                            let tag = tls.hmac(&reply);
+                            reply.extend_from_slice(&tag);
+                            tls.state = TlsState::HelloSent;
+                            (Vec::new(), reply)
+                        }
+                        TlsState::HelloSent => {
+                            if tls.recv_buf.len() < 43 {
+                                return (Vec::new(), reply);
+                            }
+                            let finished: Vec<u8> = tls.recv_buf.drain(..43).collect();
+                            assert_eq!(tls.hmac(&finished[..33]), finished[33..]);
+                            tls.state = TlsState::Established;
+                            let (out, _) = tls.decode(&[]).unwrap();
+                            (out, reply)
+                        }
+                        TlsState::Established => {
+                            let (out, _) = tls.decode(&[]).unwrap();
+                            (out, reply)
+                        }
+                    }
+                }
+            }
+        }
+
+        pub fn encode(&mut self, data: &[u8]) -> Vec<u8> {
+            match self {
+                ServerObfs::Plain => data.to_vec(),
+                ServerObfs::HttpSimple { response_sent, .. } => {
+                    let mut out = Vec::new();
+                    if !*response_sent {
+                        *response_sent = true;
+                        out.extend_from_slice(b"HTTP/1.1 200 OK\r\nConnection: keep-alive\r\n\r\n");
+                    }
+                    out.extend_from_slice(data);
+                    out
+                }
+                ServerObfs::Tls(_) => application_records(data),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::test_server::ServerObfs;
+    use super::*;
+
+    fn info(obfs_param: &str) -> ServerInfo {
+        ServerInfo {
+            host: "1.2.3.4".to_string(),
+            port: 8080,
+            key: vec![5u8; 16],
+            iv: vec![1u8; 16],
+            head_len: 7,
+            protocol_param: String::new(),
+            obfs_param: obfs_param.to_string(),
+            obfs_overhead: 0,
+        }
+    }
+
+    #[test]
+    fn test_http_simple_request() {
+        let mut obfs = HttpSimple::new(&info("cdn.example.com"));
+        let data: Vec<u8> = (0..200u8).collect();
+        let encoded = obfs.encode(&data);
+        let text = String::from_utf8_lossy(&encoded);
+        assert!(text.starts_with("GET /%00%01%02"));
+        assert!(text.contains("\r\nHost: cdn.example.com:8080\r\n"));
+        assert!(text.contains("User-Agent: "));
+
+        let mut server = ServerObfs::new("http_simple", &[]);
+        let (decoded, _) = server.decode(&encoded);
+        assert_eq!(decoded, data);
+        assert_eq!(obfs.encode(b"next"), b"next");
+    }
+
+    #[test]
+    fn test_http_simple_custom_headers() {
+        let mut obfs = HttpSimple::new(&info("a.com#User-Agent: test\\nX-Token: 1"));
+        let text = String::from_utf8(obfs.encode(b"abc")).unwrap();
+        assert_eq!(
+            text,
+            "GET /%61%62%63 HTTP/1.1\r\nHost: a.com:8080\r\nUser-Agent: test\r\nX-Token: 1\r\n\r\n"
+        );
+    }
+
+    #[test]
+    fn test_http_simple_response_split() {
+        let mut obfs = HttpSimple::new(&info(""));
+        assert!(obfs.decode(b"HTTP/1.1 200 OK\r\nServer: ngi").is_empty());
+        assert_eq!(obfs.decode(b"nx\r\n\r\npayload"), b"payload");
+        assert_eq!(obfs.decode(b"more"), b"more");
+    }
+
+    #[test]
+    fn test_tls_ticket_auth_handshake() {
+        let info = info("www.example.com");
+        let mut client = TlsTicketAuth::new(&info);
+        let mut server = ServerObfs::new("tls1.2_ticket_auth", &info.key);
+
+        // 首次写入只发出 ClientHello，数据暂存到握手完成
+        let hello = client.encode(b"first");
+        assert_eq!(&hello[..3], &[TLS_HANDSHAKE, 0x03, 0x01]);
+        let sni = b"www.example.com";
+        assert!(hello.windows(sni.len()).any(|w| w == sni));
+        assert!(client.encode(b" second").is_empty());
+
+        let (payload, server_reply) = server.decode(&hello);
+        assert!(payload.is_empty());
+
+        // 服务端握手分两次到达
+        let (split_a, split_b) = server_reply.split_at(20);
+        assert_eq!(client.decode(split_a).unwrap(), (Vec::new(), Vec::new()));
+        let mut tail = split_b.to_vec();
+        tail.extend(server.encode(b"response"));
+        let (data, finished) = client.decode(&tail).unwrap();
+        assert_eq!(data, b"response");
+
+        let (payload, _) = server.decode(&finished);
+        assert_eq!(payload, b"first second");
+
+        let big = vec![9u8; 10000];
+        let (payload, _) = server.decode(&client.encode(&big));
+        assert_eq!(payload, big);
+    }
+
+    #[test]
+    fn test_tls_ticket_auth_rejects_bad_handshake() {
+        let mut client = TlsTicketAuth::new(&info(""));
+        assert!(client.host.is_empty());
+        client.encode(b"data");
+        let mut forged = hex("160303004a020000460303");
+        forged.extend_from_slice(&[0u8; 68]);
+        forged.extend_from_slice(&hex("140303000101"));
+        forged.extend_from_slice(&hex("1603030020"));
+        forged.extend_from_slice(&[0u8; 32]);
+        assert!(client.decode(&forged).is_err());
+    }
+}
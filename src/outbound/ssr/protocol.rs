@@ -0,0 +1,818 @@
+//! ShadowsocksR 协议插件
+//! 在流加密之前对数据分包并附加认证信息，读取方向在流解密之后去除
+
+use super::{HashKind, ServerInfo, hmac};
+use crate::outbound::crypto::evp_bytes_to_key;
+use crate::outbound::stream_cipher::Rc4;
+use crate::outbound::util::invalid_data;
+use aes::cipher::{BlockEncrypt, KeyInit, generic_array::GenericArray};
+use anyhow::{Result, anyhow};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use rand::{Rng, RngCore};
+use std::io;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// auth_aes128 单包最大数据长度
+const AES128_UNIT_LEN: usize = 8100;
+/// auth_chain 单包最大数据长度
+const CHAIN_UNIT_LEN: usize = 2800;
+/// 首包在目标地址之外随机多带的数据长度上限
+const HEAD_EXTRA: usize = 31;
+
+/// 协议插件
+pub enum Protocol {
+    Origin,
+    AuthAes128(Box<AuthAes128>),
+    AuthChainA(Box<AuthChainA>),
+}
+
+impl Protocol {
+    /// 根据节点的 `protocol` 名称创建插件
+    pub fn new(name: &str, info: &ServerInfo) -> Result<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "" | "origin" => Ok(Protocol::Origin),
+            "auth_aes128_md5" => Ok(Protocol::AuthAes128(Box::new(AuthAes128::new(
+                HashKind::Md5,
+                info,
+            )))),
+            "auth_aes128_sha1" => Ok(Protocol::AuthAes128(Box::new(AuthAes128::new(
+                HashKind::Sha1,
+                info,
+            )))),
+            "auth_chain_a" => Ok(Protocol::AuthChainA(Box::new(AuthChainA::new(info)))),
+            other => Err(anyhow!("不支持的 ShadowsocksR 协议: {}", other)),
+        }
+    }
+
+    /// 封装待加密的数据
+    pub fn encode(&mut self, data: &[u8]) -> Vec<u8> {
+        match self {
+            Protocol::Origin => data.to_vec(),
+            Protocol::AuthAes128(p) => p.encode(data),
+            Protocol::AuthChainA(p) => p.encode(data),
+        }
+    }
+
+    /// 解析解密后的数据，数据包可以跨越多次读取
+    pub fn decode(&mut self, data: &[u8]) -> io::Result<Vec<u8>> {
+        match self {
+            Protocol::Origin => Ok(data.to_vec()),
+            Protocol::AuthAes128(p) => p.decode(data),
+            Protocol::AuthChainA(p) => p.decode(data),
+        }
+    }
+}
+
+/// 认证数据：时间戳 + 客户端 ID + 连接 ID（均为小端序）
+fn auth_data() -> [u8; 12] {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as u32;
+    let connection_id = (rand::random::<u32>() & 0xFF_FFFF) + 1;
+    let mut data = [0u8; 12];
+    data[..4].copy_from_slice(&timestamp.to_le_bytes());
+    rand::thread_rng().fill_bytes(&mut data[4..8]);
+    data[8..].copy_from_slice(&connection_id.to_le_bytes());
+    data
+}
+
+/// 解析 `protocol_param` 中的多用户配置 `用户ID:密钥`
+fn user_param(param: &str) -> Option<([u8; 4], &str)> {
+    let mut items = param.split(':');
+    let uid: u32 = items.next()?.trim().parse().ok()?;
+    let key = items.next()?;
+    Some((uid.to_le_bytes(), key))
+}
+
+/// 用 `EVP_BytesToKey(base64(user_key) + salt)` 派生的密钥加密 16 字节认证块
+///
+/// 原实现为零 IV 的 AES-128-CBC，单个分组时等价于 ECB
+fn encrypt_auth_block(user_key: &[u8], salt: &str, block: &mut [u8; 16]) {
+    let password = format!("{}{}", STANDARD.encode(user_key), salt);
+    let key = evp_bytes_to_key(password.as_bytes(), 16);
+    let cipher = aes::Aes128::new(GenericArray::from_slice(&key));
+    cipher.encrypt_block(GenericArray::from_mut_slice(block));
+}
+
+/// 包 ID 参与计算的 MAC 密钥
+fn packet_key(user_key: &[u8], id: u32) -> Vec<u8> {
+    let mut key = user_key.to_vec();
+    key.extend_from_slice(&id.to_le_bytes());
+    key
+}
+
+/// auth_aes128 的随机填充：首字节为填充总长（<255），否则为 0xFF + 2 字节长度
+fn aes128_padding(len: usize) -> Vec<u8> {
+    let mut padding = if len < 128 {
+        vec![(len + 1) as u8]
+    } else {
+        let mut head = vec![0xFF];
+        head.extend_from_slice(&((len + 3) as u16).to_le_bytes());
+        head
+    };
+    let start = padding.len();
+    padding.resize(start + len, 0);
+    rand::thread_rng().fill_bytes(&mut padding[start..]);
+    padding
+}
+
+/// auth_aes128 数据包：长度 + 长度 MAC + 填充 + 数据 + 整包 MAC
+fn aes128_pack(
+    hash: HashKind,
+    user_key: &[u8],
+    pack_id: u32,
+    padding: &[u8],
+    buf: &[u8],
+) -> Vec<u8> {
+    let data_len = ((padding.len() + buf.len() + 8) as u16).to_le_bytes();
+    let mac_key = packet_key(user_key, pack_id);
+    let mut packet = Vec::with_capacity(padding.len() + buf.len() + 8);
+    packet.extend_from_slice(&data_len);
+    packet.extend_from_slice(&hmac(hash, &mac_key, &data_len)[..2]);
+    packet.extend_from_slice(padding);
+    packet.extend_from_slice(buf);
+    let tag = hmac(hash, &mac_key, &packet);
+    packet.extend_from_slice(&tag[..4]);
+    packet
+}
+
+/// 从缓冲区开头解出一个 auth_aes128 数据包，返回消耗长度与数据；数据不足时返回 `None`
+fn aes128_unpack(
+    hash: HashKind,
+    user_key: &[u8],
+    recv_id: u32,
+    buf: &[u8],
+) -> io::Result<Option<(usize, Vec<u8>)>> {
+    if buf.len() <= 4 {
+        return Ok(None);
+    }
+    let mac_key = packet_key(user_key, recv_id);
+    if hmac(hash, &mac_key, &buf[..2])[..2] != buf[2..4] {
+        return Err(invalid_data("ShadowsocksR 数据包长度校验失败"));
+    }
+    let len = u16::from_le_bytes([buf[0], buf[1]]) as usize;
+    if !(7..8192).contains(&len) {
+        return Err(invalid_data("ShadowsocksR 数据包长度错误"));
+    }
+    if len > buf.len() {
+        return Ok(None);
+    }
+    if hmac(hash, &mac_key, &buf[..len - 4])[..4] != buf[len - 4..len] {
+        return Err(invalid_data("ShadowsocksR 数据包校验失败"));
+    }
+    let start = match buf[4] {
+        0xFF => u16::from_le_bytes([buf[5], buf[6]]) as usize + 4,
+        n => n as usize + 4,
+    };
+    if start > len - 4 {
+        return Err(invalid_data("ShadowsocksR 数据包填充长度错误"));
+    }
+    Ok(Some((len, buf[start..len - 4].to_vec())))
+}
+
+/// auth_aes128_md5 / auth_aes128_sha1
+pub struct AuthAes128 {
+    hash: HashKind,
+    salt: &'static str,
+    /// 客户端 IV + 主密钥，用于首包认证
+    auth_key: Vec<u8>,
+    user_key: Vec<u8>,
+    uid: [u8; 4],
+    head_len: usize,
+    has_sent_header: bool,
+    pack_id: u32,
+    recv_id: u32,
+    recv_buf: Vec<u8>,
+}
+
+impl AuthAes128 {
+    fn new(hash: HashKind, info: &ServerInfo) -> Self {
+        let (uid, user_key) = match user_param(&info.protocol_param) {
+            Some((uid, key)) => (uid, hash.digest(key.as_bytes())),
+            None => (rand::random(), info.key.clone()),
+        };
+        let salt = match hash {
+            HashKind::Md5 => "auth_aes128_md5",
+            HashKind::Sha1 => "auth_aes128_sha1",
+        };
+        Self {
+            hash,
+            salt,
+            auth_key: [info.iv.as_slice(), info.key.as_slice()].concat(),
+            user_key,
+            uid,
+            head_len: info.head_len,
+            has_sent_header: false,
+            pack_id: 1,
+            recv_id: 1,
+            recv_buf: Vec::new(),
+        }
+    }
+
+    /// 首包：校验头 + 用户 ID + 加密认证块 + MAC + 随机数据 + 数据 + MAC
+    fn pack_auth_data(&mut self, buf: &[u8]) -> Vec<u8> {
+        let mut rng = rand::thread_rng();
+        let rnd_len = if buf.len() > 400 {
+            rng.gen_range(0..512)
+        } else {
+            rng.gen_range(0..1024)
+        };
+        let total_len = 7 + 4 + 16 + 4 + buf.len() + rnd_len + 4;
+
+        let mut block = [0u8; 16];
+        block[..12].copy_from_slice(&auth_data());
+        block[12..14].copy_from_slice(&(total_len as u16).to_le_bytes());
+        block[14..].copy_from_slice(&(rnd_len as u16).to_le_bytes());
+        encrypt_auth_block(&self.user_key, self.salt, &mut block);
+
+        let mut packet = Vec::with_capacity(total_len);
+        let check: u8 = rng.r#gen();
+        packet.push(check);
+        packet.extend_from_slice(&hmac(self.hash, &self.auth_key, &[check])[..6]);
+        let mut auth = self.uid.to_vec();
+        auth.extend_from_slice(&block);
+        let tag = hmac(self.hash, &self.auth_key, &auth);
+        packet.extend_from_slice(&auth);
+        packet.extend_from_slice(&tag[..4]);
+        let start = packet.len();
+        packet.resize(start + rnd_len, 0);
+        rng.fill_bytes(&mut packet[start..]);
+        packet.extend_from_slice(buf);
+        let tag = hmac(self.hash, &self.user_key, &packet);
+        packet.extend_from_slice(&tag[..4]);
+        packet
+    }
+
+    fn pack_data(&mut self, buf: &[u8]) -> Vec<u8> {
+        let mut rng = rand::thread_rng();
+        let padding_len = if buf.len() > 1200 {
+            0
+        } else if self.pack_id > 4 {
+            rng.gen_range(0..32)
+        } else if buf.len() > 900 {
+            rng.gen_range(0..128)
+        } else {
+            rng.gen_range(0..512)
+        };
+        let packet = aes128_pack(
+            self.hash,
+            &self.user_key,
+            self.pack_id,
+            &aes128_padding(padding_len),
+            buf,
+        );
+        self.pack_id = self.pack_id.wrapping_add(1);
+        packet
+    }
+
+    fn encode(&mut self, mut data: &[u8]) -> Vec<u8> {
+        let mut out = Vec::with_capacity(data.len() + 1024);
+        if !self.has_sent_header {
+            let head_len = self.head_len + rand::thread_rng().gen_range(0..=HEAD_EXTRA);
+            let (head, rest) = data.split_at(data.len().min(head_len));
+            out.extend_from_slice(&self.pack_auth_data(head));
+            data = rest;
+            self.has_sent_header = true;
+        }
+        while data.len() > AES128_UNIT_LEN {
+            let (chunk, rest) = data.split_at(AES128_UNIT_LEN);
+            out.extend_from_slice(&self.pack_data(chunk));
+            data = rest;
+        }
+        out.extend_from_slice(&self.pack_data(data));
+        out
+    }
+
+    fn decode(&mut self, data: &[u8]) -> io::Result<Vec<u8>> {
+        self.recv_buf.extend_from_slice(data);
+        let mut out = Vec::new();
+        while let Some((len, plain)) =
+            aes128_unpack(self.hash, &self.user_key, self.recv_id, &self.recv_buf)?
+        {
+            out.extend_from_slice(&plain);
+            self.recv_buf.drain(..len);
+            self.recv_id = self.recv_id.wrapping_add(1);
+        }
+        Ok(out)
+    }
+}
+
+/// auth_chain 使用的 xorshift128+ 伪随机数发生器
+#[derive(Clone, Default)]
+struct XorShift128Plus {
+    v0: u64,
+    v1: u64,
+}
+
+impl XorShift128Plus {
+    fn next(&mut self) -> u64 {
+        let mut x = self.v0;
+        let y = self.v1;
+        self.v0 = y;
+        x ^= x << 23;
+        x ^= y ^ (x >> 17) ^ (y >> 26);
+        self.v1 = x;
+        x.wrapping_add(y)
+    }
+
+    /// 以上一个哈希（前两字节替换为长度）为种子初始化
+    fn init_from_bin_len(&mut self, bin: &[u8; 16], len: usize) {
+        let mut seed = *bin;
+        seed[..2].copy_from_slice(&(len as u16).to_le_bytes());
+        self.v0 = u64::from_le_bytes(seed[..8].try_into().unwrap());
+        self.v1 = u64::from_le_bytes(seed[8..].try_into().unwrap());
+        for _ in 0..4 {
+            self.next();
+        }
+    }
+}
+
+/// auth_chain 每个方向的分包状态
+pub struct ChainState {
+    cipher: Rc4,
+    last_hash: [u8; 16],
+    random: XorShift128Plus,
+    id: u32,
+}
+
+impl ChainState {
+    fn new(rc4_key: &[u8], last_hash: [u8; 16]) -> Self {
+        Self {
+            cipher: Rc4::new(rc4_key),
+            last_hash,
+            random: XorShift128Plus::default(),
+            id: 1,
+        }
+    }
+
+    /// 随机数据长度，由上一个哈希与数据长度决定，收发双方可以各自算出
+    fn rnd_data_len(&mut self, buf_size: usize) -> usize {
+        if buf_size > 1440 {
+            return 0;
+        }
+        self.random.init_from_bin_len(&self.last_hash, buf_size);
+        let modulus = match buf_size {
+            1301.. => 31,
+            901.. => 127,
+            401.. => 521,
+            _ => 1021,
+        };
+        (self.random.next() % modulus) as usize
+    }
+
+    fn rnd_start_pos(&mut self, rand_len: usize) -> usize {
+        (self.random.next() % 8_589_934_609 % rand_len as u64) as usize
+    }
+
+    /// 数据包：长度（与上一个哈希异或）+ 嵌入随机数据中的 RC4 密文 + 2 字节 MAC
+    fn pack(&mut self, user_key: &[u8], buf: &[u8]) -> Vec<u8> {
+        let mut encrypted = buf.to_vec();
+        self.cipher.apply(&mut encrypted);
+        let rand_len = self.rnd_data_len(encrypted.len());
+        let mut random = vec![0u8; rand_len];
+        rand::thread_rng().fill_bytes(&mut random);
+
+        let len_mask = u16::from_le_bytes([self.last_hash[14], self.last_hash[15]]);
+        let mut packet = Vec::with_capacity(2 + rand_len + encrypted.len() + 2);
+        packet.extend_from_slice(&(encrypted.len() as u16 ^ len_mask).to_le_bytes());
+        if !encrypted.is_empty() && rand_len > 0 {
+            let pos = self.rnd_start_pos(rand_len);
+            packet.extend_from_slice(&random[..pos]);
+            packet.extend_from_slice(&encrypted);
+            packet.extend_from_slice(&random[pos..]);
+        } else {
+            packet.extend_from_slice(&random);
+            packet.extend_from_slice(&encrypted);
+        }
+        self.last_hash = hmac_md5(&packet_key(user_key, self.id), &packet);
+        packet.extend_from_slice(&self.last_hash[..2]);
+        self.id = self.id.wrapping_add(1);
+        packet
+    }
+
+    /// 从缓冲区开头解出一个数据包，返回消耗长度与明文；数据不足时返回 `None`
+    fn unpack(&mut self, user_key: &[u8], buf: &[u8]) -> io::Result<Option<(usize, Vec<u8>)>> {
+        if buf.len() <= 4 {
+            return Ok(None);
+        }
+        let len_mask = u16::from_le_bytes([self.last_hash[14], self.last_hash[15]]);
+        let data_len = (u16::from_le_bytes([buf[0], buf[1]]) ^ len_mask) as usize;
+        let rand_len = self.rnd_data_len(data_len);
+        let len = data_len + rand_len;
+        if len >= 4096 {
+            return Err(invalid_data("ShadowsocksR 数据包长度错误"));
+        }
+        if len + 4 > buf.len() {
+            return Ok(None);
+        }
+        let hash = hmac_md5(&packet_key(user_key, self.id), &buf[..len + 2]);
+        if hash[..2] != buf[len + 2..len + 4] {
+            return Err(invalid_data("ShadowsocksR 数据包校验失败"));
+        }
+        let start = if data_len > 0 && rand_len > 0 {
+            2 + self.rnd_start_pos(rand_len)
+        } else {
+            2
+        };
+        let mut plain = buf[start..start + data_len].to_vec();
+        self.cipher.apply(&mut plain);
+        self.last_hash = hash;
+        self.id = self.id.wrapping_add(1);
+        Ok(Some((len + 4, plain)))
+    }
+}
+
+fn hmac_md5(key: &[u8], data: &[u8]) -> [u8; 16] {
+    hmac(HashKind::Md5, key, data).try_into().unwrap()
+}
+
+/// auth_chain_a
+pub struct AuthChainA {
+    auth_key: Vec<u8>,
+    user_key: Vec<u8>,
+    uid: [u8; 4],
+    head_len: usize,
+    /// 协议与混淆的总开销，写入认证块供服务端计算包长
+    overhead: u16,
+    send: Option<ChainState>,
+    recv: Option<ChainState>,
+    /// 服务端首个数据包开头的 2 字节 TCP MSS 尚未跳过的长度
+    mss_remaining: usize,
+    recv_buf: Vec<u8>,
+}
+
+impl AuthChainA {
+    const SALT: &'static str = "auth_chain_a";
+    const OVERHEAD: usize = 4;
+
+    fn new(info: &ServerInfo) -> Self {
+        let (uid, user_key) = match user_param(&info.protocol_param) {
+            Some((uid, key)) => (uid, key.as_bytes().to_vec()),
+            None => (rand::random(), info.key.clone()),
+        };
+        Self {
+            auth_key: [info.iv.as_slice(), info.key.as_slice()].concat(),
+            user_key,
+            uid,
+            head_len: info.head_len,
+            overhead: (Self::OVERHEAD + info.obfs_overhead) as u16,
+            send: None,
+            recv: None,
+            mss_remaining: 2,
+            recv_buf: Vec::new(),
+        }
+    }
+
+    /// 首包：校验头 + 用户 ID + 加密认证块 + MAC，之后接第一个数据包
+    fn pack_auth_data(&mut self, buf: &[u8]) -> Vec<u8> {
+        let mut block = [0u8; 16];
+        block[..12].copy_from_slice(&auth_data());
+        block[12..14].copy_from_slice(&self.overhead.to_le_bytes());
+        encrypt_auth_block(&self.user_key, Self::SALT, &mut block);
+
+        let check: [u8; 4] = rand::random();
+        let client_hash = hmac_md5(&self.auth_key, &check);
+        let mut packet = check.to_vec();
+        packet.extend_from_slice(&client_hash[..8]);
+
+        let mut auth = Vec::with_capacity(20);
+        auth.extend(self.uid.iter().zip(&client_hash[8..12]).map(|(a, b)| a ^ b));
+        auth.extend_from_slice(&block);
+        let server_hash = hmac_md5(&self.user_key, &auth);
+        packet.extend_from_slice(&auth);
+        packet.extend_from_slice(&server_hash[..4]);
+
+        let password = format!(
+            "{}{}",
+            STANDARD.encode(&self.user_key),
+            STANDARD.encode(client_hash)
+        );
+        let rc4_key = evp_bytes_to_key(password.as_bytes(), 16);
+        let mut send = ChainState::new(&rc4_key, client_hash);
+        packet.extend_from_slice(&send.pack(&self.user_key, buf));
+        self.send = Some(send);
+        self.recv = Some(ChainState::new(&rc4_key, server_hash));
+        packet
+    }
+
+    fn encode(&mut self, mut data: &[u8]) -> Vec<u8> {
+        let mut out = Vec::with_capacity(data.len() + 1024);
+        if self.send.is_none() {
+            let head_len = self.head_len + rand::thread_rng().gen_range(0..=HEAD_EXTRA);
+            let (head, rest) = data.split_at(data.len().min(head_len));
+            out.extend_from_slice(&self.pack_auth_data(head));
+            data = rest;
+        }
+        let send = self.send.as_mut().expect("auth_chain 首包已发送");
+        while data.len() > CHAIN_UNIT_LEN {
+            let (chunk, rest) = data.split_at(CHAIN_UNIT_LEN);
+            out.extend_from_slice(&send.pack(&self.user_key, chunk));
+            data = rest;
+        }
+        out.extend_from_slice(&send.pack(&self.user_key, data));
+        out
+    }
+
+    fn decode(&mut self, data: &[u8]) -> io::Result<Vec<u8>> {
+        let recv = self
+            .recv
+            .as_mut()
+            .ok_or_else(|| invalid_data("ShadowsocksR 在发送请求前收到数据"))?;
+        self.recv_buf.extend_from_slice(data);
+        let mut out = Vec::new();
+        while let Some((len, plain)) = recv.unpack(&self.user_key, &self.recv_buf)? {
+            self.recv_buf.drain(..len);
+            let skip = self.mss_remaining.min(plain.len());
+            self.mss_remaining -= skip;
+            out.extend_from_slice(&plain[skip..]);
+        }
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+pub mod test_server {
+    //! 服务端视角的协议处理（单用户），供测试使用
+
+    use super::*;
+    use aes::cipher::BlockDecrypt;
+
+    /// 与客户端配对的服务端协议状态
+    pub enum ServerProtocol {
+        Origin,
+        AuthAes128 {
+            hash: HashKind,
+            auth_key: Vec<u8>,
+            user_key: Vec<u8>,
+            authed: bool,
+            recv_id: u32,
+            pack_id: u32,
+            buf: Vec<u8>,
+        },
+        AuthChainA {
+            auth_key: Vec<u8>,
+            user_key: Vec<u8>,
+            from_client: Option<Box<ChainState>>,
+            to_client: Option<Box<ChainState>>,
+            buf: Vec<u8>,
+        },
+    }
+
+    impl ServerProtocol {
+        /// `iv` 为客户端 IV
+        pub fn new(name: &str, key: &[u8], iv: &[u8]) -> Self {
+            let auth_key = [iv, key].concat();
+            let aes128 = |hash| ServerProtocol::AuthAes128 {
+                hash,
+                auth_key: auth_key.clone(),
+                user_key: key.to_vec(),
+                authed: false,
+                recv_id: 1,
+                pack_id: 1,
+                buf: Vec::new(),
+            };
+            match name {
+                "origin" => ServerProtocol::Origin,
+                "auth_aes128_md5" => aes128(HashKind::Md5),
+                "auth_aes128_sha1" => aes128(HashKind::Sha1),
+                _ => ServerProtocol::AuthChainA {
+                    auth_key: auth_key.clone(),
+                    user_key: key.to_vec(),
+                    from_client: None,
+                    to_client: None,
+                    buf: Vec::new(),
+                },
+            }
+        }
+
+        /// 校验并解出客户端数据，数据不完整时保留在缓冲区
+        pub fn decode(&mut self, data: &[u8]) -> Vec<u8> {
+            let mut out = Vec::new();
+            match self {
+                ServerProtocol::Origin => out.extend_from_slice(data),
+                ServerProtocol::AuthAes128 {
+                    hash,
+                    auth_key,
+                    user_key,
+                    authed,
+                    recv_id,
+                    buf,
+                    ..
+                } => {
+                    buf.extend_from_slice(data);
+                    if !*authed {
+                        if buf.len() < 31 {
+                            return out;
+                        }
+                        assert_eq!(hmac(*hash, auth_key, &buf[..1])[..6], buf[1..7]);
+                        assert_eq!(hmac(*hash, auth_key, &buf[7..27])[..4], buf[27..31]);
+                        let salt = match hash {
+                            HashKind::Md5 => "auth_aes128_md5",
+                            HashKind::Sha1 => "auth_aes128_sha1",
+                        };
+                        let password = format!("{}{}", STANDARD.encode(&user_key), salt);
+                        let key = evp_bytes_to_key(password.as_bytes(), 16);
+                        let mut block: [u8; 16] = buf[11..27].try_into().unwrap();
+                        aes::Aes128::new(GenericArray::from_slice(&key))
+                            .decrypt_block(GenericArray::from_mut_slice(&mut block));
+                        let total = u16::from_le_bytes([block[12], block[13]]) as usize;
+                        let rnd_len = u16::from_le_bytes([block[14], block[15]]) as usize;
+                        if buf.len() < total {
+                            return out;
+                        }
+                        assert_eq!(
+                            hmac(*hash, user_key, &buf[..total - 4])[..4],
+                            buf[total - 4..total]
+                        );
+                        out.extend_from_slice(&buf[31 + rnd_len..total - 4]);
+                        buf.drain(..total);
+                        *authed = true;
+                    }
+                    while let Some((len, data)) =
+                        aes128_unpack(*hash, user_key, *recv_id, buf).unwrap()
+                    {
+                        out.extend_from_slice(&data);
+                        buf.drain(..len);
+                        *recv_id += 1;
+                    }
+                }
+                ServerProtocol::AuthChainA {
+                    auth_key,
+                    user_key,
+                    from_client,
+                    to_client,
+                    buf,
+                } => {
+                    buf.extend_from_slice(data);
+                    if from_client.is_none() {
+                        if buf.len() < 36 {
+                            return out;
+                        }
+                        let client_hash = hmac_md5(auth_key, &buf[..4]);
+                        assert_eq!(client_hash[..8], buf[4..12]);
+                        let server_hash = hmac_md5(user_key, &buf[12..32]);
+                        assert_eq!(server_hash[..4], buf[32..36]);
+                        let password = format!(
+                            "{}{}",
+                            STANDARD.encode(&user_key),
+                            STANDARD.encode(client_hash)
+                        );
+                        let rc4_key = evp_bytes_to_key(password.as_bytes(), 16);
+                        *from_client = Some(Box::new(ChainState::new(&rc4_key, client_hash)));
+                        *to_client = Some(Box::new(ChainState::new(&rc4_key, server_hash)));
+                        buf.drain(..36);
+                    }
+                    let state = from_client.as_mut().unwrap();
+                    while let Some((len, data)) = state.unpack(user_key, buf).unwrap() {
+                        out.extend_from_slice(&data);
+                        buf.drain(..len);
+                    }
+                }
+            }
+            out
+        }
+
+        pub fn encode(&mut self, data: &[u8]) -> Vec<u8> {
+            match self {
+                ServerProtocol::Origin => data.to_vec(),
+                ServerProtocol::AuthAes128 {
+                    hash,
+                    user_key,
+                    pack_id,
+                    ..
+                } => {
+                    let packet = aes128_pack(*hash, user_key, *pack_id, &aes128_padding(200), data);
+                    *pack_id += 1;
+                    packet
+                }
+                ServerProtocol::AuthChainA {
+                    user_key,
+                    to_client,
+                    ..
+                } => {
+                    let state = to_client.as_mut().expect("尚未收到客户端首包");
+                    if state.id == 1 {
+                        // 首个数据包开头携带 TCP MSS
+                        let mut first = 1460u16.to_le_bytes().to_vec();
+                        first.extend_from_slice(data);
+                        state.pack(user_key, &first)
+                    } else {
+                        state.pack(user_key, data)
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::test_server::ServerProtocol;
+    use super::*;
+
+    fn info(protocol_param: &str) -> ServerInfo {
+        ServerInfo {
+            host: "ssr.test".to_string(),
+            port: 443,
+            key: evp_bytes_to_key(b"password", 32),
+            iv: vec![3u8; 16],
+            head_len: 7,
+            protocol_param: protocol_param.to_string(),
+            obfs_param: String::new(),
+            obfs_overhead: 0,
+        }
+    }
+
+    /// 客户端编码的数据交给服务端解出，服务端回应以小块交给客户端
+    fn roundtrip(name: &str) {
+        let info = info("");
+        let mut client = Protocol::new(name, &info).unwrap();
+        let mut server = ServerProtocol::new(name, &info.key, &info.iv);
+
+        let payload: Vec<u8> = (0..20000u32).map(|i| (i * 7) as u8).collect();
+        let packet = client.encode(&payload);
+        let mut received = Vec::new();
+        for chunk in packet.chunks(1000) {
+            received.extend(server.decode(chunk));
+        }
+        assert_eq!(received, payload, "{}", name);
+        assert_eq!(server.decode(&client.encode(b"again")), b"again");
+
+        let mut response = server.encode(b"hello ");
+        response.extend(server.encode(b"ssr"));
+        response.extend(server.encode(&[]));
+        let mut plain = Vec::new();
+        for chunk in response.chunks(5) {
+            plain.extend(client.decode(chunk).unwrap());
+        }
+        assert_eq!(plain, b"hello ssr", "{}", name);
+    }
+
+    #[test]
+    fn test_protocol_roundtrip() {
+        for name in [
+            "origin",
+            "auth_aes128_md5",
+            "auth_aes128_sha1",
+            "auth_chain_a",
+        ] {
+            roundtrip(name);
+        }
+    }
+
+    #[test]
+    fn test_aes128_padding_layout() {
+        let short = aes128_padding(10);
+        assert_eq!(short.len(), 11);
+        assert_eq!(short[0], 11);
+        let long = aes128_padding(300);
+        assert_eq!(long.len(), 303);
+        assert_eq!(long[0], 0xFF);
+        assert_eq!(u16::from_le_bytes([long[1], long[2]]), 303);
+    }
+
+    #[test]
+    fn test_multi_user_param() {
+        let info = info("1024:user-pass");
+        let aes128 = AuthAes128::new(HashKind::Sha1, &info);
+        assert_eq!(aes128.uid, 1024u32.to_le_bytes());
+        assert_eq!(aes128.user_key, HashKind::Sha1.digest(b"user-pass"));
+
+        let chain = AuthChainA::new(&info);
+        assert_eq!(chain.uid, 1024u32.to_le_bytes());
+        assert_eq!(chain.user_key, b"user-pass");
+
+        // 没有 `:` 时参数表示最大客户端数，使用主密钥
+        let single = AuthAes128::new(HashKind::Md5, &self::info("64"));
+        assert_eq!(single.user_key, info.key);
+    }
+
+    #[test]
+    fn test_corrupted_packet() {
+        let info = info("");
+        let mut client = AuthAes128::new(HashKind::Md5, &info);
+        let mut packet = aes128_pack(HashKind::Md5, &info.key, 1, &aes128_padding(3), b"x");
+        let last = packet.len() - 1;
+        packet[last] ^= 1;
+        assert!(client.decode(&packet).is_err());
+    }
+
+    #[test]
+    fn test_xorshift128plus() {
+        let mut random = XorShift128Plus { v0: 1, v1: 2 };
+        // 与 Python 参考实现的输出一致
+        assert_eq!(random.next(), 8388677);
+        assert_eq!(random.next(), 33554692);
+    }
+
+    #[test]
+    fn test_unknown_protocol() {
+        assert!(Protocol::new("auth_chain_z", &info("")).is_err());
+        assert!(matches!(
+            Protocol::new("origin", &info("")).unwrap(),
+            Protocol::Origin
+        ));
+    }
+}
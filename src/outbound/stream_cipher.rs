@@ -0,0 +1,270 @@
+//! 流加密算法
+//! ShadowsocksR 与旧版 Shadowsocks 使用的无认证加密，密文前附带一次性 IV
+
+use aes::{Aes128, Aes192, Aes256};
+use anyhow::{Result, anyhow};
+use cfb_mode::cipher::{KeyIvInit, StreamCipher as _};
+use cfb_mode::{BufDecryptor, BufEncryptor};
+use chacha20::{ChaCha20, ChaCha20Legacy};
+use md5::{Digest, Md5};
+
+type Aes128Ctr = ctr::Ctr128BE<Aes128>;
+type Aes192Ctr = ctr::Ctr128BE<Aes192>;
+type Aes256Ctr = ctr::Ctr128BE<Aes256>;
+
+/// 流加密方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamMethod {
+    None,
+    Rc4,
+    Rc4Md5,
+    Aes128Cfb,
+    Aes192Cfb,
+    Aes256Cfb,
+    Aes128Ctr,
+    Aes192Ctr,
+    Aes256Ctr,
+    Chacha20,
+    Chacha20Ietf,
+}
+
+impl StreamMethod {
+    /// 根据配置中的 `cipher` 名称解析加密方式
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "none" | "plain" | "dummy" => Ok(StreamMethod::None),
+            "rc4" => Ok(StreamMethod::Rc4),
+            "rc4-md5" => Ok(StreamMethod::Rc4Md5),
+            "aes-128-cfb" => Ok(StreamMethod::Aes128Cfb),
+            "aes-192-cfb" => Ok(StreamMethod::Aes192Cfb),
+            "aes-256-cfb" => Ok(StreamMethod::Aes256Cfb),
+            "aes-128-ctr" => Ok(StreamMethod::Aes128Ctr),
+            "aes-192-ctr" => Ok(StreamMethod::Aes192Ctr),
+            "aes-256-ctr" => Ok(StreamMethod::Aes256Ctr),
+            "chacha20" => Ok(StreamMethod::Chacha20),
+            "chacha20-ietf" => Ok(StreamMethod::Chacha20Ietf),
+            other => Err(anyhow!("不支持的流加密方式: {}", other)),
+        }
+    }
+
+    /// 主密钥长度（由密码经 `EVP_BytesToKey` 派生）
+    pub fn key_len(self) -> usize {
+        match self {
+            StreamMethod::Aes192Cfb | StreamMethod::Aes192Ctr => 24,
+            StreamMethod::Aes256Cfb
+            | StreamMethod::Aes256Ctr
+            | StreamMethod::Chacha20
+            | StreamMethod::Chacha20Ietf => 32,
+            _ => 16,
+        }
+    }
+
+    /// IV 长度
+    pub fn iv_len(self) -> usize {
+        match self {
+            StreamMethod::None | StreamMethod::Rc4 => 0,
+            StreamMethod::Chacha20 => 8,
+            StreamMethod::Chacha20Ietf => 12,
+            _ => 16,
+        }
+    }
+}
+
+/// RC4 密钥流
+#[derive(Clone)]
+pub struct Rc4 {
+    state: [u8; 256],
+    i: u8,
+    j: u8,
+}
+
+impl Rc4 {
+    pub fn new(key: &[u8]) -> Self {
+        let mut state = [0u8; 256];
+        for (i, byte) in state.iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+        let mut j = 0u8;
+        for i in 0..256 {
+            j = j.wrapping_add(state[i]).wrapping_add(key[i % key.len()]);
+            state.swap(i, j as usize);
+        }
+        Self { state, i: 0, j: 0 }
+    }
+
+    /// 与密钥流异或（加密与解密相同）
+    pub fn apply(&mut self, data: &mut [u8]) {
+        for byte in data {
+            self.i = self.i.wrapping_add(1);
+            self.j = self.j.wrapping_add(self.state[self.i as usize]);
+            self.state.swap(self.i as usize, self.j as usize);
+            let k = self.state[self.i as usize].wrapping_add(self.state[self.j as usize]);
+            *byte ^= self.state[k as usize];
+        }
+    }
+}
+
+/// 单方向的流加解密器
+pub enum StreamCipher {
+    None,
+    Rc4(Box<Rc4>),
+    Aes128CfbEnc(Box<BufEncryptor<Aes128>>),
+    Aes192CfbEnc(Box<BufEncryptor<Aes192>>),
+    Aes256CfbEnc(Box<BufEncryptor<Aes256>>),
+    Aes128CfbDec(Box<BufDecryptor<Aes128>>),
+    Aes192CfbDec(Box<BufDecryptor<Aes192>>),
+    Aes256CfbDec(Box<BufDecryptor<Aes256>>),
+    Aes128Ctr(Box<Aes128Ctr>),
+    Aes192Ctr(Box<Aes192Ctr>),
+    Aes256Ctr(Box<Aes256Ctr>),
+    Chacha20(Box<ChaCha20Legacy>),
+    Chacha20Ietf(Box<ChaCha20>),
+}
+
+impl StreamCipher {
+    /// 创建加密器，`key` 与 `iv` 长度必须与算法匹配
+    pub fn encryptor(method: StreamMethod, key: &[u8], iv: &[u8]) -> Self {
+        Self::new(method, key, iv, true)
+    }
+
+    /// 创建解密器
+    pub fn decryptor(method: StreamMethod, key: &[u8], iv: &[u8]) -> Self {
+        Self::new(method, key, iv, false)
+    }
+
+    fn new(method: StreamMethod, key: &[u8], iv: &[u8], encrypt: bool) -> Self {
+        const LEN_ERR: &str = "流加密密钥或 IV 长度错误";
+        match method {
+            StreamMethod::None => StreamCipher::None,
+            StreamMethod::Rc4 => StreamCipher::Rc4(Box::new(Rc4::new(key))),
+            StreamMethod::Rc4Md5 => {
+                let mut hasher = Md5::new();
+                hasher.update(key);
+                hasher.update(iv);
+                StreamCipher::Rc4(Box::new(Rc4::new(&hasher.finalize())))
+            }
+            StreamMethod::Aes128Cfb if encrypt => StreamCipher::Aes128CfbEnc(Box::new(
+                BufEncryptor::new_from_slices(key, iv).expect(LEN_ERR),
+            )),
+            StreamMethod::Aes192Cfb if encrypt => StreamCipher::Aes192CfbEnc(Box::new(
+                BufEncryptor::new_from_slices(key, iv).expect(LEN_ERR),
+            )),
+            StreamMethod::Aes256Cfb if encrypt => StreamCipher::Aes256CfbEnc(Box::new(
+                BufEncryptor::new_from_slices(key, iv).expect(LEN_ERR),
+            )),
+            StreamMethod::Aes128Cfb => StreamCipher::Aes128CfbDec(Box::new(
+                BufDecryptor::new_from_slices(key, iv).expect(LEN_ERR),
+            )),
+            StreamMethod::Aes192Cfb => StreamCipher::Aes192CfbDec(Box::new(
+                BufDecryptor::new_from_slices(key, iv).expect(LEN_ERR),
+            )),
+            StreamMethod::Aes256Cfb => StreamCipher::Aes256CfbDec(Box::new(
+                BufDecryptor::new_from_slices(key, iv).expect(LEN_ERR),
+            )),
+            StreamMethod::Aes128Ctr => StreamCipher::Aes128Ctr(Box::new(
+                Aes128Ctr::new_from_slices(key, iv).expect(LEN_ERR),
+            )),
+            StreamMethod::Aes192Ctr => StreamCipher::Aes192Ctr(Box::new(
+                Aes192Ctr::new_from_slices(key, iv).expect(LEN_ERR),
+            )),
+            StreamMethod::Aes256Ctr => StreamCipher::Aes256Ctr(Box::new(
+                Aes256Ctr::new_from_slices(key, iv).expect(LEN_ERR),
+            )),
+            StreamMethod::Chacha20 => StreamCipher::Chacha20(Box::new(
+                ChaCha20Legacy::new_from_slices(key, iv).expect(LEN_ERR),
+            )),
+            StreamMethod::Chacha20Ietf => StreamCipher::Chacha20Ietf(Box::new(
+                ChaCha20::new_from_slices(key, iv).expect(LEN_ERR),
+            )),
+        }
+    }
+
+    /// 原地加密或解密
+    pub fn apply(&mut self, data: &mut [u8]) {
+        match self {
+            StreamCipher::None => {}
+            StreamCipher::Rc4(c) => c.apply(data),
+            StreamCipher::Aes128CfbEnc(c) => c.encrypt(data),
+            StreamCipher::Aes192CfbEnc(c) => c.encrypt(data),
+            StreamCipher::Aes256CfbEnc(c) => c.encrypt(data),
+            StreamCipher::Aes128CfbDec(c) => c.decrypt(data),
+            StreamCipher::Aes192CfbDec(c) => c.decrypt(data),
+            StreamCipher::Aes256CfbDec(c) => c.decrypt(data),
+            StreamCipher::Aes128Ctr(c) => c.apply_keystream(data),
+            StreamCipher::Aes192Ctr(c) => c.apply_keystream(data),
+            StreamCipher::Aes256Ctr(c) => c.apply_keystream(data),
+            StreamCipher::Chacha20(c) => c.apply_keystream(data),
+            StreamCipher::Chacha20Ietf(c) => c.apply_keystream(data),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(data: &[u8]) -> String {
+        data.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    fn unhex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_rc4_vector() {
+        let mut data = b"Plaintext".to_vec();
+        Rc4::new(b"Key").apply(&mut data);
+        assert_eq!(hex(&data), "bbf316e8d940af0ad3");
+    }
+
+    #[test]
+    fn test_aes_cfb_vector() {
+        // NIST SP 800-38A F.3.13 CFB128-AES128
+        let key = unhex("2b7e151628aed2a6abf7158809cf4f3c");
+        let iv = unhex("000102030405060708090a0b0c0d0e0f");
+        let mut data = unhex("6bc1bee22e409f96e93d7e117393172a");
+        StreamCipher::encryptor(StreamMethod::Aes128Cfb, &key, &iv).apply(&mut data);
+        assert_eq!(hex(&data), "3b3fd92eb72dad20333449f8e83cfb4a");
+    }
+
+    #[test]
+    fn test_roundtrip_in_chunks() {
+        for name in [
+            "none",
+            "rc4",
+            "rc4-md5",
+            "aes-128-cfb",
+            "aes-192-cfb",
+            "aes-256-cfb",
+            "aes-128-ctr",
+            "aes-256-ctr",
+            "chacha20",
+            "chacha20-ietf",
+        ] {
+            let method = StreamMethod::from_name(name).unwrap();
+            let key = vec![7u8; method.key_len()];
+            let iv = vec![9u8; method.iv_len()];
+            let plain: Vec<u8> = (0..1000u32).map(|i| i as u8).collect();
+
+            let mut data = plain.clone();
+            let mut enc = StreamCipher::encryptor(method, &key, &iv);
+            for chunk in data.chunks_mut(37) {
+                enc.apply(chunk);
+            }
+            if method != StreamMethod::None {
+                assert_ne!(data, plain, "{}", name);
+            }
+
+            let mut dec = StreamCipher::decryptor(method, &key, &iv);
+            for chunk in data.chunks_mut(101) {
+                dec.apply(chunk);
+            }
+            assert_eq!(data, plain, "{}", name);
+        }
+        assert!(StreamMethod::from_name("salsa20").is_err());
+    }
+}
@@ -0,0 +1,241 @@
+//! TLS 客户端封装
+//! 基于 rustls，支持自定义 SNI、ALPN 以及跳过证书校验
+
+use super::util::invalid_data;
+use crate::proxy::ProxyNode;
+use rustls::client::WebPkiServerVerifier;
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::CryptoProvider;
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{ClientConfig, DigitallySignedStruct, RootCertStore, SignatureScheme};
+use std::io;
+use std::sync::{Arc, OnceLock};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio_rustls::TlsConnector;
+use tokio_rustls::client::TlsStream;
+
+/// TLS 连接参数
+#[derive(Debug, Clone, Default)]
+pub struct TlsOptions {
+    /// 握手时使用的服务器名称（IP 地址不会作为 SNI 发送）
+    pub sni: String,
+    /// ALPN 协议列表
+    pub alpn: Vec<String>,
+    /// 是否跳过证书校验
+    pub insecure: bool,
+}
+
+impl TlsOptions {
+    /// 从节点的 TLS 配置构建，未配置 SNI 时使用服务器地址
+    pub fn from_node(proxy: &ProxyNode) -> Self {
+        Self {
+            sni: proxy.tls.server_name().unwrap_or(&proxy.server).to_string(),
+            alpn: proxy.tls.alpn.clone().unwrap_or_default(),
+            insecure: proxy.tls.skip_cert_verify.unwrap_or(false),
+        }
+    }
+}
+
+/// 统一使用 ring 作为加密后端
+pub fn crypto_provider() -> Arc<CryptoProvider> {
+    static PROVIDER: OnceLock<Arc<CryptoProvider>> = OnceLock::new();
+    PROVIDER
+        .get_or_init(|| Arc::new(rustls::crypto::ring::default_provider()))
+        .clone()
+}
+
+fn root_store() -> Arc<RootCertStore> {
+    static ROOTS: OnceLock<Arc<RootCertStore>> = OnceLock::new();
+    ROOTS
+        .get_or_init(|| {
+            let mut roots = RootCertStore::empty();
+            roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+            Arc::new(roots)
+        })
+        .clone()
+}
+
+/// 跳过证书链校验的验证器，仅校验握手签名
+#[derive(Debug)]
+struct InsecureVerifier(Arc<CryptoProvider>);
+
+impl ServerCertVerifier for InsecureVerifier {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+/// 构建证书校验器，`insecure` 为真时跳过证书链校验
+pub fn server_verifier(insecure: bool) -> Arc<dyn ServerCertVerifier> {
+    let provider = crypto_provider();
+    if insecure {
+        return Arc::new(InsecureVerifier(provider));
+    }
+    WebPkiServerVerifier::builder_with_provider(root_store(), provider)
+        .build()
+        .expect("证书校验器构建失败")
+}
+
+/// 用证书公钥校验 TLS 1.3 握手签名（CertificateVerify）
+pub fn verify_tls13_signature(
+    cert: &CertificateDer<'_>,
+    scheme: SignatureScheme,
+    message: &[u8],
+    signature: &[u8],
+) -> io::Result<()> {
+    let algorithms = crypto_provider()
+        .signature_verification_algorithms
+        .mapping
+        .iter()
+        .find(|(supported, _)| *supported == scheme)
+        .map(|(_, algorithms)| *algorithms)
+        .ok_or_else(|| invalid_data(format!("不支持的签名算法: {:?}", scheme)))?;
+    let cert = webpki::EndEntityCert::try_from(cert)
+        .map_err(|e| invalid_data(format!("证书解析失败: {:?}", e)))?;
+    if algorithms.iter().any(|algorithm| {
+        cert.verify_signature(*algorithm, message, signature)
+            .is_ok()
+    }) {
+        Ok(())
+    } else {
+        Err(invalid_data("握手签名校验失败"))
+    }
+}
+
+/// 根据连接参数构建 rustls 客户端配置
+pub fn client_config(options: &TlsOptions) -> Arc<ClientConfig> {
+    let provider = crypto_provider();
+    let builder = ClientConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()
+        .expect("TLS 协议版本配置错误");
+
+    let mut config = if options.insecure {
+        builder
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(InsecureVerifier(provider)))
+            .with_no_client_auth()
+    } else {
+        builder
+            .with_root_certificates(root_store())
+            .with_no_client_auth()
+    };
+    config.alpn_protocols = options
+        .alpn
+        .iter()
+        .map(|proto| proto.as_bytes().to_vec())
+        .collect();
+    Arc::new(config)
+}
+
+/// 在已建立的连接上完成 TLS 握手
+pub async fn connect<S>(stream: S, options: &TlsOptions) -> io::Result<TlsStream<S>>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let server_name = ServerName::try_from(options.sni.clone()).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("无效的 SNI {}: {}", options.sni, e),
+        )
+    })?;
+    TlsConnector::from(client_config(options))
+        .connect(server_name, stream)
+        .await
+}
+
+/// 测试用的自签名 TLS 服务端
+#[cfg(test)]
+pub mod test_server {
+    use super::crypto_provider;
+    use rustls::ServerConfig;
+    use rustls::pki_types::{PrivateKeyDer, PrivatePkcs8KeyDer};
+    use std::sync::Arc;
+    use tokio_rustls::TlsAcceptor;
+
+    /// 为指定域名生成自签名证书的服务端配置
+    pub fn server_config(domain: &str, alpn: &[&str]) -> Arc<ServerConfig> {
+        let certified = rcgen::generate_simple_self_signed(vec![domain.to_string()]).unwrap();
+        let key =
+            PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(certified.key_pair.serialize_der()));
+        let mut config = ServerConfig::builder_with_provider(crypto_provider())
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_no_client_auth()
+            .with_single_cert(vec![certified.cert.der().clone()], key)
+            .unwrap();
+        config.alpn_protocols = alpn.iter().map(|p| p.as_bytes().to_vec()).collect();
+        Arc::new(config)
+    }
+
+    /// 为指定域名生成自签名证书并返回 TLS 接收器
+    pub fn acceptor(domain: &str, alpn: &[&str]) -> TlsAcceptor {
+        TlsAcceptor::from(server_config(domain, alpn))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::Protocol;
+
+    #[test]
+    fn test_options_from_node() {
+        let mut node = ProxyNode::new(
+            "n".to_string(),
+            "1.2.3.4".to_string(),
+            443,
+            Protocol::Direct,
+        );
+        assert_eq!(TlsOptions::from_node(&node).sni, "1.2.3.4");
+
+        node.tls.servername = Some("example.com".to_string());
+        node.tls.skip_cert_verify = Some(true);
+        node.tls.alpn = Some(vec!["h2".to_string()]);
+        let options = TlsOptions::from_node(&node);
+        assert_eq!(options.sni, "example.com");
+        assert!(options.insecure);
+        assert_eq!(options.alpn, vec!["h2".to_string()]);
+
+        node.tls.sni = Some("sni.example.com".to_string());
+        assert_eq!(TlsOptions::from_node(&node).sni, "sni.example.com");
+    }
+}
@@ -0,0 +1,807 @@
+//! 最小 TLS 1.3 客户端
+//! REALITY 需要自定义 ClientHello 的 session id，Vision 需要在记录边界切换到裸连接，
+//! rustls 都无法做到，因此这里单独实现握手与记录层
+
+use super::crypto::{AeadCipher, AeadKind, TAG_LEN};
+use super::reality::RealityOptions;
+use super::tls;
+use super::util::{FramedWrite, copy_plain, invalid_data, poll_drain, poll_fill};
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
+use rustls::SignatureScheme;
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use sha2::{Digest, Sha256};
+use std::io;
+use std::net::IpAddr;
+use std::pin::Pin;
+use std::task::{Context, Poll, ready};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadBuf};
+use x25519_dalek::{PublicKey, StaticSecret};
+
+const RECORD_CHANGE_CIPHER_SPEC: u8 = 20;
+const RECORD_ALERT: u8 = 21;
+const RECORD_HANDSHAKE: u8 = 22;
+const RECORD_APPLICATION_DATA: u8 = 23;
+
+const HANDSHAKE_CLIENT_HELLO: u8 = 1;
+const HANDSHAKE_SERVER_HELLO: u8 = 2;
+const HANDSHAKE_ENCRYPTED_EXTENSIONS: u8 = 8;
+const HANDSHAKE_CERTIFICATE: u8 = 11;
+const HANDSHAKE_CERTIFICATE_REQUEST: u8 = 13;
+const HANDSHAKE_CERTIFICATE_VERIFY: u8 = 15;
+const HANDSHAKE_FINISHED: u8 = 20;
+const HANDSHAKE_KEY_UPDATE: u8 = 24;
+
+const EXT_SERVER_NAME: u16 = 0x0000;
+const EXT_SUPPORTED_GROUPS: u16 = 0x000a;
+const EXT_EC_POINT_FORMATS: u16 = 0x000b;
+const EXT_SIGNATURE_ALGORITHMS: u16 = 0x000d;
+const EXT_ALPN: u16 = 0x0010;
+const EXT_SUPPORTED_VERSIONS: u16 = 0x002b;
+const EXT_PSK_KEY_EXCHANGE_MODES: u16 = 0x002d;
+const EXT_KEY_SHARE: u16 = 0x0033;
+
+const GROUP_X25519: u16 = 0x001d;
+const TLS13: u16 = 0x0304;
+const TLS_AES_128_GCM_SHA256: u16 = 0x1301;
+const TLS_CHACHA20_POLY1305_SHA256: u16 = 0x1303;
+/// ClientHello 中声明支持的签名算法
+const SIGNATURE_ALGORITHMS: [u16; 9] = [
+    0x0403, 0x0804, 0x0401, 0x0503, 0x0805, 0x0501, 0x0806, 0x0601, 0x0807,
+];
+
+/// 单条记录的最大明文长度
+const MAX_PLAINTEXT: usize = 16384;
+/// 单条记录的最大密文长度
+const MAX_CIPHERTEXT: usize = MAX_PLAINTEXT + 256;
+/// 握手消息中 session id 的位置（消息头 4 + 版本 2 + 随机数 32 + 长度 1）
+const SESSION_ID_OFFSET: usize = 39;
+/// HelloRetryRequest 使用的固定随机数
+const HELLO_RETRY_RANDOM: [u8; 32] = [
+    0xcf, 0x21, 0xad, 0x74, 0xe5, 0x9a, 0x61, 0x11, 0xbe, 0x1d, 0x8c, 0x02, 0x1e, 0x65, 0xb8, 0x91,
+    0xc2, 0xa2, 0x11, 0x16, 0x7a, 0xbb, 0x8c, 0x5e, 0x07, 0x9e, 0x09, 0xe2, 0xc8, 0xa8, 0x33, 0x9c,
+];
+
+/// 服务端身份校验方式
+pub enum ServerAuth {
+    /// 常规证书链校验，`insecure` 为真时跳过链校验
+    Certificate { insecure: bool },
+    /// REALITY 临时证书校验
+    Reality(RealityOptions),
+}
+
+/// 顺序读取字节的解析器，越界时返回错误而不是 panic
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    fn take(&mut self, n: usize) -> io::Result<&'a [u8]> {
+        if self.data.len() < n {
+            return Err(invalid_data("TLS 消息长度不足"));
+        }
+        let (head, rest) = self.data.split_at(n);
+        self.data = rest;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> io::Result<u16> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u24(&mut self) -> io::Result<usize> {
+        let bytes = self.take(3)?;
+        Ok(((bytes[0] as usize) << 16) | ((bytes[1] as usize) << 8) | bytes[2] as usize)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+}
+
+fn hkdf_extract(salt: &[u8], ikm: &[u8]) -> Vec<u8> {
+    Hkdf::<Sha256>::extract(Some(salt), ikm).0.to_vec()
+}
+
+/// RFC 8446 HKDF-Expand-Label
+fn hkdf_expand_label(secret: &[u8], label: &[u8], context: &[u8], len: usize) -> Vec<u8> {
+    let mut info = Vec::with_capacity(4 + 6 + label.len() + context.len());
+    info.extend_from_slice(&(len as u16).to_be_bytes());
+    info.push((6 + label.len()) as u8);
+    info.extend_from_slice(b"tls13 ");
+    info.extend_from_slice(label);
+    info.push(context.len() as u8);
+    info.extend_from_slice(context);
+
+    let mut out = vec![0u8; len];
+    Hkdf::<Sha256>::from_prk(secret)
+        .expect("TLS 密钥长度错误")
+        .expand(&info, &mut out)
+        .expect("TLS 密钥派生长度错误");
+    out
+}
+
+/// 计算 Finished 消息的校验值
+fn finished_mac(secret: &[u8], transcript_hash: &[u8]) -> Hmac<Sha256> {
+    let key = hkdf_expand_label(secret, b"finished", &[], 32);
+    let mut mac = Hmac::<Sha256>::new_from_slice(&key).expect("HMAC 密钥长度错误");
+    mac.update(transcript_hash);
+    mac
+}
+
+/// 单方向的记录层加解密状态
+struct RecordCipher {
+    aead: AeadCipher,
+    iv: [u8; 12],
+    seq: u64,
+}
+
+impl RecordCipher {
+    fn new(kind: AeadKind, secret: &[u8]) -> Self {
+        let key = hkdf_expand_label(secret, b"key", &[], kind.key_len());
+        let mut iv = [0u8; 12];
+        iv.copy_from_slice(&hkdf_expand_label(secret, b"iv", &[], 12));
+        Self {
+            aead: AeadCipher::new(kind, &key),
+            iv,
+            seq: 0,
+        }
+    }
+
+    fn next_nonce(&mut self) -> [u8; 12] {
+        let mut nonce = self.iv;
+        for (byte, seq) in nonce[4..].iter_mut().zip(self.seq.to_be_bytes()) {
+            *byte ^= seq;
+        }
+        self.seq += 1;
+        nonce
+    }
+
+    /// 加密一条记录，返回包含记录头的完整密文
+    fn seal(&mut self, content_type: u8, data: &[u8]) -> Vec<u8> {
+        let mut inner = Vec::with_capacity(data.len() + 1);
+        inner.extend_from_slice(data);
+        inner.push(content_type);
+
+        let header = record_header(RECORD_APPLICATION_DATA, 0x0303, inner.len() + TAG_LEN);
+        let nonce = self.next_nonce();
+        let sealed = self.aead.encrypt_aad(&nonce, &inner, &header);
+
+        let mut record = header.to_vec();
+        record.extend_from_slice(&sealed);
+        record
+    }
+
+    /// 解密一条记录，返回真实的内容类型与明文
+    fn open(&mut self, header: &[u8], body: &[u8]) -> io::Result<(u8, Vec<u8>)> {
+        let nonce = self.next_nonce();
+        let mut plain = self.aead.decrypt_aad(&nonce, body, header)?;
+        while plain.last() == Some(&0) {
+            plain.pop();
+        }
+        let content_type = plain
+            .pop()
+            .ok_or_else(|| invalid_data("TLS 记录缺少内容类型"))?;
+        Ok((content_type, plain))
+    }
+}
+
+fn record_header(content_type: u8, version: u16, len: usize) -> [u8; 5] {
+    let version = version.to_be_bytes();
+    let len = (len as u16).to_be_bytes();
+    [content_type, version[0], version[1], len[0], len[1]]
+}
+
+fn plain_record(content_type: u8, version: u16, data: &[u8]) -> Vec<u8> {
+    let mut record = record_header(content_type, version, data.len()).to_vec();
+    record.extend_from_slice(data);
+    record
+}
+
+fn handshake_message(msg_type: u8, body: &[u8]) -> Vec<u8> {
+    let len = (body.len() as u32).to_be_bytes();
+    let mut msg = vec![msg_type, len[1], len[2], len[3]];
+    msg.extend_from_slice(body);
+    msg
+}
+
+fn push_extension(out: &mut Vec<u8>, ext_type: u16, data: &[u8]) {
+    out.extend_from_slice(&ext_type.to_be_bytes());
+    out.extend_from_slice(&(data.len() as u16).to_be_bytes());
+    out.extend_from_slice(data);
+}
+
+/// 构造 ClientHello 握手消息，session id 先填零，由调用方写入
+fn client_hello(random: &[u8; 32], sni: &str, alpn: &[String], key_share: &[u8; 32]) -> Vec<u8> {
+    let mut body = Vec::with_capacity(512);
+    body.extend_from_slice(&0x0303u16.to_be_bytes());
+    body.extend_from_slice(random);
+    body.push(32);
+    body.extend_from_slice(&[0u8; 32]);
+    body.extend_from_slice(&4u16.to_be_bytes());
+    body.extend_from_slice(&TLS_AES_128_GCM_SHA256.to_be_bytes());
+    body.extend_from_slice(&TLS_CHACHA20_POLY1305_SHA256.to_be_bytes());
+    body.extend_from_slice(&[1, 0]);
+
+    let mut extensions = Vec::with_capacity(256);
+    if !sni.is_empty() && sni.parse::<IpAddr>().is_err() {
+        let name = sni.as_bytes();
+        let mut data = Vec::with_capacity(name.len() + 5);
+        data.extend_from_slice(&((name.len() + 3) as u16).to_be_bytes());
+        data.push(0);
+        data.extend_from_slice(&(name.len() as u16).to_be_bytes());
+        data.extend_from_slice(name);
+        push_extension(&mut extensions, EXT_SERVER_NAME, &data);
+    }
+
+    let mut groups = 2u16.to_be_bytes().to_vec();
+    groups.extend_from_slice(&GROUP_X25519.to_be_bytes());
+    push_extension(&mut extensions, EXT_SUPPORTED_GROUPS, &groups);
+    push_extension(&mut extensions, EXT_EC_POINT_FORMATS, &[1, 0]);
+
+    let mut signatures = ((SIGNATURE_ALGORITHMS.len() * 2) as u16)
+        .to_be_bytes()
+        .to_vec();
+    for scheme in SIGNATURE_ALGORITHMS {
+        signatures.extend_from_slice(&scheme.to_be_bytes());
+    }
+    push_extension(&mut extensions, EXT_SIGNATURE_ALGORITHMS, &signatures);
+
+    if !alpn.is_empty() {
+        let mut protocols = Vec::new();
+        for proto in alpn {
+            protocols.push(proto.len() as u8);
+            protocols.extend_from_slice(proto.as_bytes());
+        }
+        let mut data = (protocols.len() as u16).to_be_bytes().to_vec();
+        data.extend_from_slice(&protocols);
+        push_extension(&mut extensions, EXT_ALPN, &data);
+    }
+
+    let mut versions = vec![2];
+    versions.extend_from_slice(&TLS13.to_be_bytes());
+    push_extension(&mut extensions, EXT_SUPPORTED_VERSIONS, &versions);
+    push_extension(&mut extensions, EXT_PSK_KEY_EXCHANGE_MODES, &[1, 1]);
+
+    let mut share = 36u16.to_be_bytes().to_vec();
+    share.extend_from_slice(&GROUP_X25519.to_be_bytes());
+    share.extend_from_slice(&32u16.to_be_bytes());
+    share.extend_from_slice(key_share);
+    push_extension(&mut extensions, EXT_KEY_SHARE, &share);
+
+    body.extend_from_slice(&(extensions.len() as u16).to_be_bytes());
+    body.extend_from_slice(&extensions);
+    handshake_message(HANDSHAKE_CLIENT_HELLO, &body)
+}
+
+/// 解析 ServerHello，返回协商的加密套件与服务端 X25519 公钥
+fn parse_server_hello(msg: &[u8]) -> io::Result<(AeadKind, [u8; 32])> {
+    let mut reader = Reader::new(msg);
+    if reader.u8()? != HANDSHAKE_SERVER_HELLO {
+        return Err(invalid_data("期望 ServerHello"));
+    }
+    reader.u24()?;
+    reader.u16()?;
+    if reader.take(32)? == HELLO_RETRY_RANDOM {
+        return Err(invalid_data("服务端要求 HelloRetryRequest，暂不支持"));
+    }
+    let session_id_len = reader.u8()? as usize;
+    reader.take(session_id_len)?;
+    let kind = match reader.u16()? {
+        TLS_AES_128_GCM_SHA256 => AeadKind::Aes128Gcm,
+        TLS_CHACHA20_POLY1305_SHA256 => AeadKind::ChaCha20Poly1305,
+        other => {
+            return Err(invalid_data(format!(
+                "服务端选择了未提供的加密套件: {:#06x}",
+                other
+            )));
+        }
+    };
+    reader.u8()?;
+
+    let ext_len = reader.u16()? as usize;
+    let mut extensions = Reader::new(reader.take(ext_len)?);
+    let mut version = None;
+    let mut server_share = None;
+    while !extensions.is_empty() {
+        let ext_type = extensions.u16()?;
+        let len = extensions.u16()? as usize;
+        let mut data = Reader::new(extensions.take(len)?);
+        match ext_type {
+            EXT_SUPPORTED_VERSIONS => version = Some(data.u16()?),
+            EXT_KEY_SHARE => {
+                if data.u16()? != GROUP_X25519 {
+                    return Err(invalid_data("服务端选择了不支持的密钥交换组"));
+                }
+                let key_len = data.u16()? as usize;
+                let key: [u8; 32] = data
+                    .take(key_len)?
+                    .try_into()
+                    .map_err(|_| invalid_data("X25519 公钥长度错误"))?;
+                server_share = Some(key);
+            }
+            _ => {}
+        }
+    }
+
+    if version != Some(TLS13) {
+        return Err(invalid_data("服务端不支持 TLS 1.3"));
+    }
+    let server_share = server_share.ok_or_else(|| invalid_data("ServerHello 缺少 key_share"))?;
+    Ok((kind, server_share))
+}
+
+/// 解析 Certificate 消息中的证书链
+fn parse_certificates(body: &[u8]) -> io::Result<Vec<CertificateDer<'static>>> {
+    let mut reader = Reader::new(body);
+    let context_len = reader.u8()? as usize;
+    reader.take(context_len)?;
+    let list_len = reader.u24()?;
+    let mut list = Reader::new(reader.take(list_len)?);
+
+    let mut certs = Vec::new();
+    while !list.is_empty() {
+        let cert_len = list.u24()?;
+        certs.push(CertificateDer::from(list.take(cert_len)?.to_vec()));
+        let ext_len = list.u16()? as usize;
+        list.take(ext_len)?;
+    }
+    if certs.is_empty() {
+        return Err(invalid_data("服务端未提供证书"));
+    }
+    Ok(certs)
+}
+
+fn alert_error(body: &[u8]) -> io::Error {
+    let description = body.get(1).copied().unwrap_or(0);
+    io::Error::new(
+        io::ErrorKind::ConnectionAborted,
+        format!("收到 TLS 警报: {}", description),
+    )
+}
+
+/// 读取一条完整记录，不会多读后续字节
+async fn read_record<S: AsyncRead + Unpin>(inner: &mut S) -> io::Result<([u8; 5], Vec<u8>)> {
+    let mut header = [0u8; 5];
+    inner.read_exact(&mut header).await?;
+    let len = u16::from_be_bytes([header[3], header[4]]) as usize;
+    if len > MAX_CIPHERTEXT {
+        return Err(invalid_data("TLS 记录过长"));
+    }
+    let mut body = vec![0u8; len];
+    inner.read_exact(&mut body).await?;
+    Ok((header, body))
+}
+
+/// 握手消息重组缓冲区，处理跨记录或同一记录内多条消息的情况
+#[derive(Default)]
+struct HandshakeReader {
+    buf: Vec<u8>,
+}
+
+impl HandshakeReader {
+    fn take_message(&mut self) -> Option<Vec<u8>> {
+        if self.buf.len() < 4 {
+            return None;
+        }
+        let len =
+            ((self.buf[1] as usize) << 16) | ((self.buf[2] as usize) << 8) | self.buf[3] as usize;
+        if self.buf.len() < 4 + len {
+            return None;
+        }
+        Some(self.buf.drain(..4 + len).collect())
+    }
+
+    /// 读取下一条握手消息，`cipher` 为空时读取明文记录
+    async fn next<S: AsyncRead + Unpin>(
+        &mut self,
+        inner: &mut S,
+        mut cipher: Option<&mut RecordCipher>,
+    ) -> io::Result<Vec<u8>> {
+        loop {
+            if let Some(msg) = self.take_message() {
+                return Ok(msg);
+            }
+
+            let (header, body) = read_record(inner).await?;
+            match (header[0], cipher.as_mut()) {
+                (RECORD_CHANGE_CIPHER_SPEC, _) => {}
+                (RECORD_ALERT, _) => return Err(alert_error(&body)),
+                (RECORD_HANDSHAKE, None) => self.buf.extend_from_slice(&body),
+                (RECORD_APPLICATION_DATA, Some(cipher)) => {
+                    let (content_type, plain) = cipher.open(&header, &body)?;
+                    match content_type {
+                        RECORD_HANDSHAKE => self.buf.extend_from_slice(&plain),
+                        RECORD_ALERT => return Err(alert_error(&plain)),
+                        _ => return Err(invalid_data("握手阶段收到非握手消息")),
+                    }
+                }
+                (other, _) => {
+                    return Err(invalid_data(format!(
+                        "握手阶段收到意外的记录类型: {}",
+                        other
+                    )));
+                }
+            }
+        }
+    }
+}
+
+/// 校验服务端证书
+fn verify_certificates(
+    auth: &ServerAuth,
+    auth_key: Option<&[u8; 32]>,
+    sni: &str,
+    certs: &[CertificateDer<'static>],
+) -> io::Result<()> {
+    match (auth, auth_key) {
+        (ServerAuth::Reality(_), Some(auth_key)) => {
+            RealityOptions::verify_certificate(auth_key, &certs[0])
+        }
+        (ServerAuth::Certificate { insecure }, _) => {
+            let server_name = ServerName::try_from(sni.to_string())
+                .map_err(|e| invalid_data(format!("无效的 SNI {}: {}", sni, e)))?;
+            tls::server_verifier(*insecure)
+                .verify_server_cert(&certs[0], &certs[1..], &server_name, &[], UnixTime::now())
+                .map_err(|e| invalid_data(format!("证书校验失败: {}", e)))?;
+            Ok(())
+        }
+        (ServerAuth::Reality(_), None) => Err(invalid_data("REALITY 认证密钥缺失")),
+    }
+}
+
+/// 校验 CertificateVerify 中的握手签名
+fn verify_handshake_signature(
+    cert: &CertificateDer<'static>,
+    body: &[u8],
+    transcript_hash: &[u8],
+) -> io::Result<()> {
+    let mut reader = Reader::new(body);
+    let scheme = SignatureScheme::from(reader.u16()?);
+    let sig_len = reader.u16()? as usize;
+    let signature = reader.take(sig_len)?;
+
+    let mut message = vec![0x20u8; 64];
+    message.extend_from_slice(b"TLS 1.3, server CertificateVerify\0");
+    message.extend_from_slice(transcript_hash);
+    tls::verify_tls13_signature(cert, scheme, &message, signature)
+}
+
+/// 在已建立的连接上完成 TLS 1.3 握手
+pub async fn connect<S>(
+    mut inner: S,
+    sni: &str,
+    alpn: &[String],
+    auth: &ServerAuth,
+) -> io::Result<Tls13Stream<S>>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let secret = StaticSecret::from(rand::random::<[u8; 32]>());
+    let key_share = PublicKey::from(&secret);
+    let random: [u8; 32] = rand::random();
+
+    let mut hello = client_hello(&random, sni, alpn, key_share.as_bytes());
+    let auth_key = match auth {
+        ServerAuth::Reality(options) => {
+            let auth_key = options.auth_key(&secret, &random);
+            let sealed = options.seal_session_id(&auth_key, &random, &hello);
+            hello[SESSION_ID_OFFSET..SESSION_ID_OFFSET + 32].copy_from_slice(&sealed);
+            Some(auth_key)
+        }
+        ServerAuth::Certificate { .. } => {
+            hello[SESSION_ID_OFFSET..SESSION_ID_OFFSET + 32]
+                .copy_from_slice(&rand::random::<[u8; 32]>());
+            None
+        }
+    };
+
+    let mut transcript = Sha256::new();
+    transcript.update(&hello);
+    inner
+        .write_all(&plain_record(RECORD_HANDSHAKE, 0x0301, &hello))
+        .await?;
+    inner.flush().await?;
+
+    let mut reader = HandshakeReader::default();
+    let server_hello = reader.next(&mut inner, None).await?;
+    let (kind, server_share) = parse_server_hello(&server_hello)?;
+    transcript.update(&server_hello);
+
+    let shared = secret.diffie_hellman(&PublicKey::from(server_share));
+    let empty_hash = Sha256::digest([]);
+    let early_secret = hkdf_extract(&[0u8; 32], &[0u8; 32]);
+    let derived = hkdf_expand_label(&early_secret, b"derived", &empty_hash, 32);
+    let handshake_secret = hkdf_extract(&derived, shared.as_bytes());
+    let hash = transcript.clone().finalize();
+    let client_hs = hkdf_expand_label(&handshake_secret, b"c hs traffic", &hash, 32);
+    let server_hs = hkdf_expand_label(&handshake_secret, b"s hs traffic", &hash, 32);
+    let derived = hkdf_expand_label(&handshake_secret, b"derived", &empty_hash, 32);
+    let master_secret = hkdf_extract(&derived, &[0u8; 32]);
+
+    let mut server_cipher = RecordCipher::new(kind, &server_hs);
+    let mut certs = Vec::new();
+    loop {
+        let msg = reader.next(&mut inner, Some(&mut server_cipher)).await?;
+        match msg[0] {
+            HANDSHAKE_ENCRYPTED_EXTENSIONS => {}
+            HANDSHAKE_CERTIFICATE => {
+                certs = parse_certificates(&msg[4..])?;
+                verify_certificates(auth, auth_key.as_ref(), sni, &certs)?;
+            }
+            HANDSHAKE_CERTIFICATE_VERIFY => {
+                // REALITY 的临时证书已由 HMAC 认证，无需再校验握手签名
+                if let (ServerAuth::Certificate { .. }, Some(cert)) = (auth, certs.first()) {
+                    verify_handshake_signature(cert, &msg[4..], &transcript.clone().finalize())?;
+                }
+            }
+            HANDSHAKE_FINISHED => {
+                if certs.is_empty() {
+                    return Err(invalid_data("服务端未提供证书"));
+                }
+                finished_mac(&server_hs, &transcript.clone().finalize())
+                    .verify_slice(&msg[4..])
+                    .map_err(|_| invalid_data("服务端 Finished 校验失败"))?;
+                transcript.update(&msg);
+                break;
+            }
+            HANDSHAKE_CERTIFICATE_REQUEST => {
+                return Err(invalid_data("服务端要求客户端证书，暂不支持"));
+            }
+            other => return Err(invalid_data(format!("意外的握手消息类型: {}", other))),
+        }
+        transcript.update(&msg);
+    }
+
+    let hash = transcript.finalize();
+    let client_ap = hkdf_expand_label(&master_secret, b"c ap traffic", &hash, 32);
+    let server_ap = hkdf_expand_label(&master_secret, b"s ap traffic", &hash, 32);
+
+    // 兼容模式下先发送一条 ChangeCipherSpec，再发送加密的 Finished
+    let verify_data = finished_mac(&client_hs, &hash).finalize().into_bytes();
+    let finished = handshake_message(HANDSHAKE_FINISHED, &verify_data);
+    let mut flight = plain_record(RECORD_CHANGE_CIPHER_SPEC, 0x0303, &[1]);
+    flight
+        .extend_from_slice(&RecordCipher::new(kind, &client_hs).seal(RECORD_HANDSHAKE, &finished));
+    inner.write_all(&flight).await?;
+    inner.flush().await?;
+
+    Ok(Tls13Stream {
+        inner,
+        read_cipher: RecordCipher::new(kind, &server_ap),
+        write_cipher: RecordCipher::new(kind, &client_ap),
+        rbuf: Vec::new(),
+        plain: Vec::new(),
+        plain_pos: 0,
+        wbuf: Vec::new(),
+        raw_read: false,
+        eof: false,
+        close_sent: false,
+    })
+}
+
+/// 已完成握手的 TLS 1.3 连接
+pub struct Tls13Stream<S> {
+    inner: S,
+    read_cipher: RecordCipher,
+    write_cipher: RecordCipher,
+    rbuf: Vec<u8>,
+    plain: Vec<u8>,
+    plain_pos: usize,
+    wbuf: Vec<u8>,
+    raw_read: bool,
+    eof: bool,
+    close_sent: bool,
+}
+
+impl<S> Tls13Stream<S> {
+    /// 之后的读取绕过 TLS 记录层，直接读取底层连接
+    ///
+    /// 已解密的明文与已读入但未解密的字节会先按原样交给调用方
+    pub fn switch_raw_read(&mut self) {
+        self.raw_read = true;
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> Tls13Stream<S> {
+    /// 处理一条完整的密文记录
+    fn process_record(&mut self, len: usize) -> io::Result<()> {
+        let record: Vec<u8> = self.rbuf.drain(..5 + len).collect();
+        let (content_type, plain) = self.read_cipher.open(&record[..5], &record[5..])?;
+        match content_type {
+            RECORD_APPLICATION_DATA => {
+                self.plain = plain;
+                self.plain_pos = 0;
+            }
+            // 握手后的 NewSessionTicket 直接忽略
+            RECORD_HANDSHAKE => {
+                if plain.first() == Some(&HANDSHAKE_KEY_UPDATE) {
+                    return Err(invalid_data("暂不支持 TLS KeyUpdate"));
+                }
+            }
+            RECORD_ALERT => {
+                if plain.get(1) == Some(&0) {
+                    self.eof = true;
+                } else {
+                    return Err(alert_error(&plain));
+                }
+            }
+            other => return Err(invalid_data(format!("意外的记录类型: {}", other))),
+        }
+        Ok(())
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> AsyncRead for Tls13Stream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        loop {
+            if copy_plain(&mut this.plain, &mut this.plain_pos, buf) || this.eof {
+                return Poll::Ready(Ok(()));
+            }
+
+            if this.raw_read {
+                if this.rbuf.is_empty() {
+                    return Pin::new(&mut this.inner).poll_read(cx, buf);
+                }
+                let n = this.rbuf.len().min(buf.remaining());
+                buf.put_slice(&this.rbuf[..n]);
+                this.rbuf.drain(..n);
+                return Poll::Ready(Ok(()));
+            }
+
+            if !ready!(poll_fill(&mut this.inner, cx, &mut this.rbuf, 5))? {
+                if this.rbuf.is_empty() {
+                    return Poll::Ready(Ok(()));
+                }
+                return Poll::Ready(Err(io::ErrorKind::UnexpectedEof.into()));
+            }
+            let len = u16::from_be_bytes([this.rbuf[3], this.rbuf[4]]) as usize;
+            if this.rbuf[0] != RECORD_APPLICATION_DATA || len > MAX_CIPHERTEXT {
+                return Poll::Ready(Err(invalid_data("TLS 记录格式错误")));
+            }
+            if !ready!(poll_fill(&mut this.inner, cx, &mut this.rbuf, 5 + len))? {
+                return Poll::Ready(Err(io::ErrorKind::UnexpectedEof.into()));
+            }
+            this.process_record(len)?;
+        }
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> FramedWrite for Tls13Stream<S> {
+    type Inner = S;
+
+    fn write_parts(&mut self) -> (&mut S, &mut Vec<u8>) {
+        (&mut self.inner, &mut self.wbuf)
+    }
+
+    fn encode_frame(&mut self, data: &[u8]) -> usize {
+        let n = data.len().min(MAX_PLAINTEXT);
+        let record = self.write_cipher.seal(RECORD_APPLICATION_DATA, &data[..n]);
+        self.wbuf.extend_from_slice(&record);
+        n
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> AsyncWrite for Tls13Stream<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.get_mut().poll_write_framed(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(poll_drain(&mut this.inner, cx, &mut this.wbuf))?;
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if !this.close_sent {
+            // close_notify 警报
+            let alert = this.write_cipher.seal(RECORD_ALERT, &[1, 0]);
+            this.wbuf.extend_from_slice(&alert);
+            this.close_sent = true;
+        }
+        ready!(poll_drain(&mut this.inner, cx, &mut this.wbuf))?;
+        Pin::new(&mut this.inner).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::outbound::tls::test_server;
+    use tokio::net::{TcpListener, TcpStream};
+
+    /// 启动基于 rustls 的 TLS 1.3 回显服务端
+    async fn spawn_echo_server(domain: &'static str) -> u16 {
+        let acceptor = test_server::acceptor(domain, &[]);
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (tcp, _) = listener.accept().await.unwrap();
+            let Ok(mut stream) = acceptor.accept(tcp).await else {
+                return;
+            };
+            assert_eq!(stream.get_ref().1.server_name(), Some(domain));
+            let mut buf = vec![0u8; 65536];
+            loop {
+                let n = stream.read(&mut buf).await.unwrap_or(0);
+                if n == 0 {
+                    break;
+                }
+                stream.write_all(&buf[..n]).await.unwrap();
+            }
+            stream.shutdown().await.ok();
+        });
+        port
+    }
+
+    #[tokio::test]
+    async fn test_tls13_roundtrip() {
+        let port = spawn_echo_server("tls13.test").await;
+        let tcp = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+        let auth = ServerAuth::Certificate { insecure: true };
+        let mut stream = connect(tcp, "tls13.test", &[], &auth).await.unwrap();
+
+        // 超过单条记录长度，验证分片与重组
+        let payload: Vec<u8> = (0..40000u32).map(|i| i as u8).collect();
+        stream.write_all(&payload).await.unwrap();
+        let mut echoed = vec![0u8; payload.len()];
+        stream.read_exact(&mut echoed).await.unwrap();
+        assert_eq!(echoed, payload);
+
+        stream.shutdown().await.unwrap();
+        let mut rest = Vec::new();
+        stream.read_to_end(&mut rest).await.unwrap();
+        assert!(rest.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_tls13_rejects_untrusted_cert() {
+        let port = spawn_echo_server("tls13.test").await;
+        let tcp = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+        let auth = ServerAuth::Certificate { insecure: false };
+        assert!(connect(tcp, "tls13.test", &[], &auth).await.is_err());
+    }
+
+    #[test]
+    fn test_hkdf_expand_label() {
+        // RFC 8448 简单 1-RTT 握手中的 early secret 与 derived secret
+        let early = hkdf_extract(&[0u8; 32], &[0u8; 32]);
+        assert_eq!(early[..8], [0x33, 0xad, 0x0a, 0x1c, 0x60, 0x7e, 0xc0, 0x3b]);
+        let derived = hkdf_expand_label(&early, b"derived", &Sha256::digest([]), 32);
+        assert_eq!(
+            derived[..8],
+            [0x6f, 0x26, 0x15, 0xa1, 0x08, 0xc7, 0x02, 0xc5]
+        );
+    }
+
+    #[test]
+    fn test_client_hello_layout() {
+        let hello = client_hello(&[7u8; 32], "example.com", &["h2".to_string()], &[9u8; 32]);
+        assert_eq!(hello[0], HANDSHAKE_CLIENT_HELLO);
+        let len = ((hello[1] as usize) << 16) | ((hello[2] as usize) << 8) | hello[3] as usize;
+        assert_eq!(len, hello.len() - 4);
+        assert_eq!(&hello[6..38], &[7u8; 32]);
+        assert_eq!(hello[SESSION_ID_OFFSET - 1], 32);
+        assert_eq!(&hello[hello.len() - 32..], &[9u8; 32]);
+    }
+}
@@ -0,0 +1,371 @@
+//! HTTP/2 与 gRPC 传输
+//! 每条代理连接单独建立一条 HTTP/2 连接并只打开一个流：
+//! HTTP/2 传输直接透传请求体与响应体，gRPC 按 v2ray gun 格式（Hunk 消息）封装
+
+use super::{HttpOptions, percent_encode};
+use crate::outbound::util::{copy_plain, invalid_data};
+use bytes::Bytes;
+use h2::client::{self, ResponseFuture};
+use h2::{RecvStream, SendStream};
+use http::{Method, Request, StatusCode};
+use log::debug;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll, ready};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+/// 单次写入封装的最大数据量
+const MAX_CHUNK: usize = 16384;
+const GRPC_USER_AGENT: &str = "grpc-go/1.60.1";
+
+/// 请求体与响应体的封装方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Framing {
+    /// 原样透传
+    Raw,
+    /// gRPC 消息：压缩标志 + 长度 + protobuf（字段 1 为数据）
+    Grpc,
+}
+
+fn h2_error(e: h2::Error) -> io::Error {
+    if e.is_io() {
+        e.into_io().unwrap()
+    } else {
+        io::Error::other(e)
+    }
+}
+
+/// 编码一条 gRPC 消息
+fn encode_grpc(data: &[u8]) -> Bytes {
+    let mut varint = Vec::with_capacity(5);
+    let mut len = data.len();
+    while len >= 0x80 {
+        varint.push((len as u8) | 0x80);
+        len >>= 7;
+    }
+    varint.push(len as u8);
+
+    let message_len = 1 + varint.len() + data.len();
+    let mut message = Vec::with_capacity(5 + message_len);
+    message.push(0);
+    message.extend_from_slice(&(message_len as u32).to_be_bytes());
+    message.push(0x0a);
+    message.extend_from_slice(&varint);
+    message.extend_from_slice(data);
+    Bytes::from(message)
+}
+
+/// 从缓冲区头部解出一条完整的 gRPC 消息，数据不足时返回 `None`
+fn decode_grpc(buf: &mut Vec<u8>) -> io::Result<Option<Vec<u8>>> {
+    if buf.len() < 5 {
+        return Ok(None);
+    }
+    let message_len = u32::from_be_bytes(buf[1..5].try_into().unwrap()) as usize;
+    if buf.len() < 5 + message_len {
+        return Ok(None);
+    }
+    let message: Vec<u8> = buf.drain(..5 + message_len).skip(5).collect();
+    if buf.is_empty() {
+        buf.shrink_to(MAX_CHUNK * 2);
+    }
+    if message.is_empty() {
+        return Ok(Some(Vec::new()));
+    }
+
+    if message[0] != 0x0a {
+        return Err(invalid_data("gRPC 消息格式错误"));
+    }
+    let mut len = 0usize;
+    let mut pos = 1;
+    for shift in (0..35).step_by(7) {
+        let byte = *message
+            .get(pos)
+            .ok_or_else(|| invalid_data("gRPC 消息长度不完整"))?;
+        pos += 1;
+        len |= ((byte & 0x7f) as usize) << shift;
+        if byte & 0x80 == 0 {
+            break;
+        }
+    }
+    if message.len() - pos != len {
+        return Err(invalid_data("gRPC 消息长度不一致"));
+    }
+    Ok(Some(message[pos..].to_vec()))
+}
+
+/// 基于单个 HTTP/2 流的双向连接
+pub struct H2Stream {
+    send: SendStream<Bytes>,
+    response: Option<ResponseFuture>,
+    recv: Option<RecvStream>,
+    framing: Framing,
+    wbuf: Bytes,
+    rbuf: Vec<u8>,
+    plain: Vec<u8>,
+    plain_pos: usize,
+    eof: bool,
+    end_sent: bool,
+}
+
+impl H2Stream {
+    /// 建立 HTTP/2 连接并发出请求，不等待响应头即可开始写入
+    pub async fn connect<S>(
+        stream: S,
+        options: &HttpOptions,
+        framing: Framing,
+        tls: bool,
+    ) -> io::Result<Self>
+    where
+        S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    {
+        let (send_request, connection) = client::handshake(stream).await.map_err(h2_error)?;
+        tokio::spawn(async move {
+            if let Err(e) = connection.await {
+                debug!("HTTP/2 连接结束: {}", e);
+            }
+        });
+        let mut send_request = send_request.ready().await.map_err(h2_error)?;
+
+        let (method, path) = match framing {
+            Framing::Raw => (Method::PUT, options.path.clone()),
+            Framing::Grpc => (
+                Method::POST,
+                format!(
+                    "/{}/Tun",
+                    percent_encode(options.path.trim_matches('/'), b"-._~")
+                ),
+            ),
+        };
+        let scheme = if tls { "https" } else { "http" };
+        let mut request = Request::builder()
+            .method(method)
+            .uri(format!("{}://{}{}", scheme, options.host, path));
+        if framing == Framing::Grpc {
+            request = request
+                .header("content-type", "application/grpc")
+                .header("te", "trailers")
+                .header("user-agent", GRPC_USER_AGENT);
+        }
+        for (name, value) in &options.headers {
+            request = request.header(name.as_str(), value.as_str());
+        }
+        let request = request
+            .body(())
+            .map_err(|e| invalid_data(format!("HTTP/2 请求构造失败: {}", e)))?;
+
+        let (response, send) = send_request
+            .send_request(request, false)
+            .map_err(h2_error)?;
+        Ok(Self {
+            send,
+            response: Some(response),
+            recv: None,
+            framing,
+            wbuf: Bytes::new(),
+            rbuf: Vec::new(),
+            plain: Vec::new(),
+            plain_pos: 0,
+            eof: false,
+            end_sent: false,
+        })
+    }
+
+    /// 按流控窗口把待发送数据交给 HTTP/2 连接
+    fn poll_send(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while !self.wbuf.is_empty() {
+            self.send.reserve_capacity(self.wbuf.len());
+            let capacity = match ready!(self.send.poll_capacity(cx)) {
+                Some(capacity) => capacity.map_err(h2_error)?,
+                None => return Poll::Ready(Err(io::ErrorKind::BrokenPipe.into())),
+            };
+            if capacity == 0 {
+                continue;
+            }
+            let chunk = self.wbuf.split_to(capacity.min(self.wbuf.len()));
+            self.send.send_data(chunk, false).map_err(h2_error)?;
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncRead for H2Stream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        loop {
+            if copy_plain(&mut this.plain, &mut this.plain_pos, buf) || this.eof {
+                return Poll::Ready(Ok(()));
+            }
+            if let Some(response) = this.response.as_mut() {
+                let response = ready!(Pin::new(response).poll(cx)).map_err(h2_error)?;
+                this.response = None;
+                if response.status() != StatusCode::OK {
+                    return Poll::Ready(Err(invalid_data(format!(
+                        "HTTP/2 响应状态异常: {}",
+                        response.status()
+                    ))));
+                }
+                this.recv = Some(response.into_body());
+            }
+            if this.framing == Framing::Grpc
+                && let Some(data) = decode_grpc(&mut this.rbuf)?
+            {
+                this.plain = data;
+                this.plain_pos = 0;
+                continue;
+            }
+
+            let recv = this.recv.as_mut().expect("响应体已就绪");
+            match ready!(recv.poll_data(cx)) {
+                Some(Ok(data)) => {
+                    recv.flow_control()
+                        .release_capacity(data.len())
+                        .map_err(h2_error)?;
+                    match this.framing {
+                        Framing::Raw => {
+                            this.plain = data.to_vec();
+                            this.plain_pos = 0;
+                        }
+                        Framing::Grpc => this.rbuf.extend_from_slice(&data),
+                    }
+                }
+                Some(Err(e)) => return Poll::Ready(Err(h2_error(e))),
+                None => this.eof = true,
+            }
+        }
+    }
+}
+
+impl AsyncWrite for H2Stream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        ready!(this.poll_send(cx))?;
+        if buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+
+        let n = buf.len().min(MAX_CHUNK);
+        this.wbuf = match this.framing {
+            Framing::Raw => Bytes::copy_from_slice(&buf[..n]),
+            Framing::Grpc => encode_grpc(&buf[..n]),
+        };
+        if let Poll::Ready(Err(e)) = this.poll_send(cx) {
+            return Poll::Ready(Err(e));
+        }
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        // 数据帧由后台连接任务负责写出
+        self.get_mut().poll_send(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_send(cx))?;
+        if !this.end_sent {
+            this.end_sent = true;
+            this.send.send_data(Bytes::new(), true).map_err(h2_error)?;
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+
+    /// 本地 HTTP/2 服务端替身：校验请求行后原样回显请求体
+    async fn spawn_echo_server() -> (u16, tokio::task::JoinHandle<http::request::Parts>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let handle = tokio::spawn(async move {
+            let (sock, _) = listener.accept().await.unwrap();
+            let mut connection = h2::server::handshake(sock).await.unwrap();
+            let (request, mut respond) = connection.accept().await.unwrap().unwrap();
+            tokio::spawn(async move { while connection.accept().await.is_some() {} });
+
+            let (parts, mut body) = request.into_parts();
+            let response = http::Response::new(());
+            let mut send = respond.send_response(response, false).unwrap();
+            while let Some(data) = body.data().await {
+                let data = data.unwrap();
+                body.flow_control().release_capacity(data.len()).unwrap();
+                send.send_data(data, false).unwrap();
+            }
+            send.send_data(Bytes::new(), true).unwrap();
+            parts
+        });
+        (port, handle)
+    }
+
+    fn options(path: &str) -> HttpOptions {
+        HttpOptions {
+            path: path.to_string(),
+            host: "h2.example.com".to_string(),
+            headers: Vec::new(),
+            max_early_data: 0,
+        }
+    }
+
+    #[test]
+    fn test_grpc_codec() {
+        for len in [0, 1, 127, 128, 20000] {
+            let data = vec![3u8; len];
+            let mut buf = encode_grpc(&data).to_vec();
+            buf.extend_from_slice(&encode_grpc(b"next"));
+            assert_eq!(decode_grpc(&mut buf).unwrap().unwrap(), data);
+            assert_eq!(decode_grpc(&mut buf).unwrap().unwrap(), b"next");
+            assert!(decode_grpc(&mut buf).unwrap().is_none());
+        }
+        let mut partial = encode_grpc(b"partial").to_vec();
+        partial.pop();
+        assert!(decode_grpc(&mut partial).unwrap().is_none());
+    }
+
+    async fn roundtrip(framing: Framing, path: &str) -> http::request::Parts {
+        let (port, server) = spawn_echo_server().await;
+        let tcp = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+        let mut stream = H2Stream::connect(tcp, &options(path), framing, false)
+            .await
+            .unwrap();
+
+        let data: Vec<u8> = (0..100_000u32).map(|i| i as u8).collect();
+        stream.write_all(&data).await.unwrap();
+        stream.flush().await.unwrap();
+        let mut echoed = vec![0u8; data.len()];
+        stream.read_exact(&mut echoed).await.unwrap();
+        assert_eq!(echoed, data);
+        stream.shutdown().await.unwrap();
+        let mut rest = Vec::new();
+        stream.read_to_end(&mut rest).await.unwrap();
+        assert!(rest.is_empty());
+        server.await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_h2_roundtrip() {
+        let parts = roundtrip(Framing::Raw, "/h2").await;
+        assert_eq!(parts.method, Method::PUT);
+        assert_eq!(parts.uri.path(), "/h2");
+        assert_eq!(parts.uri.authority().unwrap().as_str(), "h2.example.com");
+    }
+
+    #[tokio::test]
+    async fn test_grpc_roundtrip() {
+        let parts = roundtrip(Framing::Grpc, "UpdateService").await;
+        assert_eq!(parts.method, Method::POST);
+        assert_eq!(parts.uri.path(), "/UpdateService/Tun");
+        assert_eq!(parts.headers["content-type"], "application/grpc");
+    }
+}
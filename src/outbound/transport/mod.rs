@@ -0,0 +1,323 @@
+//! 传输层
+//! 在 TCP / TLS 之上叠加 WebSocket、gRPC、HTTP/2、HTTPUpgrade，各协议出站共用
+//!
+//! 参数取自节点的 `network` 与 `ws-opts`、`h2-opts`、`grpc-opts`，
+//! `ws-opts` 开启 `v2ray-http-upgrade` 时使用 HTTPUpgrade
+
+mod http2;
+mod websocket;
+
+use super::tls::{self, TlsOptions};
+use super::{BoxedStream, connect_tcp};
+use crate::proxy::ProxyNode;
+use crate::proxy::node::Network;
+use anyhow::{Result, anyhow};
+use std::io;
+
+pub use self::http2::{Framing, H2Stream};
+pub use self::websocket::{UpgradedStream, WsStream};
+
+/// WebSocket / HTTPUpgrade / HTTP/2 / gRPC 共用的 HTTP 参数
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HttpOptions {
+    /// 请求路径（已编码）；gRPC 为服务名
+    pub path: String,
+    /// Host 头，未配置时使用 TLS SNI 或服务器地址
+    pub host: String,
+    /// 其它自定义请求头
+    pub headers: Vec<(String, String)>,
+    /// WebSocket 早期数据上限（路径中的 `ed` 参数），0 表示不使用
+    pub max_early_data: usize,
+}
+
+/// 传输层类型
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Transport {
+    Tcp,
+    Ws(HttpOptions),
+    HttpUpgrade(HttpOptions),
+    Grpc(HttpOptions),
+    H2(HttpOptions),
+}
+
+impl Transport {
+    /// 从节点的传输层配置解析
+    pub fn from_node(proxy: &ProxyNode) -> Result<Self> {
+        if let Some(network) = proxy.unsupported_network() {
+            return Err(anyhow!("不支持的传输方式: {}", network));
+        }
+        let settings = &proxy.transport;
+        let network = settings.network.unwrap_or(Network::Tcp);
+        let mut options = HttpOptions::default();
+
+        let transport = match network {
+            Network::Tcp => return Ok(Transport::Tcp),
+            Network::Ws => {
+                let ws = settings.ws_opts.clone().unwrap_or_default();
+                for (name, value) in ws.headers.unwrap_or_default() {
+                    if name.eq_ignore_ascii_case("host") {
+                        options.host = value;
+                    } else {
+                        options.headers.push((name, value));
+                    }
+                }
+                let path = ws.path.as_deref().unwrap_or("").trim();
+                if ws.v2ray_http_upgrade.unwrap_or(false) {
+                    options.path = request_path(path);
+                    Transport::HttpUpgrade(options)
+                } else {
+                    let (path, max_early_data) = split_early_data(path);
+                    options.path = request_path(&path);
+                    options.max_early_data =
+                        ws.max_early_data.map_or(max_early_data, |n| n as usize);
+                    Transport::Ws(options)
+                }
+            }
+            Network::Grpc => {
+                options.path = settings
+                    .grpc_opts
+                    .as_ref()
+                    .and_then(|grpc| grpc.grpc_service_name.as_deref())
+                    .unwrap_or("")
+                    .trim()
+                    .to_string();
+                Transport::Grpc(options)
+            }
+            Network::H2 => {
+                let h2 = settings.h2_opts.clone().unwrap_or_default();
+                options.host = h2
+                    .host
+                    .and_then(|hosts| hosts.into_iter().next())
+                    .unwrap_or_default();
+                options.path = request_path(h2.path.as_deref().unwrap_or("").trim());
+                Transport::H2(options)
+            }
+            Network::Http => return Err(anyhow!("不支持的传输方式: http")),
+        };
+        Ok(transport.with_default_host(proxy))
+    }
+
+    /// 未配置 Host 头时使用 TLS SNI 或服务器地址
+    fn with_default_host(mut self, proxy: &ProxyNode) -> Self {
+        if let Transport::Ws(options)
+        | Transport::HttpUpgrade(options)
+        | Transport::Grpc(options)
+        | Transport::H2(options) = &mut self
+            && options.host.is_empty()
+        {
+            options.host = TlsOptions::from_node(proxy).sni;
+        }
+        self
+    }
+
+    /// 传输层要求的 ALPN，为空表示不限制
+    pub fn alpn(&self) -> Vec<String> {
+        match self {
+            Transport::Tcp => Vec::new(),
+            Transport::Ws(_) | Transport::HttpUpgrade(_) => vec!["http/1.1".to_string()],
+            Transport::Grpc(_) | Transport::H2(_) => vec!["h2".to_string()],
+        }
+    }
+
+    /// 在已建立（可能已加密）的连接上完成传输层握手
+    pub async fn handshake(&self, stream: BoxedStream, tls: bool) -> io::Result<BoxedStream> {
+        Ok(match self {
+            Transport::Tcp => stream,
+            Transport::Ws(options) => Box::new(WsStream::connect(stream, options).await?),
+            Transport::HttpUpgrade(options) => {
+                Box::new(UpgradedStream::connect(stream, options).await?)
+            }
+            Transport::Grpc(options) => {
+                Box::new(H2Stream::connect(stream, options, Framing::Grpc, tls).await?)
+            }
+            Transport::H2(options) => {
+                Box::new(H2Stream::connect(stream, options, Framing::Raw, tls).await?)
+            }
+        })
+    }
+}
+
+/// 连接代理服务器：TCP，按需叠加 TLS，再叠加节点配置的传输层
+pub async fn connect(proxy: &ProxyNode, tls: bool) -> Result<BoxedStream> {
+    let transport = Transport::from_node(proxy)?;
+    let tcp = connect_tcp(&proxy.server, proxy.port)
+        .await
+        .map_err(|e| anyhow!("连接服务器失败: {}", e))?;
+
+    let stream: BoxedStream = if tls {
+        let mut options = TlsOptions::from_node(proxy);
+        // 传输层对 ALPN 有硬性要求时覆盖节点配置
+        let alpn = transport.alpn();
+        if !alpn.is_empty() {
+            options.alpn = alpn;
+        }
+        Box::new(
+            tls::connect(tcp, &options)
+                .await
+                .map_err(|e| anyhow!("TLS 握手失败: {}", e))?,
+        )
+    } else {
+        Box::new(tcp)
+    };
+
+    transport
+        .handshake(stream, tls)
+        .await
+        .map_err(|e| anyhow!("传输层握手失败: {}", e))
+}
+
+/// 从 WebSocket 路径中取出 `ed` 早期数据参数，返回剩余路径与早期数据上限
+fn split_early_data(path: &str) -> (String, usize) {
+    let Some((base, query)) = path.split_once('?') else {
+        return (path.to_string(), 0);
+    };
+    let mut max_early_data = 0;
+    let mut rest = Vec::new();
+    for pair in query.split('&') {
+        match pair.strip_prefix("ed=").map(|n| n.parse::<usize>()) {
+            Some(Ok(n)) => max_early_data = n,
+            _ => rest.push(pair),
+        }
+    }
+    if rest.is_empty() {
+        (base.to_string(), max_early_data)
+    } else {
+        (format!("{}?{}", base, rest.join("&")), max_early_data)
+    }
+}
+
+/// 规范化请求路径：补全前导 `/`，并对空白与非 ASCII 字符做百分号编码
+fn request_path(path: &str) -> String {
+    // 部分订阅把整个路径编码过（如 `%2F`）
+    let path = if path.starts_with('/') || !path.contains('%') {
+        path.to_string()
+    } else {
+        urlencoding::decode(path)
+            .map(|p| p.into_owned())
+            .unwrap_or_else(|_| path.to_string())
+    };
+    let mut encoded = String::with_capacity(path.len() + 1);
+    if !path.starts_with('/') {
+        encoded.push('/');
+    }
+    encoded.push_str(&percent_encode(&path, b"/?&=%:@!$'()*+,;-._~"));
+    encoded
+}
+
+/// 对字母数字与 `keep` 之外的字节做百分号编码
+fn percent_encode(input: &str, keep: &[u8]) -> String {
+    let mut out = String::with_capacity(input.len());
+    for &byte in input.as_bytes() {
+        if byte.is_ascii_alphanumeric() || keep.contains(&byte) {
+            out.push(byte as char);
+        } else {
+            out.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::Protocol;
+    use crate::proxy::node::{GrpcOpts, H2Opts, WsOpts};
+
+    fn node(network: Network) -> ProxyNode {
+        let mut node = ProxyNode::new(
+            "node".to_string(),
+            "1.2.3.4".to_string(),
+            443,
+            Protocol::Direct,
+        );
+        node.transport.network = Some(network);
+        node
+    }
+
+    #[test]
+    fn test_split_early_data() {
+        assert_eq!(split_early_data("/?ed=2048"), ("/".to_string(), 2048));
+        assert_eq!(
+            split_early_data("/ws?ed=2560&token=1"),
+            ("/ws?token=1".to_string(), 2560)
+        );
+        assert_eq!(split_early_data("/ws"), ("/ws".to_string(), 0));
+        assert_eq!(
+            split_early_data("/ws?ed=abc"),
+            ("/ws?ed=abc".to_string(), 0)
+        );
+    }
+
+    #[test]
+    fn test_request_path() {
+        assert_eq!(request_path(""), "/");
+        assert_eq!(request_path("%2F"), "/");
+        assert_eq!(request_path("ws"), "/ws");
+        assert_eq!(request_path("/a b/ç"), "/a%20b/%C3%A7");
+        assert_eq!(request_path("/?token=a&x=1"), "/?token=a&x=1");
+    }
+
+    #[test]
+    fn test_transport_from_node() {
+        assert_eq!(
+            Transport::from_node(&node(Network::Tcp)).unwrap(),
+            Transport::Tcp
+        );
+
+        let mut ws = node(Network::Ws);
+        ws.tls.sni = Some("sni.example.com".to_string());
+        ws.transport.ws_opts = Some(WsOpts {
+            path: Some("/?ed=2048".to_string()),
+            headers: Some(
+                [("Host", "cdn.example.com"), ("User-Agent", "Mozilla/5.0")]
+                    .into_iter()
+                    .map(|(k, v)| (k.to_string(), v.to_string()))
+                    .collect(),
+            ),
+            ..Default::default()
+        });
+        assert_eq!(
+            Transport::from_node(&ws).unwrap(),
+            Transport::Ws(HttpOptions {
+                path: "/".to_string(),
+                host: "cdn.example.com".to_string(),
+                headers: vec![("User-Agent".to_string(), "Mozilla/5.0".to_string())],
+                max_early_data: 2048,
+            })
+        );
+
+        // 未配置 Host 头时回落到 SNI / 服务器地址
+        let mut grpc = node(Network::Grpc);
+        grpc.transport.grpc_opts = Some(GrpcOpts {
+            grpc_service_name: Some("UpdateService".to_string()),
+        });
+        let Transport::Grpc(grpc) = Transport::from_node(&grpc).unwrap() else {
+            panic!("应解析为 gRPC");
+        };
+        assert_eq!(grpc.path, "UpdateService");
+        assert_eq!(grpc.host, "1.2.3.4");
+
+        let mut h2 = node(Network::H2);
+        h2.transport.h2_opts = Some(H2Opts {
+            host: Some(vec!["h2.example.com".to_string()]),
+            path: Some("/h2".to_string()),
+        });
+        let Transport::H2(h2) = Transport::from_node(&h2).unwrap() else {
+            panic!("应解析为 HTTP/2");
+        };
+        assert_eq!(h2.host, "h2.example.com");
+        assert_eq!(h2.path, "/h2");
+
+        let mut upgrade = node(Network::Ws);
+        upgrade.transport.ws_opts = Some(WsOpts {
+            path: Some("/up".to_string()),
+            v2ray_http_upgrade: Some(true),
+            ..Default::default()
+        });
+        assert!(matches!(
+            Transport::from_node(&upgrade).unwrap(),
+            Transport::HttpUpgrade(_)
+        ));
+        assert!(Transport::from_node(&node(Network::Http)).is_err());
+    }
+}
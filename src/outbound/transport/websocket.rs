@@ -0,0 +1,596 @@
+//! WebSocket 与 HTTPUpgrade 传输
+//! 两者都以 HTTP/1.1 Upgrade 握手开始：WebSocket 之后按帧收发，HTTPUpgrade 之后即为原始数据流
+//!
+//! WebSocket 早期数据（`ed`）与 Xray 一致：首包的前若干字节以 base64url 放进
+//! `Sec-WebSocket-Protocol` 头，随握手请求一起发出
+
+use super::HttpOptions;
+use crate::outbound::util::{copy_plain, invalid_data, poll_drain, poll_fill};
+use base64::Engine;
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use sha1::{Digest, Sha1};
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll, ready};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadBuf};
+
+const WS_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+const EARLY_DATA_HEADER: &str = "Sec-WebSocket-Protocol";
+const OPCODE_CONTINUATION: u8 = 0x0;
+const OPCODE_TEXT: u8 = 0x1;
+const OPCODE_BINARY: u8 = 0x2;
+const OPCODE_CLOSE: u8 = 0x8;
+const OPCODE_PING: u8 = 0x9;
+const OPCODE_PONG: u8 = 0xa;
+/// 单帧最大负载
+const MAX_FRAME_PAYLOAD: usize = 16384;
+/// 响应头长度上限
+const MAX_RESPONSE_HEADER: usize = 8192;
+
+/// 构造 Upgrade 请求
+fn upgrade_request(options: &HttpOptions, ws_key: Option<&str>, early_data: &[u8]) -> Vec<u8> {
+    let mut request = format!(
+        "GET {} HTTP/1.1\r\nHost: {}\r\nConnection: Upgrade\r\nUpgrade: websocket\r\n",
+        options.path, options.host
+    );
+    if let Some(key) = ws_key {
+        request.push_str(&format!(
+            "Sec-WebSocket-Key: {}\r\nSec-WebSocket-Version: 13\r\n",
+            key
+        ));
+    }
+    for (name, value) in &options.headers {
+        request.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    if !early_data.is_empty() {
+        request.push_str(&format!(
+            "{}: {}\r\n",
+            EARLY_DATA_HEADER,
+            URL_SAFE_NO_PAD.encode(early_data)
+        ));
+    }
+    request.push_str("\r\n");
+    request.into_bytes()
+}
+
+/// 发送 Upgrade 请求并读取 101 响应，返回响应头与其后多读到的数据
+async fn upgrade<S: AsyncRead + AsyncWrite + Unpin>(
+    stream: &mut S,
+    request: &[u8],
+) -> io::Result<(String, Vec<u8>)> {
+    stream.write_all(request).await?;
+    stream.flush().await?;
+
+    let mut buf = Vec::new();
+    let mut tmp = [0u8; 1024];
+    let end = loop {
+        if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos + 4;
+        }
+        if buf.len() > MAX_RESPONSE_HEADER {
+            return Err(invalid_data("Upgrade 响应头过长"));
+        }
+        let n = stream.read(&mut tmp).await?;
+        if n == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        buf.extend_from_slice(&tmp[..n]);
+    };
+
+    let header = String::from_utf8_lossy(&buf[..end]).into_owned();
+    let status = header.lines().next().unwrap_or("");
+    if status.split_whitespace().nth(1) != Some("101") {
+        return Err(invalid_data(format!("Upgrade 失败: {}", status)));
+    }
+    Ok((header, buf.split_off(end)))
+}
+
+/// 在响应头中查找指定字段（不区分大小写）
+fn header_value<'a>(header: &'a str, name: &str) -> Option<&'a str> {
+    header.lines().skip(1).find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        key.trim()
+            .eq_ignore_ascii_case(name)
+            .then_some(value.trim())
+    })
+}
+
+/// 完成 WebSocket 握手，返回握手后多读到的数据
+async fn ws_handshake<S: AsyncRead + AsyncWrite + Unpin>(
+    stream: &mut S,
+    options: &HttpOptions,
+    early_data: &[u8],
+) -> io::Result<Vec<u8>> {
+    let key = STANDARD.encode(rand::random::<[u8; 16]>());
+    let request = upgrade_request(options, Some(&key), early_data);
+    let (header, leftover) = upgrade(stream, &request).await?;
+
+    let expected = STANDARD.encode(Sha1::digest(format!("{}{}", key, WS_GUID)));
+    if header_value(&header, "Sec-WebSocket-Accept") != Some(expected.as_str()) {
+        return Err(invalid_data("WebSocket 握手校验失败"));
+    }
+    Ok(leftover)
+}
+
+/// 编码一个客户端帧（带掩码）
+fn encode_frame(opcode: u8, payload: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(14 + payload.len());
+    frame.push(0x80 | opcode);
+    match payload.len() {
+        len if len < 126 => frame.push(0x80 | len as u8),
+        len if len <= u16::MAX as usize => {
+            frame.push(0x80 | 126);
+            frame.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            frame.push(0x80 | 127);
+            frame.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+    let mask: [u8; 4] = rand::random();
+    frame.extend_from_slice(&mask);
+    frame.extend(payload.iter().enumerate().map(|(i, b)| b ^ mask[i % 4]));
+    frame
+}
+
+/// 从缓冲区头部解析一个完整帧，返回操作码、负载与帧长度；数据不足时返回 `None`
+fn parse_frame(buf: &[u8]) -> io::Result<Option<(u8, Vec<u8>, usize)>> {
+    if buf.len() < 2 {
+        return Ok(None);
+    }
+    let opcode = buf[0] & 0x0f;
+    let masked = buf[1] & 0x80 != 0;
+    let (len, mut pos) = match buf[1] & 0x7f {
+        126 if buf.len() >= 4 => (u16::from_be_bytes([buf[2], buf[3]]) as usize, 4),
+        127 if buf.len() >= 10 => {
+            let len = u64::from_be_bytes(buf[2..10].try_into().unwrap());
+            let len = usize::try_from(len).map_err(|_| invalid_data("WebSocket 帧过长"))?;
+            (len, 10)
+        }
+        126 | 127 => return Ok(None),
+        len => (len as usize, 2),
+    };
+    let mask = if masked {
+        if buf.len() < pos + 4 {
+            return Ok(None);
+        }
+        pos += 4;
+        Some([buf[pos - 4], buf[pos - 3], buf[pos - 2], buf[pos - 1]])
+    } else {
+        None
+    };
+    if buf.len() - pos < len {
+        return Ok(None);
+    }
+
+    let mut payload = buf[pos..pos + len].to_vec();
+    if let Some(mask) = mask {
+        for (i, byte) in payload.iter_mut().enumerate() {
+            *byte ^= mask[i % 4];
+        }
+    }
+    Ok(Some((opcode, payload, pos + len)))
+}
+
+type Handshake<S> = Pin<Box<dyn Future<Output = io::Result<(S, Vec<u8>)>> + Send>>;
+
+enum State<S> {
+    /// 等待首次写入以携带早期数据
+    Pending(Option<(S, HttpOptions)>),
+    /// 握手进行中，附带已作为早期数据发出的字节数
+    Handshaking(Handshake<S>, usize),
+    Open(S),
+    Failed,
+}
+
+/// WebSocket 连接
+pub struct WsStream<S> {
+    state: State<S>,
+    rbuf: Vec<u8>,
+    plain: Vec<u8>,
+    plain_pos: usize,
+    wbuf: Vec<u8>,
+    eof: bool,
+    close_sent: bool,
+}
+
+impl<S> WsStream<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    /// 建立 WebSocket 连接；配置了早期数据时握手推迟到第一次写入
+    pub async fn connect(mut inner: S, options: &HttpOptions) -> io::Result<Self> {
+        let (state, rbuf) = if options.max_early_data > 0 {
+            (State::Pending(Some((inner, options.clone()))), Vec::new())
+        } else {
+            let leftover = ws_handshake(&mut inner, options, &[]).await?;
+            (State::Open(inner), leftover)
+        };
+        Ok(Self {
+            state,
+            rbuf,
+            plain: Vec::new(),
+            plain_pos: 0,
+            wbuf: Vec::new(),
+            eof: false,
+            close_sent: false,
+        })
+    }
+
+    /// 推进握手直到连接建立，返回作为早期数据发出的字节数
+    fn poll_open(&mut self, cx: &mut Context<'_>, early_data: &[u8]) -> Poll<io::Result<usize>> {
+        loop {
+            match &mut self.state {
+                State::Open(_) => return Poll::Ready(Ok(0)),
+                State::Failed => return Poll::Ready(Err(io::ErrorKind::NotConnected.into())),
+                State::Pending(slot) => {
+                    let (mut inner, options) = slot.take().expect("握手状态已被取走");
+                    let early_data =
+                        early_data[..early_data.len().min(options.max_early_data)].to_vec();
+                    let len = early_data.len();
+                    let handshake = async move {
+                        let leftover = ws_handshake(&mut inner, &options, &early_data).await?;
+                        Ok((inner, leftover))
+                    };
+                    self.state = State::Handshaking(Box::pin(handshake), len);
+                }
+                State::Handshaking(handshake, len) => {
+                    let len = *len;
+                    match ready!(handshake.as_mut().poll(cx)) {
+                        Ok((inner, leftover)) => {
+                            self.rbuf = leftover;
+                            self.state = State::Open(inner);
+                            return Poll::Ready(Ok(len));
+                        }
+                        Err(e) => {
+                            self.state = State::Failed;
+                            return Poll::Ready(Err(e));
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn open_inner<S>(state: &mut State<S>) -> io::Result<&mut S> {
+    match state {
+        State::Open(inner) => Ok(inner),
+        _ => Err(io::ErrorKind::NotConnected.into()),
+    }
+}
+
+impl<S> AsyncRead for WsStream<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        loop {
+            if copy_plain(&mut this.plain, &mut this.plain_pos, buf) || this.eof {
+                return Poll::Ready(Ok(()));
+            }
+            ready!(this.poll_open(cx, &[]))?;
+
+            let Some((opcode, payload, len)) = parse_frame(&this.rbuf)? else {
+                let inner = open_inner(&mut this.state)?;
+                let want = this.rbuf.len() + 1;
+                if !ready!(poll_fill(inner, cx, &mut this.rbuf, want))? {
+                    this.eof = true;
+                }
+                continue;
+            };
+            this.rbuf.drain(..len);
+            match opcode {
+                OPCODE_CONTINUATION | OPCODE_TEXT | OPCODE_BINARY => {
+                    this.plain = payload;
+                    this.plain_pos = 0;
+                }
+                OPCODE_CLOSE => this.eof = true,
+                OPCODE_PING => {
+                    // 尽力回复，写不出去的留到下次写入时发送
+                    this.wbuf
+                        .extend_from_slice(&encode_frame(OPCODE_PONG, &payload));
+                    let inner = open_inner(&mut this.state)?;
+                    if let Poll::Ready(Err(e)) = poll_drain(inner, cx, &mut this.wbuf) {
+                        return Poll::Ready(Err(e));
+                    }
+                }
+                OPCODE_PONG => {}
+                other => {
+                    return Poll::Ready(Err(invalid_data(format!(
+                        "未知的 WebSocket 操作码: {}",
+                        other
+                    ))));
+                }
+            }
+        }
+    }
+}
+
+impl<S> AsyncWrite for WsStream<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let early = ready!(this.poll_open(cx, buf))?;
+        if early > 0 {
+            return Poll::Ready(Ok(early));
+        }
+        let inner = open_inner(&mut this.state)?;
+        ready!(poll_drain(inner, cx, &mut this.wbuf))?;
+        if buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+
+        let n = buf.len().min(MAX_FRAME_PAYLOAD);
+        this.wbuf = encode_frame(OPCODE_BINARY, &buf[..n]);
+        if let Poll::Ready(Err(e)) = poll_drain(inner, cx, &mut this.wbuf) {
+            return Poll::Ready(Err(e));
+        }
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        match this.state {
+            // 尚未写入任何数据，无需握手
+            State::Pending(_) => return Poll::Ready(Ok(())),
+            State::Handshaking(..) => {
+                ready!(this.poll_open(cx, &[]))?;
+            }
+            _ => {}
+        }
+        let inner = open_inner(&mut this.state)?;
+        ready!(poll_drain(inner, cx, &mut this.wbuf))?;
+        Pin::new(inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        let State::Open(inner) = &mut this.state else {
+            return Poll::Ready(Ok(()));
+        };
+        ready!(poll_drain(inner, cx, &mut this.wbuf))?;
+        if !this.close_sent {
+            this.close_sent = true;
+            this.wbuf = encode_frame(OPCODE_CLOSE, &1000u16.to_be_bytes());
+        }
+        ready!(poll_drain(inner, cx, &mut this.wbuf))?;
+        Pin::new(inner).poll_shutdown(cx)
+    }
+}
+
+/// HTTPUpgrade 连接：握手完成后先交付响应头之后多读到的数据，再直接读写底层连接
+pub struct UpgradedStream<S> {
+    inner: S,
+    rbuf: Vec<u8>,
+    rpos: usize,
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> UpgradedStream<S> {
+    pub async fn connect(mut inner: S, options: &HttpOptions) -> io::Result<Self> {
+        let request = upgrade_request(options, None, &[]);
+        let (_, rbuf) = upgrade(&mut inner, &request).await?;
+        Ok(Self {
+            inner,
+            rbuf,
+            rpos: 0,
+        })
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> AsyncRead for UpgradedStream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if copy_plain(&mut this.rbuf, &mut this.rpos, buf) {
+            return Poll::Ready(Ok(()));
+        }
+        Pin::new(&mut this.inner).poll_read(cx, buf)
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> AsyncWrite for UpgradedStream<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::{TcpListener, TcpStream};
+
+    /// 本地 WebSocket 服务端替身：完成握手后回显收到的数据帧，返回请求头
+    async fn spawn_echo_server() -> (u16, tokio::task::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let handle = tokio::spawn(async move {
+            let (mut sock, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            while !request.ends_with(b"\r\n\r\n") {
+                request.push(sock.read_u8().await.unwrap());
+            }
+            let request = String::from_utf8(request).unwrap();
+            let key = header_value(&request, "Sec-WebSocket-Key").unwrap();
+            let accept = STANDARD.encode(Sha1::digest(format!("{}{}", key, WS_GUID)));
+            let response = format!(
+                "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+                accept
+            );
+            sock.write_all(response.as_bytes()).await.unwrap();
+            // 先发一个 ping，客户端应透明处理
+            sock.write_all(&[0x89, 0x01, b'p']).await.unwrap();
+
+            // 早期数据按首个数据帧回显
+            if let Some(early) = header_value(&request, EARLY_DATA_HEADER) {
+                let early = URL_SAFE_NO_PAD.decode(early).unwrap();
+                sock.write_all(&[0x82, early.len() as u8]).await.unwrap();
+                sock.write_all(&early).await.unwrap();
+            }
+
+            let mut buf = Vec::new();
+            let mut tmp = [0u8; 4096];
+            loop {
+                while let Some((opcode, payload, len)) = parse_frame(&buf).unwrap() {
+                    buf.drain(..len);
+                    match opcode {
+                        OPCODE_BINARY => {
+                            let mut frame = vec![0x82, 126];
+                            frame.extend_from_slice(&(payload.len() as u16).to_be_bytes());
+                            frame.extend_from_slice(&payload);
+                            sock.write_all(&frame).await.unwrap();
+                        }
+                        OPCODE_PONG => assert_eq!(payload, b"p"),
+                        OPCODE_CLOSE => return request,
+                        other => panic!("意外的操作码 {}", other),
+                    }
+                }
+                let n = sock.read(&mut tmp).await.unwrap();
+                if n == 0 {
+                    return request;
+                }
+                buf.extend_from_slice(&tmp[..n]);
+            }
+        });
+        (port, handle)
+    }
+
+    fn options(max_early_data: usize) -> HttpOptions {
+        HttpOptions {
+            path: "/ws?token=1".to_string(),
+            host: "cdn.example.com".to_string(),
+            headers: vec![("User-Agent".to_string(), "test".to_string())],
+            max_early_data,
+        }
+    }
+
+    #[test]
+    fn test_frame_roundtrip() {
+        for len in [0, 125, 126, 70000] {
+            let payload = vec![0x5a; len];
+            let frame = encode_frame(OPCODE_BINARY, &payload);
+            let (opcode, decoded, used) = parse_frame(&frame).unwrap().unwrap();
+            assert_eq!(
+                (opcode, decoded, used),
+                (OPCODE_BINARY, payload, frame.len())
+            );
+            assert!(parse_frame(&frame[..frame.len() - 1]).unwrap().is_none());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_ws_roundtrip() {
+        let (port, server) = spawn_echo_server().await;
+        let tcp = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+        let mut stream = WsStream::connect(tcp, &options(0)).await.unwrap();
+
+        let data = vec![7u8; 40000];
+        stream.write_all(&data).await.unwrap();
+        let mut echoed = vec![0u8; data.len()];
+        stream.read_exact(&mut echoed).await.unwrap();
+        assert_eq!(echoed, data);
+        stream.shutdown().await.unwrap();
+
+        let request = server.await.unwrap();
+        assert!(request.starts_with("GET /ws?token=1 HTTP/1.1\r\n"));
+        assert_eq!(header_value(&request, "Host"), Some("cdn.example.com"));
+        assert_eq!(header_value(&request, "User-Agent"), Some("test"));
+        assert_eq!(header_value(&request, EARLY_DATA_HEADER), None);
+    }
+
+    #[tokio::test]
+    async fn test_ws_early_data() {
+        let (port, server) = spawn_echo_server().await;
+        let tcp = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+        let mut stream = WsStream::connect(tcp, &options(8)).await.unwrap();
+
+        // 前 8 字节随握手发出，其余按普通数据帧发送
+        stream.write_all(b"early data!!").await.unwrap();
+        stream.flush().await.unwrap();
+        let mut echoed = [0u8; 12];
+        stream.read_exact(&mut echoed).await.unwrap();
+        assert_eq!(&echoed, b"early data!!");
+        stream.shutdown().await.unwrap();
+
+        let request = server.await.unwrap();
+        assert_eq!(
+            header_value(&request, EARLY_DATA_HEADER),
+            Some(URL_SAFE_NO_PAD.encode(b"early da").as_str())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_http_upgrade() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (mut sock, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            while !request.ends_with(b"\r\n\r\n") {
+                request.push(sock.read_u8().await.unwrap());
+            }
+            assert!(request.starts_with(b"GET /ws?token=1 HTTP/1.1\r\n"));
+            // 响应头与首段数据在同一次写入中到达
+            sock.write_all(b"HTTP/1.1 101 Switching Protocols\r\n\r\nhello")
+                .await
+                .unwrap();
+            let mut buf = [0u8; 5];
+            sock.read_exact(&mut buf).await.unwrap();
+            sock.write_all(&buf).await.unwrap();
+        });
+
+        let tcp = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+        let mut stream = UpgradedStream::connect(tcp, &options(0)).await.unwrap();
+        let mut buf = [0u8; 5];
+        stream.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"hello");
+        stream.write_all(b"world").await.unwrap();
+        stream.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"world");
+    }
+
+    #[tokio::test]
+    async fn test_upgrade_rejected() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (mut sock, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 1024];
+            let _ = sock.read(&mut buf).await;
+            sock.write_all(b"HTTP/1.1 403 Forbidden\r\n\r\n")
+                .await
+                .unwrap();
+        });
+
+        let tcp = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+        let err = WsStream::connect(tcp, &options(0)).await.err().unwrap();
+        assert!(err.to_string().contains("403"));
+    }
+}
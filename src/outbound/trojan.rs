@@ -0,0 +1,228 @@
+//! Trojan 出站
+//! TLS 握手后发送 SHA224 密码头与 CONNECT 请求，之后即为透明数据通道；
+//! UDP 关联的数据报以 ADDR LEN CRLF PAYLOAD 的帧格式在同一条连接上传输
+
+use super::{Address, BoxedDatagram, BoxedStream, Datagram, transport};
+use crate::proxy::ProxyNode;
+use crate::proxy::node::TrojanParams;
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use sha2::{Digest, Sha224};
+use std::io;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+const CMD_CONNECT: u8 = 0x01;
+const CMD_UDP_ASSOCIATE: u8 = 0x03;
+const CRLF: &[u8] = b"\r\n";
+
+/// 构造 Trojan 请求头：hex(SHA224(password)) CRLF CMD ADDR CRLF
+pub fn request_header(password: &str, cmd: u8, target: &Address) -> Vec<u8> {
+    let digest = Sha224::digest(password.as_bytes());
+    let mut header = Vec::with_capacity(56 + 2 + 1 + 259 + 2);
+    for byte in digest {
+        header.extend_from_slice(format!("{:02x}", byte).as_bytes());
+    }
+    header.extend_from_slice(CRLF);
+    header.push(cmd);
+    target.write_socks(&mut header);
+    header.extend_from_slice(CRLF);
+    header
+}
+
+/// 建立 TLS 连接并发送请求头
+async fn open(
+    proxy: &ProxyNode,
+    params: &TrojanParams,
+    cmd: u8,
+    target: &Address,
+) -> Result<BoxedStream> {
+    let mut stream = transport::connect(proxy, true)
+        .await
+        .map_err(|e| anyhow!("Trojan {}", e))?;
+
+    stream
+        .write_all(&request_header(&params.password, cmd, target))
+        .await?;
+    stream.flush().await?;
+    Ok(stream)
+}
+
+/// 通过 Trojan 节点连接目标地址
+pub async fn connect(
+    proxy: &ProxyNode,
+    params: &TrojanParams,
+    target: &Address,
+) -> Result<BoxedStream> {
+    open(proxy, params, CMD_CONNECT, target).await
+}
+
+/// 通过 Trojan 节点建立到目标的 UDP 关联
+pub async fn associate(
+    proxy: &ProxyNode,
+    params: &TrojanParams,
+    target: &Address,
+) -> Result<BoxedDatagram> {
+    let stream = open(proxy, params, CMD_UDP_ASSOCIATE, target).await?;
+    Ok(Box::new(TrojanDatagram {
+        stream,
+        target: target.clone(),
+    }))
+}
+
+/// 在 Trojan 连接上承载的 UDP 关联
+struct TrojanDatagram {
+    stream: BoxedStream,
+    target: Address,
+}
+
+#[async_trait]
+impl Datagram for TrojanDatagram {
+    async fn send(&mut self, data: &[u8]) -> io::Result<()> {
+        let len = u16::try_from(data.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "UDP 数据报过大"))?;
+        let mut frame = Vec::with_capacity(data.len() + 266);
+        self.target.write_socks(&mut frame);
+        frame.extend_from_slice(&len.to_be_bytes());
+        frame.extend_from_slice(CRLF);
+        frame.extend_from_slice(data);
+        self.stream.write_all(&frame).await?;
+        self.stream.flush().await
+    }
+
+    async fn recv(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        Address::read_socks(&mut self.stream).await?;
+        let len = self.stream.read_u16().await? as usize;
+        let mut crlf = [0u8; 2];
+        self.stream.read_exact(&mut crlf).await?;
+        let mut payload = vec![0u8; len];
+        self.stream.read_exact(&mut payload).await?;
+        let n = len.min(buf.len());
+        buf[..n].copy_from_slice(&payload[..n]);
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::outbound::tls::test_server;
+    use crate::proxy::Protocol;
+    use tokio::net::TcpListener;
+
+    /// 启动本地 Trojan 服务端替身：校验 SNI 与请求头后回显数据（UDP 帧原样回显即为合法应答）
+    async fn spawn_server(domain: &'static str, password: &'static str) -> u16 {
+        let acceptor = test_server::acceptor(domain, &[]);
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (tcp, _) = listener.accept().await.unwrap();
+            let Ok(mut stream) = acceptor.accept(tcp).await else {
+                return;
+            };
+            assert_eq!(stream.get_ref().1.server_name(), Some(domain));
+
+            let mut hash = [0u8; 56];
+            stream.read_exact(&mut hash).await.unwrap();
+            let expected =
+                request_header(password, CMD_CONNECT, &Address::Domain(String::new(), 0));
+            assert_eq!(&hash[..], &expected[..56]);
+            let mut crlf = [0u8; 2];
+            stream.read_exact(&mut crlf).await.unwrap();
+            let cmd = stream.read_u8().await.unwrap();
+            assert!(cmd == CMD_CONNECT || cmd == CMD_UDP_ASSOCIATE);
+            let target = Address::read_socks(&mut stream).await.unwrap();
+            assert_eq!(target, Address::Domain("example.com".to_string(), 80));
+            stream.read_exact(&mut crlf).await.unwrap();
+            assert_eq!(&crlf, CRLF);
+
+            let mut buf = [0u8; 1024];
+            loop {
+                let n = stream.read(&mut buf).await.unwrap_or(0);
+                if n == 0 {
+                    break;
+                }
+                stream.write_all(&buf[..n]).await.unwrap();
+            }
+        });
+        port
+    }
+
+    fn params() -> TrojanParams {
+        TrojanParams {
+            password: "password".to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn node(port: u16, insecure: bool) -> ProxyNode {
+        let mut node = ProxyNode::new(
+            "trojan".to_string(),
+            "127.0.0.1".to_string(),
+            port,
+            Protocol::Trojan(params()),
+        );
+        node.tls.sni = Some("trojan.test".to_string());
+        node.tls.skip_cert_verify = Some(insecure);
+        node
+    }
+
+    #[test]
+    fn test_request_header() {
+        let header = request_header(
+            "password",
+            CMD_CONNECT,
+            &Address::Socket("1.2.3.4:443".parse().unwrap()),
+        );
+        assert_eq!(
+            &header[..56],
+            b"d63dc919e201d7bc4c825630d2cf25fdc93d4b2f0d46706d29038d01"
+        );
+        assert_eq!(
+            &header[56..],
+            &[
+                b'\r', b'\n', 0x01, 0x01, 1, 2, 3, 4, 0x01, 0xbb, b'\r', b'\n'
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_trojan_roundtrip() {
+        let port = spawn_server("trojan.test", "password").await;
+        let target = Address::Domain("example.com".to_string(), 80);
+        let mut stream = connect(&node(port, true), &params(), &target)
+            .await
+            .unwrap();
+
+        stream.write_all(b"hello trojan").await.unwrap();
+        let mut buf = [0u8; 12];
+        stream.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"hello trojan");
+    }
+
+    #[tokio::test]
+    async fn test_trojan_udp() {
+        let port = spawn_server("trojan.test", "password").await;
+        let target = Address::Domain("example.com".to_string(), 80);
+        let mut datagram = associate(&node(port, true), &params(), &target)
+            .await
+            .unwrap();
+
+        for payload in [&b"first"[..], &b"second packet"[..]] {
+            datagram.send(payload).await.unwrap();
+            let mut buf = [0u8; 64];
+            let n = datagram.recv(&mut buf).await.unwrap();
+            assert_eq!(&buf[..n], payload);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_trojan_rejects_untrusted_cert() {
+        let port = spawn_server("trojan.test", "password").await;
+        let target = Address::Domain("example.com".to_string(), 80);
+        assert!(
+            connect(&node(port, false), &params(), &target)
+                .await
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,180 @@
+//! TUIC v5 出站
+//! 认证命令经单向流发送（令牌由 TLS 导出密钥得到），每个 TCP 请求占用一条双向流，
+//! Connect 命令之后即为透明数据通道
+
+use super::quic::{self, QuicStream};
+use super::tls::TlsOptions;
+use super::vmess::parse_uuid;
+use super::{Address, BoxedStream};
+use crate::proxy::ProxyNode;
+use crate::proxy::node::TuicParams;
+use anyhow::{Result, anyhow};
+
+const VERSION: u8 = 5;
+const CMD_AUTHENTICATE: u8 = 0x00;
+const CMD_CONNECT: u8 = 0x01;
+
+/// 构造认证命令：版本 + 类型 + UUID + 令牌
+pub fn auth_command(uuid: &[u8; 16], token: &[u8; 32]) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(2 + 16 + 32);
+    buf.push(VERSION);
+    buf.push(CMD_AUTHENTICATE);
+    buf.extend_from_slice(uuid);
+    buf.extend_from_slice(token);
+    buf
+}
+
+/// 构造 Connect 命令：版本 + 类型 + 地址
+pub fn connect_command(target: &Address) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(2 + 259);
+    buf.push(VERSION);
+    buf.push(CMD_CONNECT);
+    target.write_tuic(&mut buf);
+    buf
+}
+
+/// 通过 TUIC 节点连接目标地址
+pub async fn connect(
+    proxy: &ProxyNode,
+    params: &TuicParams,
+    target: &Address,
+) -> Result<BoxedStream> {
+    let uuid = parse_uuid(&params.uuid)?;
+    let password = &params.password;
+
+    let mut options = TlsOptions::from_node(proxy);
+    if options.alpn.is_empty() {
+        options.alpn = vec!["h3".to_string()];
+    }
+    let (endpoint, connection) = quic::connect(&proxy.server, proxy.port, &options, None)
+        .await
+        .map_err(|e| anyhow!("TUIC {}", e))?;
+
+    let mut token = [0u8; 32];
+    connection
+        .export_keying_material(&mut token, &uuid, password.as_bytes())
+        .map_err(|_| anyhow!("TUIC 导出认证令牌失败"))?;
+    let mut uni = connection
+        .open_uni()
+        .await
+        .map_err(|e| anyhow!("TUIC 打开认证流失败: {}", e))?;
+    uni.write_all(&auth_command(&uuid, &token)).await?;
+    uni.finish()?;
+
+    // 服务端在认证完成前会挂起后续请求，无需等待认证结果
+    let (mut send, recv) = connection
+        .open_bi()
+        .await
+        .map_err(|e| anyhow!("TUIC 打开流失败: {}", e))?;
+    send.write_all(&connect_command(target)).await?;
+    Ok(Box::new(QuicStream::new(
+        send,
+        recv,
+        connection,
+        endpoint,
+        (),
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::outbound::dial;
+    use crate::outbound::quic::test_server;
+    use crate::proxy::Protocol;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    const UUID: &str = "b831381d-6324-4d53-ad4f-8cda48b30811";
+
+    /// 启动本地 TUIC 服务端替身：校验认证令牌与 Connect 命令后回显数据
+    fn spawn_server(password: &'static str) -> u16 {
+        let (endpoint, addr) = test_server::endpoint("tuic.test", &["h3"], None);
+        tokio::spawn(async move {
+            let connection = endpoint.accept().await.unwrap().await.unwrap();
+
+            let mut uni = connection.accept_uni().await.unwrap();
+            let mut auth = [0u8; 50];
+            uni.read_exact(&mut auth).await.unwrap();
+            let uuid: [u8; 16] = auth[2..18].try_into().unwrap();
+            let mut token = [0u8; 32];
+            connection
+                .export_keying_material(&mut token, &uuid, password.as_bytes())
+                .unwrap();
+            if auth[..] != auth_command(&parse_uuid(UUID).unwrap(), &token)[..] {
+                connection.close(0u32.into(), b"authentication failed");
+                return;
+            }
+
+            let (mut send, mut recv) = connection.accept_bi().await.unwrap();
+            let mut header = [0u8; 2];
+            recv.read_exact(&mut header).await.unwrap();
+            assert_eq!(header, [VERSION, CMD_CONNECT]);
+            assert_eq!(recv.read_u8().await.unwrap(), 0x00);
+            let mut domain = vec![0u8; recv.read_u8().await.unwrap() as usize];
+            recv.read_exact(&mut domain).await.unwrap();
+            assert_eq!(domain, b"example.com");
+            assert_eq!(recv.read_u16().await.unwrap(), 80);
+
+            let mut buf = [0u8; 1024];
+            while let Ok(Some(n)) = recv.read(&mut buf).await {
+                send.write_all(&buf[..n]).await.unwrap();
+            }
+            let _ = send.finish();
+        });
+        addr.port()
+    }
+
+    fn node(port: u16, password: &str) -> ProxyNode {
+        let mut node = ProxyNode::new(
+            "tuic".to_string(),
+            "127.0.0.1".to_string(),
+            port,
+            Protocol::Tuic(TuicParams {
+                uuid: UUID.to_string(),
+                password: password.to_string(),
+                congestion_controller: None,
+                udp_relay_mode: None,
+                ..Default::default()
+            }),
+        );
+        node.tls.sni = Some("tuic.test".to_string());
+        node.tls.skip_cert_verify = Some(true);
+        node
+    }
+
+    #[test]
+    fn test_connect_command() {
+        let command = connect_command(&Address::Socket("1.2.3.4:443".parse().unwrap()));
+        assert_eq!(command, vec![5, 1, 0x01, 1, 2, 3, 4, 0x01, 0xbb]);
+
+        let command = connect_command(&Address::Domain("a.io".to_string(), 80));
+        assert_eq!(
+            command,
+            vec![5, 1, 0x00, 4, b'a', b'.', b'i', b'o', 0x00, 0x50]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_tuic_roundtrip() {
+        let port = spawn_server("secret");
+        let target = Address::Domain("example.com".to_string(), 80);
+        let mut stream = dial(&node(port, "secret"), &target).await.unwrap();
+
+        stream.write_all(b"hello tuic").await.unwrap();
+        let mut buf = [0u8; 10];
+        stream.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"hello tuic");
+    }
+
+    #[tokio::test]
+    async fn test_tuic_wrong_password() {
+        let port = spawn_server("secret");
+        let target = Address::Domain("example.com".to_string(), 80);
+        let mut stream = dial(&node(port, "wrong"), &target).await.unwrap();
+
+        // 认证失败时服务端关闭连接，数据无法往返
+        let _ = stream.write_all(b"hello tuic").await;
+        let mut buf = [0u8; 10];
+        assert!(stream.read_exact(&mut buf).await.is_err());
+    }
+}
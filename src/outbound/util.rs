@@ -0,0 +1,163 @@
+//! 出站协议实现共用的异步读写辅助函数
+
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll, ready};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+/// 从底层流读取数据，直到缓冲区中至少有 `n` 字节
+///
+/// 返回 `Ok(false)` 表示在凑够数据前遇到了 EOF
+pub fn poll_fill<S: AsyncRead + Unpin>(
+    inner: &mut S,
+    cx: &mut Context<'_>,
+    buf: &mut Vec<u8>,
+    n: usize,
+) -> Poll<io::Result<bool>> {
+    let mut tmp = [0u8; 8192];
+    while buf.len() < n {
+        let mut read_buf = ReadBuf::new(&mut tmp);
+        ready!(Pin::new(&mut *inner).poll_read(cx, &mut read_buf))?;
+        if read_buf.filled().is_empty() {
+            return Poll::Ready(Ok(false));
+        }
+        buf.extend_from_slice(read_buf.filled());
+    }
+    Poll::Ready(Ok(true))
+}
+
+/// 将待发送缓冲区中的数据全部写入底层流
+pub fn poll_drain<S: AsyncWrite + Unpin>(
+    inner: &mut S,
+    cx: &mut Context<'_>,
+    buf: &mut Vec<u8>,
+) -> Poll<io::Result<()>> {
+    while !buf.is_empty() {
+        let n = ready!(Pin::new(&mut *inner).poll_write(cx, buf))?;
+        if n == 0 {
+            return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+        }
+        buf.drain(..n);
+    }
+    Poll::Ready(Ok(()))
+}
+
+/// 把明文编码成帧、经发送缓冲区写出的出站流
+pub trait FramedWrite {
+    type Inner: AsyncWrite + Unpin;
+
+    /// 底层流与待发送缓冲区
+    fn write_parts(&mut self) -> (&mut Self::Inner, &mut Vec<u8>);
+
+    /// 把 `data` 开头的一段编码进发送缓冲区，返回消耗的明文字节数
+    fn encode_frame(&mut self, data: &[u8]) -> usize;
+
+    /// `poll_write` 的公共流程：先发完上一帧，再编码本次数据并尽力发出
+    fn poll_write_framed(&mut self, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let (inner, wbuf) = self.write_parts();
+        ready!(poll_drain(inner, cx, wbuf))?;
+        if buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+
+        let n = self.encode_frame(buf);
+
+        // 数据已进入发送缓冲区，底层暂时写不进去也视为写入成功
+        let (inner, wbuf) = self.write_parts();
+        if let Poll::Ready(Err(e)) = poll_drain(inner, cx, wbuf) {
+            return Poll::Ready(Err(e));
+        }
+        Poll::Ready(Ok(n))
+    }
+}
+
+/// 把已解密的明文拷贝到调用方缓冲区，返回是否拷贝了数据
+pub fn copy_plain(plain: &mut Vec<u8>, pos: &mut usize, out: &mut ReadBuf<'_>) -> bool {
+    if *pos >= plain.len() {
+        return false;
+    }
+    let n = (plain.len() - *pos).min(out.remaining());
+    out.put_slice(&plain[*pos..*pos + n]);
+    *pos += n;
+    if *pos >= plain.len() {
+        plain.clear();
+        *pos = 0;
+    }
+    true
+}
+
+/// 构造数据损坏类错误
+pub fn invalid_data(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::future::poll_fn;
+    use tokio::io::{AsyncReadExt, DuplexStream, duplex};
+
+    /// 每帧为一字节长度加至多 4 字节数据
+    struct LengthFramed {
+        inner: DuplexStream,
+        wbuf: Vec<u8>,
+    }
+
+    impl FramedWrite for LengthFramed {
+        type Inner = DuplexStream;
+
+        fn write_parts(&mut self) -> (&mut DuplexStream, &mut Vec<u8>) {
+            (&mut self.inner, &mut self.wbuf)
+        }
+
+        fn encode_frame(&mut self, data: &[u8]) -> usize {
+            let n = data.len().min(4);
+            self.wbuf.push(n as u8);
+            self.wbuf.extend_from_slice(&data[..n]);
+            n
+        }
+    }
+
+    #[tokio::test]
+    async fn test_poll_write_framed() {
+        // 底层只能容纳 3 字节，帧写不完也视为写入成功
+        let (client, mut server) = duplex(3);
+        let mut stream = LengthFramed {
+            inner: client,
+            wbuf: Vec::new(),
+        };
+        let n = poll_fn(|cx| stream.poll_write_framed(cx, b"hello"))
+            .await
+            .unwrap();
+        assert_eq!(n, 4);
+        assert_eq!(stream.wbuf, b"ll");
+
+        // 下一次写入要先等上一帧发完
+        let write = tokio::spawn(async move {
+            let n = poll_fn(|cx| stream.poll_write_framed(cx, b"o"))
+                .await
+                .unwrap();
+            poll_fn(|cx| poll_drain(&mut stream.inner, cx, &mut stream.wbuf))
+                .await
+                .unwrap();
+            n
+        });
+        let mut received = [0u8; 7];
+        server.read_exact(&mut received).await.unwrap();
+        assert_eq!(&received, b"\x04hell\x01o");
+        assert_eq!(write.await.unwrap(), 1);
+
+        // 对端关闭后写入报错
+        let (client, server) = duplex(3);
+        drop(server);
+        let mut stream = LengthFramed {
+            inner: client,
+            wbuf: Vec::new(),
+        };
+        assert!(
+            poll_fn(|cx| stream.poll_write_framed(cx, b"x"))
+                .await
+                .is_err()
+        );
+    }
+}
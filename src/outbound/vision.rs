@@ -0,0 +1,321 @@
+//! XTLS Vision 流控
+//! 对开头的若干数据包做长度填充以隐藏内层 TLS 握手特征；
+//! 服务端发出直连命令后，下行数据改为绕过外层 TLS 直接读取底层连接
+//!
+//! 上行方向不切换直连，填充结束后继续经外层 TLS 发送，服务端对两种方式均兼容
+
+use super::tls13::Tls13Stream;
+use super::util::{FramedWrite, copy_plain, poll_drain};
+use super::vless::VlessStream;
+use rand::Rng;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll, ready};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+/// 继续填充
+const COMMAND_CONTINUE: u8 = 0;
+/// 结束填充，之后数据经外层 TLS 原样传输
+const COMMAND_END: u8 = 1;
+/// 结束填充，之后数据绕过外层 TLS 直接传输
+const COMMAND_DIRECT: u8 = 2;
+/// 与 Xray 缓冲区大小一致，单帧（含 UUID 与帧头）不超过该长度
+const BUF_SIZE: usize = 8192;
+/// 单帧最大内容长度
+const MAX_CONTENT: usize = BUF_SIZE - 21;
+/// 最多填充的帧数，之后即使未识别到 TLS 应用数据也结束填充
+const MAX_PADDING_FRAMES: usize = 8;
+const TLS_HANDSHAKE_START: [u8; 2] = [0x16, 0x03];
+const TLS_APPLICATION_DATA_START: [u8; 3] = [0x17, 0x03, 0x03];
+
+/// 构造一个填充帧：[UUID] + 命令 + 内容长度 + 填充长度 + 内容 + 填充
+fn pad(uuid: Option<&[u8; 16]>, content: &[u8], command: u8, long_padding: bool) -> Vec<u8> {
+    let mut rng = rand::thread_rng();
+    let padding_len = if content.len() < 900 && long_padding {
+        rng.gen_range(0..500) + 900 - content.len()
+    } else {
+        rng.gen_range(0..256)
+    }
+    .min(BUF_SIZE - 21 - content.len());
+
+    let mut frame = Vec::with_capacity(21 + content.len() + padding_len);
+    if let Some(uuid) = uuid {
+        frame.extend_from_slice(uuid);
+    }
+    frame.push(command);
+    frame.extend_from_slice(&(content.len() as u16).to_be_bytes());
+    frame.extend_from_slice(&(padding_len as u16).to_be_bytes());
+    frame.extend_from_slice(content);
+    frame.resize(frame.len() + padding_len, 0);
+    frame
+}
+
+/// 去除填充的状态机，帧可以跨越多次读取
+struct Unpadder {
+    uuid: [u8; 16],
+    /// 是否仍处于填充阶段
+    active: bool,
+    /// 是否已读到开头的 UUID
+    started: bool,
+    remaining_command: usize,
+    remaining_content: usize,
+    remaining_padding: usize,
+    command: u8,
+}
+
+impl Unpadder {
+    fn new(uuid: [u8; 16]) -> Self {
+        Self {
+            uuid,
+            active: true,
+            started: false,
+            remaining_command: 0,
+            remaining_content: 0,
+            remaining_padding: 0,
+            command: COMMAND_CONTINUE,
+        }
+    }
+
+    /// 处理一段数据，返回去除填充后的内容与本段中结束填充的命令
+    fn unpad(&mut self, mut data: &[u8]) -> (Vec<u8>, Option<u8>) {
+        if !self.started {
+            // 服务端未填充时原样交付
+            if data.len() < 21 || data[..16] != self.uuid {
+                self.active = false;
+                return (data.to_vec(), None);
+            }
+            data = &data[16..];
+            self.started = true;
+            self.remaining_command = 5;
+        }
+
+        let mut out = Vec::with_capacity(data.len());
+        while !data.is_empty() {
+            if self.remaining_command > 0 {
+                let byte = data[0];
+                data = &data[1..];
+                match self.remaining_command {
+                    5 => self.command = byte,
+                    4 => self.remaining_content = (byte as usize) << 8,
+                    3 => self.remaining_content |= byte as usize,
+                    2 => self.remaining_padding = (byte as usize) << 8,
+                    _ => self.remaining_padding |= byte as usize,
+                }
+                self.remaining_command -= 1;
+            } else if self.remaining_content > 0 {
+                let n = self.remaining_content.min(data.len());
+                out.extend_from_slice(&data[..n]);
+                data = &data[n..];
+                self.remaining_content -= n;
+            } else {
+                let n = self.remaining_padding.min(data.len());
+                data = &data[n..];
+                self.remaining_padding -= n;
+            }
+
+            if self.remaining_command == 0
+                && self.remaining_content == 0
+                && self.remaining_padding == 0
+            {
+                if self.command == COMMAND_CONTINUE {
+                    self.remaining_command = 5;
+                } else {
+                    // 填充结束，本段剩余数据不再带帧头
+                    out.extend_from_slice(data);
+                    self.active = false;
+                    return (out, Some(self.command));
+                }
+            }
+        }
+        (out, None)
+    }
+}
+
+/// Vision 流控连接
+pub struct VisionStream<S> {
+    inner: VlessStream<Tls13Stream<S>>,
+    uuid: [u8; 16],
+    /// 第一帧需要携带 UUID
+    write_uuid: bool,
+    writing_padded: bool,
+    frames_written: usize,
+    /// 是否已写入过数据（用于识别内层 TLS）
+    data_written: bool,
+    is_tls: bool,
+    wbuf: Vec<u8>,
+    unpadder: Unpadder,
+    plain: Vec<u8>,
+    plain_pos: usize,
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> VisionStream<S> {
+    pub fn new(inner: VlessStream<Tls13Stream<S>>, uuid: [u8; 16]) -> Self {
+        Self {
+            inner,
+            uuid,
+            write_uuid: true,
+            writing_padded: true,
+            frames_written: 0,
+            data_written: false,
+            is_tls: false,
+            wbuf: Vec::new(),
+            unpadder: Unpadder::new(uuid),
+            plain: Vec::new(),
+            plain_pos: 0,
+        }
+    }
+
+    fn push_frame(&mut self, content: &[u8], command: u8, long_padding: bool) {
+        let uuid = self.write_uuid.then_some(&self.uuid);
+        let frame = pad(uuid, content, command, long_padding);
+        self.wbuf.extend_from_slice(&frame);
+        self.write_uuid = false;
+        self.frames_written += 1;
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> AsyncRead for VisionStream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        loop {
+            if copy_plain(&mut this.plain, &mut this.plain_pos, buf) {
+                return Poll::Ready(Ok(()));
+            }
+            if !this.unpadder.active {
+                return Pin::new(&mut this.inner).poll_read(cx, buf);
+            }
+
+            // 每次最多读到一条 TLS 记录的明文，保证直连切换发生在记录边界
+            let mut tmp = [0u8; 16384];
+            let mut read_buf = ReadBuf::new(&mut tmp);
+            ready!(Pin::new(&mut this.inner).poll_read(cx, &mut read_buf))?;
+            if read_buf.filled().is_empty() {
+                return Poll::Ready(Ok(()));
+            }
+
+            let (plain, command) = this.unpadder.unpad(read_buf.filled());
+            if command == Some(COMMAND_DIRECT) {
+                this.inner.get_mut().switch_raw_read();
+            }
+            this.plain = plain;
+            this.plain_pos = 0;
+        }
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> FramedWrite for VisionStream<S> {
+    type Inner = VlessStream<Tls13Stream<S>>;
+
+    fn write_parts(&mut self) -> (&mut Self::Inner, &mut Vec<u8>) {
+        (&mut self.inner, &mut self.wbuf)
+    }
+
+    fn encode_frame(&mut self, data: &[u8]) -> usize {
+        let content = &data[..data.len().min(MAX_CONTENT)];
+        if !self.data_written {
+            self.data_written = true;
+            self.is_tls = content.starts_with(&TLS_HANDSHAKE_START);
+        }
+        let app_data = content.starts_with(&TLS_APPLICATION_DATA_START);
+        let command = if app_data || self.frames_written + 1 >= MAX_PADDING_FRAMES {
+            self.writing_padded = false;
+            COMMAND_END
+        } else {
+            COMMAND_CONTINUE
+        };
+        self.push_frame(content, command, self.is_tls && !app_data);
+        content.len()
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> AsyncWrite for VisionStream<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        if this.writing_padded {
+            return this.poll_write_framed(cx, buf);
+        }
+        // 填充结束后直接写入底层流
+        ready!(poll_drain(&mut this.inner, cx, &mut this.wbuf))?;
+        Pin::new(&mut this.inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if this.write_uuid {
+            // 还没有任何数据时先发一个长填充的空帧，随 VLESS 请求头一起发出
+            this.push_frame(&[], COMMAND_CONTINUE, true);
+        }
+        ready!(poll_drain(&mut this.inner, cx, &mut this.wbuf))?;
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(poll_drain(&mut this.inner, cx, &mut this.wbuf))?;
+        Pin::new(&mut this.inner).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pad_layout() {
+        let uuid = [7u8; 16];
+        let frame = pad(Some(&uuid), b"hello", COMMAND_END, true);
+        assert_eq!(&frame[..16], &uuid);
+        assert_eq!(frame[16], COMMAND_END);
+        assert_eq!(u16::from_be_bytes([frame[17], frame[18]]), 5);
+        let padding = u16::from_be_bytes([frame[19], frame[20]]) as usize;
+        assert!(padding >= 900 - 5);
+        assert_eq!(&frame[21..26], b"hello");
+        assert_eq!(frame.len(), 26 + padding);
+
+        let big = vec![1u8; MAX_CONTENT];
+        assert_eq!(pad(None, &big, COMMAND_CONTINUE, true).len(), BUF_SIZE - 16);
+    }
+
+    #[test]
+    fn test_unpad_split_frames() {
+        let uuid = [9u8; 16];
+        let mut stream = pad(Some(&uuid), b"first", COMMAND_CONTINUE, true);
+        stream.extend_from_slice(&pad(None, b"second", COMMAND_DIRECT, false));
+        stream.extend_from_slice(b"tail");
+
+        // 以各种切分方式输入，结果应一致（首段需包含 UUID 与完整帧头）
+        for chunk_size in [21, 23, 100, stream.len()] {
+            let mut unpadder = Unpadder::new(uuid);
+            let mut out = Vec::new();
+            let mut finished = None;
+            for chunk in stream.chunks(chunk_size) {
+                if !unpadder.active {
+                    out.extend_from_slice(chunk);
+                    continue;
+                }
+                let (plain, command) = unpadder.unpad(chunk);
+                out.extend_from_slice(&plain);
+                finished = finished.or(command);
+            }
+            assert_eq!(out, b"firstsecondtail");
+            assert_eq!(finished, Some(COMMAND_DIRECT));
+        }
+    }
+
+    #[test]
+    fn test_unpad_passthrough_without_uuid() {
+        let mut unpadder = Unpadder::new([1u8; 16]);
+        let (plain, command) = unpadder.unpad(b"not padded at all, plain data");
+        assert_eq!(plain, b"not padded at all, plain data");
+        assert_eq!(command, None);
+        assert!(!unpadder.active);
+    }
+}
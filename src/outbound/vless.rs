@@ -0,0 +1,382 @@
+//! VLESS 出站
+//! 请求头为明文 UUID + 附加信息 + 目标地址，安全性依赖外层 TLS / REALITY；
+//! 支持 xtls-rprx-vision 流控
+
+use super::reality::RealityOptions;
+use super::tls::TlsOptions;
+use super::tls13::{self, ServerAuth};
+use super::transport::{self, Transport};
+use super::util::{copy_plain, invalid_data, poll_drain, poll_fill};
+use super::vision::VisionStream;
+use super::vmess::parse_uuid;
+use super::{Address, BoxedStream, connect_tcp};
+use crate::proxy::ProxyNode;
+use crate::proxy::node::VlessParams;
+use anyhow::{Result, anyhow};
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll, ready};
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt, ReadBuf};
+
+const VERSION: u8 = 0;
+const CMD_TCP: u8 = 0x01;
+/// Vision 流控名称
+pub const FLOW_VISION: &str = "xtls-rprx-vision";
+/// 与 Vision 在 TCP 上完全相同，仅影响 UDP 443 的处理
+const FLOW_VISION_UDP443: &str = "xtls-rprx-vision-udp443";
+
+/// 构造 VLESS 请求头：版本 + UUID + 附加信息 + 命令 + 目标地址
+pub fn request_header(uuid: &[u8; 16], flow: Option<&str>, target: &Address) -> Vec<u8> {
+    let mut header = Vec::with_capacity(1 + 16 + 2 + 64 + 1 + 259);
+    header.push(VERSION);
+    header.extend_from_slice(uuid);
+    match flow {
+        // 附加信息为 protobuf：字段 1（flow），长度前缀字符串
+        Some(flow) => {
+            header.push(2 + flow.len() as u8);
+            header.push(0x0a);
+            header.push(flow.len() as u8);
+            header.extend_from_slice(flow.as_bytes());
+        }
+        None => header.push(0),
+    }
+    header.push(CMD_TCP);
+    target.write_vmess(&mut header);
+    header
+}
+
+/// VLESS 连接：请求头随第一次写入一起发出，读取时先剥离响应头
+pub struct VlessStream<S> {
+    inner: S,
+    wbuf: Vec<u8>,
+    header_sent: bool,
+    response_done: bool,
+    rbuf: Vec<u8>,
+    rpos: usize,
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> VlessStream<S> {
+    pub fn new(inner: S, header: Vec<u8>) -> Self {
+        Self {
+            inner,
+            wbuf: header,
+            header_sent: false,
+            response_done: false,
+            rbuf: Vec::new(),
+            rpos: 0,
+        }
+    }
+
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> AsyncRead for VlessStream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if !this.response_done {
+            // 响应头：版本 + 附加信息长度 + 附加信息
+            if !ready!(poll_fill(&mut this.inner, cx, &mut this.rbuf, 2))? {
+                return Poll::Ready(Err(io::ErrorKind::UnexpectedEof.into()));
+            }
+            if this.rbuf[0] != VERSION {
+                return Poll::Ready(Err(invalid_data("VLESS 响应版本错误")));
+            }
+            let len = 2 + this.rbuf[1] as usize;
+            if !ready!(poll_fill(&mut this.inner, cx, &mut this.rbuf, len))? {
+                return Poll::Ready(Err(io::ErrorKind::UnexpectedEof.into()));
+            }
+            this.rpos = len;
+            this.response_done = true;
+        }
+        if copy_plain(&mut this.rbuf, &mut this.rpos, buf) {
+            return Poll::Ready(Ok(()));
+        }
+        Pin::new(&mut this.inner).poll_read(cx, buf)
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> AsyncWrite for VlessStream<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        if !this.header_sent {
+            // 请求头与首包合并发送
+            this.header_sent = true;
+            this.wbuf.extend_from_slice(buf);
+            if let Poll::Ready(Err(e)) = poll_drain(&mut this.inner, cx, &mut this.wbuf) {
+                return Poll::Ready(Err(e));
+            }
+            return Poll::Ready(Ok(buf.len()));
+        }
+        ready!(poll_drain(&mut this.inner, cx, &mut this.wbuf))?;
+        Pin::new(&mut this.inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        this.header_sent = true;
+        ready!(poll_drain(&mut this.inner, cx, &mut this.wbuf))?;
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        this.header_sent = true;
+        ready!(poll_drain(&mut this.inner, cx, &mut this.wbuf))?;
+        Pin::new(&mut this.inner).poll_shutdown(cx)
+    }
+}
+
+/// 通过 VLESS 节点连接目标地址
+pub async fn connect(
+    proxy: &ProxyNode,
+    params: &VlessParams,
+    target: &Address,
+) -> Result<BoxedStream> {
+    let uuid = parse_uuid(&params.uuid)?;
+
+    let vision = match params.flow.as_deref().map(str::trim).unwrap_or("") {
+        "" => false,
+        FLOW_VISION | FLOW_VISION_UDP443 => true,
+        other => return Err(anyhow!("不支持的 VLESS 流控: {}", other)),
+    };
+    let transport = Transport::from_node(proxy)?;
+    if vision && transport != Transport::Tcp {
+        return Err(anyhow!("Vision 流控仅支持 TCP 传输"));
+    }
+
+    let reality = RealityOptions::from_node(proxy)?;
+    if vision && reality.is_none() && !proxy.tls.tls.unwrap_or(false) {
+        return Err(anyhow!("Vision 流控需要启用 TLS 或 REALITY"));
+    }
+    let header = request_header(&uuid, vision.then_some(FLOW_VISION), target);
+
+    if reality.is_none() && !vision {
+        let inner = transport::connect(proxy, proxy.tls.tls.unwrap_or(false))
+            .await
+            .map_err(|e| anyhow!("VLESS {}", e))?;
+        let mut stream = VlessStream::new(inner, header);
+        stream.flush().await?;
+        return Ok(Box::new(stream));
+    }
+
+    // REALITY 与 Vision 需要掌控 TLS 记录层，使用自带的 TLS 1.3 客户端
+    let tls_options = TlsOptions::from_node(proxy);
+    let mut alpn = transport.alpn();
+    if alpn.is_empty() {
+        alpn = tls_options.alpn.clone();
+    }
+    let auth = match reality {
+        Some(options) => {
+            if alpn.is_empty() {
+                alpn = vec!["h2".to_string(), "http/1.1".to_string()];
+            }
+            ServerAuth::Reality(options)
+        }
+        None => ServerAuth::Certificate {
+            insecure: tls_options.insecure,
+        },
+    };
+
+    let tcp = connect_tcp(&proxy.server, proxy.port)
+        .await
+        .map_err(|e| anyhow!("连接 VLESS 服务器失败: {}", e))?;
+    let tls = tls13::connect(tcp, &tls_options.sni, &alpn, &auth)
+        .await
+        .map_err(|e| anyhow!("VLESS TLS 握手失败: {}", e))?;
+    if vision {
+        let mut stream = VisionStream::new(VlessStream::new(tls, header), uuid);
+        stream.flush().await?;
+        return Ok(Box::new(stream));
+    }
+
+    let inner = transport
+        .handshake(Box::new(tls), true)
+        .await
+        .map_err(|e| anyhow!("VLESS 传输层握手失败: {}", e))?;
+    let mut stream = VlessStream::new(inner, header);
+    stream.flush().await?;
+    Ok(Box::new(stream))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::outbound::dial;
+    use crate::outbound::tls::test_server;
+    use crate::proxy::Protocol;
+    use rand::Rng;
+    use tokio::io::{AsyncRead, AsyncReadExt};
+    use tokio::net::TcpListener;
+
+    const TEST_UUID: &str = "b831381d-6324-4d53-ad4f-8cda48b30811";
+
+    /// 读取并校验 VLESS 请求头，返回 flow
+    async fn read_request<S: AsyncRead + Unpin>(stream: &mut S) -> Option<String> {
+        assert_eq!(stream.read_u8().await.unwrap(), VERSION);
+        let mut uuid = [0u8; 16];
+        stream.read_exact(&mut uuid).await.unwrap();
+        assert_eq!(uuid, parse_uuid(TEST_UUID).unwrap());
+        let mut addons = vec![0u8; stream.read_u8().await.unwrap() as usize];
+        stream.read_exact(&mut addons).await.unwrap();
+        assert_eq!(stream.read_u8().await.unwrap(), CMD_TCP);
+        let port = stream.read_u16().await.unwrap();
+        assert_eq!(stream.read_u8().await.unwrap(), 2);
+        let mut domain = vec![0u8; stream.read_u8().await.unwrap() as usize];
+        stream.read_exact(&mut domain).await.unwrap();
+        assert_eq!((domain.as_slice(), port), (&b"example.com"[..], 80));
+        (!addons.is_empty()).then(|| String::from_utf8(addons[2..].to_vec()).unwrap())
+    }
+
+    /// 读取一个 Vision 填充帧，返回命令与内容
+    async fn read_frame<S: AsyncRead + Unpin>(stream: &mut S) -> (u8, Vec<u8>) {
+        let command = stream.read_u8().await.unwrap();
+        let mut content = vec![0u8; stream.read_u16().await.unwrap() as usize];
+        let mut padding = vec![0u8; stream.read_u16().await.unwrap() as usize];
+        stream.read_exact(&mut content).await.unwrap();
+        stream.read_exact(&mut padding).await.unwrap();
+        (command, content)
+    }
+
+    fn server_frame(uuid: Option<&[u8; 16]>, command: u8, content: &[u8]) -> Vec<u8> {
+        let padding = rand::thread_rng().gen_range(0..64u16);
+        let mut frame = uuid.map(|uuid| uuid.to_vec()).unwrap_or_default();
+        frame.push(command);
+        frame.extend_from_slice(&(content.len() as u16).to_be_bytes());
+        frame.extend_from_slice(&padding.to_be_bytes());
+        frame.extend_from_slice(content);
+        frame.resize(frame.len() + padding as usize, 0xee);
+        frame
+    }
+
+    fn node(port: u16, flow: Option<&str>) -> ProxyNode {
+        ProxyNode::new(
+            "vless".to_string(),
+            "127.0.0.1".to_string(),
+            port,
+            Protocol::Vless(VlessParams {
+                uuid: TEST_UUID.to_string(),
+                flow: flow.map(str::to_string),
+                ..Default::default()
+            }),
+        )
+    }
+
+    #[test]
+    fn test_request_header() {
+        let uuid = [0x11u8; 16];
+        let target = Address::Socket("1.2.3.4:443".parse().unwrap());
+        let header = request_header(&uuid, None, &target);
+        assert_eq!(header[0], 0);
+        assert_eq!(&header[1..17], &uuid);
+        assert_eq!(&header[17..], &[0, 0x01, 0x01, 0xbb, 0x01, 1, 2, 3, 4]);
+
+        let header = request_header(&uuid, Some(FLOW_VISION), &target);
+        assert_eq!(header[17] as usize, 2 + FLOW_VISION.len());
+        assert_eq!(&header[18..20], &[0x0a, FLOW_VISION.len() as u8]);
+        assert_eq!(&header[20..20 + FLOW_VISION.len()], FLOW_VISION.as_bytes());
+    }
+
+    #[tokio::test]
+    async fn test_vless_roundtrip() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (mut sock, _) = listener.accept().await.unwrap();
+            assert_eq!(read_request(&mut sock).await, None);
+            // 响应头带上附加信息，客户端应当跳过
+            sock.write_all(&[0, 2, 0xaa, 0xbb]).await.unwrap();
+            let mut buf = [0u8; 1024];
+            loop {
+                let n = sock.read(&mut buf).await.unwrap_or(0);
+                if n == 0 {
+                    break;
+                }
+                sock.write_all(&buf[..n]).await.unwrap();
+            }
+        });
+
+        let target = Address::Domain("example.com".to_string(), 80);
+        let mut stream = dial(&node(port, None), &target).await.unwrap();
+        stream.write_all(b"hello vless").await.unwrap();
+        let mut buf = [0u8; 11];
+        stream.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"hello vless");
+    }
+
+    #[tokio::test]
+    async fn test_vision_direct_switch() {
+        let acceptor = test_server::acceptor("vision.test", &[]);
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(async move {
+            let (tcp, _) = listener.accept().await.unwrap();
+            let mut stream = acceptor.accept(tcp).await.unwrap();
+            assert_eq!(
+                read_request(&mut stream).await.as_deref(),
+                Some(FLOW_VISION)
+            );
+
+            let uuid = parse_uuid(TEST_UUID).unwrap();
+            let mut first = [0u8; 16];
+            stream.read_exact(&mut first).await.unwrap();
+            assert_eq!(first, uuid);
+            let mut received = Vec::new();
+            loop {
+                let (command, content) = read_frame(&mut stream).await;
+                received.extend_from_slice(&content);
+                if command != 0 {
+                    assert_eq!(command, 1);
+                    break;
+                }
+            }
+            assert_eq!(received, b"\x17\x03\x03ping");
+
+            let mut response = vec![0u8, 0];
+            response.extend_from_slice(&server_frame(Some(&uuid), 0, b"hello "));
+            response.extend_from_slice(&server_frame(None, 2, b"world "));
+            stream.write_all(&response).await.unwrap();
+            stream.flush().await.unwrap();
+            // 直连之后绕过 TLS 直接写入 TCP
+            stream.get_mut().0.write_all(b"raw bytes").await.unwrap();
+
+            let mut after = [0u8; 5];
+            stream.read_exact(&mut after).await.unwrap();
+            assert_eq!(&after, b"after");
+        });
+
+        let mut node = node(port, Some(FLOW_VISION));
+        node.tls.tls = Some(true);
+        node.tls.sni = Some("vision.test".to_string());
+        node.tls.skip_cert_verify = Some(true);
+        let target = Address::Domain("example.com".to_string(), 80);
+        let mut stream = dial(&node, &target).await.unwrap();
+
+        stream.write_all(b"\x17\x03\x03ping").await.unwrap();
+        stream.flush().await.unwrap();
+        let mut buf = [0u8; 21];
+        stream.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"hello world raw bytes");
+
+        stream.write_all(b"after").await.unwrap();
+        stream.flush().await.unwrap();
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_vision_requires_tls() {
+        let node = node(1, Some(FLOW_VISION));
+        let target = Address::Domain("example.com".to_string(), 80);
+        let err = dial(&node, &target).await.err().unwrap();
+        assert!(err.to_string().contains("Vision"));
+    }
+}
@@ -0,0 +1,726 @@
+//! VMess 出站
+//! 实现 AEAD 请求头与 aes-128-gcm / chacha20-poly1305 / none 数据加密
+//!
+//! alterId 不为 0 的节点同样使用 AEAD 请求头，当前服务端实现均兼容
+
+use super::crypto::{AeadCipher, AeadKind, TAG_LEN};
+use super::util::{FramedWrite, copy_plain, invalid_data, poll_drain, poll_fill};
+use super::{Address, BoxedStream, transport};
+use crate::proxy::ProxyNode;
+use crate::proxy::node::VmessParams;
+use aes::cipher::{BlockEncrypt, KeyInit, generic_array::GenericArray};
+use anyhow::{Result, anyhow};
+use md5::Md5;
+use rand::{Rng, RngCore};
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use sha3::digest::{ExtendableOutput, XofReader};
+use sha3::{Shake128, Shake128Reader};
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll, ready};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt, ReadBuf};
+
+/// 计算 cmdKey 时附加在 UUID 之后的固定盐值
+const CMD_KEY_SALT: &[u8] = b"c48619fe-8f02-49e0-b9e9-edf763e17e21";
+/// AEAD 密钥派生的根密钥
+const KDF_SALT: &[u8] = b"VMess AEAD KDF";
+const KDF_AUTH_ID_KEY: &[u8] = b"AES Auth ID Encryption";
+const KDF_HEADER_LEN_KEY: &[u8] = b"VMess Header AEAD Key_Length";
+const KDF_HEADER_LEN_NONCE: &[u8] = b"VMess Header AEAD Nonce_Length";
+const KDF_HEADER_KEY: &[u8] = b"VMess Header AEAD Key";
+const KDF_HEADER_NONCE: &[u8] = b"VMess Header AEAD Nonce";
+const KDF_RESP_LEN_KEY: &[u8] = b"AEAD Resp Header Len Key";
+const KDF_RESP_LEN_IV: &[u8] = b"AEAD Resp Header Len IV";
+const KDF_RESP_KEY: &[u8] = b"AEAD Resp Header Key";
+const KDF_RESP_IV: &[u8] = b"AEAD Resp Header IV";
+
+const VERSION: u8 = 1;
+/// 选项：数据分块传输
+const OPTION_CHUNK_STREAM: u8 = 0x01;
+/// 选项：用 SHAKE128 掩码块长度
+const OPTION_CHUNK_MASKING: u8 = 0x04;
+const CMD_TCP: u8 = 0x01;
+/// 单个数据块的最大负载
+const MAX_CHUNK_PAYLOAD: usize = 8192;
+
+/// VMess 数据加密方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Security {
+    Aes128Gcm,
+    Chacha20Poly1305,
+    None,
+}
+
+impl Security {
+    /// 根据 Clash 配置中的 `cipher` 名称解析加密方式，`auto` 选择 aes-128-gcm
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "" | "auto" | "aes-128-gcm" => Ok(Security::Aes128Gcm),
+            "chacha20-poly1305" | "chacha20-ietf-poly1305" => Ok(Security::Chacha20Poly1305),
+            "none" => Ok(Security::None),
+            other => Err(anyhow!("不支持的 VMess 加密方式: {}", other)),
+        }
+    }
+
+    /// 请求头中的加密方式编号
+    fn code(self) -> u8 {
+        match self {
+            Security::Aes128Gcm => 0x03,
+            Security::Chacha20Poly1305 => 0x04,
+            Security::None => 0x05,
+        }
+    }
+}
+
+/// 解析用户 ID，非标准 UUID 的字符串按空命名空间的 UUIDv5 映射
+pub fn parse_uuid(id: &str) -> Result<[u8; 16]> {
+    let id = id.trim();
+    if id.is_empty() {
+        return Err(anyhow!("UUID 为空"));
+    }
+
+    let hex: Vec<u8> = id.bytes().filter(|b| *b != b'-').collect();
+    if hex.len() == 32 {
+        let mut uuid = [0u8; 16];
+        let parsed = hex.chunks(2).zip(uuid.iter_mut()).all(|(pair, byte)| {
+            std::str::from_utf8(pair)
+                .ok()
+                .and_then(|s| u8::from_str_radix(s, 16).ok())
+                .map(|value| *byte = value)
+                .is_some()
+        });
+        if parsed {
+            return Ok(uuid);
+        }
+    }
+
+    let mut hasher = Sha1::new();
+    hasher.update([0u8; 16]);
+    hasher.update(id.as_bytes());
+    let mut uuid = [0u8; 16];
+    uuid.copy_from_slice(&hasher.finalize()[..16]);
+    uuid[6] = (uuid[6] & 0x0f) | 0x50;
+    uuid[8] = (uuid[8] & 0x3f) | 0x80;
+    Ok(uuid)
+}
+
+/// 由 UUID 计算请求头加密使用的 cmdKey
+fn cmd_key(uuid: &[u8; 16]) -> [u8; 16] {
+    let mut hasher = Md5::new();
+    hasher.update(uuid);
+    hasher.update(CMD_KEY_SALT);
+    hasher.finalize().into()
+}
+
+/// 以 `keys` 最后一项为密钥、其余层为哈希函数的嵌套 HMAC，最内层为 SHA256
+fn nested_hmac(keys: &[&[u8]], data: &[u8]) -> [u8; 32] {
+    let Some((key, inner_keys)) = keys.split_last() else {
+        return Sha256::digest(data).into();
+    };
+
+    let mut block = [0u8; 64];
+    if key.len() > block.len() {
+        block[..32].copy_from_slice(&nested_hmac(inner_keys, key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+
+    let mut inner: Vec<u8> = block.iter().map(|b| b ^ 0x36).collect();
+    inner.extend_from_slice(data);
+    let inner_hash = nested_hmac(inner_keys, &inner);
+
+    let mut outer: Vec<u8> = block.iter().map(|b| b ^ 0x5c).collect();
+    outer.extend_from_slice(&inner_hash);
+    nested_hmac(inner_keys, &outer)
+}
+
+/// VMess AEAD 密钥派生
+fn kdf(key: &[u8], path: &[&[u8]]) -> [u8; 32] {
+    let mut keys = Vec::with_capacity(path.len() + 1);
+    keys.push(KDF_SALT);
+    keys.extend_from_slice(path);
+    nested_hmac(&keys, key)
+}
+
+fn kdf16(key: &[u8], path: &[&[u8]]) -> [u8; 16] {
+    let mut out = [0u8; 16];
+    out.copy_from_slice(&kdf(key, path)[..16]);
+    out
+}
+
+fn kdf_nonce(key: &[u8], path: &[&[u8]]) -> [u8; 12] {
+    let mut out = [0u8; 12];
+    out.copy_from_slice(&kdf(key, path)[..12]);
+    out
+}
+
+fn unix_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// 生成 AES 加密的认证 ID：时间戳 + 随机数 + CRC32
+fn create_auth_id(cmd_key: &[u8; 16], timestamp: u64) -> [u8; 16] {
+    let mut plain = [0u8; 16];
+    plain[..8].copy_from_slice(&timestamp.to_be_bytes());
+    rand::thread_rng().fill_bytes(&mut plain[8..12]);
+    let crc = crc32fast::hash(&plain[..12]);
+    plain[12..].copy_from_slice(&crc.to_be_bytes());
+
+    let cipher = aes::Aes128::new(GenericArray::from_slice(&kdf16(
+        cmd_key,
+        &[KDF_AUTH_ID_KEY],
+    )));
+    let mut block = GenericArray::clone_from_slice(&plain);
+    cipher.encrypt_block(&mut block);
+    let mut auth_id = [0u8; 16];
+    auth_id.copy_from_slice(&block);
+    auth_id
+}
+
+/// 用 AEAD 封装请求头：认证 ID + 加密长度 + 连接 nonce + 加密请求头
+fn seal_header(cmd_key: &[u8; 16], header: &[u8]) -> Vec<u8> {
+    let auth_id = create_auth_id(cmd_key, unix_timestamp());
+    let mut nonce = [0u8; 8];
+    rand::thread_rng().fill_bytes(&mut nonce);
+
+    let len_cipher = AeadCipher::new(
+        AeadKind::Aes128Gcm,
+        &kdf16(cmd_key, &[KDF_HEADER_LEN_KEY, &auth_id, &nonce]),
+    );
+    let len_nonce = kdf_nonce(cmd_key, &[KDF_HEADER_LEN_NONCE, &auth_id, &nonce]);
+    let sealed_len =
+        len_cipher.encrypt_aad(&len_nonce, &(header.len() as u16).to_be_bytes(), &auth_id);
+
+    let header_cipher = AeadCipher::new(
+        AeadKind::Aes128Gcm,
+        &kdf16(cmd_key, &[KDF_HEADER_KEY, &auth_id, &nonce]),
+    );
+    let header_nonce = kdf_nonce(cmd_key, &[KDF_HEADER_NONCE, &auth_id, &nonce]);
+    let sealed_header = header_cipher.encrypt_aad(&header_nonce, header, &auth_id);
+
+    let mut out = Vec::with_capacity(16 + sealed_len.len() + 8 + sealed_header.len());
+    out.extend_from_slice(&auth_id);
+    out.extend_from_slice(&sealed_len);
+    out.extend_from_slice(&nonce);
+    out.extend_from_slice(&sealed_header);
+    out
+}
+
+fn fnv1a32(data: &[u8]) -> u32 {
+    data.iter().fold(0x811c_9dc5u32, |hash, byte| {
+        (hash ^ *byte as u32).wrapping_mul(0x0100_0193)
+    })
+}
+
+/// 数据块加解密状态：计数 nonce 的 AEAD 加上 SHAKE128 长度掩码
+struct ChunkCipher {
+    aead: Option<AeadCipher>,
+    iv: [u8; 16],
+    count: u16,
+    mask: Shake128Reader,
+}
+
+impl ChunkCipher {
+    fn new(security: Security, key: &[u8; 16], iv: &[u8; 16]) -> Self {
+        let aead = match security {
+            Security::Aes128Gcm => Some(AeadCipher::new(AeadKind::Aes128Gcm, key)),
+            Security::Chacha20Poly1305 => {
+                let first = Md5::digest(key);
+                let second = Md5::digest(first);
+                let mut full_key = first.to_vec();
+                full_key.extend_from_slice(&second);
+                Some(AeadCipher::new(AeadKind::ChaCha20Poly1305, &full_key))
+            }
+            Security::None => None,
+        };
+
+        let mut shake = Shake128::default();
+        sha3::digest::Update::update(&mut shake, iv);
+        Self {
+            aead,
+            iv: *iv,
+            count: 0,
+            mask: shake.finalize_xof(),
+        }
+    }
+
+    /// 每个数据块的额外开销
+    fn overhead(&self) -> usize {
+        if self.aead.is_some() { TAG_LEN } else { 0 }
+    }
+
+    fn next_mask(&mut self) -> u16 {
+        let mut mask = [0u8; 2];
+        self.mask.read(&mut mask);
+        u16::from_be_bytes(mask)
+    }
+
+    fn next_nonce(&mut self) -> [u8; 12] {
+        let mut nonce = [0u8; 12];
+        nonce[..2].copy_from_slice(&self.count.to_be_bytes());
+        nonce[2..].copy_from_slice(&self.iv[2..12]);
+        self.count = self.count.wrapping_add(1);
+        nonce
+    }
+
+    /// 加密一个数据块并附加掩码后的长度
+    fn seal_chunk(&mut self, payload: &[u8]) -> Vec<u8> {
+        let nonce = self.next_nonce();
+        let body = match &self.aead {
+            Some(aead) => aead.encrypt(&nonce, payload),
+            None => payload.to_vec(),
+        };
+        let len = body.len() as u16 ^ self.next_mask();
+        let mut chunk = Vec::with_capacity(2 + body.len());
+        chunk.extend_from_slice(&len.to_be_bytes());
+        chunk.extend_from_slice(&body);
+        chunk
+    }
+
+    fn decode_len(&mut self, len: [u8; 2]) -> usize {
+        (u16::from_be_bytes(len) ^ self.next_mask()) as usize
+    }
+
+    fn open_chunk(&mut self, chunk: &[u8]) -> io::Result<Vec<u8>> {
+        let nonce = self.next_nonce();
+        match &self.aead {
+            Some(aead) => aead.decrypt(&nonce, chunk),
+            None => Ok(chunk.to_vec()),
+        }
+    }
+}
+
+/// 由请求密钥派生响应密钥（SHA256 前 16 字节）
+fn response_key(request_key: &[u8; 16]) -> [u8; 16] {
+    let mut key = [0u8; 16];
+    key.copy_from_slice(&Sha256::digest(request_key)[..16]);
+    key
+}
+
+/// 响应读取状态
+enum ReadState {
+    /// 等待加密的响应头长度
+    HeaderLength,
+    /// 等待指定长度的加密响应头
+    Header(usize),
+    /// 等待块长度
+    Length,
+    /// 等待指定长度的数据块
+    Payload(usize),
+    /// 已收到结束块
+    Eof,
+}
+
+/// VMess 加密连接
+pub struct VmessStream<S> {
+    inner: S,
+    enc: ChunkCipher,
+    dec: ChunkCipher,
+    response_key: [u8; 16],
+    response_iv: [u8; 16],
+    response_auth: u8,
+    read_state: ReadState,
+    rbuf: Vec<u8>,
+    plain: Vec<u8>,
+    plain_pos: usize,
+    wbuf: Vec<u8>,
+    shutdown: bool,
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> VmessStream<S> {
+    /// 在已建立的底层连接上包装 VMess 加密，请求头在第一次写入或 flush 时发出
+    pub fn new(inner: S, uuid: &[u8; 16], security: Security, target: &Address) -> Self {
+        let mut rng = rand::thread_rng();
+        let mut request_key = [0u8; 16];
+        let mut request_iv = [0u8; 16];
+        rng.fill_bytes(&mut request_key);
+        rng.fill_bytes(&mut request_iv);
+        let response_auth: u8 = rng.r#gen();
+        let padding_len: u8 = rng.gen_range(0..16);
+
+        let mut header = Vec::with_capacity(64 + padding_len as usize);
+        header.push(VERSION);
+        header.extend_from_slice(&request_iv);
+        header.extend_from_slice(&request_key);
+        header.push(response_auth);
+        header.push(OPTION_CHUNK_STREAM | OPTION_CHUNK_MASKING);
+        header.push((padding_len << 4) | security.code());
+        header.push(0);
+        header.push(CMD_TCP);
+        target.write_vmess(&mut header);
+        let mut padding = vec![0u8; padding_len as usize];
+        rng.fill_bytes(&mut padding);
+        header.extend_from_slice(&padding);
+        let checksum = fnv1a32(&header);
+        header.extend_from_slice(&checksum.to_be_bytes());
+
+        let response_key = response_key(&request_key);
+        let response_iv = self::response_key(&request_iv);
+
+        Self {
+            inner,
+            enc: ChunkCipher::new(security, &request_key, &request_iv),
+            dec: ChunkCipher::new(security, &response_key, &response_iv),
+            response_key,
+            response_iv,
+            response_auth,
+            read_state: ReadState::HeaderLength,
+            rbuf: Vec::new(),
+            plain: Vec::new(),
+            plain_pos: 0,
+            wbuf: seal_header(&cmd_key(uuid), &header),
+            shutdown: false,
+        }
+    }
+
+    /// 当前状态下需要的字节数
+    fn frame_len(&self) -> usize {
+        match self.read_state {
+            ReadState::HeaderLength => 2 + TAG_LEN,
+            ReadState::Header(len) => len + TAG_LEN,
+            ReadState::Length => 2,
+            ReadState::Payload(len) => len,
+            ReadState::Eof => 0,
+        }
+    }
+
+    /// 处理一帧完整的数据
+    fn process_frame(&mut self, frame: Vec<u8>) -> io::Result<()> {
+        match self.read_state {
+            ReadState::HeaderLength => {
+                let cipher = AeadCipher::new(
+                    AeadKind::Aes128Gcm,
+                    &kdf16(&self.response_key, &[KDF_RESP_LEN_KEY]),
+                );
+                let nonce = kdf_nonce(&self.response_iv, &[KDF_RESP_LEN_IV]);
+                let len = cipher.decrypt(&nonce, &frame)?;
+                self.read_state = ReadState::Header(u16::from_be_bytes([len[0], len[1]]) as usize);
+            }
+            ReadState::Header(_) => {
+                let cipher = AeadCipher::new(
+                    AeadKind::Aes128Gcm,
+                    &kdf16(&self.response_key, &[KDF_RESP_KEY]),
+                );
+                let nonce = kdf_nonce(&self.response_iv, &[KDF_RESP_IV]);
+                let header = cipher.decrypt(&nonce, &frame)?;
+                if header.first() != Some(&self.response_auth) {
+                    return Err(invalid_data("VMess 响应认证字节不匹配"));
+                }
+                self.read_state = ReadState::Length;
+            }
+            ReadState::Length => {
+                let len = self.dec.decode_len([frame[0], frame[1]]);
+                let overhead = self.dec.overhead();
+                self.read_state = if len == overhead {
+                    ReadState::Eof
+                } else if len < overhead {
+                    return Err(invalid_data("VMess 数据块长度错误"));
+                } else {
+                    ReadState::Payload(len)
+                };
+            }
+            ReadState::Payload(_) => {
+                self.plain = self.dec.open_chunk(&frame)?;
+                self.plain_pos = 0;
+                self.read_state = ReadState::Length;
+            }
+            ReadState::Eof => {}
+        }
+        Ok(())
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> AsyncRead for VmessStream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        loop {
+            if copy_plain(&mut this.plain, &mut this.plain_pos, buf) {
+                return Poll::Ready(Ok(()));
+            }
+            if matches!(this.read_state, ReadState::Eof) {
+                return Poll::Ready(Ok(()));
+            }
+
+            let need = this.frame_len();
+            if !ready!(poll_fill(&mut this.inner, cx, &mut this.rbuf, need))? {
+                if this.rbuf.is_empty() && matches!(this.read_state, ReadState::Length) {
+                    return Poll::Ready(Ok(()));
+                }
+                return Poll::Ready(Err(io::ErrorKind::UnexpectedEof.into()));
+            }
+
+            let frame: Vec<u8> = this.rbuf.drain(..need).collect();
+            this.process_frame(frame)?;
+        }
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> FramedWrite for VmessStream<S> {
+    type Inner = S;
+
+    fn write_parts(&mut self) -> (&mut S, &mut Vec<u8>) {
+        (&mut self.inner, &mut self.wbuf)
+    }
+
+    fn encode_frame(&mut self, data: &[u8]) -> usize {
+        let n = data.len().min(MAX_CHUNK_PAYLOAD);
+        let chunk = self.enc.seal_chunk(&data[..n]);
+        self.wbuf.extend_from_slice(&chunk);
+        n
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> AsyncWrite for VmessStream<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.get_mut().poll_write_framed(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(poll_drain(&mut this.inner, cx, &mut this.wbuf))?;
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if !this.shutdown {
+            // 空数据块通知服务端请求结束
+            let chunk = this.enc.seal_chunk(&[]);
+            this.wbuf.extend_from_slice(&chunk);
+            this.shutdown = true;
+        }
+        ready!(poll_drain(&mut this.inner, cx, &mut this.wbuf))?;
+        Pin::new(&mut this.inner).poll_shutdown(cx)
+    }
+}
+
+/// 通过 VMess 节点连接目标地址
+pub async fn connect(
+    proxy: &ProxyNode,
+    params: &VmessParams,
+    target: &Address,
+) -> Result<VmessStream<BoxedStream>> {
+    let uuid = parse_uuid(&params.uuid)?;
+    let security = Security::from_name(params.cipher.as_deref().unwrap_or("auto"))?;
+
+    let inner = transport::connect(proxy, proxy.tls.tls.unwrap_or(false))
+        .await
+        .map_err(|e| anyhow!("VMess {}", e))?;
+
+    let mut stream = VmessStream::new(inner, &uuid, security, target);
+    stream.flush().await?;
+    Ok(stream)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aes::cipher::BlockDecrypt;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+
+    const TEST_UUID: &str = "b831381d-6324-4d53-ad4f-8cda48b30811";
+
+    /// 本地 VMess 服务端替身：解开 AEAD 请求头，回写响应头后回显数据
+    async fn serve_echo_once(listener: TcpListener, uuid: [u8; 16]) -> (Address, u8) {
+        let (mut sock, _) = listener.accept().await.unwrap();
+        let cmd_key = cmd_key(&uuid);
+
+        let mut prefix = [0u8; 16 + 2 + TAG_LEN + 8];
+        sock.read_exact(&mut prefix).await.unwrap();
+        let auth_id: [u8; 16] = prefix[..16].try_into().unwrap();
+        let nonce: [u8; 8] = prefix[34..].try_into().unwrap();
+
+        let mut plain_id = GenericArray::clone_from_slice(&auth_id);
+        aes::Aes128::new(GenericArray::from_slice(&kdf16(
+            &cmd_key,
+            &[KDF_AUTH_ID_KEY],
+        )))
+        .decrypt_block(&mut plain_id);
+        assert_eq!(
+            crc32fast::hash(&plain_id[..12]).to_be_bytes(),
+            plain_id[12..16]
+        );
+        let timestamp = u64::from_be_bytes(plain_id[..8].try_into().unwrap());
+        assert!(unix_timestamp().abs_diff(timestamp) < 5);
+
+        let len = AeadCipher::new(
+            AeadKind::Aes128Gcm,
+            &kdf16(&cmd_key, &[KDF_HEADER_LEN_KEY, &auth_id, &nonce]),
+        )
+        .decrypt_aad(
+            &kdf_nonce(&cmd_key, &[KDF_HEADER_LEN_NONCE, &auth_id, &nonce]),
+            &prefix[16..34],
+            &auth_id,
+        )
+        .unwrap();
+        let len = u16::from_be_bytes([len[0], len[1]]) as usize;
+
+        let mut sealed = vec![0u8; len + TAG_LEN];
+        sock.read_exact(&mut sealed).await.unwrap();
+        let header = AeadCipher::new(
+            AeadKind::Aes128Gcm,
+            &kdf16(&cmd_key, &[KDF_HEADER_KEY, &auth_id, &nonce]),
+        )
+        .decrypt_aad(
+            &kdf_nonce(&cmd_key, &[KDF_HEADER_NONCE, &auth_id, &nonce]),
+            &sealed,
+            &auth_id,
+        )
+        .unwrap();
+
+        let (body, checksum) = header.split_at(header.len() - 4);
+        assert_eq!(fnv1a32(body).to_be_bytes(), checksum);
+        assert_eq!(body[0], VERSION);
+        let request_iv: [u8; 16] = body[1..17].try_into().unwrap();
+        let request_key: [u8; 16] = body[17..33].try_into().unwrap();
+        let response_auth = body[33];
+        assert_eq!(body[34], OPTION_CHUNK_STREAM | OPTION_CHUNK_MASKING);
+        let security_code = body[35] & 0x0f;
+        let security = match security_code {
+            0x03 => Security::Aes128Gcm,
+            0x04 => Security::Chacha20Poly1305,
+            _ => Security::None,
+        };
+        assert_eq!(body[37], CMD_TCP);
+        let port = u16::from_be_bytes([body[38], body[39]]);
+        assert_eq!(body[40], 0x02);
+        let domain_len = body[41] as usize;
+        let domain = String::from_utf8(body[42..42 + domain_len].to_vec()).unwrap();
+        assert_eq!(body.len(), 42 + domain_len + (body[35] >> 4) as usize);
+
+        let mut dec = ChunkCipher::new(security, &request_key, &request_iv);
+        let response_key = response_key(&request_key);
+        let response_iv = self::response_key(&request_iv);
+        let mut enc = ChunkCipher::new(security, &response_key, &response_iv);
+
+        let resp_header = [response_auth, 0, 0, 0];
+        let mut response = AeadCipher::new(
+            AeadKind::Aes128Gcm,
+            &kdf16(&response_key, &[KDF_RESP_LEN_KEY]),
+        )
+        .encrypt(
+            &kdf_nonce(&response_iv, &[KDF_RESP_LEN_IV]),
+            &(resp_header.len() as u16).to_be_bytes(),
+        );
+        response.extend_from_slice(
+            &AeadCipher::new(AeadKind::Aes128Gcm, &kdf16(&response_key, &[KDF_RESP_KEY]))
+                .encrypt(&kdf_nonce(&response_iv, &[KDF_RESP_IV]), &resp_header),
+        );
+        sock.write_all(&response).await.unwrap();
+
+        loop {
+            let mut len = [0u8; 2];
+            sock.read_exact(&mut len).await.unwrap();
+            let len = dec.decode_len(len);
+            if len == dec.overhead() {
+                break;
+            }
+            let mut chunk = vec![0u8; len];
+            sock.read_exact(&mut chunk).await.unwrap();
+            let data = dec.open_chunk(&chunk).unwrap();
+            sock.write_all(&enc.seal_chunk(&data)).await.unwrap();
+        }
+        sock.write_all(&enc.seal_chunk(&[])).await.unwrap();
+
+        (Address::Domain(domain, port), security_code)
+    }
+
+    async fn roundtrip(security: Security) {
+        let uuid = parse_uuid(TEST_UUID).unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(serve_echo_once(listener, uuid));
+
+        let target = Address::Domain("example.com".to_string(), 443);
+        let tcp = TcpStream::connect(addr).await.unwrap();
+        let mut stream = VmessStream::new(tcp, &uuid, security, &target);
+
+        let payload = vec![0x5au8; 20000];
+        stream.write_all(&payload).await.unwrap();
+        stream.flush().await.unwrap();
+        let mut echoed = vec![0u8; payload.len()];
+        stream.read_exact(&mut echoed).await.unwrap();
+        assert_eq!(echoed, payload);
+
+        stream.shutdown().await.unwrap();
+        let mut rest = Vec::new();
+        stream.read_to_end(&mut rest).await.unwrap();
+        assert!(rest.is_empty());
+
+        let (received, code) = server.await.unwrap();
+        assert_eq!(received, target);
+        assert_eq!(code, security.code());
+    }
+
+    #[tokio::test]
+    async fn test_vmess_roundtrip() {
+        roundtrip(Security::Aes128Gcm).await;
+        roundtrip(Security::Chacha20Poly1305).await;
+        roundtrip(Security::None).await;
+    }
+
+    #[test]
+    fn test_kdf() {
+        let hex = |bytes: &[u8]| {
+            bytes
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect::<String>()
+        };
+        assert_eq!(
+            hex(&kdf(b"key", &[])),
+            "385e28ac08671660f62ac976f5e64a31827aea172eff77cb2b046c52de9c08b0"
+        );
+        assert_eq!(
+            hex(&kdf(b"key", &[KDF_AUTH_ID_KEY])),
+            "ffe9b1a9952b7c91b33c21540d8522207e7706c52fa3a95891f69efb3b88e087"
+        );
+        assert_eq!(
+            hex(&kdf(
+                b"key",
+                &[KDF_HEADER_KEY, b"0123456789abcdef", b"nonce123"]
+            )),
+            "67bf4e7ec4fe51fe936b0361a53f691ba73f30af253eea21d941a2e30af8d7d1"
+        );
+    }
+
+    #[test]
+    fn test_parse_uuid() {
+        let uuid = parse_uuid(TEST_UUID).unwrap();
+        assert_eq!(uuid[0], 0xb8);
+        assert_eq!(uuid[15], 0x11);
+
+        // 非 UUID 字符串映射为 UUIDv5
+        let mapped = parse_uuid("example").unwrap();
+        assert_eq!(
+            mapped,
+            parse_uuid("feb54431-301b-52bb-a6dd-e1e93e81bb9e").unwrap()
+        );
+        assert!(parse_uuid("").is_err());
+    }
+
+    #[test]
+    fn test_security_from_name() {
+        assert_eq!(Security::from_name("auto").unwrap(), Security::Aes128Gcm);
+        assert_eq!(
+            Security::from_name("chacha20-poly1305").unwrap(),
+            Security::Chacha20Poly1305
+        );
+        assert_eq!(Security::from_name("none").unwrap(), Security::None);
+        assert!(Security::from_name("aes-128-cfb").is_err());
+    }
+}
@@ -0,0 +1,460 @@
+//! WireGuard 出站
+//! 完全在用户态实现：Noise 握手建立会话后，在隧道内运行 smoltcp 协议栈，
+//! 每次拨号对应隧道内的一条 TCP 连接
+//!
+//! 支持 `private-key`、`public-key`、`pre-shared-key`、`ip`/`ipv6`、`allowed-ips`、
+//! `reserved` 与 `mtu` 字段；目标域名在本地解析
+
+mod noise;
+mod stack;
+
+use self::noise::{Initiator, Keys, MSG_COOKIE_REPLY, MSG_RESPONSE, RESPONSE_LEN, Session};
+use self::stack::{Tunnel, apply_reserved};
+use super::{Address, BoxedStream};
+use crate::proxy::ProxyNode;
+use crate::proxy::node::WireGuardParams;
+use anyhow::{Result, anyhow};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use ipnetwork::IpNetwork;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Duration;
+use tokio::net::UdpSocket;
+use tokio::time::Instant;
+use x25519_dalek::{PublicKey, StaticSecret};
+
+/// 默认 MTU，与常见客户端一致
+const DEFAULT_MTU: usize = 1408;
+/// 单次握手等待响应的时间
+const REKEY_TIMEOUT: Duration = Duration::from_secs(5);
+/// 握手重试次数
+const HANDSHAKE_ATTEMPTS: usize = 3;
+
+/// 从节点字段整理出的 WireGuard 参数
+pub struct WireGuardConfig {
+    keys: Keys,
+    reserved: [u8; 3],
+    ipv4: Option<Ipv4Addr>,
+    ipv6: Option<Ipv6Addr>,
+    allowed_ips: Vec<IpNetwork>,
+    mtu: usize,
+}
+
+impl WireGuardConfig {
+    pub fn from_node(proxy: &ProxyNode, params: &WireGuardParams) -> Result<Self> {
+        let private = decode_key("私钥", Some(&params.private_key), &proxy.name)?;
+        let peer_public = decode_key("公钥", Some(&params.public_key), &proxy.name)?;
+        let pre_shared = match params.pre_shared_key.as_deref().filter(|k| !k.is_empty()) {
+            Some(key) => decode_key("预共享密钥", Some(key), &proxy.name)?,
+            None => [0u8; 32],
+        };
+
+        let ipv4 = match params.ip.as_deref().filter(|ip| !ip.is_empty()) {
+            Some(ip) => match parse_interface_ip(ip)? {
+                IpAddr::V4(ip) => Some(ip),
+                IpAddr::V6(_) => return Err(anyhow!("WireGuard ip 字段应为 IPv4 地址: {}", ip)),
+            },
+            None => None,
+        };
+        let ipv6 = match params.ipv6.as_deref().filter(|ip| !ip.is_empty()) {
+            Some(ip) => match parse_interface_ip(ip)? {
+                IpAddr::V6(ip) => Some(ip),
+                IpAddr::V4(_) => return Err(anyhow!("WireGuard ipv6 字段应为 IPv6 地址: {}", ip)),
+            },
+            None => None,
+        };
+        if ipv4.is_none() && ipv6.is_none() {
+            return Err(anyhow!("WireGuard 节点缺少隧道地址: {}", proxy.name));
+        }
+
+        let allowed_ips = params
+            .allowed_ips
+            .iter()
+            .flatten()
+            .map(|net| {
+                net.trim()
+                    .parse()
+                    .map_err(|e| anyhow!("WireGuard allowed-ips 格式错误 {}: {}", net, e))
+            })
+            .collect::<Result<Vec<IpNetwork>>>()?;
+        let reserved = match &params.reserved {
+            Some(value) => parse_reserved(value)?,
+            None => [0u8; 3],
+        };
+
+        Ok(Self {
+            keys: Keys {
+                private: StaticSecret::from(private),
+                peer_public: PublicKey::from(peer_public),
+                pre_shared,
+            },
+            reserved,
+            ipv4,
+            ipv6,
+            allowed_ips,
+            mtu: params.mtu.map_or(DEFAULT_MTU, usize::from),
+        })
+    }
+
+    /// 与目标地址同族的隧道地址
+    fn local_ip(&self, remote: IpAddr) -> Option<IpAddr> {
+        match remote {
+            IpAddr::V4(_) => self.ipv4.map(IpAddr::V4),
+            IpAddr::V6(_) => self.ipv6.map(IpAddr::V6),
+        }
+    }
+
+    /// 解析目标地址，域名优先选择隧道内可用的地址族
+    async fn resolve(&self, target: &Address) -> Result<SocketAddr> {
+        match target {
+            Address::Socket(addr) => Ok(*addr),
+            Address::Domain(domain, port) => {
+                let addrs: Vec<SocketAddr> = tokio::net::lookup_host((domain.as_str(), *port))
+                    .await
+                    .map_err(|e| anyhow!("解析目标地址失败 {}: {}", domain, e))?
+                    .collect();
+                addrs
+                    .iter()
+                    .find(|addr| self.local_ip(addr.ip()).is_some())
+                    .copied()
+                    .ok_or_else(|| anyhow!("目标地址没有隧道可用的地址族: {}", domain))
+            }
+        }
+    }
+}
+
+/// 解码 base64（或十六进制）格式的 32 字节密钥
+fn decode_key(kind: &str, value: Option<&str>, name: &str) -> Result<[u8; 32]> {
+    let value = value
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .ok_or_else(|| anyhow!("WireGuard 节点缺少{}: {}", kind, name))?;
+    // 十六进制字符也是合法的 base64，因此按解码后的长度判断格式
+    let bytes = STANDARD
+        .decode(value)
+        .ok()
+        .filter(|bytes| bytes.len() == 32)
+        .or_else(|| decode_hex(value))
+        .ok_or_else(|| anyhow!("WireGuard {}格式错误: {}", kind, name))?;
+    bytes
+        .try_into()
+        .map_err(|_| anyhow!("WireGuard {}长度错误: {}", kind, name))
+}
+
+fn decode_hex(value: &str) -> Option<Vec<u8>> {
+    if !value.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..value.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(value.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// 隧道地址允许带前缀长度，如 `172.16.0.2/32`
+fn parse_interface_ip(value: &str) -> Result<IpAddr> {
+    let addr = value.split('/').next().unwrap_or(value).trim();
+    addr.trim_start_matches('[')
+        .trim_end_matches(']')
+        .parse()
+        .map_err(|e| anyhow!("WireGuard 隧道地址格式错误 {}: {}", value, e))
+}
+
+/// 解析保留字段：支持 `[1, 2, 3]`、`"1,2,3"` 与 base64 三种写法
+pub fn parse_reserved(value: &serde_json::Value) -> Result<[u8; 3]> {
+    let bytes: Vec<u8> = match value {
+        serde_json::Value::Array(items) => items
+            .iter()
+            .map(|item| {
+                item.as_u64()
+                    .and_then(|n| u8::try_from(n).ok())
+                    .ok_or_else(|| anyhow!("WireGuard reserved 格式错误: {}", value))
+            })
+            .collect::<Result<_>>()?,
+        serde_json::Value::String(s) if s.contains(',') => s
+            .split(',')
+            .map(|n| {
+                n.trim()
+                    .parse()
+                    .map_err(|_| anyhow!("WireGuard reserved 格式错误: {}", s))
+            })
+            .collect::<Result<_>>()?,
+        serde_json::Value::String(s) if s.is_empty() => vec![0; 3],
+        serde_json::Value::String(s) => STANDARD
+            .decode(s)
+            .map_err(|e| anyhow!("WireGuard reserved 格式错误 {}: {}", s, e))?,
+        _ => return Err(anyhow!("WireGuard reserved 格式错误: {}", value)),
+    };
+    bytes
+        .try_into()
+        .map_err(|_| anyhow!("WireGuard reserved 必须为 3 字节: {}", value))
+}
+
+/// 判断地址是否在 allowed-ips 范围内，未配置时放行全部地址
+fn allows(allowed_ips: &[IpNetwork], ip: IpAddr) -> bool {
+    allowed_ips.is_empty() || allowed_ips.iter().any(|net| net.contains(ip))
+}
+
+/// 发送握手发起包并等待响应，超时后重新发起
+async fn handshake(socket: &UdpSocket, config: &WireGuardConfig) -> Result<Session> {
+    let mut buf = [0u8; 256];
+    for _ in 0..HANDSHAKE_ATTEMPTS {
+        let (initiator, mut msg) = Initiator::new(&config.keys, rand::random());
+        apply_reserved(&mut msg, config.reserved);
+        socket.send(&msg).await?;
+
+        let deadline = Instant::now() + REKEY_TIMEOUT;
+        while let Ok(result) = tokio::time::timeout_at(deadline, socket.recv(&mut buf)).await {
+            let n = result?;
+            match buf[0] {
+                MSG_RESPONSE if n == RESPONSE_LEN => {
+                    // 保留字段不参与 mac1 计算
+                    buf[1..4].fill(0);
+                    return initiator
+                        .finish(&config.keys, &buf[..n])
+                        .map_err(|e| anyhow!("{}", e));
+                }
+                MSG_COOKIE_REPLY => log::debug!("WireGuard 服务端要求 Cookie，等待重试"),
+                _ => {}
+            }
+        }
+    }
+    Err(anyhow!("WireGuard 握手超时"))
+}
+
+/// 通过 WireGuard 节点连接目标地址
+pub async fn connect(
+    proxy: &ProxyNode,
+    params: &WireGuardParams,
+    target: &Address,
+) -> Result<BoxedStream> {
+    let config = WireGuardConfig::from_node(proxy, params)?;
+    let remote = config.resolve(target).await?;
+    if !allows(&config.allowed_ips, remote.ip()) {
+        return Err(anyhow!("目标地址不在 allowed-ips 范围内: {}", remote));
+    }
+    let local = config
+        .local_ip(remote.ip())
+        .ok_or_else(|| anyhow!("WireGuard 节点没有与目标同族的隧道地址: {}", remote))?;
+
+    let host = proxy.server.trim_start_matches('[').trim_end_matches(']');
+    let server = tokio::net::lookup_host((host, proxy.port))
+        .await
+        .map_err(|e| anyhow!("解析服务器地址失败: {}", e))?
+        .next()
+        .ok_or_else(|| anyhow!("解析服务器地址失败: {}", proxy.server))?;
+    let bind: SocketAddr = if server.is_ipv4() {
+        (Ipv4Addr::UNSPECIFIED, 0).into()
+    } else {
+        (Ipv6Addr::UNSPECIFIED, 0).into()
+    };
+    let socket = UdpSocket::bind(bind).await?;
+    socket.connect(server).await?;
+
+    let session = handshake(&socket, &config).await?;
+    let tunnel = Tunnel {
+        socket,
+        session,
+        reserved: config.reserved,
+        allowed_ips: config.allowed_ips,
+        mtu: config.mtu,
+    };
+    let stream = stack::connect(tunnel, local, remote)
+        .await
+        .map_err(|e| anyhow!("WireGuard {}", e))?;
+    Ok(Box::new(stream))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::noise::{MSG_DATA, test_server};
+    use super::stack::{TunDevice, interface, ip_packet_len, tcp_socket};
+    use super::*;
+    use crate::outbound::dial;
+    use crate::proxy::Protocol;
+    use smoltcp::iface::SocketSet;
+    use smoltcp::socket::tcp;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    const SERVER_TUNNEL_IP: Ipv4Addr = Ipv4Addr::new(10, 0, 0, 1);
+
+    /// 启动本地 WireGuard 服务端替身：完成握手后在隧道内的 80 端口回显 TCP 数据
+    async fn spawn_server(private: StaticSecret, reserved: [u8; 3]) -> u16 {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let port = socket.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let mut buf = vec![0u8; 65536];
+            let (n, peer) = socket.recv_from(&mut buf).await.unwrap();
+            assert_eq!(&buf[1..4], &reserved);
+            buf[1..4].fill(0);
+            let (response, mut session) =
+                test_server::respond(&private, &[0u8; 32], &buf[..n], 7).unwrap();
+            socket.send_to(&response, peer).await.unwrap();
+
+            let mut device = TunDevice::new(DEFAULT_MTU);
+            let mut iface = interface(&mut device, IpAddr::V4(SERVER_TUNNEL_IP));
+            let mut listener = tcp_socket();
+            listener.listen(80).unwrap();
+            let mut sockets = SocketSet::new(Vec::new());
+            let handle = sockets.add(listener);
+            loop {
+                let now = smoltcp::time::Instant::now();
+                iface.poll(now, &mut device, &mut sockets);
+                while let Some(packet) = device.tx.pop_front() {
+                    socket
+                        .send_to(&session.encrypt(&packet), peer)
+                        .await
+                        .unwrap();
+                }
+                let tcp = sockets.get_mut::<tcp::Socket>(handle);
+                if tcp.can_recv() && tcp.can_send() {
+                    let data = tcp.recv(|b| (b.len(), b.to_vec())).unwrap();
+                    tcp.send_slice(&data).unwrap();
+                    continue;
+                }
+                if !tcp.may_recv() && tcp.may_send() {
+                    tcp.close();
+                }
+                let delay = iface
+                    .poll_delay(now, &sockets)
+                    .map_or(Duration::from_millis(50), Duration::from);
+                tokio::select! {
+                    result = socket.recv_from(&mut buf) => {
+                        let (n, _) = result.unwrap();
+                        assert_eq!(&buf[1..4], &reserved);
+                        if buf[0] == MSG_DATA
+                            && let Ok(mut packet) = session.decrypt(&buf[..n])
+                            && let Some(len) = ip_packet_len(&packet)
+                        {
+                            packet.truncate(len);
+                            device.rx.push_back(packet);
+                        }
+                    }
+                    _ = tokio::time::sleep(delay) => {}
+                }
+            }
+        });
+        port
+    }
+
+    fn params(server: &StaticSecret) -> WireGuardParams {
+        WireGuardParams {
+            private_key: STANDARD.encode([9u8; 32]),
+            public_key: STANDARD.encode(PublicKey::from(server).as_bytes()),
+            pre_shared_key: None,
+            ip: Some("10.0.0.2/32".to_string()),
+            ipv6: None,
+            allowed_ips: None,
+            reserved: Some(serde_json::json!([1, 2, 3])),
+            mtu: None,
+            ..Default::default()
+        }
+    }
+
+    fn node(port: u16, params: WireGuardParams) -> ProxyNode {
+        ProxyNode::new(
+            "wg".to_string(),
+            "127.0.0.1".to_string(),
+            port,
+            Protocol::WireGuard(params),
+        )
+    }
+
+    #[test]
+    fn test_parse_reserved() {
+        assert_eq!(
+            parse_reserved(&serde_json::json!([209, 98, 59])).unwrap(),
+            [209, 98, 59]
+        );
+        assert_eq!(
+            parse_reserved(&serde_json::json!("209, 98, 59")).unwrap(),
+            [209, 98, 59]
+        );
+        assert_eq!(
+            parse_reserved(&serde_json::json!("0WI7")).unwrap(),
+            [209, 98, 59]
+        );
+        assert!(parse_reserved(&serde_json::json!([1, 2])).is_err());
+        assert!(parse_reserved(&serde_json::json!([1, 2, 300])).is_err());
+    }
+
+    #[test]
+    fn test_config_from_node() {
+        let server = StaticSecret::from([5u8; 32]);
+        let proxy = node(51820, params(&server));
+        let mut wg = params(&server);
+        wg.allowed_ips = Some(vec!["0.0.0.0/0".to_string()]);
+        wg.ipv6 = Some("fd00::2".to_string());
+        let config = WireGuardConfig::from_node(&proxy, &wg).unwrap();
+        assert_eq!(config.reserved, [1, 2, 3]);
+        assert_eq!(config.ipv4, Some(Ipv4Addr::new(10, 0, 0, 2)));
+        assert_eq!(config.mtu, DEFAULT_MTU);
+        assert!(allows(&config.allowed_ips, "1.1.1.1".parse().unwrap()));
+        assert!(!allows(&config.allowed_ips, "::1".parse().unwrap()));
+
+        wg.ip = None;
+        wg.ipv6 = None;
+        assert!(WireGuardConfig::from_node(&proxy, &wg).is_err());
+
+        let mut wg = params(&server);
+        wg.public_key = "not a key".to_string();
+        assert!(WireGuardConfig::from_node(&proxy, &wg).is_err());
+        wg.public_key = hex_key(&[3u8; 32]);
+        assert!(WireGuardConfig::from_node(&proxy, &wg).is_ok());
+    }
+
+    #[test]
+    fn test_config_from_clash_yaml() {
+        let yaml = r#"
+name: warp
+type: wireguard
+server: 162.159.192.1
+port: 2408
+ip: 172.16.0.2
+ipv6: fd01:5ca1:ab1e::2
+private-key: CAkJCQkJCQkJCQkJCQkJCQkJCQkJCQkJCQkJCQkJCQk=
+public-key: bmXOC+F1FxEMF9dyiK2H5/1SUtzH0JuVo51h2wPfgyo=
+allowed-ips: ['0.0.0.0/0', '::/0']
+reserved: [209, 98, 59]
+mtu: 1280
+udp: true
+"#;
+        let proxy: ProxyNode = serde_yaml::from_str(yaml).unwrap();
+        let Protocol::WireGuard(wg) = &proxy.protocol else {
+            panic!("应解析为 WireGuard 节点");
+        };
+        let config = WireGuardConfig::from_node(&proxy, wg).unwrap();
+        assert_eq!(config.reserved, [209, 98, 59]);
+        assert_eq!(config.allowed_ips.len(), 2);
+        assert_eq!(config.mtu, 1280);
+        assert!(config.ipv6.is_some());
+    }
+
+    fn hex_key(key: &[u8]) -> String {
+        key.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    #[tokio::test]
+    async fn test_wireguard_roundtrip() {
+        let server = StaticSecret::from([5u8; 32]);
+        let port = spawn_server(server.clone(), [1, 2, 3]).await;
+        let target = Address::Socket(SocketAddr::new(IpAddr::V4(SERVER_TUNNEL_IP), 80));
+        let mut stream = dial(&node(port, params(&server)), &target).await.unwrap();
+
+        let payload: Vec<u8> = (0..20000u32).map(|i| i as u8).collect();
+        stream.write_all(&payload).await.unwrap();
+        let mut echoed = vec![0u8; payload.len()];
+        stream.read_exact(&mut echoed).await.unwrap();
+        assert_eq!(echoed, payload);
+    }
+
+    #[tokio::test]
+    async fn test_wireguard_rejects_target_outside_allowed_ips() {
+        let server = StaticSecret::from([5u8; 32]);
+        let mut wg = params(&server);
+        wg.allowed_ips = Some(vec!["192.168.0.0/16".to_string()]);
+        let target = Address::Socket("10.0.0.1:80".parse().unwrap());
+        let err = dial(&node(1, wg), &target).await.err().unwrap();
+        assert!(err.to_string().contains("allowed-ips"));
+    }
+}
@@ -0,0 +1,363 @@
+//! WireGuard 握手（Noise_IKpsk2_25519_ChaChaPoly_BLAKE2s）与数据包加解密
+
+use crate::outbound::crypto::{AeadCipher, AeadKind, TAG_LEN};
+use crate::outbound::util::invalid_data;
+use blake2::digest::consts::U16;
+use blake2::digest::{FixedOutput, KeyInit, Mac, Update};
+use blake2::{Blake2s256, Blake2sMac, Digest};
+use hmac::SimpleHmac;
+use std::io;
+use std::time::{SystemTime, UNIX_EPOCH};
+use x25519_dalek::{PublicKey, StaticSecret};
+
+const CONSTRUCTION: &[u8] = b"Noise_IKpsk2_25519_ChaChaPoly_BLAKE2s";
+const IDENTIFIER: &[u8] = b"WireGuard v1 zx2c4 Jason@zx2c4.com";
+const LABEL_MAC1: &[u8] = b"mac1----";
+
+pub const MSG_INITIATION: u8 = 1;
+pub const MSG_RESPONSE: u8 = 2;
+pub const MSG_COOKIE_REPLY: u8 = 3;
+pub const MSG_DATA: u8 = 4;
+
+pub const INITIATION_LEN: usize = 148;
+pub const RESPONSE_LEN: usize = 92;
+/// 数据包头：类型 + 保留字段 + 接收方索引 + 计数器
+pub const DATA_HEADER_LEN: usize = 16;
+
+/// 本端与对端的密钥配置
+#[derive(Clone)]
+pub struct Keys {
+    pub private: StaticSecret,
+    pub peer_public: PublicKey,
+    pub pre_shared: [u8; 32],
+}
+
+impl Keys {
+    fn public(&self) -> PublicKey {
+        PublicKey::from(&self.private)
+    }
+}
+
+fn hash(parts: &[&[u8]]) -> [u8; 32] {
+    let mut hasher = Blake2s256::new();
+    for part in parts {
+        Digest::update(&mut hasher, part);
+    }
+    hasher.finalize().into()
+}
+
+fn hmac(key: &[u8], parts: &[&[u8]]) -> [u8; 32] {
+    let mut mac =
+        <SimpleHmac<Blake2s256> as KeyInit>::new_from_slice(key).expect("HMAC 密钥长度错误");
+    for part in parts {
+        Mac::update(&mut mac, part);
+    }
+    mac.finalize_fixed().into()
+}
+
+/// HKDF：从链式密钥派生 `N` 个 32 字节输出
+fn kdf<const N: usize>(key: &[u8], input: &[u8]) -> [[u8; 32]; N] {
+    let prk = hmac(key, &[input]);
+    let mut out = [[0u8; 32]; N];
+    let mut prev: Vec<u8> = Vec::new();
+    for (i, slot) in out.iter_mut().enumerate() {
+        *slot = hmac(&prk, &[&prev, &[i as u8 + 1]]);
+        prev = slot.to_vec();
+    }
+    out
+}
+
+/// 16 字节 keyed BLAKE2s，用于 mac1
+fn mac(key: &[u8], data: &[u8]) -> [u8; 16] {
+    let mut mac = <Blake2sMac<U16> as KeyInit>::new_from_slice(key).expect("MAC 密钥长度错误");
+    Update::update(&mut mac, data);
+    mac.finalize_fixed().into()
+}
+
+/// 计数器作为 nonce：4 字节 0 + 8 字节小端序计数
+fn nonce(counter: u64) -> [u8; 12] {
+    let mut nonce = [0u8; 12];
+    nonce[4..].copy_from_slice(&counter.to_le_bytes());
+    nonce
+}
+
+fn seal(key: &[u8; 32], counter: u64, plaintext: &[u8], aad: &[u8]) -> Vec<u8> {
+    AeadCipher::new(AeadKind::ChaCha20Poly1305, key).encrypt_aad(&nonce(counter), plaintext, aad)
+}
+
+fn open(key: &[u8; 32], counter: u64, ciphertext: &[u8], aad: &[u8]) -> io::Result<Vec<u8>> {
+    AeadCipher::new(AeadKind::ChaCha20Poly1305, key).decrypt_aad(&nonce(counter), ciphertext, aad)
+}
+
+/// TAI64N 时间戳，服务端据此拒绝重放的握手包
+fn tai64n() -> [u8; 12] {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let mut out = [0u8; 12];
+    out[..8].copy_from_slice(&(0x4000_0000_0000_000a + now.as_secs()).to_be_bytes());
+    out[8..].copy_from_slice(&now.subsec_nanos().to_be_bytes());
+    out
+}
+
+fn dh(secret: &StaticSecret, public: &PublicKey) -> [u8; 32] {
+    secret.diffie_hellman(public).to_bytes()
+}
+
+fn read_u32(buf: &[u8]) -> u32 {
+    u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]])
+}
+
+/// 已发出握手发起包、等待响应的发起方状态
+pub struct Initiator {
+    chaining_key: [u8; 32],
+    hash: [u8; 32],
+    ephemeral: StaticSecret,
+    local_index: u32,
+}
+
+impl Initiator {
+    /// 构造握手发起包，`mac2` 保持全零（未收到 Cookie）
+    pub fn new(keys: &Keys, local_index: u32) -> (Self, Vec<u8>) {
+        let peer = keys.peer_public.as_bytes();
+        let chaining_key = hash(&[CONSTRUCTION]);
+        let h = hash(&[&hash(&[&chaining_key, IDENTIFIER]), peer]);
+
+        let ephemeral = StaticSecret::from(rand::random::<[u8; 32]>());
+        let ephemeral_public = PublicKey::from(&ephemeral);
+        let [ck] = kdf(&chaining_key, ephemeral_public.as_bytes());
+        let h = hash(&[&h, ephemeral_public.as_bytes()]);
+
+        let [ck, key] = kdf(&ck, &dh(&ephemeral, &keys.peer_public));
+        let encrypted_static = seal(&key, 0, keys.public().as_bytes(), &h);
+        let h = hash(&[&h, &encrypted_static]);
+
+        let [ck, key] = kdf(&ck, &dh(&keys.private, &keys.peer_public));
+        let encrypted_timestamp = seal(&key, 0, &tai64n(), &h);
+        let h = hash(&[&h, &encrypted_timestamp]);
+
+        let mut msg = Vec::with_capacity(INITIATION_LEN);
+        msg.extend_from_slice(&[MSG_INITIATION, 0, 0, 0]);
+        msg.extend_from_slice(&local_index.to_le_bytes());
+        msg.extend_from_slice(ephemeral_public.as_bytes());
+        msg.extend_from_slice(&encrypted_static);
+        msg.extend_from_slice(&encrypted_timestamp);
+        let mac1 = mac(&hash(&[LABEL_MAC1, peer]), &msg);
+        msg.extend_from_slice(&mac1);
+        msg.extend_from_slice(&[0u8; 16]);
+
+        let state = Self {
+            chaining_key: ck,
+            hash: h,
+            ephemeral,
+            local_index,
+        };
+        (state, msg)
+    }
+
+    /// 处理握手响应包，成功后得到数据传输会话
+    pub fn finish(self, keys: &Keys, msg: &[u8]) -> io::Result<Session> {
+        if msg.len() != RESPONSE_LEN || msg[0] != MSG_RESPONSE {
+            return Err(invalid_data("WireGuard 握手响应格式错误"));
+        }
+        if read_u32(&msg[8..12]) != self.local_index {
+            return Err(invalid_data("WireGuard 握手响应索引不匹配"));
+        }
+        let mac1 = mac(&hash(&[LABEL_MAC1, keys.public().as_bytes()]), &msg[..60]);
+        if msg[60..76] != mac1 {
+            return Err(invalid_data("WireGuard 握手响应 mac1 校验失败"));
+        }
+        let remote_index = read_u32(&msg[4..8]);
+        let ephemeral = PublicKey::from(<[u8; 32]>::try_from(&msg[12..44]).unwrap());
+
+        let [ck] = kdf(&self.chaining_key, ephemeral.as_bytes());
+        let h = hash(&[&self.hash, ephemeral.as_bytes()]);
+        let [ck] = kdf(&ck, &dh(&self.ephemeral, &ephemeral));
+        let [ck] = kdf(&ck, &dh(&keys.private, &ephemeral));
+        let [ck, tau, key] = kdf(&ck, &keys.pre_shared);
+        let h = hash(&[&h, &tau]);
+        open(&key, 0, &msg[44..60], &h)
+            .map_err(|_| invalid_data("WireGuard 握手响应解密失败，请检查密钥"))?;
+
+        let [send_key, recv_key] = kdf(&ck, &[]);
+        Ok(Session::new(
+            self.local_index,
+            remote_index,
+            send_key,
+            recv_key,
+        ))
+    }
+}
+
+/// 握手完成后的数据传输会话
+pub struct Session {
+    local_index: u32,
+    remote_index: u32,
+    send_key: [u8; 32],
+    recv_key: [u8; 32],
+    send_counter: u64,
+}
+
+impl Session {
+    fn new(local_index: u32, remote_index: u32, send_key: [u8; 32], recv_key: [u8; 32]) -> Self {
+        Self {
+            local_index,
+            remote_index,
+            send_key,
+            recv_key,
+            send_counter: 0,
+        }
+    }
+
+    /// 加密一个 IP 包，明文补齐到 16 字节的整数倍
+    pub fn encrypt(&mut self, packet: &[u8]) -> Vec<u8> {
+        let mut padded = packet.to_vec();
+        padded.resize(packet.len().div_ceil(16) * 16, 0);
+        let counter = self.send_counter;
+        self.send_counter += 1;
+
+        let mut msg = Vec::with_capacity(DATA_HEADER_LEN + padded.len() + TAG_LEN);
+        msg.extend_from_slice(&[MSG_DATA, 0, 0, 0]);
+        msg.extend_from_slice(&self.remote_index.to_le_bytes());
+        msg.extend_from_slice(&counter.to_le_bytes());
+        msg.extend(seal(&self.send_key, counter, &padded, &[]));
+        msg
+    }
+
+    /// 解密数据包，返回带填充的 IP 包（空包为保活）
+    pub fn decrypt(&self, msg: &[u8]) -> io::Result<Vec<u8>> {
+        if msg.len() < DATA_HEADER_LEN + TAG_LEN || msg[0] != MSG_DATA {
+            return Err(invalid_data("WireGuard 数据包格式错误"));
+        }
+        if read_u32(&msg[4..8]) != self.local_index {
+            return Err(invalid_data("WireGuard 数据包索引不匹配"));
+        }
+        let counter = u64::from_le_bytes(msg[8..16].try_into().unwrap());
+        open(&self.recv_key, counter, &msg[DATA_HEADER_LEN..], &[])
+    }
+}
+
+/// 测试用的握手响应方
+#[cfg(test)]
+pub mod test_server {
+    use super::*;
+
+    /// 处理握手发起包，返回响应包与会话
+    pub fn respond(
+        private: &StaticSecret,
+        pre_shared: &[u8; 32],
+        msg: &[u8],
+        local_index: u32,
+    ) -> io::Result<(Vec<u8>, Session)> {
+        if msg.len() != INITIATION_LEN || msg[0] != MSG_INITIATION {
+            return Err(invalid_data("握手发起包格式错误"));
+        }
+        let public = PublicKey::from(private);
+        let mac1 = mac(&hash(&[LABEL_MAC1, public.as_bytes()]), &msg[..116]);
+        if msg[116..132] != mac1 {
+            return Err(invalid_data("握手发起包 mac1 校验失败"));
+        }
+        let remote_index = read_u32(&msg[4..8]);
+        let initiator_ephemeral = PublicKey::from(<[u8; 32]>::try_from(&msg[8..40]).unwrap());
+
+        let chaining_key = hash(&[CONSTRUCTION]);
+        let h = hash(&[&hash(&[&chaining_key, IDENTIFIER]), public.as_bytes()]);
+        let [ck] = kdf(&chaining_key, initiator_ephemeral.as_bytes());
+        let h = hash(&[&h, initiator_ephemeral.as_bytes()]);
+        let [ck, key] = kdf(&ck, &dh(private, &initiator_ephemeral));
+        let initiator_static = open(&key, 0, &msg[40..88], &h)?;
+        let initiator_static =
+            PublicKey::from(<[u8; 32]>::try_from(&initiator_static[..]).unwrap());
+        let h = hash(&[&h, &msg[40..88]]);
+        let [ck, key] = kdf(&ck, &dh(private, &initiator_static));
+        open(&key, 0, &msg[88..116], &h)?;
+        let h = hash(&[&h, &msg[88..116]]);
+
+        let ephemeral = StaticSecret::from(rand::random::<[u8; 32]>());
+        let ephemeral_public = PublicKey::from(&ephemeral);
+        let [ck] = kdf(&ck, ephemeral_public.as_bytes());
+        let h = hash(&[&h, ephemeral_public.as_bytes()]);
+        let [ck] = kdf(&ck, &dh(&ephemeral, &initiator_ephemeral));
+        let [ck] = kdf(&ck, &dh(&ephemeral, &initiator_static));
+        let [ck, tau, key] = kdf(&ck, pre_shared);
+        let h = hash(&[&h, &tau]);
+        let empty = seal(&key, 0, &[], &h);
+
+        let mut response = Vec::with_capacity(RESPONSE_LEN);
+        response.extend_from_slice(&[MSG_RESPONSE, 0, 0, 0]);
+        response.extend_from_slice(&local_index.to_le_bytes());
+        response.extend_from_slice(&remote_index.to_le_bytes());
+        response.extend_from_slice(ephemeral_public.as_bytes());
+        response.extend_from_slice(&empty);
+        let mac1 = mac(&hash(&[LABEL_MAC1, initiator_static.as_bytes()]), &response);
+        response.extend_from_slice(&mac1);
+        response.extend_from_slice(&[0u8; 16]);
+
+        let [recv_key, send_key] = kdf(&ck, &[]);
+        Ok((
+            response,
+            Session::new(local_index, remote_index, send_key, recv_key),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys() -> (Keys, StaticSecret) {
+        let server = StaticSecret::from(rand::random::<[u8; 32]>());
+        let keys = Keys {
+            private: StaticSecret::from(rand::random::<[u8; 32]>()),
+            peer_public: PublicKey::from(&server),
+            pre_shared: [7u8; 32],
+        };
+        (keys, server)
+    }
+
+    #[test]
+    fn test_kdf() {
+        // HKDF 的第一个输出等于 HMAC(HMAC(key, input), 0x01)
+        let [first, second] = kdf(b"key", b"input");
+        let prk = hmac(b"key", &[b"input"]);
+        assert_eq!(first, hmac(&prk, &[&[1]]));
+        assert_eq!(second, hmac(&prk, &[&first, &[2]]));
+    }
+
+    #[test]
+    fn test_handshake_and_transport() {
+        let (keys, server) = keys();
+        let (initiator, init) = Initiator::new(&keys, 0x1234);
+        assert_eq!(init.len(), INITIATION_LEN);
+        assert_eq!(&init[4..8], &0x1234u32.to_le_bytes());
+
+        let (response, mut server_session) =
+            test_server::respond(&server, &keys.pre_shared, &init, 0x5678).unwrap();
+        let mut client_session = initiator.finish(&keys, &response).unwrap();
+
+        let msg = client_session.encrypt(b"ping");
+        assert_eq!(msg.len(), DATA_HEADER_LEN + 16 + TAG_LEN);
+        let plain = server_session.decrypt(&msg).unwrap();
+        assert_eq!(&plain[..4], b"ping");
+        assert!(plain[4..].iter().all(|&b| b == 0));
+
+        let msg = server_session.encrypt(b"pong");
+        assert_eq!(&client_session.decrypt(&msg).unwrap()[..4], b"pong");
+    }
+
+    #[test]
+    fn test_handshake_wrong_psk() {
+        let (keys, server) = keys();
+        let (initiator, init) = Initiator::new(&keys, 1);
+        let (response, _) = test_server::respond(&server, &[0u8; 32], &init, 2).unwrap();
+        assert!(initiator.finish(&keys, &response).is_err());
+    }
+
+    #[test]
+    fn test_handshake_wrong_peer_key() {
+        let (keys, _) = keys();
+        let (_, init) = Initiator::new(&keys, 1);
+        let other = StaticSecret::from(rand::random::<[u8; 32]>());
+        assert!(test_server::respond(&other, &keys.pre_shared, &init, 2).is_err());
+    }
+}
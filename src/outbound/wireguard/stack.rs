@@ -0,0 +1,342 @@
+//! 隧道内的用户态 TCP 协议栈
+//! smoltcp 负责 TCP 状态机，后台任务在 UDP 隧道、协议栈与调用方之间搬运数据
+
+use super::allows;
+use super::noise::{DATA_HEADER_LEN, MSG_DATA, Session};
+use ipnetwork::IpNetwork;
+use rand::Rng;
+use smoltcp::iface::{Config, Interface, SocketHandle, SocketSet};
+use smoltcp::phy::{self, Device, DeviceCapabilities, Medium};
+use smoltcp::socket::tcp;
+use smoltcp::time::{Duration as SmolDuration, Instant};
+use smoltcp::wire::{HardwareAddress, IpAddress, IpCidr};
+use std::collections::VecDeque;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt, DuplexStream, WriteHalf};
+use tokio::net::UdpSocket;
+use tokio::sync::oneshot;
+
+/// TCP 收发缓冲区大小
+const TCP_BUFFER_SIZE: usize = 256 * 1024;
+/// 调用方与后台任务之间的管道容量
+const PIPE_SIZE: usize = 64 * 1024;
+/// TCP 握手超时
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// 连接无响应超时，超时后协议栈会中止连接
+const IDLE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// 以内存队列收发 IP 包的虚拟网卡
+pub struct TunDevice {
+    pub rx: VecDeque<Vec<u8>>,
+    pub tx: VecDeque<Vec<u8>>,
+    mtu: usize,
+}
+
+impl TunDevice {
+    pub fn new(mtu: usize) -> Self {
+        Self {
+            rx: VecDeque::new(),
+            tx: VecDeque::new(),
+            mtu,
+        }
+    }
+}
+
+pub struct RxToken(Vec<u8>);
+
+pub struct TxToken<'a>(&'a mut VecDeque<Vec<u8>>);
+
+impl phy::RxToken for RxToken {
+    fn consume<R, F>(self, f: F) -> R
+    where
+        F: FnOnce(&[u8]) -> R,
+    {
+        f(&self.0)
+    }
+}
+
+impl phy::TxToken for TxToken<'_> {
+    fn consume<R, F>(self, len: usize, f: F) -> R
+    where
+        F: FnOnce(&mut [u8]) -> R,
+    {
+        let mut packet = vec![0u8; len];
+        let result = f(&mut packet);
+        self.0.push_back(packet);
+        result
+    }
+}
+
+impl Device for TunDevice {
+    type RxToken<'a> = RxToken;
+    type TxToken<'a> = TxToken<'a>;
+
+    fn receive(&mut self, _timestamp: Instant) -> Option<(Self::RxToken<'_>, Self::TxToken<'_>)> {
+        let packet = self.rx.pop_front()?;
+        Some((RxToken(packet), TxToken(&mut self.tx)))
+    }
+
+    fn transmit(&mut self, _timestamp: Instant) -> Option<Self::TxToken<'_>> {
+        Some(TxToken(&mut self.tx))
+    }
+
+    fn capabilities(&self) -> DeviceCapabilities {
+        let mut caps = DeviceCapabilities::default();
+        caps.medium = Medium::Ip;
+        caps.max_transmission_unit = self.mtu;
+        caps
+    }
+}
+
+/// 创建只有一个本地地址、默认路由指向隧道的协议栈接口
+pub fn interface(device: &mut TunDevice, local: IpAddr) -> Interface {
+    let mut config = Config::new(HardwareAddress::Ip);
+    config.random_seed = rand::random();
+    let mut iface = Interface::new(config, device, Instant::now());
+    let prefix = if local.is_ipv4() { 32 } else { 128 };
+    iface.update_ip_addrs(|addrs| {
+        addrs
+            .push(IpCidr::new(IpAddress::from(local), prefix))
+            .expect("接口地址数量超出上限");
+    });
+    match local {
+        IpAddr::V4(addr) => iface.routes_mut().add_default_ipv4_route(addr),
+        IpAddr::V6(addr) => iface.routes_mut().add_default_ipv6_route(addr),
+    }
+    .expect("路由表已满");
+    iface
+}
+
+/// 创建 TCP socket
+pub fn tcp_socket() -> tcp::Socket<'static> {
+    let mut socket = tcp::Socket::new(
+        tcp::SocketBuffer::new(vec![0u8; TCP_BUFFER_SIZE]),
+        tcp::SocketBuffer::new(vec![0u8; TCP_BUFFER_SIZE]),
+    );
+    socket.set_timeout(Some(SmolDuration::from(IDLE_TIMEOUT)));
+    socket.set_nagle_enabled(false);
+    socket
+}
+
+/// IP 包的实际长度，用于去掉数据包加密时补齐的填充
+pub fn ip_packet_len(packet: &[u8]) -> Option<usize> {
+    match packet.first()? >> 4 {
+        4 if packet.len() >= 20 => Some(u16::from_be_bytes([packet[2], packet[3]]) as usize),
+        6 if packet.len() >= 40 => Some(40 + u16::from_be_bytes([packet[4], packet[5]]) as usize),
+        _ => None,
+    }
+    .filter(|&len| len <= packet.len())
+}
+
+/// IP 包的源地址
+fn ip_source(packet: &[u8]) -> Option<IpAddr> {
+    match packet.first()? >> 4 {
+        4 => Some(IpAddr::from(<[u8; 4]>::try_from(packet.get(12..16)?).ok()?)),
+        6 => Some(IpAddr::from(<[u8; 16]>::try_from(packet.get(8..24)?).ok()?)),
+        _ => None,
+    }
+}
+
+/// 在所有发出的 WireGuard 消息中写入保留字段
+pub fn apply_reserved(msg: &mut [u8], reserved: [u8; 3]) {
+    msg[1..4].copy_from_slice(&reserved);
+}
+
+/// 已完成握手的 WireGuard 隧道
+pub struct Tunnel {
+    pub socket: UdpSocket,
+    pub session: Session,
+    pub reserved: [u8; 3],
+    pub allowed_ips: Vec<IpNetwork>,
+    pub mtu: usize,
+}
+
+impl Tunnel {
+    /// 加密并发出协议栈产生的全部 IP 包
+    async fn flush(&mut self, device: &mut TunDevice) -> io::Result<()> {
+        while let Some(packet) = device.tx.pop_front() {
+            let mut msg = self.session.encrypt(&packet);
+            apply_reserved(&mut msg, self.reserved);
+            self.socket.send(&msg).await?;
+        }
+        Ok(())
+    }
+
+    /// 解密收到的数据包并交给协议栈，非数据包与不在 allowed-ips 内的包直接丢弃
+    fn receive(&self, msg: &[u8], device: &mut TunDevice) {
+        if msg.len() < DATA_HEADER_LEN || msg[0] != MSG_DATA {
+            return;
+        }
+        let Ok(mut packet) = self.session.decrypt(msg) else {
+            log::debug!("WireGuard 数据包解密失败");
+            return;
+        };
+        let Some(len) = ip_packet_len(&packet) else {
+            return;
+        };
+        packet.truncate(len);
+        if ip_source(&packet).is_some_and(|ip| allows(&self.allowed_ips, ip)) {
+            device.rx.push_back(packet);
+        }
+    }
+}
+
+/// 在隧道内建立到 `remote` 的 TCP 连接，返回与之相连的管道
+pub async fn connect(
+    tunnel: Tunnel,
+    local: IpAddr,
+    remote: SocketAddr,
+) -> io::Result<DuplexStream> {
+    let mut device = TunDevice::new(tunnel.mtu);
+    let mut iface = interface(&mut device, local);
+    let mut socket = tcp_socket();
+    let local_port = rand::thread_rng().gen_range(49152..=65535);
+    socket
+        .connect(
+            iface.context(),
+            remote,
+            (IpAddress::from(local), local_port),
+        )
+        .map_err(|e| io::Error::other(format!("隧道内 TCP 连接失败: {}", e)))?;
+    let mut sockets = SocketSet::new(Vec::new());
+    let handle = sockets.add(socket);
+
+    let (client, pipe) = tokio::io::duplex(PIPE_SIZE);
+    let (connected_tx, connected_rx) = oneshot::channel();
+    tokio::spawn(async move {
+        let mut driver = Driver {
+            tunnel,
+            device,
+            iface,
+            sockets,
+            handle,
+        };
+        if let Err(e) = driver.run(pipe, connected_tx).await {
+            log::debug!("WireGuard 隧道结束: {}", e);
+        }
+    });
+
+    match tokio::time::timeout(CONNECT_TIMEOUT, connected_rx).await {
+        Ok(Ok(true)) => Ok(client),
+        Ok(_) => Err(io::Error::new(
+            io::ErrorKind::ConnectionRefused,
+            format!("隧道内 TCP 连接被拒绝: {}", remote),
+        )),
+        Err(_) => Err(io::Error::new(
+            io::ErrorKind::TimedOut,
+            format!("隧道内 TCP 连接超时: {}", remote),
+        )),
+    }
+}
+
+/// 驱动协议栈的后台任务状态
+struct Driver {
+    tunnel: Tunnel,
+    device: TunDevice,
+    iface: Interface,
+    sockets: SocketSet<'static>,
+    handle: SocketHandle,
+}
+
+impl Driver {
+    async fn run(
+        &mut self,
+        pipe: DuplexStream,
+        connected: oneshot::Sender<bool>,
+    ) -> io::Result<()> {
+        let (mut pipe_rd, mut pipe_wr) = tokio::io::split(pipe);
+        let mut connected = Some(connected);
+        let mut udp_buf = vec![0u8; 65536];
+        let mut pipe_buf = vec![0u8; 16 * 1024];
+        let mut pipe_eof = false;
+        let mut pipe_closed = false;
+
+        loop {
+            self.iface
+                .poll(Instant::now(), &mut self.device, &mut self.sockets);
+            self.tunnel.flush(&mut self.device).await?;
+
+            let socket = self.sockets.get_mut::<tcp::Socket>(self.handle);
+            if connected.is_some() {
+                match socket.state() {
+                    tcp::State::Established => {
+                        let _ = connected.take().map(|tx| tx.send(true));
+                    }
+                    tcp::State::Closed => {
+                        let _ = connected.take().map(|tx| tx.send(false));
+                        return Ok(());
+                    }
+                    _ => {}
+                }
+            }
+            if connected.is_none() {
+                if !self.forward_received(&mut pipe_wr, &mut pipe_closed).await {
+                    let socket = self.sockets.get_mut::<tcp::Socket>(self.handle);
+                    socket.abort();
+                    self.iface
+                        .poll(Instant::now(), &mut self.device, &mut self.sockets);
+                    return self.tunnel.flush(&mut self.device).await;
+                }
+                if !self.sockets.get::<tcp::Socket>(self.handle).is_active() {
+                    return Ok(());
+                }
+            }
+
+            let socket = self.sockets.get::<tcp::Socket>(self.handle);
+            let space = if socket.may_send() && !pipe_eof {
+                (socket.send_capacity() - socket.send_queue()).min(pipe_buf.len())
+            } else {
+                0
+            };
+            let delay = self
+                .iface
+                .poll_delay(Instant::now(), &self.sockets)
+                .map_or(Duration::from_secs(1), Duration::from);
+
+            tokio::select! {
+                result = self.tunnel.socket.recv(&mut udp_buf) => {
+                    let n = result?;
+                    self.tunnel.receive(&udp_buf[..n], &mut self.device);
+                }
+                result = pipe_rd.read(&mut pipe_buf[..space]), if space > 0 => {
+                    let socket = self.sockets.get_mut::<tcp::Socket>(self.handle);
+                    match result {
+                        Ok(0) | Err(_) => {
+                            pipe_eof = true;
+                            socket.close();
+                        }
+                        Ok(n) => {
+                            // 可用空间已预先确认，这里不会发生截断
+                            let _ = socket.send_slice(&pipe_buf[..n]);
+                        }
+                    }
+                }
+                _ = tokio::time::sleep(delay) => {}
+            }
+        }
+    }
+
+    /// 把 TCP 收到的数据写给调用方，对端关闭后关闭管道写端；调用方已断开时返回 `false`
+    async fn forward_received(
+        &mut self,
+        pipe_wr: &mut WriteHalf<DuplexStream>,
+        pipe_closed: &mut bool,
+    ) -> bool {
+        let socket = self.sockets.get_mut::<tcp::Socket>(self.handle);
+        while socket.can_recv() {
+            let Ok(data) = socket.recv(|buf| (buf.len(), buf.to_vec())) else {
+                break;
+            };
+            if pipe_wr.write_all(&data).await.is_err() {
+                return false;
+            }
+        }
+        if !socket.may_recv() && !*pipe_closed {
+            *pipe_closed = true;
+            let _ = pipe_wr.shutdown().await;
+        }
+        true
+    }
+}
@@ -0,0 +1,252 @@
+//! 节点去重
+//! 合并多个订阅时，按协议相关的身份（服务器、端口、凭据、传输路径与 Host）识别重复节点，
+//! 每组只保留最先出现的节点，名称按配置的策略选取
+
+use super::{Protocol, ProxyNode};
+use anyhow::{Result, anyhow};
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+
+/// 重复节点保留哪个名称
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeepName {
+    /// 最先出现的名称
+    First,
+    /// 最后出现的名称
+    Last,
+    /// 最短的名称
+    Shortest,
+    /// 最长的名称
+    Longest,
+}
+
+impl KeepName {
+    /// 解析配置中的策略名称
+    pub fn parse(value: &str) -> Result<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "first" | "" => Ok(KeepName::First),
+            "last" => Ok(KeepName::Last),
+            "shortest" => Ok(KeepName::Shortest),
+            "longest" => Ok(KeepName::Longest),
+            other => Err(anyhow!("不支持的去重命名策略: {}", other)),
+        }
+    }
+
+    /// 是否用重复节点的名称替换已保留的名称
+    fn prefer(self, kept: &str, candidate: &str) -> bool {
+        let (kept, candidate) = (kept.chars().count(), candidate.chars().count());
+        match self {
+            KeepName::First => false,
+            KeepName::Last => true,
+            KeepName::Shortest => candidate < kept,
+            KeepName::Longest => candidate > kept,
+        }
+    }
+}
+
+/// 节点身份，相同身份的节点视为重复
+#[derive(Debug, PartialEq, Eq, Hash)]
+struct Identity {
+    kind: String,
+    server: String,
+    port: u16,
+    credentials: Vec<String>,
+    network: Option<&'static str>,
+    path: Option<String>,
+    host: Option<String>,
+}
+
+impl Identity {
+    fn of(node: &ProxyNode) -> Self {
+        Self {
+            kind: node.kind().to_string(),
+            server: node.server.to_ascii_lowercase(),
+            port: node.port,
+            credentials: credentials(node),
+            network: node.transport.network.map(|network| network.as_str()),
+            path: node.transport.path().map(str::to_string),
+            host: node.transport.host().map(|host| host.to_ascii_lowercase()),
+        }
+    }
+}
+
+/// 决定能否连上同一账户的协议字段
+fn credentials(node: &ProxyNode) -> Vec<String> {
+    let fields: Vec<&str> = match &node.protocol {
+        Protocol::Shadowsocks(params) => vec![&params.cipher, &params.password],
+        Protocol::Ssr(params) => vec![
+            &params.cipher,
+            &params.password,
+            &params.protocol,
+            &params.obfs,
+        ],
+        Protocol::Vmess(params) => vec![&params.uuid],
+        Protocol::Vless(params) => vec![&params.uuid],
+        Protocol::Trojan(params) => vec![&params.password],
+        Protocol::Hysteria(params) => vec![
+            params.auth_str.as_deref().unwrap_or_default(),
+            params.auth.as_deref().unwrap_or_default(),
+        ],
+        Protocol::Hysteria2(params) => vec![&params.password],
+        Protocol::Tuic(params) => vec![&params.uuid, &params.password],
+        Protocol::AnyTls(params) => vec![&params.password],
+        Protocol::WireGuard(params) => vec![&params.private_key, &params.public_key],
+        Protocol::Http(auth) | Protocol::Socks5(auth) | Protocol::Socks4(auth) => vec![
+            auth.username.as_deref().unwrap_or_default(),
+            auth.password.as_deref().unwrap_or_default(),
+        ],
+        Protocol::Direct => vec![],
+        // 未建模协议的参数都在 extra 中
+        Protocol::Other(_) => {
+            return vec![serde_yaml::to_string(&node.extra).unwrap_or_default()];
+        }
+    };
+    fields.into_iter().map(str::to_string).collect()
+}
+
+/// 去重统计
+#[derive(Debug, Default)]
+pub struct DedupReport {
+    /// 按订阅顺序记录 (订阅名称, 被移除的重复节点数)
+    pub duplicates: Vec<(String, usize)>,
+}
+
+impl DedupReport {
+    /// 被移除的重复节点总数
+    pub fn total(&self) -> usize {
+        self.duplicates.iter().map(|(_, count)| count).sum()
+    }
+}
+
+/// 按订阅顺序合并节点并去重，重复节点记在后出现的订阅名下
+pub fn dedup_nodes(
+    sources: Vec<(String, Vec<ProxyNode>)>,
+    keep: KeepName,
+) -> (Vec<ProxyNode>, DedupReport) {
+    let mut nodes: Vec<ProxyNode> = Vec::new();
+    let mut seen: HashMap<Identity, usize> = HashMap::new();
+    let mut report = DedupReport::default();
+
+    for (source, group) in sources {
+        let mut duplicates = 0;
+        for node in group {
+            match seen.entry(Identity::of(&node)) {
+                Entry::Occupied(entry) => {
+                    duplicates += 1;
+                    let kept = &mut nodes[*entry.get()];
+                    if keep.prefer(&kept.name, &node.name) {
+                        kept.name = node.name;
+                    }
+                }
+                Entry::Vacant(entry) => {
+                    entry.insert(nodes.len());
+                    nodes.push(node);
+                }
+            }
+        }
+        report.duplicates.push((source, duplicates));
+    }
+
+    (nodes, report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::node::{Network, TrojanParams, VmessParams, WsOpts};
+
+    fn trojan(name: &str, server: &str, password: &str) -> ProxyNode {
+        ProxyNode::new(
+            name.to_string(),
+            server.to_string(),
+            443,
+            Protocol::Trojan(TrojanParams {
+                password: password.to_string(),
+                ..Default::default()
+            }),
+        )
+    }
+
+    fn vmess_ws(name: &str, path: &str) -> ProxyNode {
+        let mut node = ProxyNode::new(
+            name.to_string(),
+            "example.com".to_string(),
+            443,
+            Protocol::Vmess(VmessParams {
+                uuid: "b831381d-6324-4d53-ad4f-8cda48b30811".to_string(),
+                ..Default::default()
+            }),
+        );
+        node.transport.network = Some(Network::Ws);
+        node.transport.ws_opts = Some(WsOpts {
+            path: Some(path.to_string()),
+            ..Default::default()
+        });
+        node
+    }
+
+    #[test]
+    fn test_dedup_across_subscriptions() {
+        let sources = vec![
+            (
+                "a".to_string(),
+                vec![
+                    trojan("香港 01", "hk.example.com", "secret"),
+                    trojan("香港 02", "hk.example.com", "other"),
+                ],
+            ),
+            (
+                "b".to_string(),
+                vec![
+                    trojan("HK", "HK.example.com", "secret"),
+                    trojan("日本", "jp.example.com", "secret"),
+                ],
+            ),
+        ];
+        let (nodes, report) = dedup_nodes(sources, KeepName::First);
+        let names: Vec<&str> = nodes.iter().map(|node| node.name.as_str()).collect();
+        assert_eq!(names, ["香港 01", "香港 02", "日本"]);
+        assert_eq!(
+            report.duplicates,
+            [("a".to_string(), 0), ("b".to_string(), 1)]
+        );
+        assert_eq!(report.total(), 1);
+    }
+
+    #[test]
+    fn test_transport_path_is_part_of_identity() {
+        let sources = vec![(
+            "a".to_string(),
+            vec![
+                vmess_ws("ws1", "/a"),
+                vmess_ws("ws2", "/b"),
+                vmess_ws("ws3", "/a"),
+            ],
+        )];
+        let (nodes, report) = dedup_nodes(sources, KeepName::First);
+        assert_eq!(nodes.len(), 2);
+        assert_eq!(report.total(), 1);
+    }
+
+    #[test]
+    fn test_keep_name_policy() {
+        let sources = || {
+            vec![(
+                "a".to_string(),
+                vec![
+                    trojan("香港 01 | 高速", "hk.example.com", "secret"),
+                    trojan("HK", "hk.example.com", "secret"),
+                    trojan("香港 01 | 高速 | 解锁", "hk.example.com", "secret"),
+                ],
+            )]
+        };
+        let name = |keep| dedup_nodes(sources(), keep).0[0].name.clone();
+        assert_eq!(name(KeepName::First), "香港 01 | 高速");
+        assert_eq!(name(KeepName::Last), "香港 01 | 高速 | 解锁");
+        assert_eq!(name(KeepName::Shortest), "HK");
+        assert_eq!(name(KeepName::Longest), "香港 01 | 高速 | 解锁");
+
+        assert_eq!(KeepName::parse("Shortest").unwrap(), KeepName::Shortest);
+        assert!(KeepName::parse("random").is_err());
+    }
+}
@@ -0,0 +1,467 @@
+//! 订阅获取
+//! 以有限并发获取多个订阅，远程订阅的网络错误与服务端错误按指数退避重试；
+//! 每个订阅可以单独设置请求头、User-Agent、超时与上游代理，
+//! 未设置时使用 `system_proxy`；GitHub 链接经 `github_proxy` 前缀改写
+
+use super::source::{Source, read_files};
+use super::userinfo::{self, SubscriptionUserInfo};
+use crate::config::{Config, Subscription};
+use anyhow::{Error, Result, anyhow};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::{Client, Proxy, StatusCode};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncReadExt;
+use tokio::sync::Semaphore;
+use url::Url;
+
+/// 经 `github_proxy` 改写的 GitHub 域名（raw、Releases、Gist）
+const GITHUB_HOSTS: &[&str] = &[
+    "github.com",
+    "raw.githubusercontent.com",
+    "objects.githubusercontent.com",
+    "release-assets.githubusercontent.com",
+    "gist.github.com",
+    "gist.githubusercontent.com",
+];
+
+/// 订阅获取参数
+#[derive(Debug, Clone)]
+pub struct FetchOptions {
+    /// 同时获取的订阅数
+    pub concurrent: usize,
+    /// 失败后的重试次数
+    pub retries: u32,
+    /// 第一次重试前的等待时间，之后每次翻倍
+    pub retry_delay: Duration,
+    /// 订阅未单独设置时的超时
+    pub timeout: Duration,
+    /// 订阅未单独设置时的 User-Agent
+    pub user_agent: String,
+    /// 订阅未单独设置时的上游代理
+    pub system_proxy: Option<String>,
+    /// GitHub 加速前缀
+    pub github_proxy: Option<String>,
+}
+
+impl FetchOptions {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            concurrent: config.subscription_concurrent,
+            retries: config.subscription_retries,
+            retry_delay: Duration::from_secs(1),
+            timeout: Duration::from_secs(config.subscription_timeout),
+            user_agent: config.subscription_user_agent.clone(),
+            system_proxy: non_empty(&config.system_proxy),
+            github_proxy: non_empty(&config.github_proxy),
+        }
+    }
+}
+
+/// 配置文件中的空字符串视为未设置
+fn non_empty(value: &Option<String>) -> Option<String> {
+    value
+        .as_deref()
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(str::to_string)
+}
+
+/// 把 GitHub 链接改写为 `前缀/原链接`，其他链接原样返回
+pub fn github_proxy_url(url: &str, prefix: &str) -> String {
+    let prefix = prefix.trim_end_matches('/');
+    if url.starts_with(prefix) {
+        return url.to_string();
+    }
+    let is_github = Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_ascii_lowercase))
+        .is_some_and(|host| GITHUB_HOSTS.contains(&host.as_str()));
+    if is_github {
+        format!("{}/{}", prefix, url)
+    } else {
+        url.to_string()
+    }
+}
+
+/// 订阅中的一份内容，通配路径可能匹配多个文件
+#[derive(Debug)]
+pub struct Document {
+    /// 用于日志输出的来源
+    pub label: String,
+    pub content: String,
+    /// 远程订阅响应头中的流量与到期信息
+    pub userinfo: Option<SubscriptionUserInfo>,
+}
+
+impl Document {
+    fn single(subscription: &Subscription, content: String) -> Vec<Self> {
+        vec![Self {
+            label: subscription.name.clone(),
+            content,
+            userinfo: None,
+        }]
+    }
+}
+
+/// 单次请求的失败，区分是否值得重试
+enum Failure {
+    Retryable(Error),
+    Fatal(Error),
+}
+
+/// 并发获取全部订阅，结果与传入的订阅一一对应
+pub async fn fetch_subscriptions(
+    subscriptions: &[Subscription],
+    options: &FetchOptions,
+) -> Vec<Result<Vec<Document>>> {
+    let semaphore = Arc::new(Semaphore::new(options.concurrent.max(1)));
+    let tasks: Vec<_> = subscriptions
+        .iter()
+        .cloned()
+        .map(|subscription| {
+            let semaphore = semaphore.clone();
+            let options = options.clone();
+            tokio::spawn(async move {
+                let _permit = semaphore.acquire().await;
+                fetch_subscription(&subscription, &options).await
+            })
+        })
+        .collect();
+
+    let mut results = Vec::with_capacity(tasks.len());
+    for task in tasks {
+        results.push(
+            task.await
+                .unwrap_or_else(|e| Err(anyhow!("获取订阅的任务异常退出: {}", e))),
+        );
+    }
+    results
+}
+
+/// 获取单个订阅的内容
+async fn fetch_subscription(
+    subscription: &Subscription,
+    options: &FetchOptions,
+) -> Result<Vec<Document>> {
+    match Source::of(subscription) {
+        Source::Remote(url) => {
+            let (content, userinfo) = fetch_remote(subscription, url, options).await?;
+            Ok(vec![Document {
+                label: subscription.name.clone(),
+                content,
+                userinfo,
+            }])
+        }
+        Source::Inline(content) => Ok(Document::single(subscription, content.to_string())),
+        Source::Stdin => {
+            let mut content = String::new();
+            tokio::io::stdin()
+                .read_to_string(&mut content)
+                .await
+                .map_err(|e| anyhow!("读取标准输入失败: {}", e))?;
+            Ok(Document::single(subscription, content))
+        }
+        Source::Files(pattern) => Ok(read_files(pattern)?
+            .into_iter()
+            .map(|(path, content)| Document {
+                label: format!("{} ({})", subscription.name, path.display()),
+                content,
+                userinfo: None,
+            })
+            .collect()),
+    }
+}
+
+async fn fetch_remote(
+    subscription: &Subscription,
+    url: &str,
+    options: &FetchOptions,
+) -> Result<(String, Option<SubscriptionUserInfo>)> {
+    let client = build_client(subscription, options)?;
+    let url = match &options.github_proxy {
+        Some(prefix) => github_proxy_url(url, prefix),
+        None => url.to_string(),
+    };
+    let mut attempt = 0;
+    loop {
+        match fetch_once(&client, &url).await {
+            Ok(fetched) => return Ok(fetched),
+            Err(Failure::Retryable(e)) if attempt < options.retries => {
+                let delay = options
+                    .retry_delay
+                    .saturating_mul(2u32.saturating_pow(attempt));
+                attempt += 1;
+                println!(
+                    "⚠️  获取订阅 {} 失败，{:?} 后重试 ({}/{}): {}",
+                    subscription.name, delay, attempt, options.retries, e
+                );
+                tokio::time::sleep(delay).await;
+            }
+            Err(Failure::Retryable(e) | Failure::Fatal(e)) => return Err(e),
+        }
+    }
+}
+
+fn build_client(subscription: &Subscription, options: &FetchOptions) -> Result<Client> {
+    let mut headers = HeaderMap::new();
+    for (name, value) in &subscription.headers {
+        let header = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|e| anyhow!("请求头名称无效 {}: {}", name, e))?;
+        let value =
+            HeaderValue::from_str(value).map_err(|e| anyhow!("请求头 {} 的值无效: {}", name, e))?;
+        headers.insert(header, value);
+    }
+
+    let mut builder = Client::builder()
+        .user_agent(
+            subscription
+                .user_agent
+                .as_deref()
+                .unwrap_or(&options.user_agent),
+        )
+        .timeout(
+            subscription
+                .timeout
+                .map(Duration::from_secs)
+                .unwrap_or(options.timeout),
+        )
+        .default_headers(headers);
+    if let Some(proxy) = subscription
+        .proxy
+        .as_ref()
+        .or(options.system_proxy.as_ref())
+    {
+        let proxy = Proxy::all(proxy).map_err(|e| anyhow!("上游代理无效 {}: {}", proxy, e))?;
+        builder = builder.proxy(proxy);
+    }
+    builder
+        .build()
+        .map_err(|e| anyhow!("创建 HTTP 客户端失败: {}", e))
+}
+
+/// 请求一次订阅，返回内容与 `subscription-userinfo` 响应头
+async fn fetch_once(
+    client: &Client,
+    url: &str,
+) -> Result<(String, Option<SubscriptionUserInfo>), Failure> {
+    let response = client.get(url).send().await.map_err(|e| {
+        let error = anyhow!("请求失败: {}", e);
+        if e.is_builder() {
+            Failure::Fatal(error)
+        } else {
+            Failure::Retryable(error)
+        }
+    })?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let error = anyhow!("服务器返回 {}", status);
+        // 4xx 中只有限流值得重试
+        return Err(
+            if status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS {
+                Failure::Retryable(error)
+            } else {
+                Failure::Fatal(error)
+            },
+        );
+    }
+
+    let userinfo = response
+        .headers()
+        .get(userinfo::HEADER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(SubscriptionUserInfo::parse);
+    let content = response
+        .text()
+        .await
+        .map_err(|e| Failure::Retryable(anyhow!("读取内容失败: {}", e)))?;
+    Ok((content, userinfo))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tokio::sync::mpsc::UnboundedReceiver;
+
+    /// 启动本地 HTTP 服务端替身，按连接顺序返回预设的响应，并把请求头发回调用方
+    async fn spawn_server(responses: Vec<&'static str>) -> (String, UnboundedReceiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            for response in responses {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut request = Vec::new();
+                while !request.ends_with(b"\r\n\r\n") {
+                    request.push(socket.read_u8().await.unwrap());
+                }
+                tx.send(String::from_utf8(request).unwrap().to_ascii_lowercase())
+                    .unwrap();
+                socket.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        (format!("http://127.0.0.1:{}/sub", port), rx)
+    }
+
+    fn options() -> FetchOptions {
+        FetchOptions {
+            concurrent: 2,
+            retries: 2,
+            retry_delay: Duration::from_millis(10),
+            timeout: Duration::from_secs(5),
+            user_agent: "default-agent".to_string(),
+            system_proxy: None,
+            github_proxy: None,
+        }
+    }
+
+    fn subscription(url: String) -> Subscription {
+        Subscription {
+            name: "test".to_string(),
+            url,
+            enabled: true,
+            ..Default::default()
+        }
+    }
+
+    /// 获取只有一份内容的订阅
+    async fn fetch_one(subscription: &Subscription, options: &FetchOptions) -> Result<String> {
+        let mut documents = fetch_subscription(subscription, options).await?;
+        assert_eq!(documents.len(), 1);
+        Ok(documents.remove(0).content)
+    }
+
+    const OK: &str = "HTTP/1.1 200 OK\r\nContent-Length: 5\r\nConnection: close\r\n\r\nnodes";
+    const UNAVAILABLE: &str =
+        "HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+    const NOT_FOUND: &str =
+        "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+
+    #[tokio::test]
+    async fn test_retry_with_custom_headers() {
+        let (url, mut requests) = spawn_server(vec![UNAVAILABLE, OK]).await;
+        let mut subscription = subscription(url);
+        subscription.user_agent = Some("custom-agent".to_string());
+        subscription
+            .headers
+            .insert("X-Token".to_string(), "secret".to_string());
+
+        let content = fetch_one(&subscription, &options()).await.unwrap();
+        assert_eq!(content, "nodes");
+
+        for _ in 0..2 {
+            let request = requests.recv().await.unwrap();
+            assert!(request.contains("user-agent: custom-agent"));
+            assert!(request.contains("x-token: secret"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_subscription_userinfo_header() {
+        let (url, _requests) = spawn_server(vec![
+            "HTTP/1.1 200 OK\r\nSubscription-Userinfo: upload=1; download=2; total=10; expire=1893456000\r\n\
+             Content-Length: 5\r\nConnection: close\r\n\r\nnodes",
+        ])
+        .await;
+        let documents = fetch_subscription(&subscription(url), &options())
+            .await
+            .unwrap();
+        let userinfo = documents[0].userinfo.as_ref().unwrap();
+        assert_eq!(userinfo.used(), 3);
+        assert_eq!(userinfo.remaining(), Some(7));
+
+        let (url, _requests) = spawn_server(vec![OK]).await;
+        let documents = fetch_subscription(&subscription(url), &options())
+            .await
+            .unwrap();
+        assert!(documents[0].userinfo.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_client_error_is_not_retried() {
+        let (url, mut requests) = spawn_server(vec![NOT_FOUND]).await;
+        let error = fetch_one(&subscription(url), &options()).await.unwrap_err();
+        assert!(error.to_string().contains("404"));
+
+        assert!(requests.recv().await.unwrap().contains("default-agent"));
+        assert!(requests.recv().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_fetch_subscriptions_keeps_order() {
+        let (first, _first_requests) = spawn_server(vec![OK]).await;
+        let (second, _second_requests) = spawn_server(vec![NOT_FOUND]).await;
+        let mut invalid = subscription("http://127.0.0.1:1/sub".to_string());
+        invalid.proxy = Some("not a proxy url".to_string());
+
+        let results = fetch_subscriptions(
+            &[subscription(first), subscription(second), invalid],
+            &options(),
+        )
+        .await;
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].as_ref().unwrap()[0].content, "nodes");
+        assert!(results[1].is_err());
+        assert!(
+            results[2]
+                .as_ref()
+                .unwrap_err()
+                .to_string()
+                .contains("上游代理")
+        );
+    }
+
+    #[test]
+    fn test_github_proxy_url() {
+        let raw = "https://raw.githubusercontent.com/user/repo/main/sub.txt";
+        assert_eq!(
+            github_proxy_url(raw, "https://ghfast.top/"),
+            "https://ghfast.top/https://raw.githubusercontent.com/user/repo/main/sub.txt"
+        );
+        // 已改写的链接与非 GitHub 链接保持不变
+        let proxied = github_proxy_url(raw, "https://ghfast.top");
+        assert_eq!(github_proxy_url(&proxied, "https://ghfast.top"), proxied);
+        assert_eq!(
+            github_proxy_url("https://example.com/sub", "https://ghfast.top"),
+            "https://example.com/sub"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_system_proxy_and_github_proxy() {
+        // 本地替身充当上游 HTTP 代理，代理请求的请求行带完整 URL
+        let (proxy, mut requests) = spawn_server(vec![OK]).await;
+        let mut options = options();
+        options.system_proxy = Some(proxy.trim_end_matches("/sub").to_string());
+        options.github_proxy = Some("http://mirror.example".to_string());
+
+        let github =
+            subscription("https://raw.githubusercontent.com/user/repo/main/sub".to_string());
+        assert_eq!(fetch_one(&github, &options).await.unwrap(), "nodes");
+        assert!(requests.recv().await.unwrap().starts_with(
+            "get http://mirror.example/https://raw.githubusercontent.com/user/repo/main/sub "
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_local_sources() {
+        let mut inline = subscription(String::new());
+        inline.content = Some("trojan://secret@example.com:443".to_string());
+        assert_eq!(
+            fetch_one(&inline, &options()).await.unwrap(),
+            "trojan://secret@example.com:443"
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.txt"), "a").unwrap();
+        std::fs::write(dir.path().join("b.txt"), "b").unwrap();
+        let files = subscription(format!("file://{}/*.txt", dir.path().display()));
+        let documents = fetch_subscription(&files, &options()).await.unwrap();
+        assert_eq!(documents.len(), 2);
+        assert_eq!(documents[1].content, "b");
+        assert!(documents[1].label.ends_with("b.txt)"));
+    }
+}
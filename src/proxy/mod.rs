@@ -1,87 +1,22 @@
-use serde::{Deserialize, Serialize};
 use std::net::IpAddr;
 use url::Url;
 
+pub mod dedup;
+pub mod fetch;
+pub mod node;
 pub mod parser;
+pub mod quanx;
+pub mod share_link;
+pub mod singbox;
+pub mod sip008;
+pub mod source;
+pub mod subconverter;
+pub mod subscription;
+pub mod userinfo;
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
-pub struct ProxyNode {
-    pub name: String,
-    pub server: String,
-    pub port: u16,
-    pub protocol: Option<String>,
-    pub username: Option<String>,
-    pub password: Option<String>,
-    pub uuid: Option<String>,
-    pub alter_id: Option<u16>,
-    pub cipher: Option<String>,
-    pub tls: Option<bool>,
-    pub sni: Option<String>,
-    pub network: Option<String>,
-    pub ws_path: Option<String>,
-    pub ws_headers: Option<serde_json::Value>,
-    pub skip_cert_verify: Option<bool>,
-    pub server_name: Option<String>,
-    pub fingerprint: Option<String>,
-    pub alpn: Option<Vec<String>>,
-    pub servername: Option<String>,
-    pub flow: Option<String>,
-    pub reality_opts: Option<serde_json::Value>,
-}
+pub use node::{Protocol, ProxyNode};
 
 impl ProxyNode {
-    pub fn new(name: String, server: String, port: u16) -> Self {
-        Self {
-            name,
-            server,
-            port,
-            protocol: None,
-            username: None,
-            password: None,
-            uuid: None,
-            alter_id: None,
-            cipher: None,
-            tls: None,
-            sni: None,
-            network: None,
-            ws_path: None,
-            ws_headers: None,
-            skip_cert_verify: None,
-            server_name: None,
-            fingerprint: None,
-            alpn: None,
-            servername: None,
-            flow: None,
-            reality_opts: None,
-        }
-    }
-
-    pub fn with_auth(mut self, username: String, password: String) -> Self {
-        self.username = Some(username);
-        self.password = Some(password);
-        self
-    }
-
-    pub fn with_uuid(mut self, uuid: String) -> Self {
-        self.uuid = Some(uuid);
-        self
-    }
-
-    pub fn to_proxy_url(&self) -> String {
-        let protocol = self.protocol.as_deref().unwrap_or("socks5");
-        match (&self.username, &self.password) {
-            (Some(username), Some(password)) => {
-                format!(
-                    "{}://{}:{}@{}:{}",
-                    protocol, username, password, self.server, self.port
-                )
-            }
-            _ => {
-                format!("{}://{}:{}", protocol, self.server, self.port)
-            }
-        }
-    }
-
     pub fn get_ip_address(&self) -> Option<IpAddr> {
         self.server.parse().ok()
     }
@@ -165,20 +100,6 @@ pub fn validate_proxy_node(proxy: &ProxyNode) -> anyhow::Result<()> {
         return Err(anyhow::anyhow!("端口号不能为0"));
     }
 
-    let protocol = proxy.protocol.as_deref().unwrap_or("");
-    if protocol.is_empty() {
-        return Err(anyhow::anyhow!("协议不能为空"));
-    }
-
-    // 验证协议是否支持
-    let supported_protocols = [
-        "http", "https", "socks5", "socks5h", "ss", "ssr", "vmess", "vless", "trojan", "hysteria",
-    ];
-
-    if !supported_protocols.contains(&protocol) {
-        return Err(anyhow::anyhow!("不支持的协议: {}", protocol));
-    }
-
     Ok(())
 }
 
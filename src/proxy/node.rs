@@ -0,0 +1,1156 @@
+//! 统一的代理节点模型
+//! 解析、检测、导出共用同一个强类型节点：公共字段 + 按协议区分的参数 + 共享的 TLS / 传输层配置
+//!
+//! 字段命名与 Clash（mihomo）的代理配置保持一致，节点可以直接用 serde 从 `proxies` 条目读取；
+//! 未建模的键保存在 `extra` 中，写回时原样输出，保证 Clash 配置往返无损。
+//! 尚未支持的协议与传输方式不会让节点读取失败，原始字段同样经 `extra` 原样写回
+
+use anyhow::anyhow;
+use serde::de::IntoDeserializer;
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de, ser};
+use std::collections::BTreeMap;
+
+/// 代理节点
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(remote = "Self")]
+pub struct ProxyNode {
+    pub name: String,
+    pub server: String,
+    pub port: u16,
+    /// 协议及其参数，序列化时以 `type` 区分
+    #[serde(flatten)]
+    pub protocol: Protocol,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub udp: Option<bool>,
+    #[serde(flatten)]
+    pub tls: TlsSettings,
+    #[serde(flatten)]
+    pub transport: TransportSettings,
+    #[serde(flatten)]
+    pub dial: DialSettings,
+    /// 其余未建模的字段，必须放在最后以收集剩余的键
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, serde_yaml::Value>,
+}
+
+impl Serialize for ProxyNode {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let Protocol::Other(kind) = &self.protocol else {
+            return ProxyNode::serialize(self, serializer);
+        };
+        // 未支持的协议借用无参数的 direct 写出公共字段，再换回原来的类型
+        let mut node = self.clone();
+        node.protocol = Protocol::Direct;
+        let mut value = serde_yaml::to_value(&node).map_err(ser::Error::custom)?;
+        value["type"] = serde_yaml::Value::from(kind.as_str());
+        value.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for ProxyNode {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let original = serde_yaml::Value::deserialize(deserializer)?;
+        let kind = original
+            .get("type")
+            .and_then(|kind| kind.as_str())
+            .map(str::to_string);
+        let mut node = match kind {
+            Some(kind) if !Protocol::is_known(&kind) => {
+                let mut value = original.clone();
+                value["type"] = serde_yaml::Value::from("direct");
+                let mut node = ProxyNode::deserialize(value).map_err(de::Error::custom)?;
+                node.protocol = Protocol::Other(kind);
+                node
+            }
+            _ => ProxyNode::deserialize(original.clone()).map_err(de::Error::custom)?,
+        };
+        // extra 只保留类型化字段没有写出的原始键，包括无法识别而被置空的取值
+        node.extra.clear();
+        let known = serde_yaml::to_value(&node).map_err(de::Error::custom)?;
+        node.extra = original
+            .as_mapping()
+            .into_iter()
+            .flatten()
+            .filter_map(|(key, value)| Some((key.as_str()?.to_string(), value.clone())))
+            .filter(|(key, _)| known.get(key.as_str()).is_none())
+            .collect();
+        Ok(node)
+    }
+}
+
+/// 兼容写成字符串或浮点数的整数，如 `alterId: "0"`、`alterId: 2.0`
+fn lenient_u16<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u16>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Number {
+        Int(u16),
+        Float(f64),
+        Text(String),
+    }
+
+    let number = match Option::<Number>::deserialize(deserializer)? {
+        None => return Ok(None),
+        Some(Number::Int(number)) => return Ok(Some(number)),
+        Some(Number::Float(number)) => number,
+        Some(Number::Text(text)) if text.trim().is_empty() => return Ok(None),
+        Some(Number::Text(text)) => text
+            .trim()
+            .parse()
+            .map_err(|_| de::Error::custom(format!("不是有效的数字: {}", text)))?,
+    };
+    if number.fract() == 0.0 && (0.0..=f64::from(u16::MAX)).contains(&number) {
+        Ok(Some(number as u16))
+    } else {
+        Err(de::Error::custom(format!("数字超出范围: {}", number)))
+    }
+}
+
+/// 无法识别的传输方式读作 `None`，原始取值由 `extra` 保留
+fn lenient_network<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Network>, D::Error> {
+    let Some(network) = Option::<String>::deserialize(deserializer)? else {
+        return Ok(None);
+    };
+    let network: de::value::StringDeserializer<de::value::Error> = network.into_deserializer();
+    Ok(Network::deserialize(network).ok())
+}
+
+/// 各协议的参数
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Protocol {
+    #[serde(rename = "ss", alias = "shadowsocks")]
+    Shadowsocks(ShadowsocksParams),
+    Ssr(SsrParams),
+    Vmess(VmessParams),
+    Vless(VlessParams),
+    Trojan(TrojanParams),
+    /// Hysteria v1
+    Hysteria(HysteriaParams),
+    #[serde(alias = "hy2")]
+    Hysteria2(Hysteria2Params),
+    Tuic(TuicParams),
+    AnyTls(AnyTlsParams),
+    #[serde(alias = "wg")]
+    WireGuard(WireGuardParams),
+    Http(AuthParams),
+    #[serde(alias = "socks", alias = "socks5h")]
+    Socks5(AuthParams),
+    #[serde(alias = "socks4a")]
+    Socks4(AuthParams),
+    /// 不经过代理直连
+    Direct,
+    /// 尚未支持的协议类型，参数保存在 `extra` 中原样写回
+    #[serde(skip)]
+    Other(String),
+}
+
+/// Shadowsocks 参数
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ShadowsocksParams {
+    pub cipher: String,
+    pub password: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub plugin: Option<String>,
+    /// Clash 的参数映射或 SIP002 的 `;` 分隔字符串，由插件自行解释
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub plugin_opts: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub udp_over_tcp: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub udp_over_tcp_version: Option<u8>,
+}
+
+/// ShadowsocksR 参数
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SsrParams {
+    pub cipher: String,
+    pub password: String,
+    pub obfs: String,
+    pub protocol: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub obfs_param: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub protocol_param: Option<String>,
+}
+
+/// VMess 参数
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct VmessParams {
+    pub uuid: String,
+    #[serde(
+        rename = "alterId",
+        default,
+        deserialize_with = "lenient_u16",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub alter_id: Option<u16>,
+    /// 加密方式，缺省为 auto
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cipher: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub packet_addr: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub xudp: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub packet_encoding: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub global_padding: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub authenticated_length: Option<bool>,
+}
+
+/// VLESS 参数
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct VlessParams {
+    pub uuid: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub flow: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encryption: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub packet_addr: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub xudp: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub packet_encoding: Option<String>,
+}
+
+/// Trojan 参数
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct TrojanParams {
+    pub password: String,
+    /// Trojan-Go 的 Shadowsocks 二次加密
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ss_opts: Option<TrojanSsOpts>,
+}
+
+/// Trojan-Go 的 Shadowsocks 二次加密参数
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TrojanSsOpts {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub method: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+}
+
+/// Hysteria v1 参数
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct HysteriaParams {
+    /// 认证字符串
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth_str: Option<String>,
+    /// base64 编码的认证数据
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub up: Option<Bandwidth>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub down: Option<Bandwidth>,
+    /// Stash 写法的上行带宽（Mbps）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub up_speed: Option<u64>,
+    /// Stash 写法的下行带宽（Mbps）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub down_speed: Option<u64>,
+    /// 伪装协议：udp、wechat-video、faketcp
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub protocol: Option<String>,
+    /// Stash 写法的伪装协议
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub obfs_protocol: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub obfs: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ports: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hop_interval: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recv_window_conn: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recv_window: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub disable_mtu_discovery: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fast_open: Option<bool>,
+}
+
+/// Hysteria2 参数
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Hysteria2Params {
+    pub password: String,
+    /// 端口跳跃范围，如 `20000-30000`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ports: Option<String>,
+    /// 端口跳跃间隔（秒）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hop_interval: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub obfs: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub obfs_password: Option<String>,
+    /// 上行带宽
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub up: Option<Bandwidth>,
+    /// 下行带宽
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub down: Option<Bandwidth>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwnd: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub udp_mtu: Option<u32>,
+}
+
+/// 带宽，纯数字按 Mbps 计，也可以写成 `100 Mbps` 等带单位的字符串
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Bandwidth {
+    Mbps(u64),
+    Text(String),
+}
+
+impl std::fmt::Display for Bandwidth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Bandwidth::Mbps(mbps) => write!(f, "{}", mbps),
+            Bandwidth::Text(text) => f.write_str(text),
+        }
+    }
+}
+
+/// TUIC v5 参数
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct TuicParams {
+    pub uuid: String,
+    pub password: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub congestion_controller: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub udp_relay_mode: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+    /// 服务器 IP，设置后不再解析 server
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ip: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub heartbeat_interval: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reduce_rtt: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_timeout: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub disable_sni: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_udp_relay_packet_size: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fast_open: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_open_streams: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwnd: Option<u32>,
+}
+
+/// AnyTLS 参数
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct AnyTlsParams {
+    pub password: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idle_session_check_interval: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idle_session_timeout: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_idle_session: Option<u32>,
+}
+
+/// WireGuard 参数
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct WireGuardParams {
+    pub private_key: String,
+    pub public_key: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pre_shared_key: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ip: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ipv6: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed_ips: Option<Vec<String>>,
+    /// 三字节数组或其 base64 字符串
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reserved: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mtu: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub workers: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub persistent_keepalive: Option<u32>,
+    /// 是否经由隧道解析目标域名
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remote_dns_resolve: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dns: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refresh_server_ip_interval: Option<u32>,
+}
+
+/// HTTP / SOCKS 的可选认证
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AuthParams {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+    /// HTTP 代理 CONNECT 请求附加的请求头
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub headers: Option<BTreeMap<String, String>>,
+}
+
+/// 共享的 TLS 配置
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct TlsSettings {
+    /// VMess / VLESS / HTTP / SOCKS 是否启用 TLS，其余协议自带 TLS
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls: Option<bool>,
+    /// Trojan、Hysteria2、TUIC 等使用的 SNI
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sni: Option<String>,
+    /// VMess / VLESS 使用的 SNI
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub servername: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alpn: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub skip_cert_verify: Option<bool>,
+    /// 证书指纹
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fingerprint: Option<String>,
+    /// uTLS 客户端指纹
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_fingerprint: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reality_opts: Option<RealityOpts>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ech_opts: Option<EchOpts>,
+    /// Hysteria2 / TUIC 的自定义 CA 证书路径
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ca: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ca_str: Option<String>,
+}
+
+impl TlsSettings {
+    /// 配置的 SNI，`sni` 优先于 `servername`，空值视为未配置
+    pub fn server_name(&self) -> Option<&str> {
+        self.sni
+            .as_deref()
+            .or(self.servername.as_deref())
+            .filter(|name| !name.is_empty())
+    }
+}
+
+/// REALITY 参数
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct RealityOpts {
+    pub public_key: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub short_id: Option<String>,
+}
+
+/// ECH 参数
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct EchOpts {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enable: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config: Option<String>,
+}
+
+/// 共享的传输层配置
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct TransportSettings {
+    #[serde(
+        default,
+        deserialize_with = "lenient_network",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub network: Option<Network>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ws_opts: Option<WsOpts>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub h2_opts: Option<H2Opts>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub http_opts: Option<HttpOpts>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub grpc_opts: Option<GrpcOpts>,
+}
+
+impl TransportSettings {
+    /// 按传输方式把伪装域名与路径放入对应参数，gRPC 的路径为服务名
+    pub fn from_network(
+        network: &str,
+        host: Option<String>,
+        path: Option<String>,
+    ) -> anyhow::Result<Self> {
+        let mut transport = Self::default();
+        match network {
+            "" | "tcp" => {}
+            "ws" => {
+                transport.network = Some(Network::Ws);
+                transport.ws_opts = Some(WsOpts {
+                    path,
+                    headers: host.map(|host| BTreeMap::from([("Host".to_string(), host)])),
+                    ..Default::default()
+                });
+            }
+            "h2" => {
+                transport.network = Some(Network::H2);
+                transport.h2_opts = Some(H2Opts {
+                    host: host.map(|host| vec![host]),
+                    path,
+                });
+            }
+            "http" => {
+                transport.network = Some(Network::Http);
+                transport.http_opts = Some(HttpOpts {
+                    path: path.map(|path| vec![path]),
+                    headers: host.map(|host| BTreeMap::from([("Host".to_string(), vec![host])])),
+                    ..Default::default()
+                });
+            }
+            "grpc" => {
+                transport.network = Some(Network::Grpc);
+                transport.grpc_opts = Some(GrpcOpts {
+                    grpc_service_name: path,
+                });
+            }
+            other => return Err(anyhow!("不支持的传输方式: {}", other)),
+        }
+        Ok(transport)
+    }
+
+    /// 伪装域名：WebSocket 的 Host 头或 HTTP/2 的首个 host
+    pub fn host(&self) -> Option<&str> {
+        match self.network? {
+            Network::Ws => self
+                .ws_opts
+                .as_ref()?
+                .headers
+                .as_ref()?
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case("host"))
+                .map(|(_, value)| value.as_str()),
+            Network::H2 => self
+                .h2_opts
+                .as_ref()?
+                .host
+                .as_ref()?
+                .first()
+                .map(String::as_str),
+            Network::Http => self
+                .http_opts
+                .as_ref()?
+                .headers
+                .as_ref()?
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case("host"))?
+                .1
+                .first()
+                .map(String::as_str),
+            _ => None,
+        }
+        .filter(|host| !host.is_empty())
+    }
+
+    /// 请求路径，gRPC 为服务名
+    pub fn path(&self) -> Option<&str> {
+        match self.network? {
+            Network::Ws => self.ws_opts.as_ref()?.path.as_deref(),
+            Network::H2 => self.h2_opts.as_ref()?.path.as_deref(),
+            Network::Http => self
+                .http_opts
+                .as_ref()?
+                .path
+                .as_ref()?
+                .first()
+                .map(String::as_str),
+            Network::Grpc => self.grpc_opts.as_ref()?.grpc_service_name.as_deref(),
+            Network::Tcp => None,
+        }
+        .filter(|path| !path.is_empty())
+    }
+}
+
+/// 传输方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Network {
+    /// Xray 把 TCP 传输改名为 raw，部分转换器用 none 表示不使用传输层
+    #[serde(alias = "raw", alias = "none")]
+    Tcp,
+    Ws,
+    /// HTTP/1.1 伪装
+    Http,
+    H2,
+    Grpc,
+}
+
+impl Network {
+    /// 与 Clash 的 `network` 取值一致
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Network::Tcp => "tcp",
+            Network::Ws => "ws",
+            Network::Http => "http",
+            Network::H2 => "h2",
+            Network::Grpc => "grpc",
+        }
+    }
+}
+
+/// WebSocket 参数
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct WsOpts {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub headers: Option<BTreeMap<String, String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_early_data: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub early_data_header_name: Option<String>,
+    /// 使用 HTTPUpgrade 代替 WebSocket
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub v2ray_http_upgrade: Option<bool>,
+}
+
+/// HTTP/2 参数
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct H2Opts {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+}
+
+/// HTTP/1.1 伪装参数
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct HttpOpts {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub method: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub headers: Option<BTreeMap<String, Vec<String>>>,
+}
+
+/// gRPC 参数
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct GrpcOpts {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub grpc_service_name: Option<String>,
+}
+
+/// 所有协议共用的拨号选项
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct DialSettings {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tfo: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mptcp: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interface_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub routing_mark: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ip_version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dialer_proxy: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub smux: Option<SmuxOpts>,
+}
+
+/// sing-mux 多路复用参数
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SmuxOpts {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub protocol: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_connections: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_streams: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_streams: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub padding: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub statistic: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub only_tcp: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub brutal_opts: Option<serde_yaml::Value>,
+}
+
+impl ProxyNode {
+    /// 创建节点，TLS 与传输层使用默认配置
+    pub fn new(name: String, server: String, port: u16, protocol: Protocol) -> Self {
+        Self {
+            name,
+            server,
+            port,
+            protocol,
+            udp: None,
+            tls: TlsSettings::default(),
+            transport: TransportSettings::default(),
+            dial: DialSettings::default(),
+            extra: BTreeMap::new(),
+        }
+    }
+
+    /// 协议类型名称，与 Clash 的 `type` 一致
+    pub fn kind(&self) -> &str {
+        self.protocol.kind()
+    }
+
+    /// 模型无法表示的传输方式，此类节点无法按原样连接
+    pub fn unsupported_network(&self) -> Option<&str> {
+        self.extra.get("network")?.as_str()
+    }
+
+    /// 逐条读取 Clash 的 `proxies` 列表，单个节点无效不影响其余节点
+    pub fn from_clash_proxies(proxies: &serde_yaml::Value) -> Vec<anyhow::Result<Self>> {
+        let Some(entries) = proxies.as_sequence() else {
+            return vec![Err(anyhow!("proxies 字段不是列表"))];
+        };
+        entries
+            .iter()
+            .map(|entry| {
+                serde_yaml::from_value(entry.clone()).map_err(|e| {
+                    let name = entry
+                        .get("name")
+                        .and_then(|n| n.as_str())
+                        .unwrap_or("未命名");
+                    anyhow!("代理配置无效 {}: {}", name, e)
+                })
+            })
+            .collect()
+    }
+}
+
+impl Protocol {
+    /// 已建模的 `type` 取值，包括别名
+    const KINDS: &[&str] = &[
+        "ss",
+        "shadowsocks",
+        "ssr",
+        "vmess",
+        "vless",
+        "trojan",
+        "hysteria",
+        "hysteria2",
+        "hy2",
+        "tuic",
+        "anytls",
+        "wireguard",
+        "wg",
+        "http",
+        "socks5",
+        "socks",
+        "socks5h",
+        "socks4",
+        "socks4a",
+        "direct",
+    ];
+
+    fn is_known(kind: &str) -> bool {
+        Self::KINDS.contains(&kind)
+    }
+
+    /// 协议类型名称，与 Clash 的 `type` 一致
+    pub fn kind(&self) -> &str {
+        match self {
+            Protocol::Shadowsocks(_) => "ss",
+            Protocol::Ssr(_) => "ssr",
+            Protocol::Vmess(_) => "vmess",
+            Protocol::Vless(_) => "vless",
+            Protocol::Trojan(_) => "trojan",
+            Protocol::Hysteria(_) => "hysteria",
+            Protocol::Hysteria2(_) => "hysteria2",
+            Protocol::Tuic(_) => "tuic",
+            Protocol::AnyTls(_) => "anytls",
+            Protocol::WireGuard(_) => "wireguard",
+            Protocol::Http(_) => "http",
+            Protocol::Socks5(_) => "socks5",
+            Protocol::Socks4(_) => "socks4",
+            Protocol::Direct => "direct",
+            Protocol::Other(kind) => kind,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deserialize_clash_proxy() {
+        let yaml = r#"
+name: vmess-ws
+type: vmess
+server: example.com
+port: 443
+uuid: 12345678-1234-1234-1234-123456789012
+alterId: 0
+cipher: auto
+udp: true
+tls: true
+servername: cdn.example.com
+skip-cert-verify: true
+network: ws
+ws-opts:
+  path: /ray
+  headers:
+    Host: cdn.example.com
+"#;
+        let node: ProxyNode = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(node.kind(), "vmess");
+        let Protocol::Vmess(vmess) = &node.protocol else {
+            panic!("协议解析错误");
+        };
+        assert_eq!(vmess.alter_id, Some(0));
+        assert_eq!(node.udp, Some(true));
+        assert_eq!(node.tls.server_name(), Some("cdn.example.com"));
+        assert_eq!(node.tls.skip_cert_verify, Some(true));
+        assert_eq!(node.transport.network, Some(Network::Ws));
+        let ws = node.transport.ws_opts.as_ref().unwrap();
+        assert_eq!(ws.path.as_deref(), Some("/ray"));
+        assert_eq!(ws.headers.as_ref().unwrap()["Host"], "cdn.example.com");
+
+        // 序列化后再读取，节点保持不变
+        let text = serde_yaml::to_string(&node).unwrap();
+        assert!(text.contains("type: vmess"));
+        assert!(text.contains("skip-cert-verify: true"));
+        assert_eq!(serde_yaml::from_str::<ProxyNode>(&text).unwrap(), node);
+    }
+
+    #[test]
+    fn test_deserialize_aliases_and_numbers() {
+        let yaml = r#"
+name: hy2
+type: hy2
+server: 1.2.3.4
+port: 8443
+password: secret
+up: 50
+down: "100 Mbps"
+sni: hy2.example.com
+"#;
+        let node: ProxyNode = serde_yaml::from_str(yaml).unwrap();
+        let Protocol::Hysteria2(hy2) = &node.protocol else {
+            panic!("协议解析错误");
+        };
+        assert_eq!(hy2.up, Some(Bandwidth::Mbps(50)));
+        assert_eq!(hy2.down, Some(Bandwidth::Text("100 Mbps".to_string())));
+        assert_eq!(node.kind(), "hysteria2");
+    }
+
+    #[test]
+    fn test_deserialize_rejects_incomplete_node() {
+        let yaml = "name: t\ntype: trojan\nserver: example.com\nport: 443\n";
+        assert!(serde_yaml::from_str::<ProxyNode>(yaml).is_err());
+        let yaml = "name: t\ntype: snell\nserver: example.com\n";
+        assert!(serde_yaml::from_str::<ProxyNode>(yaml).is_err());
+    }
+
+    #[test]
+    fn test_from_clash_proxies_skips_invalid_entries() {
+        let yaml = r#"
+- {name: a, type: socks5, server: 1.1.1.1, port: 1080}
+- {name: b, type: trojan, server: 2.2.2.2, port: 443}
+"#;
+        let value: serde_yaml::Value = serde_yaml::from_str(yaml).unwrap();
+        let nodes = ProxyNode::from_clash_proxies(&value);
+        assert_eq!(nodes.len(), 2);
+        assert_eq!(nodes[0].as_ref().unwrap().kind(), "socks5");
+        assert!(nodes[1].as_ref().unwrap_err().to_string().contains("b"));
+    }
+
+    #[test]
+    fn test_lenient_fields_and_unknown_values() {
+        let yaml = r#"
+- {name: a, type: vmess, server: a.com, port: 443, uuid: u, alterId: "2"}
+- {name: b, type: vmess, server: b.com, port: 443, uuid: u, alterId: 2.0, network: raw}
+- {name: c, type: vless, server: c.com, port: 443, uuid: u, network: xhttp, xhttp-opts: {path: /x}}
+- {name: d, type: snell, server: d.com, port: 443, psk: secret, version: 3, udp: true}
+"#;
+        let value: serde_yaml::Value = serde_yaml::from_str(yaml).unwrap();
+        let nodes: Vec<ProxyNode> = ProxyNode::from_clash_proxies(&value)
+            .into_iter()
+            .map(Result::unwrap)
+            .collect();
+
+        for node in &nodes[..2] {
+            let Protocol::Vmess(vmess) = &node.protocol else {
+                panic!("协议解析错误");
+            };
+            assert_eq!(vmess.alter_id, Some(2));
+        }
+        assert_eq!(nodes[1].transport.network, Some(Network::Tcp));
+
+        // 未知的传输方式与协议保留原始字段，写回后与输入一致
+        let vless = &nodes[2];
+        assert_eq!(vless.transport.network, None);
+        assert_eq!(vless.unsupported_network(), Some("xhttp"));
+        let snell = &nodes[3];
+        assert_eq!(snell.kind(), "snell");
+        assert_eq!(snell.udp, Some(true));
+        assert_eq!(snell.extra.keys().collect::<Vec<_>>(), ["psk", "version"]);
+        let entries = value.as_sequence().unwrap();
+        for (entry, node) in entries[2..].iter().zip(&nodes[2..]) {
+            assert_eq!(&serde_yaml::to_value(node).unwrap(), entry);
+            let text = serde_yaml::to_string(node).unwrap();
+            assert_eq!(&serde_yaml::from_str::<ProxyNode>(&text).unwrap(), node);
+        }
+    }
+
+    #[test]
+    fn test_hysteria_and_network_aliases_round_trip() {
+        let yaml = r#"
+- name: hysteria
+  type: hysteria
+  server: 208.87.243.151
+  port: 38845
+  auth-str: secret
+  up: 11 Mbps
+  down: 55 Mbps
+  protocol: udp
+  obfs: xplus
+  ports: 20000-30000
+  hop-interval: 30
+  recv-window-conn: 12582912
+  recv-window: 52428800
+  disable-mtu-discovery: true
+  fast-open: true
+  udp: true
+  sni: www.bing.com
+  alpn: [h3]
+  skip-cert-verify: true
+- {name: stash, type: hysteria, server: a.com, port: 443, auth: c2VjcmV0, up-speed: 10, down-speed: 50, obfs-protocol: wechat-video}
+"#;
+        let value: serde_yaml::Value = serde_yaml::from_str(yaml).unwrap();
+        for (entry, node) in value
+            .as_sequence()
+            .unwrap()
+            .iter()
+            .zip(ProxyNode::from_clash_proxies(&value))
+        {
+            let node = node.unwrap();
+            assert_eq!(node.kind(), "hysteria");
+            assert!(node.extra.is_empty(), "{} 残留未建模字段", node.name);
+            assert_eq!(&serde_yaml::to_value(&node).unwrap(), entry);
+        }
+
+        // raw 与 none 都是不使用传输层，写回时统一为 tcp
+        for network in ["raw", "none"] {
+            let yaml = format!(
+                "{{name: v, type: vless, server: a.com, port: 443, uuid: u, network: {}}}",
+                network
+            );
+            let node: ProxyNode = serde_yaml::from_str(&yaml).unwrap();
+            assert_eq!(node.transport.network, Some(Network::Tcp));
+            assert!(node.extra.is_empty());
+            let text = serde_yaml::to_string(&node).unwrap();
+            assert!(text.contains("network: tcp"));
+            assert_eq!(serde_yaml::from_str::<ProxyNode>(&text).unwrap(), node);
+        }
+    }
+
+    #[test]
+    fn test_sample_config_keeps_every_proxy() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/sample.yaml");
+        let config: serde_yaml::Value =
+            serde_yaml::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+        let proxies = &config["proxies"];
+        let nodes = ProxyNode::from_clash_proxies(proxies);
+        assert_eq!(nodes.len(), proxies.as_sequence().unwrap().len());
+        let failed: Vec<String> = nodes
+            .iter()
+            .filter_map(|node| node.as_ref().err().map(|e| e.to_string()))
+            .collect();
+        assert!(failed.is_empty(), "{:#?}", failed);
+        // 样例中的协议都已建模
+        assert!(
+            nodes
+                .iter()
+                .flatten()
+                .all(|node| !matches!(node.protocol, Protocol::Other(_)))
+        );
+    }
+
+    #[test]
+    fn test_clash_proxies_round_trip() {
+        let yaml = r#"
+- name: vmess-ws
+  type: vmess
+  server: vmess.example.com
+  port: 443
+  uuid: b831381d-6324-4d53-ad4f-8cda48b30811
+  alterId: 0
+  cipher: auto
+  udp: true
+  tls: true
+  servername: cdn.example.com
+  skip-cert-verify: true
+  client-fingerprint: chrome
+  network: ws
+  ws-opts:
+    path: /ray
+    headers:
+      Host: cdn.example.com
+    max-early-data: 2048
+    early-data-header-name: Sec-WebSocket-Protocol
+  xudp: true
+  packet-encoding: xudp
+  global-padding: false
+  authenticated-length: true
+  smux:
+    enabled: true
+    protocol: h2mux
+    max-connections: 4
+- name: vless-reality
+  type: vless
+  server: 1.2.3.4
+  port: 443
+  uuid: b831381d-6324-4d53-ad4f-8cda48b30811
+  flow: xtls-rprx-vision
+  encryption: ""
+  tls: true
+  servername: www.microsoft.com
+  client-fingerprint: chrome
+  reality-opts:
+    public-key: CrrQSjAG_YkHLwvM2M-7XkKJilgL5upBKCp0od0tLhE
+    short-id: 10f897e26c4b9478
+  network: grpc
+  grpc-opts:
+    grpc-service-name: grpc
+- name: ss-obfs
+  type: ss
+  server: ss.example.com
+  port: 8388
+  cipher: aes-128-gcm
+  password: secret
+  udp-over-tcp: true
+  udp-over-tcp-version: 2
+  plugin: obfs
+  plugin-opts:
+    mode: tls
+    host: bing.com
+  tfo: true
+  interface-name: eth0
+  routing-mark: 1234
+  ip-version: ipv4-prefer
+- name: trojan-go
+  type: trojan
+  server: trojan.example.com
+  port: 443
+  password: secret
+  sni: trojan.example.com
+  alpn: [h2, http/1.1]
+  ss-opts:
+    enabled: true
+    method: aes-128-gcm
+    password: inner
+- name: hy2
+  type: hysteria2
+  server: hy2.example.com
+  port: 443
+  ports: 20000-30000
+  hop-interval: 30
+  password: secret
+  up: 50
+  down: 100 Mbps
+  obfs: salamander
+  obfs-password: obfs
+  ca-str: PEM
+  ech-opts:
+    enable: true
+    config: base64
+- name: tuic
+  type: tuic
+  server: tuic.example.com
+  port: 443
+  uuid: 00000000-0000-0000-0000-000000000001
+  password: secret
+  heartbeat-interval: 10000
+  reduce-rtt: true
+  congestion-controller: bbr
+  udp-relay-mode: native
+  max-udp-relay-packet-size: 1500
+- name: anytls
+  type: anytls
+  server: anytls.example.com
+  port: 443
+  password: secret
+  idle-session-check-interval: 30
+  idle-session-timeout: 30
+  min-idle-session: 0
+- name: wg
+  type: wireguard
+  server: 162.159.192.1
+  port: 2480
+  private-key: eCtXsJZ27+4PbhDkHnB923tkUn2Gj59wZw5wFA75MnU=
+  public-key: Cr8hWlKvtDt7nrvf+f0brNQQzabAqrjfBvas9pmowjo=
+  ip: 172.16.0.2
+  allowed-ips: ['0.0.0.0/0']
+  reserved: [209, 98, 59]
+  mtu: 1280
+  remote-dns-resolve: true
+  dns: [1.1.1.1]
+  dialer-proxy: relay
+- name: http
+  type: http
+  server: http.example.com
+  port: 8080
+  username: user
+  password: pass
+  headers:
+    User-Agent: subs-check
+  future-option: kept
+"#;
+        let value: serde_yaml::Value = serde_yaml::from_str(yaml).unwrap();
+        let entries = value.as_sequence().unwrap();
+        let nodes = ProxyNode::from_clash_proxies(&value);
+        assert_eq!(nodes.len(), entries.len());
+
+        for (entry, node) in entries.iter().zip(nodes) {
+            let node = node.unwrap();
+            assert_eq!(&serde_yaml::to_value(&node).unwrap(), entry);
+            if node.name == "http" {
+                assert_eq!(node.extra.keys().collect::<Vec<_>>(), ["future-option"]);
+            } else {
+                assert!(node.extra.is_empty(), "{} 残留未建模字段", node.name);
+            }
+        }
+    }
+}
@@ -0,0 +1,282 @@
+//! Quantumult X 节点列表解析
+//! 读取 `[server_local]` 格式的节点行 `类型=服务器:端口, 键=值, ..., tag=名称`，
+//! 支持 shadowsocks（含 SSR）、vmess、vless、trojan、http 与 socks5
+
+use super::node::{
+    AuthParams, RealityOpts, ShadowsocksParams, SsrParams, TransportSettings, TrojanParams,
+    VlessParams, VmessParams,
+};
+use super::{Protocol, ProxyNode};
+use anyhow::{Error, Result, anyhow};
+use serde_json::json;
+use std::collections::HashMap;
+
+/// Quantumult X 的节点类型
+const TYPES: &[&str] = &["shadowsocks", "vmess", "vless", "trojan", "http", "socks5"];
+
+/// 节点行的参数
+struct Fields<'a> {
+    kind: &'a str,
+    server: String,
+    port: u16,
+    values: HashMap<String, &'a str>,
+}
+
+impl<'a> Fields<'a> {
+    fn parse(line: &'a str) -> Option<Self> {
+        let mut items = line.split(',').map(str::trim);
+        let (kind, address) = items.next()?.split_once('=')?;
+        let kind = TYPES.iter().find(|t| t.eq_ignore_ascii_case(kind.trim()))?;
+        let (server, port) = address.trim().rsplit_once(':')?;
+        let port = port.trim().parse().ok()?;
+        let server = server.trim_start_matches('[').trim_end_matches(']');
+        let values = items
+            .filter_map(|item| item.split_once('='))
+            .map(|(key, value)| (key.trim().to_ascii_lowercase(), value.trim()))
+            .collect();
+        Some(Self {
+            kind,
+            server: server.to_string(),
+            port,
+            values,
+        })
+    }
+
+    fn get(&self, key: &str) -> Option<String> {
+        self.values
+            .get(key)
+            .filter(|value| !value.is_empty())
+            .map(|value| value.to_string())
+    }
+
+    fn required(&self, key: &str) -> Result<String> {
+        self.get(key)
+            .ok_or_else(|| anyhow!("Quantumult X {} 节点缺少 {}", self.kind, key))
+    }
+
+    fn flag(&self, key: &str) -> Option<bool> {
+        match self.values.get(key)?.to_ascii_lowercase().as_str() {
+            "true" => Some(true),
+            "false" => Some(false),
+            _ => None,
+        }
+    }
+}
+
+/// 是否为 Quantumult X 节点行；Surge 的 `名称 = 类型, 服务器, 端口` 不会被误认
+pub fn is_quanx_line(line: &str) -> bool {
+    Fields::parse(line.trim()).is_some()
+}
+
+/// 解析节点列表或完整配置中的 `[server_local]` 段
+pub fn parse_quanx(content: &str) -> (Vec<ProxyNode>, Vec<Error>) {
+    let mut nodes = Vec::new();
+    let mut errors = Vec::new();
+    let mut in_servers = true;
+    for line in content.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if line.starts_with('[') && line.ends_with(']') {
+            in_servers = line.eq_ignore_ascii_case("[server_local]");
+            continue;
+        }
+        if !in_servers {
+            continue;
+        }
+        match parse_line(line) {
+            Ok(node) => nodes.push(node),
+            Err(e) => errors.push(anyhow!("Quantumult X 节点解析失败: {} - {}", line, e)),
+        }
+    }
+    (nodes, errors)
+}
+
+/// 解析单个节点行
+pub fn parse_line(line: &str) -> Result<ProxyNode> {
+    let fields = Fields::parse(line).ok_or_else(|| anyhow!("无法识别的节点行"))?;
+    let name = fields
+        .get("tag")
+        .unwrap_or_else(|| format!("{}:{}", fields.server, fields.port));
+    let protocol = match fields.kind {
+        "shadowsocks" => shadowsocks(&fields)?,
+        "vmess" => Protocol::Vmess(VmessParams {
+            uuid: fields.required("password")?,
+            cipher: fields.get("method").map(|method| match method.as_str() {
+                "chacha20-ietf-poly1305" => "chacha20-poly1305".to_string(),
+                _ => method,
+            }),
+            // aead=false 表示旧版 MD5 认证，需要非零 alterId
+            alter_id: fields.flag("aead").map(|aead| if aead { 0 } else { 1 }),
+            ..Default::default()
+        }),
+        "vless" => Protocol::Vless(VlessParams {
+            uuid: fields.required("password")?,
+            flow: fields.get("vless-flow"),
+            ..Default::default()
+        }),
+        "trojan" => Protocol::Trojan(TrojanParams {
+            password: fields.required("password")?,
+            ..Default::default()
+        }),
+        "http" => Protocol::Http(auth(&fields)),
+        _ => Protocol::Socks5(auth(&fields)),
+    };
+
+    let mut node = ProxyNode::new(name, fields.server.clone(), fields.port, protocol);
+    if fields.kind != "shadowsocks" {
+        obfs(&mut node, &fields)?;
+    }
+    node.dial.tfo = fields.flag("fast-open");
+    node.udp = fields.flag("udp-relay");
+    Ok(node)
+}
+
+fn shadowsocks(fields: &Fields) -> Result<Protocol> {
+    let cipher = fields.required("method")?;
+    let password = fields.required("password")?;
+    if let Some(protocol) = fields.get("ssr-protocol") {
+        return Ok(Protocol::Ssr(SsrParams {
+            cipher,
+            password,
+            protocol,
+            protocol_param: fields.get("ssr-protocol-param"),
+            obfs: fields.get("obfs").unwrap_or_else(|| "plain".to_string()),
+            obfs_param: fields.get("obfs-host"),
+        }));
+    }
+
+    let host = fields.get("obfs-host");
+    let (plugin, plugin_opts) = match fields.get("obfs").as_deref() {
+        None => (None, None),
+        Some(mode @ ("http" | "tls")) => (Some("obfs"), Some(json!({"mode": mode, "host": host}))),
+        Some(mode @ ("ws" | "wss")) => (
+            Some("v2ray-plugin"),
+            Some(json!({
+                "mode": "websocket",
+                "host": host,
+                "path": fields.get("obfs-uri"),
+                "tls": mode == "wss",
+            })),
+        ),
+        Some(other) => return Err(anyhow!("不支持的 obfs: {}", other)),
+    };
+    Ok(Protocol::Shadowsocks(ShadowsocksParams {
+        cipher,
+        password,
+        plugin: plugin.map(str::to_string),
+        plugin_opts,
+        ..Default::default()
+    }))
+}
+
+fn auth(fields: &Fields) -> AuthParams {
+    AuthParams {
+        username: fields.get("username"),
+        password: fields.get("password"),
+        ..Default::default()
+    }
+}
+
+/// `obfs` 换算为传输层，`over-tls`、`wss` 与 REALITY 表示启用 TLS
+fn obfs(node: &mut ProxyNode, fields: &Fields) -> Result<()> {
+    let (network, secure) = match fields.get("obfs").as_deref() {
+        None => ("tcp", false),
+        Some("over-tls") => ("tcp", true),
+        Some("ws") => ("ws", false),
+        Some("wss") => ("ws", true),
+        Some("http") => ("http", false),
+        Some(other) => return Err(anyhow!("不支持的 obfs: {}", other)),
+    };
+    node.transport =
+        TransportSettings::from_network(network, fields.get("obfs-host"), fields.get("obfs-uri"))?;
+
+    if let Some(public_key) = fields.get("reality-base64-pubkey") {
+        node.tls.reality_opts = Some(RealityOpts {
+            public_key,
+            short_id: fields.get("reality-hex-shortid"),
+        });
+    }
+    let secure = secure
+        || fields.flag("over-tls") == Some(true)
+        || node.tls.reality_opts.is_some()
+        || matches!(node.protocol, Protocol::Trojan(_));
+    if !secure {
+        return Ok(());
+    }
+    // Trojan 自带 TLS，不需要 tls 开关
+    if !matches!(node.protocol, Protocol::Trojan(_)) {
+        node.tls.tls = Some(true);
+    }
+    let server_name = fields.get("tls-host");
+    match node.protocol {
+        Protocol::Vmess(_) | Protocol::Vless(_) => node.tls.servername = server_name,
+        _ => node.tls.sni = server_name,
+    }
+    if fields.flag("tls-verification") == Some(false) {
+        node.tls.skip_cert_verify = Some(true);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::quanx;
+    use crate::proxy::node::Network;
+
+    #[test]
+    fn test_parse_quanx_lines() {
+        let content = "[server_local]\n\
+             shadowsocks=hk.example.com:8388, method=aes-128-gcm, password=secret, obfs=http, obfs-host=bing.com, udp-relay=true, tag=香港\n\
+             vmess=example.com:443, method=chacha20-ietf-poly1305, password=b831381d-6324-4d53-ad4f-8cda48b30811, obfs=wss, obfs-host=cdn.example.com, obfs-uri=/ws, tag=vmess\n\
+             trojan=example.com:443, password=secret, over-tls=true, tls-host=sni.example.com, tls-verification=false, tag=trojan\n\
+             hysteria2=example.com:443, password=secret, tag=hy2\n\
+             [filter_local]\n\
+             host-suffix, example.com, direct\n";
+        let (nodes, errors) = parse_quanx(content);
+        assert_eq!(nodes.len(), 3);
+        assert_eq!(errors.len(), 1);
+
+        assert_eq!(nodes[0].name, "香港");
+        assert_eq!(nodes[0].udp, Some(true));
+        let Protocol::Shadowsocks(params) = &nodes[0].protocol else {
+            panic!("应为 Shadowsocks 节点");
+        };
+        assert_eq!(params.plugin.as_deref(), Some("obfs"));
+
+        let vmess = &nodes[1];
+        assert_eq!(vmess.tls.tls, Some(true));
+        assert_eq!(vmess.transport.network, Some(Network::Ws));
+        assert_eq!(vmess.transport.host(), Some("cdn.example.com"));
+        assert_eq!(vmess.transport.path(), Some("/ws"));
+
+        let trojan = &nodes[2];
+        assert_eq!(trojan.tls.server_name(), Some("sni.example.com"));
+        assert_eq!(trojan.tls.skip_cert_verify, Some(true));
+    }
+
+    #[test]
+    fn test_round_trip_with_exporter() {
+        let (nodes, _) = parse_quanx(
+            "vless=example.com:443, method=none, password=b831381d-6324-4d53-ad4f-8cda48b30811, \
+             obfs=over-tls, tls-host=www.microsoft.com, vless-flow=xtls-rprx-vision, \
+             reality-base64-pubkey=pubkey, reality-hex-shortid=abcd, tag=reality",
+        );
+        let line = quanx::proxy_line(&nodes[0]).unwrap();
+        assert_eq!(parse_line(&line).unwrap(), nodes[0]);
+    }
+
+    #[test]
+    fn test_is_quanx_line() {
+        assert!(is_quanx_line(
+            "trojan=example.com:443, password=secret, tag=a"
+        ));
+        assert!(!is_quanx_line(
+            "trojan = trojan, example.com, 443, password=secret"
+        ));
+        assert!(!is_quanx_line(
+            "vmess = vmess, example.com, 443, username=uuid"
+        ));
+    }
+}
@@ -0,0 +1,505 @@
+//! sing-box 配置解析
+//! 读取 `outbounds` 与 1.11 起的 `endpoints`（WireGuard），转换为 [`ProxyNode`]；
+//! selector、urltest、direct 等不是代理节点的出站直接忽略
+
+use super::node::{
+    AnyTlsParams, AuthParams, Bandwidth, GrpcOpts, H2Opts, HttpOpts, Hysteria2Params, Network,
+    RealityOpts, ShadowsocksParams, TlsSettings, TransportSettings, TrojanParams, TuicParams,
+    VlessParams, VmessParams, WireGuardParams, WsOpts,
+};
+use super::parser::sip002_plugin;
+use super::{Protocol, ProxyNode};
+use anyhow::{Error, Result, anyhow};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// 不是代理节点的出站类型
+const IGNORED_TYPES: &[&str] = &["selector", "urltest", "direct", "block", "dns"];
+
+/// 顶层带有 `outbounds` 或 `endpoints` 数组时视为 sing-box 配置
+pub fn is_singbox(document: &Value) -> bool {
+    document["outbounds"].is_array() || document["endpoints"].is_array()
+}
+
+/// 逐个转换出站，单个出站无效不影响其余出站
+pub fn parse_singbox(document: &Value) -> (Vec<ProxyNode>, Vec<Error>) {
+    let mut nodes = Vec::new();
+    let mut errors = Vec::new();
+    let entries = ["outbounds", "endpoints"]
+        .into_iter()
+        .flat_map(|key| document[key].as_array().into_iter().flatten());
+    for outbound in entries {
+        match from_outbound(outbound) {
+            Ok(Some(node)) => nodes.push(node),
+            Ok(None) => {}
+            Err(e) => errors.push(e),
+        }
+    }
+    (nodes, errors)
+}
+
+/// 转换单个出站，不是代理节点时返回 `None`
+pub fn from_outbound(outbound: &Value) -> Result<Option<ProxyNode>> {
+    let kind = outbound["type"]
+        .as_str()
+        .ok_or_else(|| anyhow!("sing-box 出站缺少 type"))?;
+    if IGNORED_TYPES.contains(&kind) {
+        return Ok(None);
+    }
+    let tag = outbound["tag"].as_str().unwrap_or(kind);
+    let node = if kind == "wireguard" {
+        wireguard(outbound)
+    } else {
+        proxy(kind, outbound)
+    };
+    node.map(Some)
+        .map_err(|e| anyhow!("sing-box 出站 {} 无效: {}", tag, e))
+}
+
+fn proxy(kind: &str, outbound: &Value) -> Result<ProxyNode> {
+    let protocol = match kind {
+        "shadowsocks" => shadowsocks(outbound)?,
+        "vmess" => Protocol::Vmess(VmessParams {
+            uuid: required(outbound, "uuid")?,
+            alter_id: number(outbound, "alter_id"),
+            cipher: string(outbound, "security"),
+            global_padding: outbound["global_padding"].as_bool(),
+            authenticated_length: outbound["authenticated_length"].as_bool(),
+            packet_encoding: string(outbound, "packet_encoding"),
+            ..Default::default()
+        }),
+        "vless" => Protocol::Vless(VlessParams {
+            uuid: required(outbound, "uuid")?,
+            flow: string(outbound, "flow"),
+            packet_encoding: string(outbound, "packet_encoding"),
+            ..Default::default()
+        }),
+        "trojan" => Protocol::Trojan(TrojanParams {
+            password: required(outbound, "password")?,
+            ..Default::default()
+        }),
+        "hysteria2" => hysteria2(outbound)?,
+        "tuic" => Protocol::Tuic(TuicParams {
+            uuid: required(outbound, "uuid")?,
+            password: string(outbound, "password").unwrap_or_default(),
+            congestion_controller: string(outbound, "congestion_control"),
+            udp_relay_mode: string(outbound, "udp_relay_mode"),
+            reduce_rtt: outbound["zero_rtt_handshake"].as_bool(),
+            heartbeat_interval: duration_ms(outbound, "heartbeat")
+                .and_then(|ms| u32::try_from(ms).ok()),
+            disable_sni: outbound["tls"]["disable_sni"].as_bool(),
+            ..Default::default()
+        }),
+        "anytls" => Protocol::AnyTls(AnyTlsParams {
+            password: required(outbound, "password")?,
+            idle_session_check_interval: seconds(outbound, "idle_session_check_interval"),
+            idle_session_timeout: seconds(outbound, "idle_session_timeout"),
+            min_idle_session: number(outbound, "min_idle_session"),
+        }),
+        "http" => Protocol::Http(AuthParams {
+            headers: headers(&outbound["headers"]),
+            ..auth(outbound)
+        }),
+        "socks" => match outbound["version"].as_str() {
+            Some("4" | "4a") => Protocol::Socks4(auth(outbound)),
+            _ => Protocol::Socks5(auth(outbound)),
+        },
+        other => return Err(anyhow!("不支持的出站类型: {}", other)),
+    };
+
+    let server = required(outbound, "server")?;
+    let port = number(outbound, "server_port")
+        .or_else(|| first_hop_port(outbound))
+        .ok_or_else(|| anyhow!("缺少 server_port"))?;
+    let name = string(outbound, "tag").unwrap_or_else(|| format!("{}:{}", server, port));
+    let mut node = ProxyNode::new(name, server, port, protocol);
+    node.tls = tls(kind, &outbound["tls"]);
+    node.transport = transport(&outbound["transport"], node.tls.tls == Some(true))?;
+    if outbound["network"].as_str() == Some("tcp") {
+        node.udp = Some(false);
+    }
+    node.dial.tfo = outbound["tcp_fast_open"].as_bool();
+    Ok(node)
+}
+
+fn shadowsocks(outbound: &Value) -> Result<Protocol> {
+    let (plugin, plugin_opts) = match string(outbound, "plugin") {
+        Some(plugin) => {
+            let value = match string(outbound, "plugin_opts") {
+                Some(opts) => format!("{};{}", plugin, opts),
+                None => plugin,
+            };
+            let (name, opts) = sip002_plugin(&value);
+            (Some(name), opts)
+        }
+        None => (None, None),
+    };
+    // udp_over_tcp 可以是开关或 {enabled, version}
+    let uot = &outbound["udp_over_tcp"];
+    Ok(Protocol::Shadowsocks(ShadowsocksParams {
+        cipher: required(outbound, "method")?,
+        password: required(outbound, "password")?,
+        plugin,
+        plugin_opts,
+        udp_over_tcp: uot.as_bool().or(uot["enabled"].as_bool()),
+        udp_over_tcp_version: number(uot, "version"),
+    }))
+}
+
+fn hysteria2(outbound: &Value) -> Result<Protocol> {
+    // sing-box 的端口跳跃写作 `20000:30000` 列表，Clash 为 `20000-30000,40000`
+    let ports = strings(outbound, "server_ports").map(|ports| {
+        ports
+            .iter()
+            .map(|range| range.replace(':', "-"))
+            .collect::<Vec<_>>()
+            .join(",")
+    });
+    let obfs = &outbound["obfs"];
+    Ok(Protocol::Hysteria2(Hysteria2Params {
+        password: required(outbound, "password")?,
+        ports,
+        hop_interval: seconds(outbound, "hop_interval"),
+        obfs: string(obfs, "type"),
+        obfs_password: string(obfs, "password"),
+        up: number(outbound, "up_mbps").map(Bandwidth::Mbps),
+        down: number(outbound, "down_mbps").map(Bandwidth::Mbps),
+        ..Default::default()
+    }))
+}
+
+/// 1.11 起的 endpoint 把对端放在 `peers` 中，旧版出站直接写在顶层
+fn wireguard(outbound: &Value) -> Result<ProxyNode> {
+    let peer = outbound["peers"].as_array().and_then(|peers| peers.first());
+    let field = |peer_key: &str, legacy_key: &str| {
+        peer.and_then(|peer| string(peer, peer_key))
+            .or_else(|| string(outbound, legacy_key))
+    };
+
+    let server = field("address", "server").ok_or_else(|| anyhow!("缺少对端地址"))?;
+    let port = peer
+        .and_then(|peer| number(peer, "port"))
+        .or_else(|| number(outbound, "server_port"))
+        .ok_or_else(|| anyhow!("缺少对端端口"))?;
+    let addresses = strings(outbound, "address")
+        .or_else(|| strings(outbound, "local_address"))
+        .unwrap_or_default();
+    let address = |ipv6: bool, prefix: &str| {
+        addresses
+            .iter()
+            .find(|address| address.contains(':') == ipv6)
+            .map(|address| address.trim_end_matches(prefix).to_string())
+    };
+    let reserved = peer
+        .map(|peer| &peer["reserved"])
+        .filter(|reserved| !reserved.is_null())
+        .or_else(|| outbound.get("reserved"))
+        .cloned();
+
+    let params = WireGuardParams {
+        private_key: required(outbound, "private_key")?,
+        public_key: field("public_key", "peer_public_key")
+            .ok_or_else(|| anyhow!("缺少对端公钥"))?,
+        pre_shared_key: field("pre_shared_key", "pre_shared_key"),
+        ip: address(false, "/32"),
+        ipv6: address(true, "/128"),
+        allowed_ips: peer.and_then(|peer| strings(peer, "allowed_ips")),
+        reserved,
+        mtu: number(outbound, "mtu"),
+        persistent_keepalive: peer.and_then(|peer| number(peer, "persistent_keepalive_interval")),
+        ..Default::default()
+    };
+    let name = string(outbound, "tag").unwrap_or_else(|| format!("{}:{}", server, port));
+    Ok(ProxyNode::new(
+        name,
+        server,
+        port,
+        Protocol::WireGuard(params),
+    ))
+}
+
+/// VMess / VLESS / HTTP / SOCKS 需要显式开启 TLS，其余协议自带 TLS
+fn tls(kind: &str, tls: &Value) -> TlsSettings {
+    if tls["enabled"].as_bool() != Some(true) {
+        return TlsSettings::default();
+    }
+    let explicit = matches!(kind, "vmess" | "vless" | "http" | "socks");
+    let server_name = string(tls, "server_name");
+    let (sni, servername) = match kind {
+        "vmess" | "vless" => (None, server_name),
+        _ => (server_name, None),
+    };
+    let utls = &tls["utls"];
+    let reality = &tls["reality"];
+    TlsSettings {
+        tls: explicit.then_some(true),
+        sni,
+        servername,
+        alpn: strings(tls, "alpn"),
+        skip_cert_verify: tls["insecure"].as_bool(),
+        client_fingerprint: string(utls, "fingerprint").filter(|_| utls["enabled"] == true),
+        reality_opts: string(reality, "public_key")
+            .filter(|_| reality["enabled"] == true)
+            .map(|public_key| RealityOpts {
+                public_key,
+                short_id: string(reality, "short_id"),
+            }),
+        ca: string(tls, "certificate_path"),
+        ..Default::default()
+    }
+}
+
+/// V2Ray 传输层；`http` 传输在启用 TLS 时为 HTTP/2
+fn transport(transport: &Value, secure: bool) -> Result<TransportSettings> {
+    let mut settings = TransportSettings::default();
+    match transport["type"].as_str() {
+        None => {}
+        Some("ws") => {
+            settings.network = Some(Network::Ws);
+            settings.ws_opts = Some(WsOpts {
+                path: string(transport, "path"),
+                headers: headers(&transport["headers"]),
+                max_early_data: number(transport, "max_early_data"),
+                early_data_header_name: string(transport, "early_data_header_name"),
+                ..Default::default()
+            });
+        }
+        Some("httpupgrade") => {
+            let mut headers = headers(&transport["headers"]).unwrap_or_default();
+            if let Some(host) = string(transport, "host") {
+                headers.insert("Host".to_string(), host);
+            }
+            settings.network = Some(Network::Ws);
+            settings.ws_opts = Some(WsOpts {
+                path: string(transport, "path"),
+                headers: Some(headers).filter(|headers| !headers.is_empty()),
+                v2ray_http_upgrade: Some(true),
+                ..Default::default()
+            });
+        }
+        Some("http") if secure => {
+            settings.network = Some(Network::H2);
+            settings.h2_opts = Some(H2Opts {
+                host: strings(transport, "host"),
+                path: string(transport, "path"),
+            });
+        }
+        Some("http") => {
+            let mut headers: BTreeMap<String, Vec<String>> = transport["headers"]
+                .as_object()
+                .into_iter()
+                .flatten()
+                .filter_map(|(name, value)| Some((name.clone(), string_list(value)?)))
+                .collect();
+            if let Some(host) = strings(transport, "host") {
+                headers.insert("Host".to_string(), host);
+            }
+            settings.network = Some(Network::Http);
+            settings.http_opts = Some(HttpOpts {
+                method: string(transport, "method"),
+                path: string(transport, "path").map(|path| vec![path]),
+                headers: Some(headers).filter(|headers| !headers.is_empty()),
+            });
+        }
+        Some("grpc") => {
+            settings.network = Some(Network::Grpc);
+            settings.grpc_opts = Some(GrpcOpts {
+                grpc_service_name: string(transport, "service_name"),
+            });
+        }
+        Some(other) => return Err(anyhow!("不支持的传输方式: {}", other)),
+    }
+    Ok(settings)
+}
+
+fn auth(outbound: &Value) -> AuthParams {
+    AuthParams {
+        username: string(outbound, "username"),
+        password: string(outbound, "password"),
+        ..Default::default()
+    }
+}
+
+/// 请求头的值可以是字符串或字符串数组，只取第一个值
+fn headers(value: &Value) -> Option<BTreeMap<String, String>> {
+    let headers: BTreeMap<String, String> = value
+        .as_object()?
+        .iter()
+        .filter_map(|(name, value)| Some((name.clone(), string_list(value)?.remove(0))))
+        .collect();
+    Some(headers).filter(|headers| !headers.is_empty())
+}
+
+/// 端口跳跃范围中的第一个端口
+fn first_hop_port(outbound: &Value) -> Option<u16> {
+    let ports = strings(outbound, "server_ports")?;
+    ports.first()?.split(':').next()?.trim().parse().ok()
+}
+
+/// 非空字符串字段
+fn string(value: &Value, key: &str) -> Option<String> {
+    value[key]
+        .as_str()
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+}
+
+fn required(value: &Value, key: &str) -> Result<String> {
+    string(value, key).ok_or_else(|| anyhow!("缺少 {}", key))
+}
+
+fn number<T: TryFrom<u64>>(value: &Value, key: &str) -> Option<T> {
+    value[key].as_u64().and_then(|n| T::try_from(n).ok())
+}
+
+/// 字符串或非空字符串数组
+fn string_list(value: &Value) -> Option<Vec<String>> {
+    let list: Vec<String> = match value {
+        Value::String(s) => vec![s.clone()],
+        Value::Array(items) => items
+            .iter()
+            .filter_map(|item| item.as_str().map(str::to_string))
+            .collect(),
+        _ => return None,
+    };
+    Some(list).filter(|list| !list.is_empty())
+}
+
+fn strings(value: &Value, key: &str) -> Option<Vec<String>> {
+    string_list(&value[key])
+}
+
+/// sing-box 的时长写作 `30s`、`1m`、`500ms` 等
+fn duration_ms(value: &Value, key: &str) -> Option<u64> {
+    let text = value[key].as_str()?.trim();
+    let split = text.find(|c: char| !c.is_ascii_digit())?;
+    let amount: u64 = text[..split].parse().ok()?;
+    let unit = match &text[split..] {
+        "ms" => 1,
+        "s" => 1_000,
+        "m" => 60_000,
+        "h" => 3_600_000,
+        _ => return None,
+    };
+    amount.checked_mul(unit)
+}
+
+fn seconds(value: &Value, key: &str) -> Option<u32> {
+    duration_ms(value, key).and_then(|ms| u32::try_from(ms / 1_000).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::singbox;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_outbounds() {
+        let document = json!({
+            "outbounds": [
+                {"type": "selector", "tag": "proxy", "outbounds": ["vless"]},
+                {
+                    "type": "vless",
+                    "tag": "vless",
+                    "server": "example.com",
+                    "server_port": 443,
+                    "uuid": "b831381d-6324-4d53-ad4f-8cda48b30811",
+                    "flow": "xtls-rprx-vision",
+                    "tls": {
+                        "enabled": true,
+                        "server_name": "www.microsoft.com",
+                        "utls": {"enabled": true, "fingerprint": "chrome"},
+                        "reality": {"enabled": true, "public_key": "pubkey", "short_id": "abcd"}
+                    },
+                    "transport": {"type": "grpc", "service_name": "grpc"}
+                },
+                {
+                    "type": "hysteria2",
+                    "tag": "hy2",
+                    "server": "example.com",
+                    "server_ports": ["20000:30000"],
+                    "hop_interval": "30s",
+                    "password": "secret",
+                    "up_mbps": 100,
+                    "obfs": {"type": "salamander", "password": "gawr"},
+                    "tls": {"enabled": true, "server_name": "example.com"}
+                },
+                {"type": "vmess", "tag": "broken", "server": "example.com", "server_port": 443},
+                {"type": "direct", "tag": "direct"}
+            ],
+            "endpoints": [{
+                "type": "wireguard",
+                "tag": "wg",
+                "address": ["172.16.0.2/32", "fd01::2/128"],
+                "private_key": "private",
+                "peers": [{
+                    "address": "engage.cloudflareclient.com",
+                    "port": 2408,
+                    "public_key": "public",
+                    "reserved": [1, 2, 3]
+                }]
+            }]
+        });
+        assert!(is_singbox(&document));
+
+        let (nodes, errors) = parse_singbox(&document);
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].to_string().contains("broken"));
+        let names: Vec<&str> = nodes.iter().map(|node| node.name.as_str()).collect();
+        assert_eq!(names, ["vless", "hy2", "wg"]);
+
+        let vless = &nodes[0];
+        assert_eq!(vless.tls.tls, Some(true));
+        assert_eq!(vless.tls.server_name(), Some("www.microsoft.com"));
+        assert_eq!(vless.tls.client_fingerprint.as_deref(), Some("chrome"));
+        assert_eq!(vless.transport.path(), Some("grpc"));
+
+        let hy2 = &nodes[1];
+        assert_eq!(hy2.port, 20000);
+        let Protocol::Hysteria2(params) = &hy2.protocol else {
+            panic!("应为 Hysteria2 节点");
+        };
+        assert_eq!(params.ports.as_deref(), Some("20000-30000"));
+        assert_eq!(params.hop_interval, Some(30));
+        assert_eq!(params.up, Some(Bandwidth::Mbps(100)));
+
+        let wg = &nodes[2];
+        assert_eq!(wg.server, "engage.cloudflareclient.com");
+        let Protocol::WireGuard(params) = &wg.protocol else {
+            panic!("应为 WireGuard 节点");
+        };
+        assert_eq!(params.ip.as_deref(), Some("172.16.0.2"));
+        assert_eq!(params.ipv6.as_deref(), Some("fd01::2"));
+        assert_eq!(params.reserved, Some(json!([1, 2, 3])));
+    }
+
+    #[test]
+    fn test_round_trip_with_exporter() {
+        let mut node = ProxyNode::new(
+            "vmess".to_string(),
+            "example.com".to_string(),
+            443,
+            Protocol::Vmess(VmessParams {
+                uuid: "b831381d-6324-4d53-ad4f-8cda48b30811".to_string(),
+                alter_id: Some(0),
+                cipher: Some("auto".to_string()),
+                ..Default::default()
+            }),
+        );
+        node.tls.tls = Some(true);
+        node.tls.servername = Some("example.com".to_string());
+        node.transport = TransportSettings::from_network(
+            "ws",
+            Some("cdn.example.com".to_string()),
+            Some("/ws".to_string()),
+        )
+        .unwrap();
+
+        let singbox::Outbound::Outbound(outbound) = singbox::outbound(&node, "vmess").unwrap()
+        else {
+            panic!("VMess 应为 outbound");
+        };
+        assert_eq!(from_outbound(&outbound).unwrap(), Some(node));
+    }
+}
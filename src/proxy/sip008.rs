@@ -0,0 +1,119 @@
+//! SIP008 在线配置解析
+//! Shadowsocks 的 JSON 订阅格式 `{"version": 1, "servers": [...]}`，
+//! 每个条目对应一个 Shadowsocks 节点，插件参数与 SIP002 相同
+
+use super::node::ShadowsocksParams;
+use super::parser::sip002_plugin;
+use super::{Protocol, ProxyNode};
+use anyhow::{Error, Result, anyhow};
+use serde::Deserialize;
+use serde_json::Value;
+
+/// SIP008 中的单个服务器
+#[derive(Debug, Deserialize)]
+struct Server {
+    #[serde(default)]
+    remarks: Option<String>,
+    server: String,
+    server_port: u16,
+    password: String,
+    method: String,
+    #[serde(default)]
+    plugin: Option<String>,
+    #[serde(default)]
+    plugin_opts: Option<String>,
+}
+
+/// 顶层 `servers` 数组的条目带有 `server_port` 时视为 SIP008
+pub fn is_sip008(document: &Value) -> bool {
+    document["servers"]
+        .as_array()
+        .is_some_and(|servers| servers.iter().any(|s| s.get("server_port").is_some()))
+}
+
+/// 逐个转换 `servers` 中的条目，单个条目无效不影响其余条目
+pub fn parse_sip008(document: &Value) -> (Vec<ProxyNode>, Vec<Error>) {
+    let mut nodes = Vec::new();
+    let mut errors = Vec::new();
+    for server in document["servers"].as_array().into_iter().flatten() {
+        match from_server(server) {
+            Ok(node) => nodes.push(node),
+            Err(e) => errors.push(e),
+        }
+    }
+    (nodes, errors)
+}
+
+fn from_server(value: &Value) -> Result<ProxyNode> {
+    let server = Server::deserialize(value).map_err(|e| anyhow!("SIP008 节点无效: {}", e))?;
+    let (plugin, plugin_opts) = match server.plugin.as_deref().filter(|p| !p.is_empty()) {
+        Some(plugin) => {
+            let value = match server.plugin_opts.as_deref().filter(|o| !o.is_empty()) {
+                Some(opts) => format!("{};{}", plugin, opts),
+                None => plugin.to_string(),
+            };
+            let (name, opts) = sip002_plugin(&value);
+            (Some(name), opts)
+        }
+        None => (None, None),
+    };
+    let name = server
+        .remarks
+        .filter(|remarks| !remarks.is_empty())
+        .unwrap_or_else(|| format!("{}:{}", server.server, server.server_port));
+    Ok(ProxyNode::new(
+        name,
+        server.server,
+        server.server_port,
+        Protocol::Shadowsocks(ShadowsocksParams {
+            cipher: server.method,
+            password: server.password,
+            plugin,
+            plugin_opts,
+            ..Default::default()
+        }),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_sip008() {
+        let document = json!({
+            "version": 1,
+            "servers": [
+                {
+                    "id": "27b8a625-4f4b-4428-9f0f-8a2317db7c79",
+                    "remarks": "香港",
+                    "server": "hk.example.com",
+                    "server_port": 8388,
+                    "password": "secret",
+                    "method": "chacha20-ietf-poly1305",
+                    "plugin": "obfs-local",
+                    "plugin_opts": "obfs=http;obfs-host=bing.com"
+                },
+                {"server": "jp.example.com", "server_port": 8388, "password": "secret", "method": "aes-128-gcm"},
+                {"server": "broken.example.com", "password": "secret"}
+            ],
+            "bytes_used": 1024
+        });
+        assert!(is_sip008(&document));
+
+        let (nodes, errors) = parse_sip008(&document);
+        assert_eq!(nodes.len(), 2);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(nodes[0].name, "香港");
+        let Protocol::Shadowsocks(params) = &nodes[0].protocol else {
+            panic!("应为 Shadowsocks 节点");
+        };
+        assert_eq!(params.plugin.as_deref(), Some("obfs"));
+        assert_eq!(
+            params.plugin_opts,
+            Some(json!({"mode": "http", "host": "bing.com"}))
+        );
+        assert_eq!(nodes[1].name, "jp.example.com:8388");
+    }
+}
@@ -0,0 +1,181 @@
+//! 订阅来源
+//! 订阅的 `url` 可以是远程链接、`file://` 或本地路径（文件名支持 `*`、`?` 通配）、
+//! `-`（标准输入），也可以用 `content` 直接在配置中写入节点列表
+
+use crate::config::Subscription;
+use anyhow::{Result, anyhow};
+use regex::Regex;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// 订阅内容的来源
+#[derive(Debug, PartialEq, Eq)]
+pub enum Source<'a> {
+    /// 远程链接
+    Remote(&'a str),
+    /// 本地文件路径或通配模式
+    Files(&'a str),
+    /// 标准输入
+    Stdin,
+    /// 配置中内联的内容
+    Inline(&'a str),
+}
+
+impl<'a> Source<'a> {
+    pub fn of(subscription: &'a Subscription) -> Self {
+        if let Some(content) = &subscription.content {
+            return Source::Inline(content);
+        }
+        let url = subscription.url.trim();
+        if url == "-" {
+            Source::Stdin
+        } else if let Some(path) = url.strip_prefix("file://") {
+            Source::Files(path)
+        } else if url.contains("://") {
+            Source::Remote(url)
+        } else {
+            Source::Files(url)
+        }
+    }
+}
+
+/// 读取路径或通配模式匹配的全部文件，按路径排序
+pub fn read_files(pattern: &str) -> Result<Vec<(PathBuf, String)>> {
+    let paths = expand_glob(pattern)?;
+    if paths.is_empty() {
+        return Err(anyhow!("没有匹配 {} 的文件", pattern));
+    }
+    paths
+        .into_iter()
+        .map(|path| {
+            let content = fs::read_to_string(&path)
+                .map_err(|e| anyhow!("读取 {} 失败: {}", path.display(), e))?;
+            Ok((path, content))
+        })
+        .collect()
+}
+
+fn has_wildcard(value: &str) -> bool {
+    value.contains(['*', '?'])
+}
+
+/// 展开通配模式，不含通配符时原样返回
+fn expand_glob(pattern: &str) -> Result<Vec<PathBuf>> {
+    if !has_wildcard(pattern) {
+        return Ok(vec![PathBuf::from(pattern)]);
+    }
+
+    let mut paths = vec![PathBuf::new()];
+    for component in Path::new(pattern).components() {
+        let part = component.as_os_str().to_string_lossy();
+        if !has_wildcard(&part) {
+            for path in &mut paths {
+                path.push(component);
+            }
+            continue;
+        }
+
+        let matcher = component_regex(&part)?;
+        let mut matched = Vec::new();
+        for dir in paths {
+            let read_dir = if dir.as_os_str().is_empty() {
+                Path::new(".")
+            } else {
+                &dir
+            };
+            let Ok(entries) = fs::read_dir(read_dir) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                let name = entry.file_name();
+                let name_str = name.to_string_lossy();
+                // 与 shell 一致，通配符不匹配隐藏文件
+                if name_str.starts_with('.') && !part.starts_with('.') {
+                    continue;
+                }
+                if matcher.is_match(&name_str) {
+                    matched.push(dir.join(&name));
+                }
+            }
+        }
+        paths = matched;
+    }
+
+    paths.retain(|path| path.is_file());
+    paths.sort();
+    Ok(paths)
+}
+
+/// 把单个路径组件中的通配符转换为正则
+fn component_regex(part: &str) -> Result<Regex> {
+    let mut pattern = String::from("^");
+    for c in part.chars() {
+        match c {
+            '*' => pattern.push_str(".*"),
+            '?' => pattern.push('.'),
+            c => pattern.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    pattern.push('$');
+    Regex::new(&pattern).map_err(|e| anyhow!("通配模式无效 {}: {}", part, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn subscription(url: &str) -> Subscription {
+        Subscription {
+            name: "test".to_string(),
+            url: url.to_string(),
+            enabled: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_source_of() {
+        assert_eq!(
+            Source::of(&subscription("https://example.com/sub")),
+            Source::Remote("https://example.com/sub")
+        );
+        assert_eq!(
+            Source::of(&subscription("file:///etc/subs/a.txt")),
+            Source::Files("/etc/subs/a.txt")
+        );
+        assert_eq!(
+            Source::of(&subscription("./subs/*.yaml")),
+            Source::Files("./subs/*.yaml")
+        );
+        assert_eq!(Source::of(&subscription("-")), Source::Stdin);
+
+        let mut inline = subscription("");
+        inline.content = Some("trojan://secret@example.com:443".to_string());
+        assert_eq!(
+            Source::of(&inline),
+            Source::Inline("trojan://secret@example.com:443")
+        );
+    }
+
+    #[test]
+    fn test_read_files_with_glob() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["b.txt", "a.txt", "c.yaml", ".hidden.txt"] {
+            fs::write(dir.path().join(name), name).unwrap();
+        }
+        fs::create_dir(dir.path().join("d.txt")).unwrap();
+
+        let pattern = format!("{}/*.txt", dir.path().display());
+        let files = read_files(&pattern).unwrap();
+        let contents: Vec<&str> = files.iter().map(|(_, content)| content.as_str()).collect();
+        assert_eq!(contents, ["a.txt", "b.txt"]);
+
+        let pattern = format!("{}/?.yaml", dir.path().display());
+        assert_eq!(read_files(&pattern).unwrap().len(), 1);
+
+        let pattern = format!("{}/*.json", dir.path().display());
+        assert!(read_files(&pattern).is_err());
+        let missing = dir.path().join("missing.txt");
+        assert!(read_files(&missing.to_string_lossy()).is_err());
+    }
+}
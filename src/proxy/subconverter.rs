@@ -0,0 +1,373 @@
+//! libsubconverter 兜底解析
+//! 自有解析器无法识别的订阅正文（Surge / Quantumult 配置、SSD、V2Ray JSON 等）交给
+//! libsubconverter 的 `explode_conf_content`（`add_nodes` 解析订阅正文的部分），
+//! 再把它的 `Proxy` 模型转换为 [`ProxyNode`]
+
+use super::node::{
+    AnyTlsParams, AuthParams, Bandwidth, GrpcOpts, H2Opts, HttpOpts, Hysteria2Params, Network,
+    RealityOpts, ShadowsocksParams, SsrParams, TransportSettings, TrojanParams, VlessParams,
+    VmessParams, WireGuardParams, WsOpts,
+};
+use super::{Protocol, ProxyNode};
+use anyhow::{Result, anyhow};
+use libsubconverter::models::proxy_node::combined::CombinedProxy;
+use libsubconverter::parser::explodes::{explode_conf_content, explode_surge};
+use libsubconverter::{Proxy, ProxyType};
+use std::collections::HashSet;
+
+/// 解析订阅正文，返回转换成功的节点与被跳过节点的原因
+pub fn parse_with_subconverter(content: &str) -> (Vec<ProxyNode>, Vec<anyhow::Error>) {
+    let mut proxies = Vec::new();
+    explode_conf_content(content, &mut proxies);
+    convert_all(&proxies)
+}
+
+/// 解析 Surge 配置或节点列表，缺少 `[Proxy]` 段头的列表按整段节点处理
+pub fn parse_surge(content: &str) -> (Vec<ProxyNode>, Vec<anyhow::Error>) {
+    let mut proxies = Vec::new();
+    if content
+        .lines()
+        .any(|line| line.trim().eq_ignore_ascii_case("[Proxy]"))
+    {
+        explode_surge(content, &mut proxies);
+    } else {
+        explode_surge(&format!("[Proxy]\n{}", content), &mut proxies);
+    }
+    convert_all(&proxies)
+}
+
+fn convert_all(proxies: &[Proxy]) -> (Vec<ProxyNode>, Vec<anyhow::Error>) {
+    let mut nodes = Vec::new();
+    let mut errors = Vec::new();
+    for proxy in proxies {
+        match from_subconverter(proxy) {
+            Ok(node) => nodes.push(node),
+            Err(e) => errors.push(e),
+        }
+    }
+    (nodes, errors)
+}
+
+/// 把 libsubconverter 的节点转换为 [`ProxyNode`]
+pub fn from_subconverter(proxy: &Proxy) -> Result<ProxyNode> {
+    let protocol = match (&proxy.proxy_type, &proxy.combined_proxy) {
+        (ProxyType::Shadowsocks, Some(CombinedProxy::Shadowsocks(ss))) => {
+            Protocol::Shadowsocks(ShadowsocksParams {
+                cipher: ss.cipher.clone(),
+                password: ss.password.clone(),
+                plugin: non_empty(&ss.plugin),
+                plugin_opts: non_empty(&ss.plugin_opts).map(serde_json::Value::String),
+                udp_over_tcp: ss.udp_over_tcp,
+                udp_over_tcp_version: ss.udp_over_tcp_version,
+            })
+        }
+        (ProxyType::Shadowsocks, _) => Protocol::Shadowsocks(ShadowsocksParams {
+            cipher: required(proxy, &proxy.encrypt_method, "加密方式")?,
+            password: required(proxy, &proxy.password, "密码")?,
+            plugin: non_empty(&proxy.plugin),
+            plugin_opts: non_empty(&proxy.plugin_option).map(serde_json::Value::String),
+            ..Default::default()
+        }),
+        (ProxyType::ShadowsocksR, _) => Protocol::Ssr(SsrParams {
+            cipher: required(proxy, &proxy.encrypt_method, "加密方式")?,
+            password: required(proxy, &proxy.password, "密码")?,
+            obfs: non_empty(&proxy.obfs).unwrap_or_else(|| "plain".to_string()),
+            protocol: non_empty(&proxy.protocol).unwrap_or_else(|| "origin".to_string()),
+            obfs_param: non_empty(&proxy.obfs_param),
+            protocol_param: non_empty(&proxy.protocol_param),
+        }),
+        (ProxyType::VMess, _) => Protocol::Vmess(VmessParams {
+            uuid: required(proxy, &proxy.user_id, "UUID")?,
+            alter_id: Some(proxy.alter_id),
+            cipher: non_empty(&proxy.encrypt_method),
+            ..Default::default()
+        }),
+        (ProxyType::Vless, Some(CombinedProxy::Vless(vless))) => Protocol::Vless(VlessParams {
+            uuid: vless.uuid.clone(),
+            flow: non_empty(&vless.flow),
+            packet_addr: vless.packet_addr,
+            xudp: vless.xudp,
+            packet_encoding: non_empty(&vless.packet_encoding),
+            ..Default::default()
+        }),
+        (ProxyType::Trojan, _) => Protocol::Trojan(TrojanParams {
+            password: required(proxy, &proxy.password, "密码")?,
+            ..Default::default()
+        }),
+        (ProxyType::Hysteria2, _) => Protocol::Hysteria2(Hysteria2Params {
+            password: required(proxy, &proxy.password, "密码")?,
+            ports: non_empty(&proxy.ports),
+            hop_interval: positive(proxy.hop_interval),
+            obfs: non_empty(&proxy.obfs),
+            obfs_password: non_empty(&proxy.obfs_param),
+            up: positive(proxy.up_speed).map(|mbps| Bandwidth::Mbps(mbps.into())),
+            down: positive(proxy.down_speed).map(|mbps| Bandwidth::Mbps(mbps.into())),
+            cwnd: positive(proxy.cwnd),
+            ..Default::default()
+        }),
+        (ProxyType::AnyTls, Some(CombinedProxy::AnyTls(anytls))) => {
+            Protocol::AnyTls(AnyTlsParams {
+                password: anytls.password.clone(),
+                idle_session_check_interval: anytls
+                    .idle_session_check_interval
+                    .and_then(|v| v.try_into().ok()),
+                idle_session_timeout: anytls.idle_session_timeout.and_then(|v| v.try_into().ok()),
+                min_idle_session: anytls.min_idle_session.and_then(|v| v.try_into().ok()),
+            })
+        }
+        (ProxyType::WireGuard, _) => Protocol::WireGuard(WireGuardParams {
+            private_key: required(proxy, &proxy.private_key, "私钥")?,
+            public_key: required(proxy, &proxy.public_key, "公钥")?,
+            pre_shared_key: non_empty(&proxy.pre_shared_key),
+            ip: non_empty(&proxy.self_ip),
+            ipv6: non_empty(&proxy.self_ipv6),
+            allowed_ips: Some(
+                proxy
+                    .allowed_ips
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|ip| !ip.is_empty())
+                    .map(str::to_string)
+                    .collect(),
+            ),
+            mtu: positive(proxy.mtu),
+            persistent_keepalive: positive(proxy.keep_alive).map(u32::from),
+            dns: (!proxy.dns_servers.is_empty()).then(|| sorted(&proxy.dns_servers)),
+            ..Default::default()
+        }),
+        (ProxyType::HTTP | ProxyType::HTTPS, _) => Protocol::Http(auth(proxy)),
+        (ProxyType::Socks5, _) => Protocol::Socks5(auth(proxy)),
+        (other, _) => {
+            return Err(anyhow!(
+                "libsubconverter 节点 {} 的协议暂不支持: {}",
+                proxy.remark,
+                other.to_string()
+            ));
+        }
+    };
+
+    let mut node = ProxyNode::new(
+        proxy.remark.clone(),
+        proxy.hostname.clone(),
+        proxy.port,
+        protocol,
+    );
+    node.udp = proxy.udp;
+    node.dial.tfo = proxy.tcp_fast_open;
+    node.dial.dialer_proxy = non_empty(&proxy.underlying_proxy);
+    node.tls.skip_cert_verify = proxy.allow_insecure;
+    node.tls.fingerprint = non_empty(&proxy.fingerprint);
+    node.tls.ca = non_empty(&proxy.ca);
+    node.tls.ca_str = non_empty(&proxy.ca_str);
+    if !proxy.alpn.is_empty() {
+        node.tls.alpn = Some(sorted(&proxy.alpn));
+    }
+
+    match (&proxy.proxy_type, &proxy.combined_proxy) {
+        (ProxyType::VMess, _) => {
+            node.tls.tls = Some(proxy.tls_secure);
+            node.tls.servername = non_empty(&proxy.server_name);
+            // TCP 下的 http 伪装即 Clash 的 http 传输
+            let network = match (
+                proxy.transfer_protocol.as_deref(),
+                proxy.fake_type.as_deref(),
+            ) {
+                (Some("tcp") | None, Some("http")) => "http",
+                (network, _) => network.unwrap_or("tcp"),
+            };
+            node.transport = TransportSettings::from_network(
+                network,
+                non_empty(&proxy.host),
+                non_empty(&proxy.path),
+            )?;
+        }
+        (ProxyType::Trojan, _) => {
+            // Surge 等格式把 SNI 解析到 host
+            node.tls.sni = non_empty(&proxy.sni).or_else(|| non_empty(&proxy.host));
+            node.transport = TransportSettings::from_network(
+                proxy.transfer_protocol.as_deref().unwrap_or("tcp"),
+                non_empty(&proxy.host),
+                non_empty(&proxy.path),
+            )?;
+        }
+        (ProxyType::Hysteria2, _) => node.tls.sni = non_empty(&proxy.sni),
+        (ProxyType::HTTPS, _) => node.tls.tls = Some(true),
+        (_, Some(CombinedProxy::Shadowsocks(ss))) => {
+            node.tls.client_fingerprint = non_empty(&ss.client_fingerprint);
+        }
+        (_, Some(CombinedProxy::Vless(vless))) => {
+            node.tls.tls = Some(vless.tls);
+            node.tls.servername = non_empty(&vless.servername);
+            node.tls.skip_cert_verify = vless.skip_cert_verify.or(node.tls.skip_cert_verify);
+            node.tls.fingerprint = non_empty(&vless.fingerprint);
+            node.tls.client_fingerprint = non_empty(&vless.client_fingerprint);
+            if !vless.alpn.is_empty() {
+                node.tls.alpn = Some(sorted(&vless.alpn));
+            }
+            node.tls.reality_opts =
+                non_empty(&vless.reality_public_key).map(|public_key| RealityOpts {
+                    public_key,
+                    short_id: non_empty(&vless.reality_short_id),
+                });
+            node.transport = vless_transport(vless.network.as_deref().unwrap_or("tcp"), vless)?;
+        }
+        (_, Some(CombinedProxy::AnyTls(anytls))) => {
+            node.tls.sni = non_empty(&anytls.sni);
+            node.tls.skip_cert_verify = anytls.skip_cert_verify.or(node.tls.skip_cert_verify);
+            node.tls.fingerprint = non_empty(&anytls.fingerprint);
+            node.tls.client_fingerprint = non_empty(&anytls.client_fingerprint);
+            if let Some(alpn) = anytls.alpn.as_ref().filter(|alpn| !alpn.is_empty()) {
+                node.tls.alpn = Some(sorted(alpn));
+            }
+        }
+        _ => {}
+    }
+    Ok(node)
+}
+
+/// libsubconverter 用空字符串表示未设置
+fn non_empty(value: &Option<String>) -> Option<String> {
+    value.clone().filter(|v| !v.is_empty())
+}
+
+/// libsubconverter 用 0 表示未设置
+fn positive<T: Default + PartialEq>(value: T) -> Option<T> {
+    (value != T::default()).then_some(value)
+}
+
+fn required(proxy: &Proxy, value: &Option<String>, field: &str) -> Result<String> {
+    non_empty(value).ok_or_else(|| anyhow!("libsubconverter 节点 {} 缺少{}", proxy.remark, field))
+}
+
+/// 集合转换为有序列表，保证输出稳定
+fn sorted(values: &HashSet<String>) -> Vec<String> {
+    let mut values: Vec<String> = values.iter().cloned().collect();
+    values.sort();
+    values
+}
+
+fn auth(proxy: &Proxy) -> AuthParams {
+    AuthParams {
+        username: non_empty(&proxy.username),
+        password: non_empty(&proxy.password),
+        ..Default::default()
+    }
+}
+
+/// VLESS 各传输方式的参数分别存放
+fn vless_transport(
+    network: &str,
+    vless: &libsubconverter::models::proxy_node::vless::VlessProxy,
+) -> Result<TransportSettings> {
+    let mut transport = TransportSettings::default();
+    match network {
+        "" | "tcp" => {}
+        "ws" => {
+            transport.network = Some(Network::Ws);
+            transport.ws_opts = Some(WsOpts {
+                path: non_empty(&vless.ws_path),
+                headers: vless
+                    .ws_headers
+                    .as_ref()
+                    .map(|headers| headers.clone().into_iter().collect()),
+                ..Default::default()
+            });
+        }
+        "h2" => {
+            transport.network = Some(Network::H2);
+            transport.h2_opts = Some(H2Opts {
+                host: vless.h2_host.clone(),
+                path: non_empty(&vless.h2_path),
+            });
+        }
+        "http" => {
+            transport.network = Some(Network::Http);
+            transport.http_opts = Some(HttpOpts {
+                method: non_empty(&vless.http_method),
+                path: non_empty(&vless.http_path).map(|path| vec![path]),
+                headers: vless
+                    .http_headers
+                    .as_ref()
+                    .map(|headers| headers.clone().into_iter().collect()),
+            });
+        }
+        "grpc" => {
+            transport.network = Some(Network::Grpc);
+            transport.grpc_opts = Some(GrpcOpts {
+                grpc_service_name: non_empty(&vless.grpc_service_name),
+            });
+        }
+        other => return Err(anyhow!("不支持的传输方式: {}", other)),
+    }
+    Ok(transport)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_surge_config() {
+        let content = "[Proxy]\n\
+                       ss = ss, 1.2.3.4, 8388, encrypt-method=aes-128-gcm, password=secret\n\
+                       tj = trojan, example.com, 443, password=pass, sni=example.org\n";
+        let (nodes, errors) = parse_with_subconverter(content);
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(nodes.len(), 2);
+
+        assert_eq!(nodes[0].name, "ss");
+        assert_eq!(nodes[0].server, "1.2.3.4");
+        match &nodes[0].protocol {
+            Protocol::Shadowsocks(params) => {
+                assert_eq!(params.cipher, "aes-128-gcm");
+                assert_eq!(params.password, "secret");
+            }
+            other => panic!("unexpected protocol: {:?}", other),
+        }
+
+        assert_eq!(nodes[1].port, 443);
+        assert_eq!(nodes[1].tls.server_name(), Some("example.org"));
+        assert!(matches!(&nodes[1].protocol, Protocol::Trojan(p) if p.password == "pass"));
+    }
+
+    #[test]
+    fn test_vmess_transport() {
+        let proxy = Proxy::vmess_construct(
+            "group",
+            "vmess",
+            "example.com",
+            443,
+            "none",
+            "uuid",
+            0,
+            "ws",
+            "auto",
+            "/ws",
+            "cdn.example.com",
+            "",
+            "tls",
+            "sni.example.com",
+            None,
+            None,
+            None,
+            None,
+            "",
+        );
+        let node = from_subconverter(&proxy).unwrap();
+        assert_eq!(node.tls.tls, Some(true));
+        assert_eq!(node.tls.server_name(), Some("sni.example.com"));
+        assert_eq!(node.transport.network, Some(Network::Ws));
+        assert_eq!(node.transport.host(), Some("cdn.example.com"));
+        assert_eq!(node.transport.path(), Some("/ws"));
+    }
+
+    #[test]
+    fn test_unsupported_protocol() {
+        let proxy = Proxy {
+            proxy_type: ProxyType::Snell,
+            remark: "snell".to_string(),
+            ..Default::default()
+        };
+        assert!(from_subconverter(&proxy).is_err());
+    }
+}
@@ -0,0 +1,405 @@
+//! 订阅内容解析
+//! 先识别订阅格式再交给对应的解析器：Clash 配置、sing-box 配置、SIP008、
+//! 分享链接列表（可整体 base64 编码）、Surge 与 Quantumult X 节点列表；
+//! 单个节点无效不影响其余节点，识别不出节点时可交给 libsubconverter 兜底
+
+use super::ProxyNode;
+use super::parser::parse_proxy_link;
+use super::quanx::{is_quanx_line, parse_quanx};
+use super::singbox::{is_singbox, parse_singbox};
+use super::sip008::{is_sip008, parse_sip008};
+use super::subconverter::{parse_surge, parse_with_subconverter};
+use anyhow::{Error, anyhow};
+use base64::Engine;
+use base64::engine::general_purpose::{STANDARD_NO_PAD, URL_SAFE_NO_PAD};
+use regex::Regex;
+use serde_yaml::Value;
+use std::sync::LazyLock;
+
+/// 分享链接的协议头
+static SHARE_LINK: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^[A-Za-z][A-Za-z0-9+.-]*://\S").unwrap());
+
+/// Surge 节点行 `名称 = 类型, ...` 中的类型
+const SURGE_TYPES: &[&str] = &[
+    "ss",
+    "custom",
+    "vmess",
+    "trojan",
+    "http",
+    "https",
+    "socks5",
+    "socks5-tls",
+    "snell",
+    "hysteria2",
+    "tuic",
+    "tuic-v5",
+    "wireguard",
+];
+
+/// 订阅内容格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubscriptionFormat {
+    /// Clash / mihomo 配置
+    Clash,
+    /// sing-box 配置
+    Singbox,
+    /// Shadowsocks 的 SIP008 JSON
+    Sip008,
+    /// 整体 base64 编码的分享链接列表
+    Base64Links,
+    /// 每行一个分享链接
+    ShareLinks,
+    /// Surge 配置或节点列表
+    Surge,
+    /// Quantumult X 配置或节点列表
+    QuanX,
+    /// 由 libsubconverter 识别的其他格式
+    Subconverter,
+}
+
+impl SubscriptionFormat {
+    /// 用于日志输出的名称
+    pub fn name(self) -> &'static str {
+        match self {
+            SubscriptionFormat::Clash => "Clash格式",
+            SubscriptionFormat::Singbox => "sing-box 配置",
+            SubscriptionFormat::Sip008 => "SIP008 格式",
+            SubscriptionFormat::Base64Links => "base64 分享链接格式",
+            SubscriptionFormat::ShareLinks => "分享链接格式",
+            SubscriptionFormat::Surge => "Surge 格式",
+            SubscriptionFormat::QuanX => "Quantumult X 格式",
+            SubscriptionFormat::Subconverter => "libsubconverter 兜底",
+        }
+    }
+}
+
+/// 订阅解析结果
+#[derive(Debug)]
+pub struct ParsedSubscription {
+    pub format: SubscriptionFormat,
+    pub nodes: Vec<ProxyNode>,
+    /// 被跳过的节点及原因
+    pub errors: Vec<Error>,
+}
+
+impl ParsedSubscription {
+    fn new(format: SubscriptionFormat, (nodes, errors): (Vec<ProxyNode>, Vec<Error>)) -> Self {
+        Self {
+            format,
+            nodes,
+            errors,
+        }
+    }
+}
+
+/// 识别订阅格式，无法识别时返回 `None`
+pub fn detect_format(content: &str) -> Option<SubscriptionFormat> {
+    let content = content.trim_start_matches('\u{feff}').trim();
+    if content.starts_with('{') {
+        let document: serde_json::Value = serde_json::from_str(content).ok()?;
+        return if is_singbox(&document) {
+            Some(SubscriptionFormat::Singbox)
+        } else if is_sip008(&document) {
+            Some(SubscriptionFormat::Sip008)
+        } else if document.get("proxies").is_some() {
+            Some(SubscriptionFormat::Clash)
+        } else {
+            None
+        };
+    }
+    if clash_proxies(content).is_some() {
+        return Some(SubscriptionFormat::Clash);
+    }
+    // 明文先按行识别，只有解码结果确实是分享链接时才当作 base64
+    detect_lines(content)
+        .or_else(|| decoded_links(content).map(|_| SubscriptionFormat::Base64Links))
+}
+
+/// 自动识别格式并解析订阅内容，无法识别时按分享链接逐行报告错误
+pub fn parse_subscription(content: &str) -> ParsedSubscription {
+    let format = detect_format(content).unwrap_or(SubscriptionFormat::ShareLinks);
+    parse_subscription_as(content, format)
+}
+
+/// 自动识别格式并解析，没有得到任何节点时交给 libsubconverter 再试一次
+pub fn parse_subscription_with_fallback(content: &str) -> ParsedSubscription {
+    let parsed = parse_subscription(content);
+    if !parsed.nodes.is_empty() {
+        return parsed;
+    }
+    let fallback = parse_subscription_as(content, SubscriptionFormat::Subconverter);
+    if fallback.nodes.is_empty() {
+        parsed
+    } else {
+        fallback
+    }
+}
+
+/// 按指定格式解析订阅内容
+pub fn parse_subscription_as(content: &str, format: SubscriptionFormat) -> ParsedSubscription {
+    let errors = |error: Error| ParsedSubscription::new(format, (Vec::new(), vec![error]));
+    match format {
+        SubscriptionFormat::Clash => match clash_proxies(content) {
+            Some(proxies) => ParsedSubscription::new(format, parse_clash_proxies(&proxies)),
+            None => errors(anyhow!("不是有效的 Clash 配置: 缺少 proxies 字段")),
+        },
+        SubscriptionFormat::Singbox | SubscriptionFormat::Sip008 => {
+            match serde_json::from_str::<serde_json::Value>(content.trim_start_matches('\u{feff}'))
+            {
+                Ok(document) if format == SubscriptionFormat::Singbox => {
+                    ParsedSubscription::new(format, parse_singbox(&document))
+                }
+                Ok(document) => ParsedSubscription::new(format, parse_sip008(&document)),
+                Err(e) => errors(anyhow!("不是有效的 JSON: {}", e)),
+            }
+        }
+        SubscriptionFormat::Base64Links | SubscriptionFormat::ShareLinks => {
+            let decoded = decoded_links(content);
+            ParsedSubscription::new(
+                format,
+                parse_share_links(decoded.as_deref().unwrap_or(content)),
+            )
+        }
+        SubscriptionFormat::Surge => ParsedSubscription::new(format, parse_surge(content)),
+        SubscriptionFormat::QuanX => ParsedSubscription::new(format, parse_quanx(content)),
+        SubscriptionFormat::Subconverter => {
+            let decoded = decode_base64(content);
+            ParsedSubscription::new(
+                format,
+                parse_with_subconverter(decoded.as_deref().unwrap_or(content)),
+            )
+        }
+    }
+}
+
+/// 按行识别分享链接、Surge 与 Quantumult X 节点行，取匹配行数最多的格式
+fn detect_lines(content: &str) -> Option<SubscriptionFormat> {
+    let (mut links, mut surge, mut quanx) = (0, 0, 0);
+    for line in content.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        // 完整配置的段头直接决定格式
+        if line.eq_ignore_ascii_case("[Proxy]") {
+            return Some(SubscriptionFormat::Surge);
+        }
+        if line.eq_ignore_ascii_case("[server_local]") {
+            return Some(SubscriptionFormat::QuanX);
+        }
+        if SHARE_LINK.is_match(line) {
+            links += 1;
+        } else if is_quanx_line(line) {
+            quanx += 1;
+        } else if is_surge_line(line) {
+            surge += 1;
+        }
+    }
+    [
+        (links, SubscriptionFormat::ShareLinks),
+        (surge, SubscriptionFormat::Surge),
+        (quanx, SubscriptionFormat::QuanX),
+    ]
+    .into_iter()
+    .filter(|(count, _)| *count > 0)
+    .max_by(|(a, _), (b, _)| a.cmp(b).then(std::cmp::Ordering::Greater))
+    .map(|(_, format)| format)
+}
+
+fn is_surge_line(line: &str) -> bool {
+    let Some((_, value)) = line.split_once('=') else {
+        return false;
+    };
+    let mut items = value.split(',');
+    let kind = items.next().unwrap_or_default().trim().to_ascii_lowercase();
+    SURGE_TYPES.contains(&kind.as_str()) && items.next().is_some()
+}
+
+/// 整体 base64 编码的内容解码为文本，支持 URL 安全字母表、省略填充与按列折行
+fn decode_base64(content: &str) -> Option<String> {
+    let compact: String = content.split_whitespace().collect();
+    let compact = compact.trim_end_matches('=');
+    if compact.is_empty()
+        || !compact
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'+' | b'/' | b'-' | b'_'))
+    {
+        return None;
+    }
+    let decoded = URL_SAFE_NO_PAD
+        .decode(compact)
+        .or_else(|_| STANDARD_NO_PAD.decode(compact))
+        .ok()?;
+    String::from_utf8(decoded).ok()
+}
+
+/// base64 解码后确实是分享链接列表时返回解码结果
+fn decoded_links(content: &str) -> Option<String> {
+    decode_base64(content)
+        .filter(|decoded| detect_lines(decoded) == Some(SubscriptionFormat::ShareLinks))
+}
+
+/// Clash 配置中的 `proxies` 列表
+fn clash_proxies(content: &str) -> Option<Value> {
+    serde_yaml::from_str::<Value>(content)
+        .ok()?
+        .get("proxies")
+        .cloned()
+}
+
+fn parse_clash_proxies(proxies: &Value) -> (Vec<ProxyNode>, Vec<Error>) {
+    let mut nodes = Vec::new();
+    let mut errors = Vec::new();
+    for node in ProxyNode::from_clash_proxies(proxies) {
+        match node {
+            Ok(node) => nodes.push(node),
+            Err(e) => errors.push(e),
+        }
+    }
+    (nodes, errors)
+}
+
+fn parse_share_links(content: &str) -> (Vec<ProxyNode>, Vec<Error>) {
+    let mut nodes = Vec::new();
+    let mut errors = Vec::new();
+    for line in content.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match parse_proxy_link(line) {
+            Ok(node) => nodes.push(node),
+            Err(e) => errors.push(anyhow!("代理链接解析失败: {} - {}", line, e)),
+        }
+    }
+    (nodes, errors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::engine::general_purpose::STANDARD;
+
+    const LINKS: &str = "trojan://secret@example.com:443#trojan\n\
+                         # 注释行\n\
+                         socks5://127.0.0.1:1080#socks\n\
+                         unknown://whatever\n";
+
+    #[test]
+    fn test_parse_share_links() {
+        let parsed = parse_subscription(LINKS);
+        assert_eq!(parsed.format, SubscriptionFormat::ShareLinks);
+        assert_eq!(parsed.nodes.len(), 2);
+        assert_eq!(parsed.nodes[0].name, "trojan");
+        assert_eq!(parsed.errors.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_base64_share_links() {
+        // 折行的 base64 也能解码
+        let encoded = STANDARD.encode(LINKS);
+        let wrapped = format!("{}\n{}\n", &encoded[..40], &encoded[40..]);
+        let parsed = parse_subscription(&wrapped);
+        assert_eq!(parsed.nodes.len(), 2);
+        assert_eq!(parsed.nodes[1].name, "socks");
+    }
+
+    #[test]
+    fn test_parse_clash_config() {
+        let yaml = "proxies:\n\
+                    - {name: a, type: socks5, server: example.com, port: 1080}\n\
+                    - {name: b, type: trojan, server: example.com, port: 443}\n";
+        let parsed = parse_subscription(yaml);
+        assert_eq!(parsed.format, SubscriptionFormat::Clash);
+        assert_eq!(parsed.nodes.len(), 1);
+        assert_eq!(parsed.errors.len(), 1);
+
+        let parsed = parse_subscription_as(LINKS, SubscriptionFormat::Clash);
+        assert!(parsed.nodes.is_empty());
+        assert_eq!(parsed.errors.len(), 1);
+    }
+
+    #[test]
+    fn test_fallback_to_subconverter() {
+        // SSD 只有 libsubconverter 能解析
+        let ssd = format!(
+            "ssd://{}",
+            STANDARD.encode(
+                r#"{"airport":"机场","port":8388,"encryption":"aes-128-gcm","password":"secret","servers":[{"server":"1.2.3.4","remarks":"ssd"}]}"#
+            )
+        );
+        assert!(parse_subscription(&ssd).nodes.is_empty());
+
+        let parsed = parse_subscription_with_fallback(&ssd);
+        assert_eq!(parsed.format, SubscriptionFormat::Subconverter);
+        assert_eq!(parsed.nodes.len(), 1);
+        assert_eq!(parsed.nodes[0].name, "机场 - ssd");
+
+        // 自有解析器能识别时不走兜底
+        let parsed = parse_subscription_with_fallback(LINKS);
+        assert_eq!(parsed.format, SubscriptionFormat::ShareLinks);
+    }
+
+    #[test]
+    fn test_detect_format() {
+        let detect = |content: &str| detect_format(content);
+        assert_eq!(detect(LINKS), Some(SubscriptionFormat::ShareLinks));
+        assert_eq!(
+            detect("\u{feff}proxies: []\n"),
+            Some(SubscriptionFormat::Clash)
+        );
+        assert_eq!(
+            detect(r#"{"outbounds": [{"type": "direct", "tag": "direct"}]}"#),
+            Some(SubscriptionFormat::Singbox)
+        );
+        assert_eq!(
+            detect(r#"{"version": 1, "servers": [{"server": "a", "server_port": 1}]}"#),
+            Some(SubscriptionFormat::Sip008)
+        );
+        assert_eq!(
+            detect("ss = ss, 1.2.3.4, 8388, encrypt-method=aes-128-gcm, password=secret"),
+            Some(SubscriptionFormat::Surge)
+        );
+        assert_eq!(
+            detect("trojan=example.com:443, password=secret, tag=a"),
+            Some(SubscriptionFormat::QuanX)
+        );
+        assert_eq!(detect(r#"{"log": {}}"#), None);
+        assert_eq!(detect(""), None);
+
+        // URL 安全字母表且省略填充的 base64
+        let encoded = URL_SAFE_NO_PAD.encode(
+            "vless://b831381d-6324-4d53-ad4f-8cda48b30811@example.com:443?type=ws&path=/a?b#名称\n",
+        );
+        assert!(encoded.contains(['-', '_']));
+        assert_eq!(detect(&encoded), Some(SubscriptionFormat::Base64Links));
+        assert_eq!(parse_subscription(&encoded).nodes.len(), 1);
+
+        // 恰好是合法 base64 的普通文本不会被解码
+        assert_eq!(detect("abcd"), None);
+        assert_eq!(detect("hello"), None);
+    }
+
+    #[test]
+    fn test_parse_detected_formats() {
+        let surge = "[General]\nloglevel = notify\n[Proxy]\n\
+                     ss = ss, 1.2.3.4, 8388, encrypt-method=aes-128-gcm, password=secret\n";
+        let parsed = parse_subscription(surge);
+        assert_eq!(parsed.format, SubscriptionFormat::Surge);
+        assert_eq!(parsed.nodes.len(), 1);
+        assert_eq!(parsed.nodes[0].name, "ss");
+
+        let sip008 = r#"{"version": 1, "servers": [{"server": "a.example.com", "server_port": 8388, "password": "p", "method": "aes-128-gcm"}]}"#;
+        let parsed = parse_subscription(sip008);
+        assert_eq!(parsed.format, SubscriptionFormat::Sip008);
+        assert_eq!(parsed.nodes.len(), 1);
+
+        let quanx = "[server_local]\ntrojan=example.com:443, password=secret, tag=trojan\n";
+        let parsed = parse_subscription(quanx);
+        assert_eq!(parsed.format, SubscriptionFormat::QuanX);
+        assert_eq!(parsed.nodes[0].name, "trojan");
+
+        let parsed = parse_subscription_as("not json", SubscriptionFormat::Singbox);
+        assert!(parsed.nodes.is_empty());
+        assert_eq!(parsed.errors.len(), 1);
+    }
+}
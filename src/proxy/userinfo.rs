@@ -0,0 +1,196 @@
+//! 订阅流量与到期信息
+//! 解析机场在响应头 `subscription-userinfo: upload=…; download=…; total=…; expire=…`
+//! 中返回的已用流量、总流量与到期时间；`total` 为 0 表示不限流量，`expire` 为 0 或缺省表示长期有效
+
+use chrono::{DateTime, Local, TimeDelta, Utc};
+use serde::Serialize;
+
+/// 携带流量信息的响应头
+pub const HEADER: &str = "subscription-userinfo";
+
+/// 响应头中的流量与到期信息
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct SubscriptionUserInfo {
+    /// 已用上传流量（字节）
+    pub upload: u64,
+    /// 已用下载流量（字节）
+    pub download: u64,
+    /// 总流量（字节），0 表示不限
+    pub total: u64,
+    pub expire: Option<DateTime<Utc>>,
+}
+
+impl SubscriptionUserInfo {
+    /// 解析响应头的值，不含任何已知字段时返回 `None`
+    pub fn parse(value: &str) -> Option<Self> {
+        let mut info = Self::default();
+        let mut found = false;
+        for item in value.split(';') {
+            let Some((key, value)) = item.split_once('=') else {
+                continue;
+            };
+            // 部分机场用浮点数或科学计数法表示字节数
+            let Some(number) = value
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|number| number.is_finite() && *number >= 0.0)
+            else {
+                continue;
+            };
+            match key.trim().to_ascii_lowercase().as_str() {
+                "upload" => info.upload = number as u64,
+                "download" => info.download = number as u64,
+                "total" => info.total = number as u64,
+                "expire" => {
+                    info.expire = (number >= 1.0)
+                        .then(|| DateTime::from_timestamp(number as i64, 0))
+                        .flatten()
+                }
+                _ => continue,
+            }
+            found = true;
+        }
+        found.then_some(info)
+    }
+
+    /// 已用流量
+    pub fn used(&self) -> u64 {
+        self.upload.saturating_add(self.download)
+    }
+
+    /// 剩余流量，不限流量时为 `None`
+    pub fn remaining(&self) -> Option<u64> {
+        (self.total > 0).then(|| self.total.saturating_sub(self.used()))
+    }
+
+    /// 需要提醒的情况：流量用尽、已经到期或将在 `warn_days` 天内到期
+    pub fn warnings(&self, now: DateTime<Utc>, warn_days: u32) -> Vec<String> {
+        let mut warnings = Vec::new();
+        if self.remaining() == Some(0) {
+            warnings.push(format!("流量已用完 ({})", self.traffic()));
+        }
+        if let Some(expire) = self.expire {
+            let left = expire - now;
+            if left <= TimeDelta::zero() {
+                warnings.push(format!("已于 {} 到期", date(expire)));
+            } else if left <= TimeDelta::days(warn_days.into()) {
+                warnings.push(format!(
+                    "将于 {} 到期，剩余 {} 天",
+                    date(expire),
+                    left.num_days()
+                ));
+            }
+        }
+        warnings
+    }
+
+    /// 形如 `已用 12.50 GB / 共 100.00 GB` 的流量描述
+    pub fn traffic(&self) -> String {
+        match self.total {
+            0 => format!("已用 {}，不限流量", gigabytes(self.used())),
+            total => format!("已用 {} / 共 {}", gigabytes(self.used()), gigabytes(total)),
+        }
+    }
+
+    /// 流量与到期时间的一行摘要
+    pub fn summary(&self) -> String {
+        match self.expire {
+            Some(expire) => format!("{}，{} 到期", self.traffic(), date(expire)),
+            None => format!("{}，长期有效", self.traffic()),
+        }
+    }
+}
+
+/// 写入结果文件的单个订阅记录
+#[derive(Debug, Clone, Serialize)]
+pub struct SubscriptionUsage {
+    pub name: String,
+    #[serde(flatten)]
+    pub info: SubscriptionUserInfo,
+    pub used: u64,
+    pub remaining: Option<u64>,
+    pub warnings: Vec<String>,
+}
+
+impl SubscriptionUsage {
+    pub fn new(
+        name: String,
+        info: SubscriptionUserInfo,
+        now: DateTime<Utc>,
+        warn_days: u32,
+    ) -> Self {
+        Self {
+            name,
+            used: info.used(),
+            remaining: info.remaining(),
+            warnings: info.warnings(now, warn_days),
+            info,
+        }
+    }
+}
+
+fn gigabytes(bytes: u64) -> String {
+    format!("{:.2} GB", bytes as f64 / 1024.0 / 1024.0 / 1024.0)
+}
+
+fn date(time: DateTime<Utc>) -> String {
+    time.with_timezone(&Local).format("%Y-%m-%d").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GB: u64 = 1024 * 1024 * 1024;
+
+    #[test]
+    fn test_parse_userinfo() {
+        let info = SubscriptionUserInfo::parse(
+            "upload=1073741824; download=2.147483648e9; total=10737418240; expire=1893456000",
+        )
+        .unwrap();
+        assert_eq!(info.used(), 3 * GB);
+        assert_eq!(info.remaining(), Some(7 * GB));
+        assert_eq!(info.expire.unwrap().timestamp(), 1893456000);
+        assert_eq!(info.traffic(), "已用 3.00 GB / 共 10.00 GB");
+
+        // 不限流量且长期有效
+        let info =
+            SubscriptionUserInfo::parse("upload=0; download=1024; total=0; expire=0").unwrap();
+        assert_eq!(info.remaining(), None);
+        assert_eq!(info.expire, None);
+        assert!(info.summary().ends_with("长期有效"));
+
+        assert_eq!(SubscriptionUserInfo::parse("foo=1; bar"), None);
+    }
+
+    #[test]
+    fn test_warnings() {
+        let now = Utc::now();
+        let info = SubscriptionUserInfo {
+            upload: 6 * GB,
+            download: 5 * GB,
+            total: 10 * GB,
+            expire: Some(now + TimeDelta::days(3) + TimeDelta::hours(1)),
+        };
+        let warnings = info.warnings(now, 7);
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings[0].starts_with("流量已用完"));
+        assert!(warnings[1].ends_with("剩余 3 天"));
+        assert_eq!(info.warnings(now, 2).len(), 1);
+
+        let expired = SubscriptionUserInfo {
+            expire: Some(now - TimeDelta::days(1)),
+            ..Default::default()
+        };
+        assert!(expired.warnings(now, 0)[0].starts_with("已于"));
+
+        let usage = SubscriptionUsage::new("机场".to_string(), info, now, 7);
+        assert_eq!(usage.used, 11 * GB);
+        assert_eq!(usage.remaining, Some(0));
+        let json = serde_json::to_value(&usage).unwrap();
+        assert_eq!(json["total"], 10 * GB);
+        assert_eq!(json["warnings"].as_array().unwrap().len(), 2);
+    }
+}